@@ -0,0 +1,118 @@
+//! 解析与分块热路径的基准测试
+//!
+//! - `vasp_parse`：`VaspParser::parse_from_file` 解析不同大小的 ASCII / 二进制 VASP 文件
+//! - `split_encode`：按不同 chunk_size 把网格切分为 chunk 并编码为小端序 f64（chunk 接口的默认格式）
+//!
+//! 测试文件在首次运行时生成到系统临时目录，不随仓库提交。运行：`cargo bench --bench parse`
+
+use std::hint::black_box;
+use std::io::Write;
+use std::path::PathBuf;
+
+use actix_web::mime;
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use demos_3d_backend::parsers::VaspParser;
+use demos_3d_backend::task::compute_chunks;
+use demos_3d_backend::utils::chunk_encoding::{ChunkEncoding, encode_chunk};
+use demos_3d_backend::utils::parser::VoxelGridParser;
+
+/// 测试网格的边长（n³ 个体素）
+const GRID_SIZES: [usize; 3] = [32, 64, 128];
+
+/// 分块测试使用的 chunk_size（元素个数）
+const CHUNK_SIZES: [usize; 3] = [4096, 65_536, 1_048_576];
+
+/// 分块测试使用的网格边长
+const SPLIT_GRID_SIZE: usize = 128;
+
+/// 与真实电荷密度类似的平滑数据，避免全部相同的值让解析或编码走捷径
+fn sample_value(index: usize) -> f64 {
+    let x = index as f64 * 1e-3;
+    x.sin() * 0.5 + (x * 0.37).cos() * 1e-2
+}
+
+/// 生成（或复用已生成的）n³ 的 VASP 测试文件，数据段为 ASCII（每行 5 个值）或二进制 f64
+fn vasp_fixture(n: usize, binary: bool) -> PathBuf {
+    let kind = if binary { "bin" } else { "ascii" };
+    let path = std::env::temp_dir().join(format!("demos3d-bench-{n}-{kind}.vasp"));
+    if path.exists() {
+        return path;
+    }
+
+    let mut header = String::from("bench grid\n   1.00000000000000\n");
+    for row in ["10.0 0.0 0.0", "0.0 10.0 0.0", "0.0 0.0 10.0"] {
+        header.push_str(row);
+        header.push('\n');
+    }
+    // 第 6–28 行为原子信息，这里留空
+    header.push_str(&"\n".repeat(23));
+    header.push_str(&format!("{n} {n} {n}\n"));
+
+    let total = n * n * n;
+    let mut body = Vec::new();
+    if binary {
+        for index in 0..total {
+            body.extend_from_slice(&sample_value(index).to_le_bytes());
+        }
+    } else {
+        for index in 0..total {
+            let separator = if (index + 1) % 5 == 0 { '\n' } else { ' ' };
+            write!(body, "{:.11E}{separator}", sample_value(index)).unwrap();
+        }
+    }
+
+    let mut file = std::fs::File::create(&path).unwrap();
+    file.write_all(header.as_bytes()).unwrap();
+    file.write_all(&body).unwrap();
+    path
+}
+
+fn bench_vasp_parse(c: &mut Criterion) {
+    let parser = VaspParser::new();
+    let mut group = c.benchmark_group("vasp_parse");
+    group.sample_size(10);
+    for binary in [false, true] {
+        let kind = if binary { "binary" } else { "ascii" };
+        for n in GRID_SIZES {
+            let path = vasp_fixture(n, binary);
+            let path = path.to_str().unwrap();
+            group.throughput(Throughput::Elements((n * n * n) as u64));
+            group.bench_with_input(BenchmarkId::new(kind, format!("{n}^3")), path, |b, path| {
+                b.iter(|| parser.parse_from_file(black_box(path)).unwrap())
+            });
+        }
+    }
+    group.finish();
+}
+
+fn bench_split_encode(c: &mut Criterion) {
+    let total = SPLIT_GRID_SIZE.pow(3);
+    let shape = [SPLIT_GRID_SIZE; 3];
+    let data: Vec<f64> = (0..total).map(sample_value).collect();
+
+    let mut group = c.benchmark_group("split_encode");
+    group.sample_size(20);
+    group.throughput(Throughput::Bytes(
+        std::mem::size_of_val(data.as_slice()) as u64
+    ));
+    for chunk_size in CHUNK_SIZES {
+        let chunks = compute_chunks(total, chunk_size);
+        group.bench_with_input(
+            BenchmarkId::from_parameter(chunk_size),
+            &chunks,
+            |b, chunks| {
+                b.iter(|| {
+                    for descriptor in chunks {
+                        let values = descriptor.extract(black_box(&data), shape);
+                        let encoding = ChunkEncoding::F64Le(mime::APPLICATION_OCTET_STREAM);
+                        black_box(encode_chunk(&values, encoding).unwrap());
+                    }
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_vasp_parse, bench_split_encode);
+criterion_main!(benches);
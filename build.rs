@@ -0,0 +1,33 @@
+use std::process::Command;
+
+/// 构建时记录当前 git commit，供根路径接口返回，便于确认线上运行的版本
+fn main() {
+    let commit = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|hash| hash.trim().to_string())
+        .filter(|hash| !hash.is_empty())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    println!("cargo:rustc-env=GIT_COMMIT_HASH={commit}");
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs/heads");
+
+    #[cfg(feature = "grpc")]
+    compile_protos();
+}
+
+/// 编译 gRPC 接口定义，生成的代码通过 `tonic::include_proto!` 引入
+#[cfg(feature = "grpc")]
+fn compile_protos() {
+    let descriptors = protox::compile(["proto/voxel_grid.proto"], ["proto"])
+        .expect("编译 proto/voxel_grid.proto 失败");
+    tonic_build::configure()
+        .build_client(false)
+        .compile_fds(descriptors)
+        .expect("生成 gRPC 代码失败");
+    println!("cargo:rerun-if-changed=proto/voxel_grid.proto");
+}
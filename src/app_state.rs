@@ -1,13 +1,141 @@
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Instant;
 
+use crate::config::Config;
+use crate::parse_dedup::InFlightParses;
+use crate::parse_queue::ParseQueue;
+use crate::parser_health::ParserHealth;
 use crate::performance::PerformanceStore;
+use crate::preprocess_cache::PreprocessCache;
+use crate::request_metrics::RequestMetrics;
+use crate::stream_limit::StreamLimits;
 use crate::task::TaskStore;
 use crate::utils::parser_registry::ParserRegistry;
 
-/// 全局应用状态，负责在各个 handler 之间共享解析器与资源目录
+/// 全局应用状态，负责在各个 handler 之间共享解析器、任务存储与配置
 pub struct AppState {
     pub parser_registry: Arc<ParserRegistry>,
-    pub resource_dir: String,
     pub task_store: Arc<TaskStore>,
     pub performance_store: Arc<PerformanceStore>,
+    /// 后台解析调度队列（限制并发并按优先级调度）
+    pub parse_queue: Arc<ParseQueue>,
+    /// 进行中的后台解析（用于相同解析的去重）
+    pub in_flight_parses: Arc<InFlightParses>,
+    /// 最近的预处理结果（用于客户端的缓存校验 `if_unchanged`）
+    pub preprocess_cache: Arc<PreprocessCache>,
+    /// 流式订阅的并发上限
+    pub stream_limits: Arc<StreamLimits>,
+    /// 各解析器最近一次自检的结果（后台定期刷新）
+    pub parser_health: Arc<ParserHealth>,
+    /// 按路由统计的 HTTP 请求数与延迟
+    pub request_metrics: Arc<RequestMetrics>,
+    pub config: Config,
+    /// 资源目录的规范化绝对路径（构造时解析），目录不存在时为 None
+    pub resource_root: Option<PathBuf>,
+    /// 服务启动时间，用于计算运行时长
+    pub started_at: Instant,
+}
+
+impl AppState {
+    /// 按配置构造完整的应用状态（解析器注册表、任务存储等均使用默认实现）
+    pub fn from_config(config: Config) -> Self {
+        AppStateBuilder::new().config(config).build()
+    }
+
+    /// 创建构造器，便于在测试或特殊部署中替换部分依赖
+    pub fn builder() -> AppStateBuilder {
+        AppStateBuilder::new()
+    }
+}
+
+/// `AppState` 构造器
+/// 未显式提供的依赖会根据配置自动创建
+#[derive(Default)]
+pub struct AppStateBuilder {
+    config: Config,
+    parser_registry: Option<Arc<ParserRegistry>>,
+    task_store: Option<Arc<TaskStore>>,
+    performance_store: Option<Arc<PerformanceStore>>,
+}
+
+impl AppStateBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置配置（TTL 等参数会用于创建默认的任务存储）
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// 使用自定义解析器注册表（例如只包含 mock 解析器）
+    pub fn parser_registry(mut self, parser_registry: Arc<ParserRegistry>) -> Self {
+        self.parser_registry = Some(parser_registry);
+        self
+    }
+
+    /// 使用已有的任务存储
+    pub fn task_store(mut self, task_store: Arc<TaskStore>) -> Self {
+        self.task_store = Some(task_store);
+        self
+    }
+
+    /// 使用已有的性能数据存储
+    pub fn performance_store(mut self, performance_store: Arc<PerformanceStore>) -> Self {
+        self.performance_store = Some(performance_store);
+        self
+    }
+
+    pub fn build(self) -> AppState {
+        let config = self.config;
+        let parser_registry = self
+            .parser_registry
+            .unwrap_or_else(|| Arc::new(ParserRegistry::from_config(&config)));
+        let task_store = self
+            .task_store
+            .unwrap_or_else(|| {
+                Arc::new(
+                    TaskStore::with_ttl(config.task_ttl)
+                        .with_limit(config.max_tasks, config.task_limit_policy)
+                        .with_recent_chunk_cache(config.recent_chunk_cache),
+                )
+            });
+        let performance_store = self
+            .performance_store
+            .unwrap_or_else(|| {
+                Arc::new(
+                    PerformanceStore::new()
+                        .with_max_records_per_session(config.max_performance_records),
+                )
+            });
+        let parse_queue = Arc::new(ParseQueue::new(config.parse_workers));
+        let stream_limits = Arc::new(StreamLimits::new(config.max_task_streams, config.max_streams));
+        let resource_root = resolve_dir(&config.resource_dir);
+        let parser_health = Arc::new(ParserHealth::new(&parser_registry));
+
+        AppState {
+            parser_registry,
+            task_store,
+            performance_store,
+            parse_queue,
+            in_flight_parses: Arc::new(InFlightParses::new()),
+            preprocess_cache: Arc::new(PreprocessCache::new()),
+            stream_limits,
+            parser_health,
+            request_metrics: Arc::new(RequestMetrics::new()),
+            config,
+            resource_root,
+            started_at: Instant::now(),
+        }
+    }
+}
+
+/// 解析目录的规范化绝对路径，不存在或不是目录时返回 None
+fn resolve_dir(dir: &str) -> Option<PathBuf> {
+    Path::new(dir)
+        .canonicalize()
+        .ok()
+        .filter(|path| path.is_dir())
 }
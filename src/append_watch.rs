@@ -0,0 +1,128 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::web;
+
+use crate::app_state::AppState;
+use crate::task::{AppendState, TaskData};
+
+/// 启动追加检查：每隔 `interval` 检查所有支持追加的固定任务的文件是否增长（为 0 时不启动）
+///
+/// 文件的大小或修改时间变化时，只解析上次读取位置之后新追加的完整 z 层，
+/// 把它们切分为新的 chunk 追加到任务末尾（task_id 与原有 chunk 不变），并唤醒等待追加的请求
+pub fn spawn_watch(app_state: web::Data<AppState>, interval: Duration) {
+    if interval.is_zero() {
+        return;
+    }
+    actix_web::rt::spawn(async move {
+        let mut ticker = actix_web::rt::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            for (task_id, task) in app_state.task_store.snapshot() {
+                if !task.is_pinned()
+                    || task.cancel.is_cancelled()
+                    || task.failure().is_some()
+                    || task.append.lock().is_none()
+                {
+                    continue;
+                }
+                check_task(&app_state, &task_id, task).await;
+            }
+        }
+    });
+}
+
+/// 一次检查的结果
+enum AppendOutcome {
+    /// 解析器不支持追加（例如插件配置已改变），不再检查该任务
+    Unsupported,
+    /// 文件增长了，但还没有新的完整层
+    Pending,
+    /// 追加后的新任务与新增的层数
+    Appended(Box<TaskData>, usize),
+}
+
+/// 检查单个任务的文件，有新的完整层时用追加后的任务替换原任务
+async fn check_task(app_state: &AppState, task_id: &str, task: Arc<TaskData>) {
+    let Some(state) = task.append.lock().clone() else {
+        return;
+    };
+    let Ok(metadata) = std::fs::metadata(&task.file_path) else {
+        return;
+    };
+    let file_size = metadata.len();
+    let file_mtime = crate::handlers::preprocess::file_mtime_ms(&task.file_path);
+    if file_size == state.file_size && file_mtime == state.file_mtime {
+        return;
+    }
+    if file_size < state.file_size {
+        eprintln!(
+            "[追加] 任务 {task_id} 的文件 {} 变小了（{} -> {file_size} 字节），不是追加写入，停止检查",
+            task.file_path, state.file_size
+        );
+        *task.append.lock() = None;
+        return;
+    }
+
+    let registry = app_state.parser_registry.clone();
+    let block_task = task.clone();
+    let result = web::block(move || {
+        let task = block_task;
+        let (parser, _) = registry
+            .find_parser_for_file(&task.file_path)
+            .ok_or_else(|| "找不到解析器".to_string())?;
+        let appended = parser
+            .read_appended_layers(&task.file_path, task.data_length(), state.cursor)
+            .map_err(|e| e.to_string())?;
+        let Some(appended) = appended else {
+            return Ok(AppendOutcome::Unsupported);
+        };
+        let state = AppendState {
+            file_size,
+            file_mtime,
+            cursor: Some(appended.cursor),
+            ..state
+        };
+        if appended.layers == 0 {
+            // 新写入的内容还不足一层，只记录读取位置，等待下一次检查
+            *task.append.lock() = Some(state);
+            return Ok(AppendOutcome::Pending);
+        }
+        let layers = appended.layers;
+        let replacement = task.with_appended_layers(appended.values, layers, state);
+        Ok::<_, String>(AppendOutcome::Appended(Box::new(replacement), layers))
+    })
+    .await;
+
+    let (replacement, layers) = match result {
+        Ok(Ok(AppendOutcome::Appended(replacement, layers))) => (replacement, layers),
+        Ok(Ok(AppendOutcome::Pending)) => return,
+        Ok(Ok(AppendOutcome::Unsupported)) => {
+            *task.append.lock() = None;
+            return;
+        }
+        Ok(Err(e)) => {
+            eprintln!("[追加] 任务 {task_id} 读取追加的数据失败: {e}");
+            // 记录当前的文件状态，文件再次变化时重试，而不是每次检查都重复报错
+            if let Some(state) = task.append.lock().as_mut() {
+                state.file_size = file_size;
+                state.file_mtime = file_mtime;
+            }
+            return;
+        }
+        Err(e) => {
+            eprintln!("[追加] 任务 {task_id} 追加线程异常: {e}");
+            return;
+        }
+    };
+
+    let shape = replacement.shape;
+    let chunk_count = replacement.chunks.len();
+    if app_state.task_store.replace(task_id, &task, *replacement) {
+        task.notify_replaced();
+        println!(
+            "[追加] 任务 {task_id} 追加了 {layers} 层: shape {:?} -> {shape:?}，共 {chunk_count} 个 chunk",
+            task.shape
+        );
+    }
+}
@@ -0,0 +1,54 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// 请求级别的取消标记
+///
+/// 每个预处理请求创建一个，保存在任务上并传给后台解析；克隆共享同一个标记。
+/// 后台解析只在粗粒度的阶段边界（等待解析名额后、解析完成后、分割每个 chunk 前）检查，
+/// 已经开始的文件读取不会被打断
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 标记为已取消；返回 true 表示本次调用完成了取消（之前未取消）
+    pub fn cancel(&self) -> bool {
+        !self.cancelled.swap(true, Ordering::AcqRel)
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Acquire)
+    }
+
+    /// 返回一个守卫：守卫在 `disarm` 之前被释放（例如 handler 的 future 被丢弃）时取消
+    pub fn cancel_on_drop(&self) -> CancelOnDrop {
+        CancelOnDrop {
+            token: Some(self.clone()),
+        }
+    }
+}
+
+/// 见 `CancelToken::cancel_on_drop`
+pub struct CancelOnDrop {
+    token: Option<CancelToken>,
+}
+
+impl CancelOnDrop {
+    /// 请求已正常完成，释放守卫时不再取消
+    pub fn disarm(mut self) {
+        self.token = None;
+    }
+}
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        if let Some(token) = self.token.take() {
+            token.cancel();
+        }
+    }
+}
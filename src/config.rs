@@ -0,0 +1,312 @@
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::handlers::chunk::ChunkFormat;
+use crate::performance::ChannelIdScheme;
+use crate::task::TaskLimitPolicy;
+
+/// 服务配置，集中管理所有可调参数
+/// 默认值适合本地开发，部署时可通过环境变量覆盖
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// 监听地址
+    pub host: String,
+    /// 监听端口
+    pub port: u16,
+    /// 资源目录（体素网格文件所在目录）
+    pub resource_dir: String,
+    /// 任务 TTL：超过该时长的任务会被后台清理
+    pub task_ttl: Duration,
+    /// 后台清理任务的执行间隔
+    pub cleanup_interval: Duration,
+    /// JSON 格式 chunk 响应允许的最大元素数量，超过时要求改用二进制格式
+    pub max_json_chunk_values: usize,
+    /// 同时进行的后台解析数量（解析 worker 池大小），默认取 CPU 核数
+    pub parse_workers: usize,
+    /// 解析完成后是否进行数据顺序的启发式自检（仅记录警告，不影响结果）
+    pub ordering_check: bool,
+    /// 同一文件、同一 chunk_size 的解析正在进行时，新请求是否共享该次解析
+    pub parse_dedup: bool,
+    /// 排队等待解析的任务数上限，超过时预处理请求返回 503（0 表示不限制）
+    pub max_parse_queue_depth: usize,
+    /// 根路径接口是否返回版本号、git commit 与运行时长
+    pub expose_build_info: bool,
+    /// 每个任务最多驻留内存的 chunk 数量（0 表示不限制）
+    /// 开启后只保留当前请求位置起的窗口，窗口外的 chunk 按需从文件重新读取；仅对支持随机读取的文件生效
+    pub resident_window: usize,
+    /// gRPC 监听端口（仅在以 `grpc` feature 编译时使用）
+    pub grpc_port: u16,
+    /// 二进制 chunk 响应（`binary` / `f32`）的 Content-Type，可被请求参数 `content_type` 覆盖
+    pub binary_content_type: String,
+    /// 管理接口（`/admin/*`）的访问令牌，未设置时管理接口不可用
+    pub admin_token: Option<String>,
+    /// 任务持久化目录：设置后在正常关闭时保存未过期的任务，启动时重新加载
+    pub task_persist_dir: Option<String>,
+    /// 单个任务同时进行的流式订阅（如 gRPC `StreamChunks`）上限，0 表示不限制
+    pub max_task_streams: usize,
+    /// 所有任务合计同时进行的流式订阅上限，0 表示不限制
+    pub max_streams: usize,
+    /// 资源目录不存在时是否拒绝启动（默认只记录警告）
+    pub require_resource_dir: bool,
+    /// 同时存在的任务数上限，0 表示不限制
+    pub max_tasks: usize,
+    /// 任务数达到上限时的策略：`reject`（预处理返回 503）或 `evict_oldest`（移除最早的任务）
+    pub task_limit_policy: TaskLimitPolicy,
+    /// TLS 证书链（PEM）路径；与 `tls_key` 同时设置时以 HTTPS（HTTP/2 + HTTP/1.1）提供服务（需要 `tls` feature）
+    pub tls_cert: Option<String>,
+    /// TLS 私钥（PEM，PKCS#8 / PKCS#1 / SEC1）路径
+    pub tls_key: Option<String>,
+    /// chunk 接口的默认响应格式（`binary` / `f32` / `json` / `arrow`），请求既没有 `format` 参数、`Accept` 头也没有指定具体类型时使用
+    pub default_chunk_format: ChunkFormat,
+    /// 每个任务保留最近被请求的 chunk 数量（LRU），在窗口内重复请求同一 chunk 仍能成功；0 表示不保留
+    pub recent_chunk_cache: usize,
+    /// 每个会话最多保存的性能记录数，超出时丢弃最早的记录；0 表示不限制
+    pub max_performance_records: usize,
+    /// 性能记录 channel 编号的分配方式：`thread`（按线程首次记录的顺序，默认）或 `logical`（chunk 相关操作使用 chunk 索引，跨运行稳定）
+    pub perf_channel_ids: ChannelIdScheme,
+    /// 空闲连接的 keep-alive 时长，0 表示关闭 keep-alive。默认 75 秒，轮询 chunk 的客户端两次请求之间可复用连接
+    pub keep_alive: Duration,
+    /// 新连接发送完整请求头的时限，超时的连接被关闭，0 表示不限制
+    pub client_request_timeout: Duration,
+    /// 关闭连接时等待客户端确认断开的时限，超时后直接丢弃连接，0 表示不限制
+    pub client_disconnect_timeout: Duration,
+    /// 请求未携带 session_id 时是否由服务端生成一个（通过 `X-Session-Id` 响应头返回），关闭后不记录这类请求的性能数据
+    pub auto_session_id: bool,
+    /// chunk 请求 `wait_ms` 的上限：请求的等待时长超过该值时按该值等待，0 表示禁用长轮询（总是立即返回 202）
+    pub max_chunk_wait: Duration,
+    /// JSON / Arrow 格式的 chunk 元素数不少于该值时边序列化边发送，不在内存中组装完整 body（0 表示总是完整组装）
+    pub stream_chunk_values: usize,
+    /// 解析文件时的读取缓冲区大小（字节）：数据段按该大小分批读入、逐行解析，不在内存中保留整个文件
+    pub parse_buffer_bytes: usize,
+    /// 解析器自检（根路径的 `parsers`）的间隔，0 表示只在启动时检查一次
+    pub parser_check_interval: Duration,
+    /// 解析器自检的样例目录：设置后每个解析器改用该目录中第一个扩展名匹配的文件自检，而不是内置样例
+    pub parser_fixture_dir: Option<String>,
+    /// 解析器插件目录：启动时为其中每个 `*.json` 描述文件注册一个声明式文本网格解析器，无需重新编译即可支持新的文本格式
+    pub parser_plugin_dir: Option<String>,
+    /// 追加检查间隔：每隔该时长检查支持追加的固定任务的文件是否增长，解析新追加的层，为 0 时不检查
+    pub append_poll_interval: Duration,
+    /// 单个 chunk 解析失败（如文件的对应区域损坏）时代替其数据的填充值，响应带 `X-Chunk-Degraded: true`；None 时该 chunk 返回错误
+    pub chunk_failure_fill: Option<f64>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            host: "127.0.0.1".to_string(),
+            port: 8080,
+            resource_dir: "test/resource".to_string(),
+            task_ttl: Duration::from_secs(30 * 60), // 30 分钟
+            cleanup_interval: Duration::from_secs(5 * 60), // 5 分钟
+            max_json_chunk_values: 1_000_000,
+            parse_workers: default_parse_workers(),
+            ordering_check: true,
+            parse_dedup: true,
+            max_parse_queue_depth: 64,
+            expose_build_info: true,
+            resident_window: 0,
+            grpc_port: 50051,
+            binary_content_type: "application/octet-stream".to_string(),
+            admin_token: None,
+            task_persist_dir: None,
+            max_task_streams: 16,
+            max_streams: 256,
+            require_resource_dir: false,
+            max_tasks: 0,
+            task_limit_policy: TaskLimitPolicy::Reject,
+            tls_cert: None,
+            tls_key: None,
+            default_chunk_format: ChunkFormat::Binary,
+            recent_chunk_cache: 2,
+            max_performance_records: 10_000,
+            perf_channel_ids: ChannelIdScheme::Thread,
+            keep_alive: Duration::from_secs(75),
+            client_request_timeout: Duration::from_secs(5),
+            client_disconnect_timeout: Duration::from_secs(1),
+            auto_session_id: true,
+            max_chunk_wait: Duration::from_secs(10),
+            stream_chunk_values: 262_144,
+            parse_buffer_bytes: crate::parsers::VaspParser::DEFAULT_READ_BUFFER_BYTES,
+            parser_check_interval: Duration::from_secs(10 * 60),
+            parser_fixture_dir: None,
+            parser_plugin_dir: None,
+            append_poll_interval: Duration::from_secs(5),
+            chunk_failure_fill: None,
+        }
+    }
+}
+
+impl Config {
+    /// 从环境变量读取配置，未设置的项使用默认值
+    ///
+    /// | 环境变量                        | 说明                |
+    /// |---------------------------------|---------------------|
+    /// | `DEMOS3D_HOST`                  | 监听地址            |
+    /// | `DEMOS3D_PORT`                  | 监听端口            |
+    /// | `DEMOS3D_RESOURCE_DIR`          | 资源目录            |
+    /// | `DEMOS3D_TASK_TTL_SECS`         | 任务 TTL（秒）      |
+    /// | `DEMOS3D_CLEANUP_INTERVAL_SECS` | 清理间隔（秒）      |
+    /// | `DEMOS3D_MAX_JSON_CHUNK_VALUES` | JSON chunk 元素上限 |
+    /// | `DEMOS3D_PARSE_WORKERS`         | 后台解析并发数      |
+    /// | `DEMOS3D_ORDERING_CHECK`        | 解析后检查数据顺序  |
+    /// | `DEMOS3D_PARSE_DEDUP`           | 相同解析去重        |
+    /// | `DEMOS3D_MAX_PARSE_QUEUE_DEPTH` | 排队解析数上限      |
+    /// | `DEMOS3D_EXPOSE_BUILD_INFO`     | 根路径返回构建信息  |
+    /// | `DEMOS3D_RESIDENT_WINDOW`       | 驻留 chunk 窗口大小 |
+    /// | `DEMOS3D_GRPC_PORT`             | gRPC 监听端口       |
+    /// | `DEMOS3D_BINARY_CONTENT_TYPE`   | chunk Content-Type  |
+    /// | `DEMOS3D_ADMIN_TOKEN`           | 管理接口令牌        |
+    /// | `DEMOS3D_TASK_PERSIST_DIR`      | 任务持久化目录      |
+    /// | `DEMOS3D_MAX_TASK_STREAMS`      | 单任务流订阅上限    |
+    /// | `DEMOS3D_MAX_STREAMS`           | 流订阅总数上限      |
+    /// | `DEMOS3D_REQUIRE_RESOURCE_DIR`  | 资源目录必须存在    |
+    /// | `DEMOS3D_MAX_TASKS`             | 任务数上限          |
+    /// | `DEMOS3D_TASK_LIMIT_POLICY`     | 任务数上限策略      |
+    /// | `DEMOS3D_TLS_CERT`              | TLS 证书路径        |
+    /// | `DEMOS3D_TLS_KEY`               | TLS 私钥路径        |
+    /// | `DEMOS3D_DEFAULT_CHUNK_FORMAT`  | 默认 chunk 格式     |
+    /// | `DEMOS3D_RECENT_CHUNK_CACHE`    | 最近 chunk 缓存数   |
+    /// | `DEMOS3D_MAX_PERF_RECORDS`      | 每会话性能记录上限  |
+    /// | `DEMOS3D_PERF_CHANNEL_IDS`      | channel 编号方式    |
+    /// | `DEMOS3D_KEEP_ALIVE_SECS`       | keep-alive（秒）    |
+    /// | `DEMOS3D_REQUEST_TIMEOUT_MS`    | 请求头超时（毫秒）  |
+    /// | `DEMOS3D_DISCONNECT_TIMEOUT_MS` | 断开超时（毫秒）    |
+    /// | `DEMOS3D_AUTO_SESSION_ID`       | 自动生成 session_id |
+    /// | `DEMOS3D_MAX_CHUNK_WAIT_MS`     | 长轮询上限（毫秒）  |
+    /// | `DEMOS3D_STREAM_CHUNK_VALUES`   | 流式序列化阈值      |
+    /// | `DEMOS3D_PARSE_BUFFER_BYTES`    | 解析读缓冲（字节）  |
+    /// | `DEMOS3D_PARSER_CHECK_SECS`     | 自检间隔（秒）      |
+    /// | `DEMOS3D_PARSER_FIXTURE_DIR`    | 解析器自检样例目录  |
+    /// | `DEMOS3D_PARSER_PLUGIN_DIR`     | 解析器插件目录      |
+    /// | `DEMOS3D_APPEND_POLL_SECS`      | 追加检查间隔（秒）  |
+    /// | `DEMOS3D_CHUNK_FAILURE_FILL`    | chunk 失败填充值    |
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        Self {
+            host: env_or("DEMOS3D_HOST", default.host),
+            port: env_or("DEMOS3D_PORT", default.port),
+            resource_dir: env_or("DEMOS3D_RESOURCE_DIR", default.resource_dir),
+            task_ttl: env_secs_or("DEMOS3D_TASK_TTL_SECS", default.task_ttl),
+            cleanup_interval: env_secs_or(
+                "DEMOS3D_CLEANUP_INTERVAL_SECS",
+                default.cleanup_interval,
+            ),
+            max_json_chunk_values: env_or(
+                "DEMOS3D_MAX_JSON_CHUNK_VALUES",
+                default.max_json_chunk_values,
+            ),
+            parse_workers: env_or("DEMOS3D_PARSE_WORKERS", default.parse_workers),
+            ordering_check: env_or("DEMOS3D_ORDERING_CHECK", default.ordering_check),
+            parse_dedup: env_or("DEMOS3D_PARSE_DEDUP", default.parse_dedup),
+            max_parse_queue_depth: env_or(
+                "DEMOS3D_MAX_PARSE_QUEUE_DEPTH",
+                default.max_parse_queue_depth,
+            ),
+            expose_build_info: env_or("DEMOS3D_EXPOSE_BUILD_INFO", default.expose_build_info),
+            resident_window: env_or("DEMOS3D_RESIDENT_WINDOW", default.resident_window),
+            grpc_port: env_or("DEMOS3D_GRPC_PORT", default.grpc_port),
+            binary_content_type: env_or("DEMOS3D_BINARY_CONTENT_TYPE", default.binary_content_type),
+            admin_token: std::env::var("DEMOS3D_ADMIN_TOKEN")
+                .ok()
+                .filter(|token| !token.trim().is_empty()),
+            task_persist_dir: std::env::var("DEMOS3D_TASK_PERSIST_DIR")
+                .ok()
+                .filter(|dir| !dir.trim().is_empty()),
+            max_task_streams: env_or("DEMOS3D_MAX_TASK_STREAMS", default.max_task_streams),
+            max_streams: env_or("DEMOS3D_MAX_STREAMS", default.max_streams),
+            require_resource_dir: env_or(
+                "DEMOS3D_REQUIRE_RESOURCE_DIR",
+                default.require_resource_dir,
+            ),
+            max_tasks: env_or("DEMOS3D_MAX_TASKS", default.max_tasks),
+            task_limit_policy: env_or("DEMOS3D_TASK_LIMIT_POLICY", default.task_limit_policy),
+            tls_cert: std::env::var("DEMOS3D_TLS_CERT")
+                .ok()
+                .filter(|path| !path.trim().is_empty()),
+            tls_key: std::env::var("DEMOS3D_TLS_KEY")
+                .ok()
+                .filter(|path| !path.trim().is_empty()),
+            default_chunk_format: env_or(
+                "DEMOS3D_DEFAULT_CHUNK_FORMAT",
+                default.default_chunk_format,
+            ),
+            recent_chunk_cache: env_or("DEMOS3D_RECENT_CHUNK_CACHE", default.recent_chunk_cache),
+            max_performance_records: env_or(
+                "DEMOS3D_MAX_PERF_RECORDS",
+                default.max_performance_records,
+            ),
+            perf_channel_ids: env_or("DEMOS3D_PERF_CHANNEL_IDS", default.perf_channel_ids),
+            keep_alive: env_secs_or("DEMOS3D_KEEP_ALIVE_SECS", default.keep_alive),
+            client_request_timeout: env_millis_or(
+                "DEMOS3D_REQUEST_TIMEOUT_MS",
+                default.client_request_timeout,
+            ),
+            client_disconnect_timeout: env_millis_or(
+                "DEMOS3D_DISCONNECT_TIMEOUT_MS",
+                default.client_disconnect_timeout,
+            ),
+            auto_session_id: env_or("DEMOS3D_AUTO_SESSION_ID", default.auto_session_id),
+            max_chunk_wait: env_millis_or("DEMOS3D_MAX_CHUNK_WAIT_MS", default.max_chunk_wait),
+            stream_chunk_values: env_or("DEMOS3D_STREAM_CHUNK_VALUES", default.stream_chunk_values),
+            parse_buffer_bytes: env_or("DEMOS3D_PARSE_BUFFER_BYTES", default.parse_buffer_bytes),
+            parser_check_interval: env_secs_or(
+                "DEMOS3D_PARSER_CHECK_SECS",
+                default.parser_check_interval,
+            ),
+            parser_fixture_dir: std::env::var("DEMOS3D_PARSER_FIXTURE_DIR")
+                .ok()
+                .filter(|dir| !dir.trim().is_empty()),
+            parser_plugin_dir: std::env::var("DEMOS3D_PARSER_PLUGIN_DIR")
+                .ok()
+                .filter(|dir| !dir.trim().is_empty()),
+            append_poll_interval: env_secs_or(
+                "DEMOS3D_APPEND_POLL_SECS",
+                default.append_poll_interval,
+            ),
+            chunk_failure_fill: env_opt("DEMOS3D_CHUNK_FAILURE_FILL"),
+        }
+    }
+}
+
+/// 读取环境变量并解析为指定类型，缺失或解析失败时返回默认值
+fn env_or<T: FromStr>(key: &str, default: T) -> T {
+    match std::env::var(key) {
+        Ok(raw) => match raw.trim().parse::<T>() {
+            Ok(value) => value,
+            Err(_) => {
+                eprintln!("[配置] 环境变量 {key}={raw} 无法解析，使用默认值");
+                default
+            }
+        },
+        Err(_) => default,
+    }
+}
+
+/// 读取可选的配置，未设置或为空时返回 None，解析失败时同样返回 None 并记录日志
+fn env_opt<T: FromStr>(key: &str) -> Option<T> {
+    let raw = std::env::var(key).ok().filter(|raw| !raw.trim().is_empty())?;
+    match raw.trim().parse::<T>() {
+        Ok(value) => Some(value),
+        Err(_) => {
+            eprintln!("[配置] 环境变量 {key}={raw} 无法解析，视为未设置");
+            None
+        }
+    }
+}
+
+/// 读取以秒为单位的时长配置
+fn env_secs_or(key: &str, default: Duration) -> Duration {
+    Duration::from_secs(env_or(key, default.as_secs()))
+}
+
+/// 读取以毫秒为单位的时长配置
+fn env_millis_or(key: &str, default: Duration) -> Duration {
+    Duration::from_millis(env_or(key, default.as_millis() as u64))
+}
+
+/// 默认解析并发数：CPU 核数
+fn default_parse_workers() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(4)
+}
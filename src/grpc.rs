@@ -0,0 +1,291 @@
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use actix_web::HttpResponse;
+use actix_web::body::MessageBody;
+use actix_web::http::StatusCode;
+use actix_web::rt::System;
+use actix_web::web;
+use tokio::sync::{mpsc, oneshot};
+use tokio_stream::Stream;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+
+use crate::app_state::AppState;
+use crate::cancel::CancelToken;
+use crate::handlers::preprocess::{PreprocessOptions, load_resident_window, preprocess_file};
+use crate::parse_queue::ParsePriority;
+use crate::stream_limit::StreamSubscription;
+
+mod proto {
+    tonic::include_proto!("demos3d");
+}
+
+use proto::voxel_grid_service_server::{VoxelGridService, VoxelGridServiceServer};
+use proto::{
+    Chunk, PreprocessReply, PreprocessRequest, StatusReply, StatusRequest, StreamChunksRequest,
+};
+
+/// 流式返回 chunk 时，没有新 chunk 就绪的情况下再次检查的间隔
+const STREAM_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// 流式返回 chunk 的通道容量（背压：客户端读取慢时暂停取出 chunk）
+const STREAM_BUFFER: usize = 4;
+
+/// gRPC 服务，与 HTTP 接口共享同一个 `AppState`
+pub struct GrpcService {
+    app_state: web::Data<AppState>,
+    /// actix 主系统：解析流程通过 actix 的本地任务派生后台任务，
+    /// 不能直接在 tonic 的 tokio 任务中执行，需要转交给 actix 的 arbiter
+    system: System,
+}
+
+impl GrpcService {
+    /// 在 actix 运行时中执行 `future` 并等待其结果
+    async fn on_actix<T: Send + 'static>(
+        &self,
+        future: impl Future<Output = T> + Send + 'static,
+    ) -> Result<T, Status> {
+        let (sender, receiver) = oneshot::channel();
+        self.system.arbiter().spawn(async move {
+            let _ = sender.send(future.await);
+        });
+        receiver
+            .await
+            .map_err(|_| Status::internal("actix 运行时已停止"))
+    }
+}
+
+/// 占用流式订阅名额的 chunk 流：名额随流一起释放（流结束或客户端断开时）
+pub struct ChunkStream {
+    inner: ReceiverStream<Result<Chunk, Status>>,
+    _subscription: StreamSubscription,
+}
+
+impl Stream for ChunkStream {
+    type Item = Result<Chunk, Status>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.inner).poll_next(cx)
+    }
+}
+
+/// 将 HTTP 层的错误响应转换为 gRPC 状态，消息为原始 JSON 错误体
+fn http_error_to_status(response: HttpResponse) -> Status {
+    let code = match response.status() {
+        StatusCode::BAD_REQUEST => tonic::Code::InvalidArgument,
+        StatusCode::NOT_FOUND => tonic::Code::NotFound,
+        StatusCode::SERVICE_UNAVAILABLE => tonic::Code::Unavailable,
+        _ => tonic::Code::Internal,
+    };
+    let message = response
+        .into_body()
+        .try_into_bytes()
+        .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+        .unwrap_or_default();
+    Status::new(code, message)
+}
+
+#[tonic::async_trait]
+impl VoxelGridService for GrpcService {
+    async fn preprocess(
+        &self,
+        request: Request<PreprocessRequest>,
+    ) -> Result<Response<PreprocessReply>, Status> {
+        let request = request.into_inner();
+        let priority = match request.priority.as_str() {
+            "" | "normal" => ParsePriority::Normal,
+            "low" => ParsePriority::Low,
+            "high" => ParsePriority::High,
+            other => {
+                return Err(Status::invalid_argument(format!(
+                    "无效的 priority '{other}'，可选 low / normal / high"
+                )));
+            }
+        };
+        let options = PreprocessOptions {
+            priority,
+            round_decimals: request.round_decimals,
+            omit_chunks: true,
+            ..Default::default()
+        };
+
+        let app_state = self.app_state.clone();
+        let result = self
+            .on_actix(async move {
+                preprocess_file(
+                    &app_state,
+                    &request.file,
+                    request.chunk_size as usize,
+                    request.session_id,
+                    false,
+                    options,
+                    CancelToken::new(),
+                )
+                .await
+                .map_err(http_error_to_status)
+            })
+            .await??;
+
+        Ok(Response::new(PreprocessReply {
+            task_id: result.task_id,
+            file_size: result.file_size,
+            shape: result.shape.iter().map(|&n| n as u64).collect(),
+            data_length: result.data_length as u64,
+            chunk_size: result.chunk_size as u64,
+            chunk_count: result.chunk_count as u64,
+            cell_volume: result.cell_volume,
+        }))
+    }
+
+    type StreamChunksStream = ChunkStream;
+
+    async fn stream_chunks(
+        &self,
+        request: Request<StreamChunksRequest>,
+    ) -> Result<Response<Self::StreamChunksStream>, Status> {
+        let task_id = request.into_inner().task_id;
+        let Some(task) = self.app_state.task_store.get(&task_id) else {
+            return Err(Status::not_found(format!("无效的 task_id: {task_id}")));
+        };
+        // 超出订阅上限时以 RESOURCE_EXHAUSTED 拒绝，名额在流结束（或客户端断开）时归还
+        let subscription = self
+            .app_state
+            .stream_limits
+            .try_subscribe(&task)
+            .map_err(|e| Status::resource_exhausted(e.to_string()))?;
+
+        let (sender, receiver) = mpsc::channel(STREAM_BUFFER);
+        let app_state = self.app_state.clone();
+        // 生产者在 actix 运行时中执行：驻留窗口模式需要按需读取 chunk
+        self.system.arbiter().spawn(async move {
+            // 只流式返回尚未被请求的 chunk；仍在解析中的 chunk 留到下一轮再试
+            let mut pending: Vec<usize> = task
+                .chunks
+                .iter()
+                .map(|descriptor| descriptor.index)
+                .filter(|index| task.chunk_data.read().contains_key(index))
+                .collect();
+
+            while !pending.is_empty() {
+                if let Some(reason) = task.failure() {
+                    let _ = sender
+                        .send(Err(Status::internal(format!("任务解析失败: {reason}"))))
+                        .await;
+                    return;
+                }
+
+                let mut progressed = false;
+                let mut remaining = Vec::with_capacity(pending.len());
+                for index in pending {
+                    if task.resident_window.is_some()
+                        && !task.is_chunk_ready(index)
+                        && task.chunk_data.read().contains_key(&index)
+                        && let Err(e) = load_resident_window(&app_state, &task, index).await
+                    {
+                        let _ = sender
+                            .send(Err(Status::internal(format!("读取 chunk 失败: {e}"))))
+                            .await;
+                        return;
+                    }
+
+                    // 分割失败的 chunk 不会再就绪，与 HTTP chunk 接口一致返回失败原因
+                    if let Some(reason) = task.chunk_failure(index) {
+                        let _ = sender
+                            .send(Err(Status::internal(format!(
+                                "chunk {index} 分割失败: {reason}"
+                            ))))
+                            .await;
+                        return;
+                    }
+
+                    // take_chunk 会移除未就绪的条目，必须先确认已就绪（与 HTTP chunk 接口一致）
+                    if !task.is_chunk_ready(index) {
+                        if task.chunk_data.read().contains_key(&index) {
+                            remaining.push(index);
+                        }
+                        continue;
+                    }
+
+                    // 已被其他请求取走的 chunk 直接跳过
+                    if let Some(values) = task.take_chunk(index) {
+                        let descriptor = &task.chunks[index];
+                        let chunk = Chunk {
+                            index: descriptor.index as u64,
+                            start: descriptor.start as u64,
+                            end: descriptor.end as u64,
+                            values,
+                        };
+                        // 客户端已断开
+                        if sender.send(Ok(chunk)).await.is_err() {
+                            return;
+                        }
+                        progressed = true;
+                    }
+                }
+                pending = remaining;
+
+                if !progressed && !pending.is_empty() {
+                    actix_web::rt::time::sleep(STREAM_POLL_INTERVAL).await;
+                }
+            }
+        });
+
+        Ok(Response::new(ChunkStream {
+            inner: ReceiverStream::new(receiver),
+            _subscription: subscription,
+        }))
+    }
+
+    async fn get_status(
+        &self,
+        request: Request<StatusRequest>,
+    ) -> Result<Response<StatusReply>, Status> {
+        let task_id = request.into_inner().task_id;
+        let Some(task) = self.app_state.task_store.get(&task_id) else {
+            return Err(Status::not_found(format!("无效的 task_id: {task_id}")));
+        };
+
+        let total_chunks = task.chunks.len();
+        let remaining_chunks = task.remaining_chunk_count();
+        let ready_chunks = task.ready_chunk_count();
+        Ok(Response::new(StatusReply {
+            status: task.status_label().to_string(),
+            total_chunks: total_chunks as u64,
+            ready_chunks: ready_chunks as u64,
+            pending_chunks: (remaining_chunks - ready_chunks) as u64,
+            consumed_chunks: (total_chunks - remaining_chunks) as u64,
+            data_hash: task.data_hash_hex(),
+            error: task.failure().map(str::to_string),
+            percent: task.progress_percent(),
+            integral: task.integral(),
+        }))
+    }
+}
+
+/// 在 `config.grpc_port` 上启动 gRPC 服务（在当前 actix 系统中后台运行）
+pub fn spawn_server(app_state: web::Data<AppState>) -> std::io::Result<()> {
+    let config = &app_state.config;
+    let addr: SocketAddr = format!("{}:{}", config.host, config.grpc_port)
+        .parse()
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+    let service = GrpcService {
+        app_state: app_state.clone(),
+        system: System::current(),
+    };
+
+    println!("gRPC 服务启动在 {addr}");
+    actix_web::rt::spawn(async move {
+        if let Err(e) = tonic::transport::Server::builder()
+            .add_service(VoxelGridServiceServer::new(service))
+            .serve(addr)
+            .await
+        {
+            eprintln!("[gRPC] 服务异常退出: {e}");
+        }
+    });
+    Ok(())
+}
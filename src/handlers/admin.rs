@@ -0,0 +1,120 @@
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::HeaderMap;
+use actix_web::middleware::Next;
+use actix_web::{Error, HttpResponse, Responder, post, web};
+use serde::Deserialize;
+
+use crate::app_state::AppState;
+
+/// 管理接口鉴权中间件：要求 `Authorization: Bearer <DEMOS3D_ADMIN_TOKEN>`
+///
+/// 未配置令牌时管理接口整体不可用（返回 404），避免暴露在公开接口中
+pub async fn require_admin_token(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let expected = req
+        .app_data::<web::Data<AppState>>()
+        .and_then(|data| data.config.admin_token.clone());
+
+    if let Err(response) = check_admin_token(expected.as_deref(), req.headers(), req.path()) {
+        return Ok(req.into_response(response).map_into_right_body());
+    }
+
+    next.call(req).await.map(ServiceResponse::map_into_left_body)
+}
+
+/// 校验请求头中的管理令牌，供中间件与需要管理权限的普通接口选项（如预处理的 `pinned`）共用
+///
+/// 未配置令牌时返回 404，令牌缺失或不匹配时返回 401
+pub fn check_admin_token(
+    expected: Option<&str>,
+    headers: &HeaderMap,
+    path: &str,
+) -> Result<(), HttpResponse> {
+    let Some(expected) = expected else {
+        return Err(HttpResponse::NotFound().json(serde_json::json!({
+            "error": "管理接口未启用",
+            "message": "设置 DEMOS3D_ADMIN_TOKEN 后可用",
+        })));
+    };
+
+    let provided = headers
+        .get("Authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    if provided != Some(expected) {
+        eprintln!("[管理接口] 鉴权失败: {path}");
+        return Err(HttpResponse::Unauthorized().json(serde_json::json!({
+            "error": "管理接口鉴权失败",
+        })));
+    }
+    Ok(())
+}
+
+/// 立即执行一次过期清理（任务与性能数据会话），不必等待定期清理
+#[post("/cleanup")]
+pub async fn admin_cleanup(data: web::Data<AppState>) -> impl Responder {
+    let tasks_reclaimed = data.task_store.cleanup_expired();
+    let sessions_reclaimed = data.performance_store.cleanup_expired();
+    let tasks_remaining = data.task_store.task_count();
+    let sessions_remaining = data.performance_store.session_count();
+
+    println!(
+        "[管理接口] 手动清理: 任务 {tasks_reclaimed} 个（剩余 {tasks_remaining}），性能会话 {sessions_reclaimed} 个（剩余 {sessions_remaining}）"
+    );
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "tasks": {
+            "reclaimed": tasks_reclaimed,
+            "remaining": tasks_remaining,
+        },
+        "performance_sessions": {
+            "reclaimed": sessions_reclaimed,
+            "remaining": sessions_remaining,
+        },
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct PinRequest {
+    pub task_id: String,
+}
+
+/// 固定任务：固定后不会因 TTL 过期被清理，也不会因任务数上限被移除，用于常驻的参考数据
+#[post("/pin")]
+pub async fn admin_pin(data: web::Data<AppState>, payload: web::Json<PinRequest>) -> impl Responder {
+    set_task_pinned(&data, &payload.task_id, true)
+}
+
+/// 取消固定：任务重新按创建时间计算 TTL，已超过 TTL 的任务随即视为过期
+#[post("/unpin")]
+pub async fn admin_unpin(
+    data: web::Data<AppState>,
+    payload: web::Json<PinRequest>,
+) -> impl Responder {
+    set_task_pinned(&data, &payload.task_id, false)
+}
+
+fn set_task_pinned(data: &AppState, task_id: &str, pinned: bool) -> HttpResponse {
+    let Some(task) = data.task_store.get(task_id) else {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "无效的 task_id",
+            "task_id": task_id,
+        }));
+    };
+
+    let previous = task.is_pinned();
+    task.set_pinned(pinned);
+    if previous != pinned {
+        let action = if pinned { "固定" } else { "取消固定" };
+        println!("[管理接口] 已{action}任务 {task_id}");
+    }
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "task_id": task_id,
+        "pinned": pinned,
+        "previous": previous,
+    }))
+}
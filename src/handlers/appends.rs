@@ -0,0 +1,103 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use actix_web::{HttpResponse, Responder, get, web};
+use serde::Deserialize;
+
+use crate::app_state::AppState;
+use crate::task::{AppendState, TaskData};
+
+#[derive(Deserialize)]
+pub struct AppendsQuery {
+    pub task_id: String,
+    /// 只返回序号大于该值的追加，默认 0（返回全部追加）
+    #[serde(default)]
+    pub since: usize,
+    /// 没有新的追加时最多等待的毫秒数（长轮询），不超过配置的 `max_chunk_wait`，缺省或为 0 时立即返回
+    #[serde(default)]
+    pub wait_ms: Option<u64>,
+}
+
+/// 查询固定任务随文件增长追加的 z 层及对应的新 chunk
+///
+/// 客户端保存上一次响应的 `generation`，作为下一次请求的 `since`，即可只拉取新追加的 chunk；
+/// 指定 `wait_ms` 时在有新的追加（或任务被取消、失败）之前挂起请求
+#[get("/voxel-grid/appends")]
+pub async fn get_appends(
+    data: web::Data<AppState>,
+    query: web::Query<AppendsQuery>,
+) -> impl Responder {
+    let wait = query
+        .wait_ms
+        .map(Duration::from_millis)
+        .unwrap_or_default()
+        .min(data.config.max_chunk_wait);
+    let deadline = Instant::now() + wait;
+
+    loop {
+        let Some(task) = data.task_store.get(&query.task_id) else {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "无效的 task_id",
+                "task_id": query.task_id,
+            }));
+        };
+        let Some(state) = task.append.lock().clone() else {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "任务不支持追加",
+                "task_id": query.task_id,
+                "message": "只有支持追加的文件（如 stack 模式的文本网格插件）在按原样预处理时才能追加",
+            }));
+        };
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if state.generation() > query.since || task.status_label() != "ready" || remaining.is_zero()
+        {
+            return appends_response(&query.task_id, &task, &state, query.since);
+        }
+        // 任务被追加后的新任务替换时，原任务会唤醒等待者，重新查找任务
+        let store = data.task_store.clone();
+        let task_id = query.task_id.clone();
+        let current = task.clone();
+        let replaced = move || {
+            !store
+                .get(&task_id)
+                .is_some_and(|task| Arc::ptr_eq(&task, &current))
+        };
+        if !task.wait_for_event(remaining, replaced).await {
+            return appends_response(&query.task_id, &task, &state, query.since);
+        }
+    }
+}
+
+/// 序号大于 `since` 的追加，每次追加附带新增 chunk 的描述
+fn appends_response(
+    task_id: &str,
+    task: &TaskData,
+    state: &AppendState,
+    since: usize,
+) -> HttpResponse {
+    let appends: Vec<_> = state
+        .appends
+        .iter()
+        .filter(|record| record.generation > since)
+        .map(|record| {
+            let chunks = &task.chunks[record.first_chunk..record.first_chunk + record.chunk_count];
+            serde_json::json!({
+                "generation": record.generation,
+                "z_start": record.z_start,
+                "layers": record.layers,
+                "appended_at": record.appended_at,
+                "chunks": chunks,
+            })
+        })
+        .collect();
+    HttpResponse::Ok().json(serde_json::json!({
+        "task_id": task_id,
+        "status": task.status_label(),
+        "generation": state.generation(),
+        "since": since,
+        "shape": task.shape,
+        "data_length": task.data_length(),
+        "chunk_count": task.chunks.len(),
+        "appends": appends,
+    }))
+}
@@ -0,0 +1,42 @@
+use actix_web::{HttpResponse, Responder, post, web};
+use serde::Deserialize;
+
+use crate::app_state::AppState;
+
+#[derive(Deserialize)]
+pub struct CancelRequest {
+    pub task_id: String,
+}
+
+/// 取消任务：停止后台解析并释放已就绪的 chunk
+///
+/// 后台解析在下一个阶段边界（开始解析前、解析完成后、分割每个 chunk 前）停止；
+/// 与其他任务共享的解析只有在所有订阅者都取消后才会放弃。任务保留到过期，状态为 `cancelled`
+#[post("/voxel-grid/cancel")]
+pub async fn cancel_task(
+    data: web::Data<AppState>,
+    payload: web::Json<CancelRequest>,
+) -> impl Responder {
+    let Some(task) = data.task_store.get(&payload.task_id) else {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "无效的 task_id",
+            "task_id": payload.task_id,
+        }));
+    };
+
+    let previous_status = task.status_label();
+    let cancelled = task.cancel();
+    if cancelled {
+        println!(
+            "[取消] 任务 {} 已取消（取消前状态: {previous_status}）",
+            payload.task_id
+        );
+    }
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "task_id": payload.task_id,
+        "status": task.status_label(),
+        "previous_status": previous_status,
+        "already_cancelled": !cancelled,
+    }))
+}
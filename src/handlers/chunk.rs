@@ -1,9 +1,117 @@
-use actix_web::{HttpResponse, Responder, get, http::header::ContentType, web};
-use byteorder::{LittleEndian, WriteBytesExt};
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use actix_web::body::{BodySize, MessageBody};
+use actix_web::http::header::{self, Accept, Header, Quality};
+use actix_web::mime::{self, Mime};
+use actix_web::web::Bytes;
+use actix_web::{HttpRequest, HttpResponse, Responder, get, web};
 use serde::Deserialize;
 
 use crate::app_state::AppState;
-use crate::performance::{get_thread_id, get_unix_timestamp_ms, PerformanceRecord};
+use crate::handlers::preprocess::load_resident_window;
+use crate::performance::{
+    get_unix_timestamp_ms, resolve_session_id, PerformanceRecord, SESSION_ID_HEADER,
+};
+use crate::request_metrics::EncodeTime;
+use crate::task::TaskData;
+use crate::utils::axis_order::{AxisOrder, transpose};
+use crate::utils::chunk_encoding::{
+    ARROW_STREAM_CONTENT_TYPE, ChunkEncoding, ChunkStream, encode_chunk,
+};
+use crate::utils::stats::GridStats;
+use crate::utils::voxel_grid::{window_value, window_values};
+
+/// chunk 响应格式
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChunkFormat {
+    /// 小端序 Float64Array（默认）
+    #[default]
+    Binary,
+    /// 小端序 Float32Array，体积减半
+    F32,
+    /// JSON 数组，便于调试；受 `max_json_chunk_values` 限制
+    Json,
+    /// Arrow IPC stream（单列 Float64），需要以 `arrow` feature 编译
+    Arrow,
+}
+
+impl ChunkFormat {
+    /// 与 MIME 类型对应的格式；`f32` 没有专用的 MIME 类型，只能通过 `format` 参数选择
+    fn from_mime(mime: &Mime, binary_mime: &Mime) -> Option<Self> {
+        match mime.essence_str() {
+            "application/octet-stream" => Some(ChunkFormat::Binary),
+            "application/json" => Some(ChunkFormat::Json),
+            ARROW_STREAM_CONTENT_TYPE => Some(ChunkFormat::Arrow),
+            essence if essence == binary_mime.essence_str() => Some(ChunkFormat::Binary),
+            _ => None,
+        }
+    }
+
+    /// 当前编译的服务端能否提供该格式
+    fn is_available(self) -> bool {
+        self != ChunkFormat::Arrow || cfg!(feature = "arrow")
+    }
+}
+
+impl FromStr for ChunkFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "binary" => Ok(Self::Binary),
+            "f32" => Ok(Self::F32),
+            "json" => Ok(Self::Json),
+            "arrow" => Ok(Self::Arrow),
+            other => Err(format!("未知的 chunk 格式: {other}")),
+        }
+    }
+}
+
+/// 确定 chunk 响应格式，所有格式选择都经过这里：
+///
+/// 1. 请求参数 `format`（显式指定时总是生效）
+/// 2. `Accept` 头：按 q 值（相同时具体类型优先）从高到低取第一个能提供的格式，q=0 的类型被排除；
+///    `*/*` 与 `application/*` 表示接受默认格式
+/// 3. 配置的默认格式（`DEMOS3D_DEFAULT_CHUNK_FORMAT`）
+///
+/// `Accept` 头列出了类型但都无法提供时返回 None（406）
+fn negotiate_format(
+    requested: Option<ChunkFormat>,
+    accept: Option<&Accept>,
+    default: ChunkFormat,
+    binary_mime: &Mime,
+) -> Option<ChunkFormat> {
+    if let Some(format) = requested {
+        return Some(format);
+    }
+    let Some(accept) = accept.filter(|accept| !accept.is_empty()) else {
+        return Some(default);
+    };
+
+    let refused: Vec<&Mime> = accept
+        .iter()
+        .filter(|item| item.quality == Quality::ZERO)
+        .map(|item| &item.item)
+        .collect();
+    accept
+        .ranked()
+        .into_iter()
+        .filter(|mime| !refused.contains(&mime))
+        .find_map(|mime| {
+            let wildcard = mime.type_() == mime::STAR
+                || (mime.type_() == mime::APPLICATION && mime.subtype() == mime::STAR);
+            if wildcard {
+                Some(default)
+            } else {
+                ChunkFormat::from_mime(&mime, binary_mime).filter(|format| format.is_available())
+            }
+        })
+}
 
 #[derive(Deserialize)]
 pub struct ChunkQuery {
@@ -11,16 +119,143 @@ pub struct ChunkQuery {
     pub chunk_index: usize,
     #[serde(default)]
     pub session_id: Option<String>,
+    /// 响应格式；缺省时按 `Accept` 头协商，见 `negotiate_format`
+    #[serde(default)]
+    pub format: Option<ChunkFormat>,
+    /// 覆盖二进制格式（`binary` / `f32`）响应的 Content-Type，必须是 `application/*`
+    #[serde(default)]
+    pub content_type: Option<String>,
+    /// 所有元素都相同的 chunk 返回 204 与 `X-Chunk-Fill` 头而不返回数据，由客户端自行填充
+    #[serde(default)]
+    pub skip_uniform: bool,
+    /// 只返回 `values[0], values[stride], values[2*stride], ...`，用于 chunk 内的渐进加载（先粗后细）
+    /// 指定时不会消费 chunk，之后仍可请求完整数据
+    #[serde(default)]
+    pub stride: Option<usize>,
+    /// tile 模式下把 tile 内的体素重排为指定的轴顺序（按变化从快到慢，如 `zyx`），默认 `xyz`
+    #[serde(default)]
+    pub axis_order: Option<String>,
+    /// chunk 仍在解析中时最多等待的毫秒数（长轮询），期间就绪则直接返回数据；
+    /// 不超过配置的 `max_chunk_wait`，缺省或为 0 时立即返回 202
+    #[serde(default)]
+    pub wait_ms: Option<u64>,
+    /// 窗宽：与 `level` 一起指定时，把 `[level - window/2, level + window/2]` 线性映射到 `[0, 1]`（范围外截断），
+    /// 逐 chunk 应用，必须为正的有限数
+    #[serde(default)]
+    pub window: Option<f64>,
+    /// 窗位（窗口中心），与 `window` 同时指定
+    #[serde(default)]
+    pub level: Option<f64>,
+    /// 为 true 时始终完整组装 body 并返回 `X-Chunk-Checksum`，即使超过流式发送的阈值
+    #[serde(default)]
+    pub checksum: bool,
+}
+
+/// 按步长抽取 `values[0], values[stride], ...`
+fn strided(values: &[f64], stride: usize) -> Vec<f64> {
+    values.iter().step_by(stride).copied().collect()
+}
+
+/// 被消费的 chunk 的 body 每次交给连接的字节数
+///
+/// 连接的写缓冲区满时会先写出再继续读取 body，分段发送使"body 读取完毕"基本等价于数据已写入 socket
+const DELIVERY_SEGMENT_BYTES: usize = 64 * 1024;
+
+/// chunk 响应体的内容
+enum ChunkPayload {
+    /// 已完整序列化的字节
+    Encoded(Bytes),
+    /// 边发送边序列化（JSON / Arrow 格式的大 chunk，见 `stream_chunk_values`）
+    Streamed(ChunkStream),
+}
+
+/// chunk 的响应体：分段交给连接；被消费的 chunk 在全部交付之前被丢弃（客户端中途断开、连接出错）时放回任务，
+/// 客户端可以重新请求，数据不会因传输失败而丢失
+struct ChunkBody {
+    payload: ChunkPayload,
+    /// 尚未交付完成时需要放回的 chunk；body 读取完毕后置为 None
+    restore: Option<(Arc<TaskData>, usize)>,
+    /// 放回时使用的数据；为 None 时取流式序列化自身的数据（未重排轴顺序、未应用窗宽窗位时二者相同，无需另存一份）
+    original: Option<Vec<f64>>,
+}
+
+impl MessageBody for ChunkBody {
+    type Error = std::io::Error;
+
+    fn size(&self) -> BodySize {
+        match &self.payload {
+            ChunkPayload::Encoded(bytes) => BodySize::Sized(bytes.len() as u64),
+            ChunkPayload::Streamed(_) => BodySize::Stream,
+        }
+    }
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        let next = match &mut self.payload {
+            ChunkPayload::Encoded(remaining) if remaining.is_empty() => None,
+            ChunkPayload::Encoded(remaining) => {
+                let segment = remaining.len().min(DELIVERY_SEGMENT_BYTES);
+                Some(Ok(remaining.split_to(segment)))
+            }
+            // 序列化失败时响应头已经发出，只能中断连接，chunk 随后被放回
+            ChunkPayload::Streamed(stream) => stream
+                .next_segment()
+                .map(|segment| segment.map_err(std::io::Error::other)),
+        };
+        if next.is_none() {
+            self.restore = None;
+        }
+        Poll::Ready(next)
+    }
+}
+
+impl Drop for ChunkBody {
+    fn drop(&mut self) {
+        let Some((task, chunk_index)) = self.restore.take() else {
+            return;
+        };
+        let values = match (self.original.take(), &mut self.payload) {
+            (Some(values), _) => values,
+            (None, ChunkPayload::Streamed(stream)) => stream.take_values(),
+            (None, ChunkPayload::Encoded(_)) => return,
+        };
+        eprintln!(
+            "[Chunk接口] chunk {chunk_index} 未能完整发送（客户端可能已断开），已放回任务，可重新请求"
+        );
+        task.restore_chunk(chunk_index, values);
+    }
+}
+
+/// 解析二进制 chunk 响应的 Content-Type：请求参数优先，其次为配置项
+///
+/// 请求参数无效时返回错误信息；配置项无效时回退到 `application/octet-stream`
+fn binary_content_type(requested: Option<&str>, configured: &str) -> Result<Mime, String> {
+    if let Some(requested) = requested {
+        return match requested.parse::<Mime>() {
+            Ok(mime) if mime.type_() == actix_web::mime::APPLICATION => Ok(mime),
+            _ => Err(format!("无效的 content_type '{requested}'，必须是 application/* 类型")),
+        };
+    }
+    match configured.parse::<Mime>() {
+        Ok(mime) => Ok(mime),
+        Err(_) => {
+            eprintln!("警告: 配置的 binary_content_type '{configured}' 无效，使用 application/octet-stream");
+            Ok(actix_web::mime::APPLICATION_OCTET_STREAM)
+        }
+    }
 }
 
 #[get("/voxel-grid/chunk")]
 pub async fn get_voxel_chunk(
+    req: HttpRequest,
     data: web::Data<AppState>,
     query: web::Query<ChunkQuery>,
 ) -> impl Responder {
     let start_time = get_unix_timestamp_ms();
-    let thread_id = get_thread_id();
-    let channel_index = format!("get_chunk_{}", thread_id);
+    let channel_id = data.config.perf_channel_ids.channel_id(Some(query.chunk_index));
+    let channel_index = format!("get_chunk_{}", channel_id);
     
     let Some(task) = data.task_store.get(&query.task_id) else {
         return HttpResponse::BadRequest().json(serde_json::json!({
@@ -36,8 +271,192 @@ pub async fn get_voxel_chunk(
         }));
     };
 
+    // Content-Type 覆盖同样在取出数据之前校验
+    let binary_mime = match binary_content_type(
+        query.content_type.as_deref(),
+        &data.config.binary_content_type,
+    ) {
+        Ok(mime) => mime,
+        Err(e) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": e,
+                "content_type": query.content_type,
+            }));
+        }
+    };
+
+    // 无法解析的 Accept 头按未提供处理
+    let accept = Accept::parse(&req).ok();
+    let Some(format) = negotiate_format(
+        query.format,
+        accept.as_ref(),
+        data.config.default_chunk_format,
+        &binary_mime,
+    ) else {
+        let mut available = vec![binary_mime.to_string(), mime::APPLICATION_JSON.to_string()];
+        if cfg!(feature = "arrow") {
+            available.push(ARROW_STREAM_CONTENT_TYPE.to_string());
+        }
+        return HttpResponse::NotAcceptable().json(serde_json::json!({
+            "error": "Accept 头中没有可提供的 chunk 格式",
+            "accept": accept.map(|accept| accept.to_string()),
+            "available": available,
+            "message": "请在 Accept 中加入上述类型之一或 */*，或使用 format 参数指定格式",
+        }));
+    };
+
+    if let Some(stride) = query.stride {
+        if stride == 0 {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "stride 必须大于 0",
+                "stride": stride,
+            }));
+        }
+        // Arrow 的 schema 元数据描述的是完整 chunk 的范围，与抽样后的数据不一致
+        if format == ChunkFormat::Arrow {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "stride 不支持 arrow 格式",
+                "message": "请改用 format=binary、format=f32 或 format=json",
+            }));
+        }
+    }
+
+    // 轴顺序只对 tile 有意义：线性分块的 chunk 不是长方体
+    let axis_order = match query.axis_order.as_deref().map(str::parse::<AxisOrder>) {
+        None => None,
+        Some(Err(e)) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": e,
+                "axis_order": query.axis_order,
+            }));
+        }
+        Some(Ok(_)) if descriptor.tile.is_none() => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "axis_order 仅适用于 tile 模式的任务",
+                "axis_order": query.axis_order,
+            }));
+        }
+        Some(Ok(_)) if query.stride.is_some() || format == ChunkFormat::Arrow => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "axis_order 不能与 stride 或 arrow 格式同时使用",
+                "axis_order": query.axis_order,
+            }));
+        }
+        Some(Ok(order)) => Some(order),
+    };
+
+    // 窗宽窗位：(level, window)，同样在取出数据之前校验
+    let window = match (query.level, query.window) {
+        (None, None) => None,
+        (Some(level), Some(window)) if level.is_finite() && window.is_finite() && window > 0.0 => {
+            Some((level, window))
+        }
+        (Some(_), Some(_)) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "window 必须是正的有限数，level 必须是有限数",
+                "window": query.window,
+                "level": query.level,
+            }));
+        }
+        _ => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "window 与 level 必须同时指定",
+                "window": query.window,
+                "level": query.level,
+            }));
+        }
+    };
+
+    // JSON 格式体积巨大，超过上限时直接拒绝（在取出数据之前检查，避免 chunk 被消费）
+    let chunk_length = descriptor.end - descriptor.start;
+    let served_length = query.stride.map_or(chunk_length, |stride| chunk_length.div_ceil(stride));
+    let max_json_values = data.config.max_json_chunk_values;
+    if format == ChunkFormat::Json && served_length > max_json_values {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "chunk 过大，无法以 JSON 格式返回",
+            "chunk_index": query.chunk_index,
+            "chunk_length": served_length,
+            "max_json_values": max_json_values,
+            "message": "请改用二进制格式（format=binary 或 format=f32），或使用更小的 chunk_size",
+        }));
+    }
+
+    if !format.is_available() {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "服务端未启用 Arrow 输出",
+            "message": "请以 --features arrow 重新编译服务端，或改用 format=binary",
+        }));
+    }
+
+    // 长轮询：chunk 仍在解析中时在上限内等待其就绪，减少客户端在解析期间的重试次数
+    // 驻留窗口模式下的 chunk 按需读取，不需要等待
+    let wait = query
+        .wait_ms
+        .map(Duration::from_millis)
+        .unwrap_or_default()
+        .min(data.config.max_chunk_wait);
+    if !wait.is_zero() && task.resident_window.is_none() {
+        task.wait_for_chunk(query.chunk_index, wait).await;
+    }
+
+    // 后台解析失败时 chunk 永远不会就绪，直接返回失败原因，避免客户端无限重试
+    if let Some(reason) = task.failure() {
+        return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "任务解析失败",
+            "task_id": query.task_id,
+            "status": "failed",
+            "details": reason,
+        }));
+    }
+
+    // 刚被请求过的 chunk 仍在最近请求缓存中时返回缓存的副本，客户端的重复请求不会失败
+    let recent = if task.chunk_data.read().contains_key(&query.chunk_index) {
+        None
+    } else {
+        task.recent_chunk(query.chunk_index)
+    };
+
+    // 驻留窗口模式：请求的 chunk 不在内存中（且未被请求过）时，从文件读取以它开始的窗口
+    if recent.is_none()
+        && task.resident_window.is_some()
+        && !task.is_chunk_ready(query.chunk_index)
+        && task.chunk_data.read().contains_key(&query.chunk_index)
+        && let Err(e) = load_resident_window(&data, &task, query.chunk_index).await
+    {
+        return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "读取 chunk 失败",
+            "task_id": query.task_id,
+            "chunk_index": query.chunk_index,
+            "details": e,
+        }));
+    }
+
+    // 分割或读取失败的 chunk 不会再就绪：配置了填充值时以填充值代替（降级），否则返回失败原因，
+    // 而不是让客户端继续重试；任务的其余 chunk 不受影响
+    let degraded = match task.chunk_failure(query.chunk_index).filter(|_| recent.is_none()) {
+        None => None,
+        Some(reason) => match data.config.chunk_failure_fill {
+            Some(fill) => {
+                eprintln!(
+                    "[Chunk接口] task_id: {}, chunk_index: {} 解析失败，以填充值 {fill} 代替: {reason}",
+                    query.task_id, query.chunk_index
+                );
+                Some(fill)
+            }
+            None => {
+                return HttpResponse::InternalServerError().json(serde_json::json!({
+                    "error": "chunk 分割失败",
+                    "task_id": query.task_id,
+                    "chunk_index": query.chunk_index,
+                    "status": "failed",
+                    "details": reason,
+                }));
+            }
+        },
+    };
+
     // 检查 chunk 是否已就绪（后台解析是否完成）
-    if !task.is_chunk_ready(query.chunk_index) {
+    if recent.is_none() && degraded.is_none() && !task.is_chunk_ready(query.chunk_index) {
         return HttpResponse::Accepted().json(serde_json::json!({
             "error": "chunk 正在解析中，请稍后重试",
             "task_id": query.task_id,
@@ -46,31 +465,120 @@ pub async fn get_voxel_chunk(
         }));
     }
 
-    // 获取并移除 chunk 数据（请求后立即释放内存）
-    // 如果 chunk 已被请求，take_chunk 会返回 None
-    let Some(chunk_values) = task.take_chunk(query.chunk_index) else {
-        return HttpResponse::BadRequest().json(serde_json::json!({
-            "error": "chunk 已被请求或不存在",
-            "task_id": query.task_id,
-            "chunk_index": query.chunk_index,
-        }));
+    // 获取并移除 chunk 数据（取出即占有，并发的重复请求不会重复下载）；响应体未能完整发送时会被放回
+    // 如果 chunk 已被请求，take_chunk 会返回 None；指定 stride 时只读取抽样，不移除数据
+    // 降级的 chunk 由填充值生成，可以重复请求，不消费任何数据
+    let consumed = query.stride.is_none() && recent.is_none() && degraded.is_none();
+    let taken = match (degraded, query.stride, recent.as_deref()) {
+        (Some(fill), _, _) => Some(vec![fill; served_length]),
+        (None, Some(stride), Some(values)) => Some(strided(values, stride)),
+        (None, Some(stride), None) => task.peek_chunk(query.chunk_index, |values| strided(values, stride)),
+        (None, None, Some(_)) => None,
+        (None, None, None) => task.take_chunk(query.chunk_index),
+    };
+    let chunk_values: &[f64] = match (&taken, recent.as_deref()) {
+        (Some(values), _) => values,
+        (None, Some(values)) => values,
+        (None, None) => {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "chunk 已被请求或不存在",
+                "task_id": query.task_id,
+                "chunk_index": query.chunk_index,
+            }));
+        }
+    };
+
+    // tile 内的体素按请求的轴顺序重排（tile 是较小的长方体，重排开销可以忽略）
+    let transposed = match (axis_order, descriptor.tile) {
+        (Some(order), Some(tile)) if !order.is_identity() => {
+            Some(transpose(chunk_values, tile.dims, order))
+        }
+        _ => None,
     };
+    // 窗宽窗位在重排之后逐 chunk 应用，只复制当前 chunk；发送失败时放回任务的仍是原始数据
+    let windowed = window.map(|(level, window)| {
+        let mut values = transposed.as_deref().unwrap_or(chunk_values).to_vec();
+        window_values(&mut values, level, window);
+        values
+    });
+    let transformed = windowed.or(transposed);
+    let chunk_values = transformed.as_deref().unwrap_or(chunk_values);
+
+    // 均匀 chunk 只需告知取值，无需序列化数据
+    let fill = query
+        .skip_uniform
+        .then(|| task.chunk_fill(query.chunk_index).or(degraded))
+        .flatten()
+        .map(|fill| window.map_or(fill, |(level, window)| window_value(fill, level, window)));
 
-    // 将 chunk 数据序列化为二进制格式
-    let mut bytes = Vec::with_capacity(chunk_values.len() * std::mem::size_of::<f64>());
-    for value in chunk_values {
-        if let Err(e) = bytes.write_f64::<LittleEndian>(value) {
+    // JSON / Arrow 的大 chunk 边序列化边发送，客户端无需等待整个 body 组装完成；
+    // 流式 body 无法在响应头中给出校验和，客户端要求校验和时仍完整组装
+    let value_count = chunk_values.len();
+    let stream_threshold = data.config.stream_chunk_values;
+    let streamed = fill.is_none()
+        && !query.checksum
+        && matches!(format, ChunkFormat::Json | ChunkFormat::Arrow)
+        && stream_threshold > 0
+        && value_count >= stream_threshold;
+
+    // 按请求格式序列化 chunk 数据
+    let encoding = match format {
+        ChunkFormat::Binary => ChunkEncoding::F64Le(binary_mime),
+        ChunkFormat::F32 => ChunkEncoding::F32Le(binary_mime),
+        ChunkFormat::Json => ChunkEncoding::Json,
+        ChunkFormat::Arrow => ChunkEncoding::Arrow {
+            descriptor,
+            shape: task.shape,
+        },
+    };
+    let content_type = encoding.content_type();
+    // 被消费的 chunk 发送失败时放回的数据（见 `ChunkBody`）
+    let mut original = None;
+    // 非流式响应在 handler 中完成序列化，耗时单独计入请求指标（流式响应边发送边序列化，不计入延迟）
+    let mut encode_time = None;
+    let encoded = if fill.is_some() {
+        Ok((ChunkPayload::Encoded(Bytes::new()), Default::default()))
+    } else if streamed {
+        // 流式序列化需要持有数据：变换（重排、窗宽窗位）后或取出的数据直接移入，最近请求缓存中的副本复制一份
+        let values = match (transformed, taken) {
+            (Some(values), taken) => {
+                original = taken.filter(|_| consumed);
+                values
+            }
+            (None, Some(values)) => values,
+            (None, None) => recent.as_deref().map(<[f64]>::to_vec).unwrap_or_default(),
+        };
+        ChunkStream::new(values, encoding)
+            .map(|(stream, headers)| (ChunkPayload::Streamed(stream), headers))
+    } else {
+        let encode_start = Instant::now();
+        let encoded = encode_chunk(chunk_values, encoding)
+            .map(|(bytes, headers)| (ChunkPayload::Encoded(Bytes::from(bytes)), headers));
+        encode_time = Some(EncodeTime(encode_start.elapsed()));
+        original = taken.filter(|_| consumed);
+        encoded
+    };
+    let (payload, encoding_headers) = match encoded {
+        Ok(encoded) => encoded,
+        Err(e) => {
             return HttpResponse::InternalServerError().json(serde_json::json!({
-                "error": "写入 chunk 数据失败",
-                "details": e.to_string(),
+                "error": "序列化 chunk 数据失败",
+                "details": e,
             }));
         }
-    }
+    };
 
     let end_time = get_unix_timestamp_ms();
     
+    // 客户端未指定 session_id 时沿用创建任务时的会话（或生成一个新的），通过 X-Session-Id 返回
+    let (session_id, assigned_session) = resolve_session_id(
+        query.session_id.as_deref(),
+        task.session_id.as_deref(),
+        data.config.auto_session_id,
+    );
+
     // 记录性能数据
-    if let Some(ref session_id) = query.session_id {
+    if let Some(ref session_id) = session_id {
         let record = PerformanceRecord {
             start_time,
             end_time,
@@ -81,18 +589,214 @@ pub async fn get_voxel_chunk(
         eprintln!("[性能数据记录] Chunk接口 - session_id: {}, channel_index: {}", session_id, channel_index);
         data.performance_store.add_record(session_id, record);
     } else {
-        eprintln!("[性能数据记录] Chunk接口 - session_id 为空，未记录性能数据");
+        eprintln!("[性能数据记录] Chunk接口 - session_id 为空且未开启自动生成，未记录性能数据");
+    }
+
+    eprintln!(
+        "[Chunk接口] task_id: {}, chunk_index: {}, Content-Type: {}",
+        query.task_id, query.chunk_index, content_type
+    );
+
+    let mut response = match fill {
+        Some(_) => HttpResponse::NoContent(),
+        None => HttpResponse::Ok(),
+    };
+    if let Some(data_hash) = task.data_hash_hex() {
+        response.append_header(("X-Data-Hash", data_hash));
+    }
+    if recent.is_some() {
+        response.append_header(("X-Chunk-Recent", "true"));
+    }
+    if degraded.is_some() {
+        response.append_header(("X-Chunk-Degraded", "true"));
+    }
+    if let Some(session_id) = session_id.filter(|_| assigned_session) {
+        response.append_header((SESSION_ID_HEADER, session_id));
+    }
+    if let Some((level, window)) = window {
+        response.append_header(("X-Window", window.to_string()));
+        response.append_header(("X-Level", level.to_string()));
+    }
+    if let Some(stride) = query.stride {
+        response.append_header(("X-Chunk-Stride", stride.to_string()));
+        response.append_header(("X-Chunk-Stride-Count", value_count.to_string()));
+    }
+    if let Some(tile) = descriptor.tile {
+        let join = |v: [usize; 3]| format!("{},{},{}", v[0], v[1], v[2]);
+        response.append_header(("X-Tile-Origin", join(tile.origin)));
+        response.append_header(("X-Tile-Dims", join(tile.dims)));
+        if let Some(order) = axis_order {
+            response.append_header(("X-Axis-Order", order.to_string()));
+        }
     }
 
-    HttpResponse::Ok()
-        .content_type(ContentType::octet_stream())
+    response
+        // 未指定 format 时响应格式取决于 Accept 头，缓存需要区分
+        .append_header((header::VARY, "Accept"))
         .append_header(("X-Chunk-Index", descriptor.index.to_string()))
         .append_header(("X-Chunk-Start", descriptor.start.to_string()))
         .append_header(("X-Chunk-End", descriptor.end.to_string()))
-        .append_header((
-            "X-Chunk-Length",
-            (descriptor.end - descriptor.start).to_string(),
-        ))
-        .append_header(("X-Chunk-Task", query.task_id.clone()))
-        .body(bytes)
+        .append_header(("X-Chunk-Length", chunk_length.to_string()))
+        .append_header(("X-Chunk-Task", query.task_id.clone()));
+    if let Some(fill) = fill {
+        return response
+            .append_header(("X-Chunk-Fill", fill.to_string()))
+            .finish();
+    }
+    for (name, value) in encoding_headers {
+        response.insert_header((name, value));
+    }
+    let restore = match &payload {
+        ChunkPayload::Encoded(bytes) if bytes.is_empty() => None,
+        _ => consumed.then(|| (task, query.chunk_index)),
+    };
+    let mut response = response.body(ChunkBody {
+        payload,
+        restore,
+        original,
+    });
+    if let Some(encode_time) = encode_time {
+        response.extensions_mut().insert(encode_time);
+    }
+    response
+}
+
+#[derive(Deserialize)]
+pub struct ChunkStatsQuery {
+    pub task_id: String,
+    pub chunk_index: usize,
+}
+
+/// 获取 chunk 的统计信息（min / max / mean / count），不会消费 chunk
+/// 客户端可据此决定下载哪些 chunk
+#[get("/voxel-grid/chunk/stats")]
+pub async fn get_voxel_chunk_stats(
+    data: web::Data<AppState>,
+    query: web::Query<ChunkStatsQuery>,
+) -> impl Responder {
+    let Some(task) = data.task_store.get(&query.task_id) else {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "无效的 task_id",
+            "task_id": query.task_id,
+        }));
+    };
+
+    if task.chunks.get(query.chunk_index).is_none() {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "无效的 chunk_index",
+            "chunk_index": query.chunk_index,
+        }));
+    }
+
+    if let Some(reason) = task.failure() {
+        return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "任务解析失败",
+            "task_id": query.task_id,
+            "status": "failed",
+            "details": reason,
+        }));
+    }
+
+    if let Some(stats) = task.peek_chunk(query.chunk_index, |values| {
+        GridStats::from_values(values.iter().copied())
+    }) {
+        return HttpResponse::Ok().json(serde_json::json!({
+            "task_id": query.task_id,
+            "chunk_index": query.chunk_index,
+            "min": stats.min,
+            "max": stats.max,
+            "mean": stats.mean,
+            "count": stats.count,
+        }));
+    }
+
+    // chunk 仍在 chunk_data 中说明还在解析；否则已被请求，数据已释放
+    if task.chunk_data.read().contains_key(&query.chunk_index) {
+        HttpResponse::Accepted().json(serde_json::json!({
+            "error": "chunk 正在解析中，请稍后重试",
+            "task_id": query.task_id,
+            "chunk_index": query.chunk_index,
+            "status": "processing",
+        }))
+    } else {
+        HttpResponse::NotFound().json(serde_json::json!({
+            "error": "chunk 已被请求，数据已释放",
+            "task_id": query.task_id,
+            "chunk_index": query.chunk_index,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::poll_fn;
+
+    use actix_web::http::StatusCode;
+    use actix_web::{App, test};
+
+    use super::*;
+    use crate::config::Config;
+    use crate::utils::chunk_encoding::{CHUNK_CHECKSUM_HEADER, chunk_checksum};
+    use crate::utils::voxel_grid::VoxelGrid;
+
+    /// 请求一个 20000 个元素的 JSON chunk（`query` 为附加的查询参数），逐帧读取 body，
+    /// 返回 `X-Chunk-Checksum`、body 的大小声明与所有帧
+    async fn fetch_json_chunk(
+        stream_chunk_values: usize,
+        query: &str,
+    ) -> (Option<String>, BodySize, Vec<Bytes>) {
+        let app_state = AppState::builder()
+            .config(Config {
+                stream_chunk_values,
+                ..Config::default()
+            })
+            .build();
+        let values = (0..20_000).map(|i| f64::from(i) / 3.0).collect();
+        let grid = VoxelGrid::new([20_000, 1, 1], values).unwrap();
+        let task = TaskData::from_grid(grid, 20_000, "test".to_string());
+        let task_id = app_state.task_store.insert(task).unwrap();
+        let app = test::init_service(
+            App::new()
+                .app_data(web::Data::new(app_state))
+                .service(get_voxel_chunk),
+        )
+        .await;
+
+        let uri = format!("/voxel-grid/chunk?task_id={task_id}&chunk_index=0&format=json{query}");
+        let response =
+            test::call_service(&app, test::TestRequest::get().uri(&uri).to_request()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let checksum = response
+            .headers()
+            .get(CHUNK_CHECKSUM_HEADER)
+            .map(|value| value.to_str().unwrap().to_string());
+        let mut body = std::pin::pin!(response.into_body());
+        let size = body.size();
+        let mut frames = Vec::new();
+        while let Some(frame) = poll_fn(|cx| body.as_mut().poll_next(cx)).await {
+            frames.push(frame.unwrap());
+        }
+        (checksum, size, frames)
+    }
+
+    #[actix_web::test]
+    async fn large_json_chunk_is_streamed() {
+        let (checksum, size, frames) = fetch_json_chunk(1_000, "").await;
+        // 流式 body 长度未知，分多帧发出，没有校验和
+        assert_eq!(size, BodySize::Stream);
+        assert!(frames.len() > 1, "{} 帧", frames.len());
+        assert_eq!(checksum, None);
+
+        // 关闭流式序列化时完整缓冲，拼接后的字节完全相同
+        let (_, buffered_size, buffered) = fetch_json_chunk(0, "").await;
+        assert!(matches!(buffered_size, BodySize::Sized(_)));
+        assert_eq!(frames.concat(), buffered.concat());
+    }
+
+    #[actix_web::test]
+    async fn checksum_request_keeps_large_chunk_buffered() {
+        let (checksum, size, frames) = fetch_json_chunk(1_000, "&checksum=true").await;
+        assert!(matches!(size, BodySize::Sized(_)));
+        assert_eq!(checksum, Some(chunk_checksum(&frames.concat())));
+    }
 }
@@ -0,0 +1,105 @@
+use actix_web::{HttpResponse, Responder, get, http::header::ContentType, web};
+use serde::Deserialize;
+
+use crate::app_state::AppState;
+use crate::handlers::range::{chunk_unavailable, read_grid_values};
+use crate::utils::voxel_grid::VoxelGrid;
+
+#[derive(Deserialize)]
+pub struct ComponentsQuery {
+    pub task_id: String,
+    /// 值大于该阈值的体素视为前景
+    pub threshold: f64,
+    /// 连通方式：6（共面，默认）或 26（共面、共边、共顶点）
+    #[serde(default = "default_connectivity")]
+    pub connectivity: u8,
+}
+
+fn default_connectivity() -> u8 {
+    6
+}
+
+/// 对值大于 `threshold` 的体素做连通域标记，返回每个体素的标签
+///
+/// 响应体为与网格等长的小端序 u32 标签数组（x 最快、z 最慢），背景为 0，连通域按扫描顺序编号为 `1..=count`，
+/// 数量见 `X-Component-Count`。读取是非破坏性的，所有 chunk 必须已就绪且未被请求
+#[get("/voxel-grid/components")]
+pub async fn get_components(
+    data: web::Data<AppState>,
+    query: web::Query<ComponentsQuery>,
+) -> impl Responder {
+    let Some(task) = data.task_store.get(&query.task_id) else {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "无效的 task_id",
+            "task_id": query.task_id,
+        }));
+    };
+
+    if query.threshold.is_nan() {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "无效的 threshold，不能为 NaN",
+        }));
+    }
+    if query.connectivity != 6 && query.connectivity != 26 {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "无效的 connectivity，只能为 6 或 26",
+            "connectivity": query.connectivity,
+        }));
+    }
+    // 标签为 u32，体素数超出范围时无法表示
+    let voxel_count = task.shape.iter().product::<usize>();
+    if voxel_count > u32::MAX as usize {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "网格过大，标签无法以 u32 表示",
+            "voxel_count": voxel_count,
+        }));
+    }
+
+    if let Some(reason) = task.failure() {
+        return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "任务解析失败",
+            "task_id": query.task_id,
+            "status": "failed",
+            "details": reason,
+        }));
+    }
+
+    let values = match read_grid_values(&task) {
+        Ok(values) => values,
+        Err(chunk_index) => return chunk_unavailable(&task, &query.task_id, chunk_index),
+    };
+    let grid = match VoxelGrid::new(task.shape, values) {
+        Ok(grid) => grid,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": e,
+                "task_id": query.task_id,
+            }));
+        }
+    };
+
+    // 连通域标记需要遍历整个网格，放到阻塞线程池执行
+    let (threshold, connectivity) = (query.threshold, query.connectivity);
+    let (labels, count) =
+        match web::block(move || grid.connected_components(threshold, connectivity)).await {
+            Ok(result) => result,
+            Err(e) => {
+                return HttpResponse::InternalServerError().json(serde_json::json!({
+                    "error": "连通域标记失败",
+                    "details": e.to_string(),
+                }));
+            }
+        };
+
+    let body: Vec<u8> = labels
+        .iter()
+        .flat_map(|label| label.to_le_bytes())
+        .collect();
+    HttpResponse::Ok()
+        .content_type(ContentType::octet_stream())
+        .append_header(("X-Component-Count", count.to_string()))
+        .append_header(("X-Threshold", threshold.to_string()))
+        .append_header(("X-Connectivity", connectivity.to_string()))
+        .append_header(("X-Chunk-Task", query.task_id.clone()))
+        .body(body)
+}
@@ -0,0 +1,220 @@
+use std::collections::HashSet;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use actix_web::web::Bytes;
+use actix_web::{HttpResponse, Responder, get, web};
+use futures_core::Stream;
+use serde::Deserialize;
+use tokio::sync::mpsc;
+
+use crate::app_state::AppState;
+use crate::stream_limit::StreamSubscription;
+use crate::task::TaskData;
+
+/// 没有事件时发送 SSE 注释的间隔，防止代理因连接空闲而断开
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+#[derive(Deserialize)]
+pub struct EventsQuery {
+    pub task_id: String,
+}
+
+/// SSE 事件流的响应体
+struct EventStream {
+    receiver: mpsc::Receiver<Bytes>,
+}
+
+impl Stream for EventStream {
+    type Item = Result<Bytes, actix_web::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx).map(|event| event.map(Ok))
+    }
+}
+
+/// 以 Server-Sent Events 推送单个任务的 chunk 就绪事件
+///
+/// 浏览器可以直接用 `EventSource` 订阅：每个 chunk 就绪时发送 `chunk-ready`（单个 chunk 失败时发送 `chunk-failed`），
+/// 客户端收到后通过普通的 chunk 接口获取数据。连接建立时先补发已经就绪过的 chunk；
+/// 任务结束时发送 `complete` 或 `failed` 并关闭连接。
+/// 每个连接在整个推送期间占用一个流式订阅名额（见 `StreamLimits`），超出上限时返回 429
+#[get("/voxel-grid/events")]
+pub async fn stream_events(
+    data: web::Data<AppState>,
+    query: web::Query<EventsQuery>,
+) -> impl Responder {
+    let Some(task) = data.task_store.get(&query.task_id) else {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "无效的 task_id",
+            "task_id": query.task_id,
+        }));
+    };
+
+    let subscription = match data.stream_limits.try_subscribe(&task) {
+        Ok(subscription) => subscription,
+        Err(e) => {
+            return HttpResponse::TooManyRequests().json(serde_json::json!({
+                "error": e.to_string(),
+                "task_id": query.task_id,
+            }));
+        }
+    };
+
+    let (sender, receiver) = mpsc::channel(16);
+    actix_web::rt::spawn(produce_events(
+        query.task_id.clone(),
+        task,
+        sender,
+        subscription,
+    ));
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .append_header(("Cache-Control", "no-cache"))
+        // 关闭 nginx 等反向代理的响应缓冲，事件才能及时送达
+        .append_header(("X-Accel-Buffering", "no"))
+        .streaming(EventStream { receiver })
+}
+
+/// 一个 SSE 事件：`event` 为事件名，`data` 为单行 JSON
+fn sse_event(event: &str, data: serde_json::Value) -> Bytes {
+    Bytes::from(format!("event: {event}\ndata: {data}\n\n"))
+}
+
+/// 等待任务的 chunk 事件通知，把新就绪 / 失败的 chunk 发送给客户端，直到任务结束或客户端断开
+///
+/// `_subscription` 在推送结束时释放，归还订阅名额
+async fn produce_events(
+    task_id: String,
+    task: Arc<TaskData>,
+    sender: mpsc::Sender<Bytes>,
+    _subscription: StreamSubscription,
+) {
+    let mut sent_ready = HashSet::new();
+    let mut sent_failed = HashSet::new();
+    loop {
+        // 先确定是否已结束再发送 chunk 事件，结束前就绪的 chunk 都会在终止事件之前发出
+        let status = task.status_label();
+
+        let mut events = Vec::new();
+        for index in task.stored_chunk_indices() {
+            if sent_ready.insert(index) {
+                let descriptor = &task.chunks[index];
+                events.push(sse_event(
+                    "chunk-ready",
+                    serde_json::json!({
+                        "task_id": task_id,
+                        "chunk_index": index,
+                        "start": descriptor.start,
+                        "end": descriptor.end,
+                    }),
+                ));
+            }
+        }
+        for (index, reason) in task.chunk_failures() {
+            if sent_failed.insert(index) {
+                events.push(sse_event(
+                    "chunk-failed",
+                    serde_json::json!({
+                        "task_id": task_id,
+                        "chunk_index": index,
+                        "error": reason,
+                    }),
+                ));
+            }
+        }
+        let terminal = match status {
+            "processing" => None,
+            "ready" => Some(sse_event(
+                "complete",
+                serde_json::json!({
+                    "task_id": task_id,
+                    "total_chunks": task.chunks.len(),
+                    "failed_chunks": task.failed_chunk_indices(),
+                }),
+            )),
+            status => Some(sse_event(
+                "failed",
+                serde_json::json!({
+                    "task_id": task_id,
+                    "status": status,
+                    "error": task.failure(),
+                }),
+            )),
+        };
+        let finished = terminal.is_some();
+        for event in events.into_iter().chain(terminal) {
+            if sender.send(event).await.is_err() {
+                return;
+            }
+        }
+        if finished {
+            return;
+        }
+
+        // chunk 就绪、失败或任务失败时会被唤醒；在等待注册之后再比较，期间发生的变化不会被错过
+        let (ready_count, failed_count) = (sent_ready.len(), sent_failed.len());
+        let changed = || {
+            task.stored_chunk_indices().len() != ready_count
+                || task.chunk_failures().len() != failed_count
+                || task.status_label() != "processing"
+        };
+        if !task.wait_for_event(KEEP_ALIVE_INTERVAL, changed).await
+            && sender
+                .send(Bytes::from_static(b": keep-alive\n\n"))
+                .await
+                .is_err()
+        {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use actix_web::{App, test};
+
+    use super::*;
+    use crate::config::Config;
+    use crate::task::compute_chunks;
+
+    #[actix_web::test]
+    async fn event_streams_count_against_stream_limits() {
+        let app_state = AppState::builder()
+            .config(Config {
+                max_task_streams: 1,
+                ..Config::default()
+            })
+            .build();
+        // 没有 chunk 就绪的任务：事件流保持打开，持续占用订阅名额
+        let task = TaskData::new([2, 2, 2], compute_chunks(8, 4), "test".to_string());
+        let task_id = app_state.task_store.insert(task).unwrap();
+        let app_state = web::Data::new(app_state);
+        let app = test::init_service(
+            App::new()
+                .app_data(app_state.clone())
+                .service(stream_events),
+        )
+        .await;
+        let uri = format!("/voxel-grid/events?task_id={task_id}");
+
+        let first = test::call_service(&app, test::TestRequest::get().uri(&uri).to_request()).await;
+        assert_eq!(first.status(), StatusCode::OK);
+        assert_eq!(app_state.stream_limits.active(), 1);
+
+        let second =
+            test::call_service(&app, test::TestRequest::get().uri(&uri).to_request()).await;
+        assert_eq!(second.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(app_state.stream_limits.active(), 1);
+
+        // 任务结束后事件流发送终止事件并关闭，名额随之归还
+        app_state.task_store.get(&task_id).unwrap().cancel();
+        let body = test::read_body(first).await;
+        assert!(String::from_utf8_lossy(&body).contains("event: failed"));
+        assert_eq!(app_state.stream_limits.active(), 0);
+    }
+}
@@ -0,0 +1,58 @@
+use actix_web::{HttpResponse, Responder, get, web};
+
+use crate::app_state::AppState;
+use crate::utils::resource_files::list_files;
+
+/// 列出资源目录（含子目录）中的文件
+///
+/// 每个文件返回相对于资源目录的路径、大小与能够解析它的解析器名称（无法识别时为 null），
+/// 路径可直接作为预处理接口的 `file` 参数
+#[get("/files")]
+pub async fn list_resource_files(data: web::Data<AppState>) -> impl Responder {
+    let Some(root) = data.resource_root.clone() else {
+        return HttpResponse::NotFound().json(serde_json::json!({
+            "error": "资源目录不存在或不是目录",
+            "resource_dir": data.config.resource_dir,
+        }));
+    };
+
+    // 遍历目录与识别格式都需要读取文件系统，放到阻塞线程池执行
+    let registry = data.parser_registry.clone();
+    let walk_root = root.clone();
+    let listing = web::block(move || {
+        let (files, truncated) = list_files(&walk_root)?;
+        let entries: Vec<_> = files
+            .into_iter()
+            .map(|file| {
+                let full_path = walk_root.join(&file.path);
+                let parser = registry
+                    .find_parser_for_file(&full_path.to_string_lossy())
+                    .map(|(parser, _)| parser.name());
+                serde_json::json!({
+                    "path": file.path,
+                    "size": file.size,
+                    "parser": parser,
+                })
+            })
+            .collect();
+        Ok::<_, std::io::Error>((entries, truncated))
+    })
+    .await;
+
+    match listing {
+        Ok(Ok((files, truncated))) => HttpResponse::Ok().json(serde_json::json!({
+            "resource_dir": root.display().to_string(),
+            "count": files.len(),
+            "truncated": truncated,
+            "files": files,
+        })),
+        Ok(Err(e)) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "读取资源目录失败",
+            "details": e.to_string(),
+        })),
+        Err(e) => HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "读取资源目录失败",
+            "details": e.to_string(),
+        })),
+    }
+}
@@ -0,0 +1,164 @@
+use std::sync::{Arc, OnceLock};
+
+use actix_web::{HttpResponse, Responder, get, web};
+use serde::Deserialize;
+
+use crate::app_state::AppState;
+use crate::handlers::range::{chunk_unavailable, read_grid_values};
+use crate::task::TaskData;
+use crate::utils::stats::{DEFAULT_HISTOGRAM_BINS, GridStats, Histogram, MAX_HISTOGRAM_BINS};
+use crate::utils::voxel_grid::VoxelGrid;
+
+#[derive(Deserialize)]
+pub struct GridStatsQuery {
+    pub task_id: String,
+}
+
+/// 整个网格的 min / max / mean / count
+///
+/// 预处理时请求了 `precompute: ["stats"]` 的任务直接返回预计算的结果；否则首次请求时计算并缓存在任务上，
+/// 此时所有 chunk 必须已就绪且未被请求（读取是非破坏性的）
+#[get("/voxel-grid/stats")]
+pub async fn get_grid_stats(
+    data: web::Data<AppState>,
+    query: web::Query<GridStatsQuery>,
+) -> impl Responder {
+    let task = match find_task(&data, &query.task_id) {
+        Ok(task) => task,
+        Err(response) => return response,
+    };
+
+    let (stats, cached) = match cached_or_compute(&task, &query.task_id, &task.stats, |grid| {
+        GridStats::from_values(grid.get_data().iter().copied())
+    })
+    .await
+    {
+        Ok(result) => result,
+        Err(response) => return response,
+    };
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "task_id": query.task_id,
+        "min": stats.min,
+        "max": stats.max,
+        "mean": stats.mean,
+        "count": stats.count,
+        "total_voxels": task.data_length(),
+        "cached": cached,
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct HistogramQuery {
+    pub task_id: String,
+    /// bin 数（1..=4096），默认 64；只有默认 bin 数的结果会被缓存（也是预计算使用的 bin 数）
+    pub bins: Option<usize>,
+}
+
+/// 整个网格有限值的等宽直方图
+///
+/// 默认 bin 数的直方图与 `/voxel-grid/stats` 一样可以预计算、首次请求后缓存；
+/// 其他 bin 数每次请求都重新计算，所有 chunk 必须已就绪且未被请求
+#[get("/voxel-grid/histogram")]
+pub async fn get_histogram(
+    data: web::Data<AppState>,
+    query: web::Query<HistogramQuery>,
+) -> impl Responder {
+    let bins = query.bins.unwrap_or(DEFAULT_HISTOGRAM_BINS);
+    if !(1..=MAX_HISTOGRAM_BINS).contains(&bins) {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("bins 必须在 1..={MAX_HISTOGRAM_BINS} 之间"),
+            "bins": bins,
+        }));
+    }
+    let task = match find_task(&data, &query.task_id) {
+        Ok(task) => task,
+        Err(response) => return response,
+    };
+
+    let result = if bins == DEFAULT_HISTOGRAM_BINS {
+        cached_or_compute(&task, &query.task_id, &task.histogram, |grid| {
+            Histogram::from_values(grid.get_data(), DEFAULT_HISTOGRAM_BINS)
+        })
+        .await
+    } else {
+        compute(&task, &query.task_id, move |grid| {
+            Histogram::from_values(grid.get_data(), bins)
+        })
+        .await
+        .map(|histogram| (histogram, false))
+    };
+    let (histogram, cached) = match result {
+        Ok(result) => result,
+        Err(response) => return response,
+    };
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "task_id": query.task_id,
+        "bins": bins,
+        "min": histogram.min,
+        "max": histogram.max,
+        "bin_width": histogram.bin_width,
+        "counts": histogram.counts,
+        "count": histogram.count,
+        "total_voxels": task.data_length(),
+        "cached": cached,
+    }))
+}
+
+/// 查找任务；任务不存在返回 400，解析失败返回 500
+fn find_task(data: &AppState, task_id: &str) -> Result<Arc<TaskData>, HttpResponse> {
+    let Some(task) = data.task_store.get(task_id) else {
+        return Err(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "无效的 task_id",
+            "task_id": task_id,
+        })));
+    };
+    if let Some(reason) = task.failure() {
+        return Err(HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "任务解析失败",
+            "task_id": task_id,
+            "status": "failed",
+            "details": reason,
+        })));
+    }
+    Ok(task)
+}
+
+/// 返回缓存的结果（第二项为 true），没有缓存时计算并写入缓存
+///
+/// 并发的请求可能同时计算，以先写入缓存的结果为准
+async fn cached_or_compute<T: Clone + Send + 'static>(
+    task: &TaskData,
+    task_id: &str,
+    cache: &OnceLock<T>,
+    analyse: fn(&VoxelGrid) -> T,
+) -> Result<(T, bool), HttpResponse> {
+    if let Some(value) = cache.get() {
+        return Ok((value.clone(), true));
+    }
+    let value = compute(task, task_id, analyse).await?;
+    Ok((cache.get_or_init(|| value).clone(), false))
+}
+
+/// 非破坏性地读取整个网格，在阻塞线程池中执行分析
+async fn compute<T: Send + 'static>(
+    task: &TaskData,
+    task_id: &str,
+    analyse: impl FnOnce(&VoxelGrid) -> T + Send + 'static,
+) -> Result<T, HttpResponse> {
+    let values = read_grid_values(task)
+        .map_err(|chunk_index| chunk_unavailable(task, task_id, chunk_index))?;
+    let grid = VoxelGrid::new(task.shape, values).map_err(|e| {
+        HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": e,
+            "task_id": task_id,
+        }))
+    })?;
+    web::block(move || analyse(&grid)).await.map_err(|e| {
+        HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "计算分析失败",
+            "details": e.to_string(),
+        }))
+    })
+}
@@ -6,10 +6,22 @@ use crate::app_state::AppState;
 #[get("/")]
 pub async fn hello(data: web::Data<AppState>) -> impl Responder {
     let supported = data.parser_registry.supported_extensions();
-    HttpResponse::Ok().json(serde_json::json!({
+    let mut body = serde_json::json!({
         "message": "体素网格数据服务",
         "endpoint": "/voxel-grid?file=<filename>",
         "supported_extensions": supported,
-        "resource_dir": data.resource_dir,
-    }))
+        "resource_dir": data.config.resource_dir,
+        "resource_dir_resolved": data.resource_root.as_ref().map(|root| root.display().to_string()),
+        "stream_subscribers": data.stream_limits.active(),
+        "parsers": data.parser_health.snapshot(),
+    });
+
+    // 构建信息：用于确认线上实际运行的版本
+    if data.config.expose_build_info {
+        body["version"] = env!("CARGO_PKG_VERSION").into();
+        body["git_commit"] = env!("GIT_COMMIT_HASH").into();
+        body["uptime_secs"] = data.started_at.elapsed().as_secs().into();
+    }
+
+    HttpResponse::Ok().json(body)
 }
@@ -0,0 +1,88 @@
+use actix_web::{HttpResponse, Responder, get, http::header::ContentType, web};
+use serde::Deserialize;
+
+use crate::app_state::AppState;
+use crate::handlers::range::{chunk_unavailable, read_grid_values};
+use crate::utils::voxel_grid::VoxelGrid;
+
+#[derive(Deserialize)]
+pub struct IsosurfaceQuery {
+    pub task_id: String,
+    /// 等值面的取值
+    pub isovalue: f64,
+}
+
+/// 在服务端用 Marching Cubes 提取等值面，返回二进制网格
+///
+/// 响应体依次为顶点坐标（每个顶点 3 个小端序 f32，体素索引单位）与三角形索引（小端序 u32，每 3 个一个三角形），
+/// 数量见 `X-Vertex-Count` / `X-Triangle-Count`。读取是非破坏性的，所有 chunk 必须已就绪且未被请求
+#[get("/voxel-grid/isosurface")]
+pub async fn get_isosurface(
+    data: web::Data<AppState>,
+    query: web::Query<IsosurfaceQuery>,
+) -> impl Responder {
+    let Some(task) = data.task_store.get(&query.task_id) else {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "无效的 task_id",
+            "task_id": query.task_id,
+        }));
+    };
+
+    if !query.isovalue.is_finite() {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "无效的 isovalue，要求为有限值",
+            "isovalue": query.isovalue,
+        }));
+    }
+
+    if let Some(reason) = task.failure() {
+        return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "任务解析失败",
+            "task_id": query.task_id,
+            "status": "failed",
+            "details": reason,
+        }));
+    }
+
+    let values = match read_grid_values(&task) {
+        Ok(values) => values,
+        Err(chunk_index) => return chunk_unavailable(&task, &query.task_id, chunk_index),
+    };
+    let grid = match VoxelGrid::new(task.shape, values) {
+        Ok(grid) => grid,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": e,
+                "task_id": query.task_id,
+            }));
+        }
+    };
+
+    // 大网格的等值面提取是 CPU 密集型操作，放到阻塞线程池执行
+    let isovalue = query.isovalue;
+    let (vertices, indices) = match web::block(move || grid.marching_cubes(isovalue)).await {
+        Ok(mesh) => mesh,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "等值面提取失败",
+                "details": e.to_string(),
+            }));
+        }
+    };
+
+    let mut body = Vec::with_capacity(vertices.len() * 12 + indices.len() * 4);
+    for coord in vertices.iter().flatten() {
+        body.extend_from_slice(&coord.to_le_bytes());
+    }
+    for index in &indices {
+        body.extend_from_slice(&index.to_le_bytes());
+    }
+
+    HttpResponse::Ok()
+        .content_type(ContentType::octet_stream())
+        .append_header(("X-Isovalue", isovalue.to_string()))
+        .append_header(("X-Vertex-Count", vertices.len().to_string()))
+        .append_header(("X-Triangle-Count", (indices.len() / 3).to_string()))
+        .append_header(("X-Chunk-Task", query.task_id.clone()))
+        .body(body)
+}
@@ -0,0 +1,61 @@
+use actix_web::error::{InternalError, JsonPayloadError};
+use actix_web::http::header;
+use actix_web::{HttpRequest, HttpResponse, ResponseError, web};
+
+/// 所有 JSON 请求体共用的提取配置（在 `routes::configure` 中注册）
+///
+/// actix 默认对所有提取错误返回纯文本的 400；这里按错误类型区分状态码，并与其他接口一样返回 JSON 错误信息
+pub fn json_config() -> web::JsonConfig {
+    web::JsonConfig::default().error_handler(json_error_handler)
+}
+
+/// - `Content-Type` 缺失或不是 `application/json`（及 `+json` 后缀类型）：415
+/// - 请求体不是合法的 JSON（语法错误、为空或被截断）：400，附带出错的行列
+/// - JSON 合法但字段缺失、类型不符或取值未知：400，附带出错的行列
+/// - 请求体超过大小上限：413
+fn json_error_handler(err: JsonPayloadError, req: &HttpRequest) -> actix_web::Error {
+    let response = match &err {
+        JsonPayloadError::ContentType => {
+            let content_type = req
+                .headers()
+                .get(header::CONTENT_TYPE)
+                .map(|value| value.to_str().unwrap_or("<非 ASCII>"));
+            HttpResponse::UnsupportedMediaType().json(serde_json::json!({
+                "error": "Content-Type 必须为 application/json",
+                "content_type": content_type,
+                "path": req.path(),
+            }))
+        }
+        JsonPayloadError::Deserialize(e) => {
+            let error = if e.is_syntax() || e.is_eof() {
+                "请求体不是有效的 JSON"
+            } else {
+                "请求参数无效"
+            };
+            HttpResponse::BadRequest().json(serde_json::json!({
+                "error": error,
+                "details": e.to_string(),
+                "line": e.line(),
+                "column": e.column(),
+                "path": req.path(),
+            }))
+        }
+        JsonPayloadError::OverflowKnownLength { length, limit } => HttpResponse::PayloadTooLarge()
+            .json(serde_json::json!({
+                "error": "请求体过大",
+                "length": length,
+                "limit": limit,
+            })),
+        JsonPayloadError::Overflow { limit } => {
+            HttpResponse::PayloadTooLarge().json(serde_json::json!({
+                "error": "请求体过大",
+                "limit": limit,
+            }))
+        }
+        _ => HttpResponse::build(err.status_code()).json(serde_json::json!({
+            "error": "读取请求体失败",
+            "details": err.to_string(),
+        })),
+    };
+    InternalError::from_response(err, response).into()
+}
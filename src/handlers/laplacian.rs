@@ -0,0 +1,99 @@
+use std::sync::Arc;
+
+use actix_web::{HttpResponse, Responder, get, http::header::ContentType, web};
+use serde::Deserialize;
+
+use crate::app_state::AppState;
+use crate::handlers::range::{chunk_unavailable, read_grid_values};
+use crate::utils::voxel_grid::VoxelGrid;
+
+#[derive(Deserialize)]
+pub struct LaplacianQuery {
+    pub task_id: String,
+    /// 按周期边界处理（默认 false，边界处使用单侧差分）
+    #[serde(default)]
+    pub periodic: bool,
+}
+
+/// 返回整个网格的拉普拉斯量（标准 7 点模板），小端序 f64，顺序与 chunk 数据相同
+///
+/// 计算需要遍历整个网格，结果按边界模式缓存在任务上：首次请求时所有 chunk 必须已就绪且未被请求（读取是非破坏性的），
+/// 之后的请求直接返回缓存，不再依赖 chunk 数据
+#[get("/voxel-grid/laplacian")]
+pub async fn get_laplacian(
+    data: web::Data<AppState>,
+    query: web::Query<LaplacianQuery>,
+) -> impl Responder {
+    let Some(task) = data.task_store.get(&query.task_id) else {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "无效的 task_id",
+            "task_id": query.task_id,
+        }));
+    };
+
+    if let Some(reason) = task.failure() {
+        return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "任务解析失败",
+            "task_id": query.task_id,
+            "status": "failed",
+            "details": reason,
+        }));
+    }
+
+    let periodic = query.periodic;
+    let cache = &task.laplacian[periodic as usize];
+    let cached = cache.get().is_some();
+    let laplacian = match cache.get() {
+        Some(laplacian) => laplacian.clone(),
+        None => {
+            let values = match read_grid_values(&task) {
+                Ok(values) => values,
+                Err(chunk_index) => return chunk_unavailable(&task, &query.task_id, chunk_index),
+            };
+            let grid = match VoxelGrid::new(task.shape, values) {
+                Ok(grid) => grid,
+                Err(e) => {
+                    return HttpResponse::InternalServerError().json(serde_json::json!({
+                        "error": e,
+                        "task_id": query.task_id,
+                    }));
+                }
+            };
+
+            // 遍历整个网格，放到阻塞线程池执行
+            let computed = web::block(move || {
+                let values = if periodic {
+                    grid.laplacian_periodic()
+                } else {
+                    grid.laplacian()
+                };
+                Arc::<[f64]>::from(values)
+            })
+            .await;
+            match computed {
+                // 并发的请求可能同时计算，以先写入缓存的结果为准
+                Ok(laplacian) => cache.get_or_init(|| laplacian).clone(),
+                Err(e) => {
+                    return HttpResponse::InternalServerError().json(serde_json::json!({
+                        "error": "计算拉普拉斯量失败",
+                        "details": e.to_string(),
+                    }));
+                }
+            }
+        }
+    };
+
+    let mut body = Vec::with_capacity(laplacian.len() * 8);
+    for value in laplacian.iter() {
+        body.extend_from_slice(&value.to_le_bytes());
+    }
+
+    let [nx, ny, nz] = task.shape;
+    HttpResponse::Ok()
+        .content_type(ContentType::octet_stream())
+        .append_header(("X-Grid-Shape", format!("{nx},{ny},{nz}")))
+        .append_header(("X-Periodic", periodic.to_string()))
+        .append_header(("X-Laplacian-Cached", cached.to_string()))
+        .append_header(("X-Chunk-Task", query.task_id.clone()))
+        .body(body)
+}
@@ -0,0 +1,80 @@
+use actix_web::{HttpResponse, Responder, get, web};
+use serde::Deserialize;
+
+use crate::app_state::AppState;
+use crate::performance::get_unix_timestamp_ms;
+use crate::task::ChunkSummary;
+
+#[derive(Deserialize)]
+pub struct LayoutQuery {
+    pub task_id: String,
+    /// 从第几个 chunk 开始返回，默认 0
+    #[serde(default)]
+    pub offset: usize,
+    /// 最多返回多少个 chunk，默认返回 offset 之后的全部
+    #[serde(default)]
+    pub limit: Option<usize>,
+    /// 为 true 时额外返回每个 chunk 的取值摘要（最小 / 最大值、是否均匀），默认不返回
+    #[serde(default)]
+    pub metadata: bool,
+}
+
+/// 获取任务的分块布局（chunk 描述列表）
+///
+/// 预处理时指定 `omit_chunks` 的客户端可以通过该接口按需（可分页）获取布局，
+/// 而不必在预处理响应中一次性接收全部 chunk 描述。
+/// `ready_at` 与 `chunks` 一一对应，为每个 chunk 首次就绪的时间（Unix 毫秒，尚未就绪时为 null），
+/// 与 `created_at`（任务创建时间）相减即可得到各 chunk 相对预处理开始的就绪时间线。
+/// 指定 `metadata=true` 时 `metadata` 同样与 `chunks` 一一对应，为 chunk 的取值摘要（尚未就绪时为 null），
+/// 客户端可据此跳过均匀 chunk、优先下载取值范围大的 chunk
+#[get("/voxel-grid/layout")]
+pub async fn get_layout(
+    data: web::Data<AppState>,
+    query: web::Query<LayoutQuery>,
+) -> impl Responder {
+    let Some(task) = data.task_store.get(&query.task_id) else {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "无效的 task_id",
+            "task_id": query.task_id,
+        }));
+    };
+
+    let chunk_count = task.chunks.len();
+    let start = query.offset.min(chunk_count);
+    let end = match query.limit {
+        Some(limit) => start.saturating_add(limit).min(chunk_count),
+        None => chunk_count,
+    };
+    // 除最后一个 chunk 外每个 chunk 的长度都等于 chunk_size
+    let chunk_size = task.chunks.first().map(|d| d.end - d.start);
+    let chunks = &task.chunks[start..end];
+    let ready_at: Vec<Option<u64>> = chunks
+        .iter()
+        .map(|descriptor| task.chunk_ready_at(descriptor.index))
+        .collect();
+    let metadata: Option<Vec<Option<ChunkSummary>>> = query.metadata.then(|| {
+        chunks
+            .iter()
+            .map(|descriptor| task.chunk_summary(descriptor.index))
+            .collect()
+    });
+    // 任务只记录了单调时钟的创建时刻，按已存活时长换算为 Unix 毫秒
+    let created_at =
+        get_unix_timestamp_ms().saturating_sub(task.created_at.elapsed().as_millis() as u64);
+
+    let mut response = serde_json::json!({
+        "task_id": query.task_id,
+        "shape": task.shape,
+        "data_length": task.data_length(),
+        "chunk_size": chunk_size,
+        "chunk_count": chunk_count,
+        "offset": start,
+        "created_at": created_at,
+        "chunks": chunks,
+        "ready_at": ready_at,
+    });
+    if let Some(metadata) = metadata {
+        response["metadata"] = serde_json::json!(metadata);
+    }
+    HttpResponse::Ok().json(response)
+}
@@ -0,0 +1,137 @@
+use actix_web::{HttpResponse, Responder, get, http::header::ContentType, web};
+use serde::Deserialize;
+
+use crate::app_state::AppState;
+use crate::handlers::range::{chunk_unavailable, read_grid_values};
+use crate::utils::voxel_grid::{VoxelGrid, line_point};
+
+/// 单次剖面的采样数上限
+pub const MAX_LINE_SAMPLES: usize = 1 << 20;
+
+/// 剖面响应格式
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LineFormat {
+    /// JSON 对象，越界的采样点为 null（默认）
+    #[default]
+    Json,
+    /// 小端序 Float64Array，越界的采样点为 NaN
+    Binary,
+}
+
+#[derive(Deserialize)]
+pub struct LineQuery {
+    pub task_id: String,
+    /// 线段起点，单位为体素索引（体素 `(i, j, k)` 位于 `(i, j, k)`），可以是小数
+    pub x0: f64,
+    pub y0: f64,
+    pub z0: f64,
+    /// 线段终点
+    pub x1: f64,
+    pub y1: f64,
+    pub z1: f64,
+    /// 采样点数（含两个端点），1..=MAX_LINE_SAMPLES
+    pub samples: usize,
+    #[serde(default)]
+    pub format: LineFormat,
+}
+
+/// 沿线段等距三线性插值采样，返回一维剖面（如沿化学键方向的密度分布）
+///
+/// 超出 `[0, n - 1]` 的采样点不外推：JSON 中为 null 并在 `out_of_bounds` 中列出序号，
+/// 二进制格式中为 NaN，数量见 `X-Out-Of-Bounds`。读取是非破坏性的，所有 chunk 必须已就绪且未被请求
+#[get("/voxel-grid/line")]
+pub async fn get_line_profile(
+    data: web::Data<AppState>,
+    query: web::Query<LineQuery>,
+) -> impl Responder {
+    let Some(task) = data.task_store.get(&query.task_id) else {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "无效的 task_id",
+            "task_id": query.task_id,
+        }));
+    };
+
+    let start = [query.x0, query.y0, query.z0];
+    let end = [query.x1, query.y1, query.z1];
+    if start
+        .iter()
+        .chain(&end)
+        .any(|coordinate| !coordinate.is_finite())
+    {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "线段端点坐标必须是有限数",
+            "start": start,
+            "end": end,
+        }));
+    }
+    if !(1..=MAX_LINE_SAMPLES).contains(&query.samples) {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "samples 超出范围",
+            "samples": query.samples,
+            "max": MAX_LINE_SAMPLES,
+        }));
+    }
+
+    if let Some(reason) = task.failure() {
+        return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "任务解析失败",
+            "task_id": query.task_id,
+            "status": "failed",
+            "details": reason,
+        }));
+    }
+
+    let values = match read_grid_values(&task) {
+        Ok(values) => values,
+        Err(chunk_index) => return chunk_unavailable(&task, &query.task_id, chunk_index),
+    };
+    let grid = match VoxelGrid::new(task.shape, values) {
+        Ok(grid) => grid,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": e,
+                "task_id": query.task_id,
+            }));
+        }
+    };
+
+    let samples = query.samples;
+    let profile = grid.line_profile(start, end, samples);
+    // 按取点位置判断越界，不与数据本身的 NaN 混淆
+    let out_of_bounds: Vec<usize> = (0..samples)
+        .filter(|&index| {
+            let point = line_point(start, end, samples, index);
+            (0..3).any(|axis| !(0.0..=(grid.shape[axis] as f64 - 1.0)).contains(&point[axis]))
+        })
+        .collect();
+    let length = (0..3)
+        .map(|axis| (end[axis] - start[axis]).powi(2))
+        .sum::<f64>()
+        .sqrt();
+
+    match query.format {
+        LineFormat::Json => HttpResponse::Ok().json(serde_json::json!({
+            "task_id": query.task_id,
+            "start": start,
+            "end": end,
+            "samples": samples,
+            "length": length,
+            "values": profile,
+            "out_of_bounds": out_of_bounds,
+        })),
+        LineFormat::Binary => {
+            let body: Vec<u8> = profile
+                .iter()
+                .flat_map(|value| value.to_le_bytes())
+                .collect();
+            HttpResponse::Ok()
+                .content_type(ContentType::octet_stream())
+                .append_header(("X-Line-Samples", samples.to_string()))
+                .append_header(("X-Line-Length", length.to_string()))
+                .append_header(("X-Out-Of-Bounds", out_of_bounds.len().to_string()))
+                .append_header(("X-Chunk-Task", query.task_id.clone()))
+                .body(body)
+        }
+    }
+}
@@ -1,11 +1,63 @@
+pub mod admin;
+pub mod appends;
+pub mod cancel;
 pub mod chunk;
+pub mod components;
+pub mod events;
+pub mod files;
+pub mod grid_stats;
 pub mod health;
+pub mod isosurface;
+pub mod json_error;
+pub mod laplacian;
+pub mod layout;
+pub mod line_profile;
+pub mod multi_stream;
+pub mod paired_chunk;
 pub mod performance;
 pub mod preprocess;
+pub mod progress;
+pub mod range;
+pub mod ready;
+pub mod request_metrics;
+pub mod roi_stats;
+pub mod sample;
+pub mod session;
+pub mod slice_png;
+pub mod slices;
+pub mod sparse;
+pub mod status;
+pub mod vdb_export;
 pub mod voxel_grid;
 
-pub use chunk::get_voxel_chunk;
+pub use admin::{admin_cleanup, admin_pin, admin_unpin};
+pub use appends::get_appends;
+pub use cancel::cancel_task;
+pub use chunk::{get_voxel_chunk, get_voxel_chunk_stats};
+pub use components::get_components;
+pub use events::stream_events;
+pub use files::list_resource_files;
+pub use grid_stats::{get_grid_stats, get_histogram};
 pub use health::hello;
+pub use isosurface::get_isosurface;
+pub use json_error::json_config;
+pub use laplacian::get_laplacian;
+pub use layout::get_layout;
+pub use line_profile::get_line_profile;
+pub use multi_stream::multi_stream_chunks;
+pub use paired_chunk::get_paired_chunk;
 pub use performance::get_performance;
 pub use preprocess::preprocess_voxel_grid;
+pub use progress::stream_progress;
+pub use range::get_range_data;
+pub use ready::get_ready_chunks;
+pub use request_metrics::get_request_metrics;
+pub use roi_stats::get_roi_stats;
+pub use sample::get_sample;
+pub use session::get_session_summary;
+pub use slice_png::get_slice_png;
+pub use slices::get_slices;
+pub use sparse::get_sparse;
+pub use status::get_task_status;
+pub use vdb_export::export_vdb;
 pub use voxel_grid::get_voxel_grid;
@@ -0,0 +1,279 @@
+use std::collections::HashSet;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use actix_web::web::Bytes;
+use actix_web::{HttpResponse, Responder, post, web};
+use futures_core::Stream;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+use crate::app_state::AppState;
+use crate::handlers::preprocess::load_resident_window;
+use crate::stream_limit::StreamSubscription;
+use crate::task::TaskData;
+use crate::utils::chunk_encoding::chunk_checksum;
+
+/// 没有新 chunk 就绪时的轮询间隔
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// 生产者与响应体之间最多缓冲的帧数，客户端读取较慢时生产者随之暂停
+const FRAME_BUFFER: usize = 4;
+
+#[derive(Deserialize)]
+pub struct MultiStreamRequest {
+    pub tasks: Vec<MultiStreamTask>,
+}
+
+#[derive(Deserialize)]
+pub struct MultiStreamTask {
+    pub task_id: String,
+    /// 需要的 chunk，缺省时为该任务所有尚未被请求的 chunk
+    #[serde(default)]
+    pub chunk_indices: Option<Vec<usize>>,
+}
+
+/// 帧头（JSON），紧随其后的是 `length` 个小端序 f64（仅 `status` 为 `ready` 时）
+#[derive(Serialize)]
+struct FrameHeader<'a> {
+    task_id: &'a str,
+    chunk_index: usize,
+    /// ready / processing / consumed / failed
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    start: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    end: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    length: Option<usize>,
+    /// chunk 数据字节的校验和（xxh3-64，十六进制），仅 `ready` 帧
+    #[serde(skip_serializing_if = "Option::is_none")]
+    checksum: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl<'a> FrameHeader<'a> {
+    fn status(task_id: &'a str, chunk_index: usize, status: &'static str) -> Self {
+        Self {
+            task_id,
+            chunk_index,
+            status,
+            start: None,
+            end: None,
+            length: None,
+            checksum: None,
+            error: None,
+        }
+    }
+}
+
+/// 编码一帧：`u32`（小端序）帧头长度 + 帧头 JSON + chunk 数据
+///
+/// 整帧组装完成后才发送，`ready` 帧的校验和因此可以放在帧头中，无需 HTTP trailer
+fn encode_frame(mut header: FrameHeader, values: &[f64]) -> Bytes {
+    let data: Vec<u8> = values.iter().flat_map(|value| value.to_le_bytes()).collect();
+    if header.status == "ready" {
+        header.checksum = Some(chunk_checksum(&data));
+    }
+    let header = serde_json::to_vec(&header).unwrap_or_default();
+    let mut frame = Vec::with_capacity(4 + header.len() + data.len());
+    frame.extend_from_slice(&(header.len() as u32).to_le_bytes());
+    frame.extend_from_slice(&header);
+    frame.extend_from_slice(&data);
+    Bytes::from(frame)
+}
+
+/// 一个待发送的 chunk
+struct PendingChunk {
+    /// 在请求列表中的位置
+    task: usize,
+    chunk_index: usize,
+    /// 是否已发送过 `processing` 状态帧
+    announced: bool,
+}
+
+/// 占用流式订阅名额的响应体：名额随响应体一起释放（传输结束或客户端断开时）
+struct FrameStream {
+    receiver: mpsc::Receiver<Bytes>,
+    _subscriptions: Vec<StreamSubscription>,
+}
+
+impl Stream for FrameStream {
+    type Item = Result<Bytes, actix_web::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx).map(|frame| frame.map(Ok))
+    }
+}
+
+/// 在一个响应中交错传输多个任务的 chunk
+///
+/// 多数据集对比视图需要同时加载多个网格，逐个任务请求会占用多个连接。
+/// 这里按请求顺序轮流检查各任务的 chunk，哪个先就绪就先发送，每个 chunk 一帧，帧头标明所属的 task_id 与 chunk_index。
+/// 与 chunk 接口一样，发送后 chunk 即被消费
+#[post("/voxel-grid/multi-stream")]
+pub async fn multi_stream_chunks(
+    data: web::Data<AppState>,
+    request: web::Json<MultiStreamRequest>,
+) -> impl Responder {
+    let request = request.into_inner();
+    if request.tasks.is_empty() {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "tasks 不能为空",
+        }));
+    }
+
+    // 先校验全部参数，避免开始传输后才发现错误
+    let mut tasks: Vec<(String, Arc<TaskData>)> = Vec::with_capacity(request.tasks.len());
+    let mut per_task: Vec<Vec<usize>> = Vec::with_capacity(request.tasks.len());
+    for entry in request.tasks {
+        let Some(task) = data.task_store.get(&entry.task_id) else {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "无效的 task_id",
+                "task_id": entry.task_id,
+            }));
+        };
+        let indices = match entry.chunk_indices {
+            Some(indices) => indices,
+            None => task
+                .chunks
+                .iter()
+                .map(|descriptor| descriptor.index)
+                .filter(|index| task.chunk_data.read().contains_key(index))
+                .collect(),
+        };
+        if let Some(&invalid) = indices.iter().find(|&&index| index >= task.chunks.len()) {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "无效的 chunk_index",
+                "task_id": entry.task_id,
+                "chunk_index": invalid,
+            }));
+        }
+        tasks.push((entry.task_id, task));
+        per_task.push(indices);
+    }
+
+    // 每个不同的任务占用一个流式订阅名额
+    let mut subscriptions = Vec::new();
+    let mut subscribed = HashSet::new();
+    for (task_id, task) in &tasks {
+        if !subscribed.insert(task_id.as_str()) {
+            continue;
+        }
+        match data.stream_limits.try_subscribe(task) {
+            Ok(subscription) => subscriptions.push(subscription),
+            Err(e) => {
+                return HttpResponse::TooManyRequests().json(serde_json::json!({
+                    "error": e.to_string(),
+                    "task_id": task_id,
+                }));
+            }
+        }
+    }
+
+    // 各任务的 chunk 轮流排列，使多个任务的传输交错进行
+    let mut pending = Vec::new();
+    let longest = per_task.iter().map(Vec::len).max().unwrap_or(0);
+    for position in 0..longest {
+        for (task, indices) in per_task.iter().enumerate() {
+            if let Some(&chunk_index) = indices.get(position) {
+                pending.push(PendingChunk {
+                    task,
+                    chunk_index,
+                    announced: false,
+                });
+            }
+        }
+    }
+
+    println!(
+        "[多任务流] 开始传输 {} 个任务的 {} 个 chunk",
+        subscriptions.len(),
+        pending.len()
+    );
+
+    let (sender, receiver) = mpsc::channel(FRAME_BUFFER);
+    actix_web::rt::spawn(produce_frames(data.clone(), tasks, pending, sender));
+
+    HttpResponse::Ok()
+        .content_type("application/octet-stream")
+        .streaming(FrameStream {
+            receiver,
+            _subscriptions: subscriptions,
+        })
+}
+
+/// 按就绪顺序发送各个 chunk，直到全部发送完毕或客户端断开
+async fn produce_frames(
+    app_state: web::Data<AppState>,
+    tasks: Vec<(String, Arc<TaskData>)>,
+    mut pending: Vec<PendingChunk>,
+    sender: mpsc::Sender<Bytes>,
+) {
+    while !pending.is_empty() {
+        let mut progressed = false;
+        let mut remaining = Vec::with_capacity(pending.len());
+        for mut chunk in pending {
+            let (task_id, task) = &tasks[chunk.task];
+            let index = chunk.chunk_index;
+
+            // 驻留窗口模式：请求的 chunk 不在内存中时从文件读取以它开始的窗口
+            let load_error = if task.failure().is_none()
+                && task.resident_window.is_some()
+                && !task.is_chunk_ready(index)
+                && task.chunk_data.read().contains_key(&index)
+            {
+                load_resident_window(&app_state, task, index).await.err()
+            } else {
+                None
+            };
+
+            // take_chunk 会移除未就绪的条目，必须先确认已就绪（与 chunk 接口一致）
+            let failure = task
+                .failure()
+                .map(str::to_string)
+                .or_else(|| task.chunk_failure(index))
+                .or(load_error);
+            let frame = if let Some(reason) = failure {
+                let mut header = FrameHeader::status(task_id, index, "failed");
+                header.error = Some(reason);
+                encode_frame(header, &[])
+            } else if !task.is_chunk_ready(index) && task.chunk_data.read().contains_key(&index) {
+                // 仍在解析中：首次发现时告知客户端，之后就绪时再发送数据
+                let announce = !chunk.announced;
+                chunk.announced = true;
+                remaining.push(chunk);
+                if !announce {
+                    continue;
+                }
+                encode_frame(FrameHeader::status(task_id, index, "processing"), &[])
+            } else if let Some(values) = task.take_chunk(index) {
+                let descriptor = &task.chunks[index];
+                let mut header = FrameHeader::status(task_id, index, "ready");
+                header.start = Some(descriptor.start);
+                header.end = Some(descriptor.end);
+                header.length = Some(values.len());
+                encode_frame(header, &values)
+            } else {
+                let mut header = FrameHeader::status(task_id, index, "consumed");
+                header.error = Some("chunk 已被请求或不存在".to_string());
+                encode_frame(header, &[])
+            };
+
+            // 客户端已断开
+            if sender.send(frame).await.is_err() {
+                println!("[多任务流] 客户端已断开，停止传输");
+                return;
+            }
+            progressed = true;
+        }
+        pending = remaining;
+
+        if !progressed && !pending.is_empty() {
+            actix_web::rt::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+}
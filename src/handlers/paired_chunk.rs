@@ -0,0 +1,158 @@
+use std::sync::Arc;
+
+use actix_web::{HttpResponse, Responder, get, http::header::ContentType, web};
+use serde::Deserialize;
+
+use crate::app_state::AppState;
+use crate::handlers::preprocess::load_resident_window;
+use crate::handlers::range::chunk_unavailable;
+use crate::task::TaskData;
+use crate::utils::chunk_encoding::{ChunkEncoding, encode_chunk};
+
+/// 配对 chunk 的响应格式（只支持二进制，交错后的数据直接作为双通道纹理上传）
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PairedFormat {
+    /// 小端序 Float64Array（默认）
+    #[default]
+    Binary,
+    /// 小端序 Float32Array，体积减半
+    F32,
+}
+
+#[derive(Deserialize)]
+pub struct PairedChunkQuery {
+    pub task_a: String,
+    pub task_b: String,
+    pub chunk_index: usize,
+    #[serde(default)]
+    pub format: PairedFormat,
+}
+
+/// 同时返回两个任务中对应的 chunk，按值交错为 `a0, b0, a1, b1, ...`，用于并排对比渲染时上传双通道纹理
+///
+/// 两个任务的 shape 与 chunk 布局必须完全相同。读取是非破坏性的：chunk 已被请求时使用最近请求缓存中的副本，
+/// 两个 chunk 之后仍可通过 chunk 接口单独请求
+#[get("/voxel-grid/paired-chunk")]
+pub async fn get_paired_chunk(
+    data: web::Data<AppState>,
+    query: web::Query<PairedChunkQuery>,
+) -> impl Responder {
+    let mut tasks = Vec::with_capacity(2);
+    for task_id in [&query.task_a, &query.task_b] {
+        let Some(task) = data.task_store.get(task_id) else {
+            return HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "无效的 task_id",
+                "task_id": task_id,
+            }));
+        };
+        tasks.push(task);
+    }
+    let (task_a, task_b) = (&tasks[0], &tasks[1]);
+
+    if let Some(response) = layout_mismatch(task_a, task_b) {
+        return response;
+    }
+    let Some(descriptor) = task_a.chunks.get(query.chunk_index) else {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "无效的 chunk_index",
+            "chunk_index": query.chunk_index,
+        }));
+    };
+
+    let mut values = Vec::with_capacity(2);
+    for (task, task_id) in [(task_a, &query.task_a), (task_b, &query.task_b)] {
+        if let Some(reason) = task.failure() {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "任务解析失败",
+                "task_id": task_id,
+                "status": "failed",
+                "details": reason,
+            }));
+        }
+        // 驻留窗口模式：chunk 不在内存中（且未被请求过）时与 chunk 接口一样从文件读取以它开始的窗口
+        if task.resident_window.is_some()
+            && !task.is_chunk_ready(query.chunk_index)
+            && task.chunk_data.read().contains_key(&query.chunk_index)
+            && let Err(e) = load_resident_window(&data, task, query.chunk_index).await
+        {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "读取 chunk 失败",
+                "task_id": task_id,
+                "chunk_index": query.chunk_index,
+                "details": e,
+            }));
+        }
+        match read_chunk(task, query.chunk_index) {
+            Some(chunk) => values.push(chunk),
+            None => return chunk_unavailable(task, task_id, query.chunk_index),
+        }
+    }
+
+    let interleaved: Vec<f64> = values[0]
+        .iter()
+        .zip(values[1].iter())
+        .flat_map(|(&a, &b)| [a, b])
+        .collect();
+    let encoding = match query.format {
+        PairedFormat::Binary => ChunkEncoding::F64Le(ContentType::octet_stream().0),
+        PairedFormat::F32 => ChunkEncoding::F32Le(ContentType::octet_stream().0),
+    };
+    let (body, encoding_headers) = match encode_chunk(&interleaved, encoding) {
+        Ok(encoded) => encoded,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "序列化 chunk 数据失败",
+                "details": e,
+            }));
+        }
+    };
+
+    let chunk_length = descriptor.end - descriptor.start;
+    let mut response = HttpResponse::Ok();
+    for (name, value) in encoding_headers {
+        response.insert_header((name, value));
+    }
+    response
+        .append_header(("X-Chunk-Index", descriptor.index.to_string()))
+        .append_header(("X-Chunk-Start", descriptor.start.to_string()))
+        .append_header(("X-Chunk-End", descriptor.end.to_string()))
+        .append_header(("X-Chunk-Length", chunk_length.to_string()))
+        .append_header(("X-Chunk-Channels", "2"))
+        .append_header(("X-Chunk-Task-A", query.task_a.clone()))
+        .append_header(("X-Chunk-Task-B", query.task_b.clone()));
+    if let Some(tile) = descriptor.tile {
+        let join = |v: [usize; 3]| format!("{},{},{}", v[0], v[1], v[2]);
+        response.append_header(("X-Tile-Origin", join(tile.origin)));
+        response.append_header(("X-Tile-Dims", join(tile.dims)));
+    }
+    response.body(body)
+}
+
+/// 两个任务的 shape 或 chunk 布局不同时返回 400，并指出第一个不同的 chunk
+fn layout_mismatch(task_a: &TaskData, task_b: &TaskData) -> Option<HttpResponse> {
+    if task_a.shape == task_b.shape && task_a.chunks == task_b.chunks {
+        return None;
+    }
+    let first_difference = task_a
+        .chunks
+        .iter()
+        .zip(&task_b.chunks)
+        .position(|(a, b)| a != b)
+        .unwrap_or(task_a.chunks.len().min(task_b.chunks.len()));
+    Some(HttpResponse::BadRequest().json(serde_json::json!({
+        "error": "两个任务的 chunk 布局不同，无法配对",
+        "shape_a": task_a.shape,
+        "shape_b": task_b.shape,
+        "chunk_count_a": task_a.chunks.len(),
+        "chunk_count_b": task_b.chunks.len(),
+        "first_difference": (task_a.shape == task_b.shape).then_some(first_difference),
+        "message": "请用相同的 chunk_size / tile_size 对两个文件做预处理",
+    })))
+}
+
+/// 非破坏性地读取 chunk：仍在任务中时复制一份，已被请求时取最近请求缓存中的副本
+fn read_chunk(task: &TaskData, chunk_index: usize) -> Option<Arc<[f64]>> {
+    task.peek_chunk(chunk_index, |values| Arc::from(values))
+        .or_else(|| task.recent_chunk(chunk_index))
+}
@@ -34,9 +34,13 @@ pub async fn get_performance(
 
     // 即使没有记录，也返回空数组，而不是 404 错误
     // 因为可能所有数据都来自缓存，后端没有收到任何请求
+    // 记录数达到上限后最早的记录被丢弃，客户端据此判断时间线是否完整
+    let cap = data.performance_store.cap_status(&query.session_id);
     HttpResponse::Ok().json(serde_json::json!({
         "session_id": query.session_id,
         "records": records.unwrap_or_default(),
+        "max_records": cap.max_records,
+        "dropped_records": cap.dropped_records,
     }))
 }
 
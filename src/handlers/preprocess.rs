@@ -1,47 +1,389 @@
-use std::time::Instant;
-
-use actix_web::{HttpResponse, Responder, post, web};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::{HttpRequest, HttpResponse, Responder, post, web};
 use serde::{Deserialize, Serialize};
+use std::any::Any;
+use std::panic::{self, RefUnwindSafe};
+use std::sync::Arc;
 
 use crate::app_state::AppState;
-use crate::performance::{get_thread_id, get_unix_timestamp_ms, PerformanceRecord};
-use crate::task::{ChunkDescriptor, TaskData};
+use crate::cancel::CancelToken;
+use crate::handlers::admin::check_admin_token;
+use crate::parse_dedup::{ParseKey, ParseSubscriber};
+use crate::parse_queue::ParsePriority;
+use crate::performance::{
+    get_thread_id, get_unix_timestamp_ms, resolve_session_id, PerformanceRecord,
+    SESSION_ID_HEADER,
+};
+use crate::preprocess_cache::{CacheValidator, PreprocessCacheKey};
+use crate::task::{
+    AppendState, ChunkDescriptor, ChunkSummary, TaskData, check_chunk_layout, compute_chunks,
+    compute_tiles,
+};
+use crate::utils::analysis::{Analysis, PrecomputedAnalyses, normalize_analyses};
+use crate::utils::parser::{DEFAULT_GRID_NAME, VoxelGridParser};
+use crate::utils::preview::{MAX_PREVIEW_RESOLUTION, Preview, PreviewPlan};
+use crate::utils::resource_files::sanitize_relative_path;
+use crate::utils::stats::MAX_HISTOGRAM_BINS;
+use crate::utils::value_transform::ValueTransform;
+use crate::utils::voxel_grid::{
+    PadMode, VoxelGrid, checked_grid_len, pow2_shape, round_values_to_decimals,
+};
 
 #[derive(Deserialize)]
 pub struct PreprocessRequest {
     pub file: String,
+    /// 线性分块的大小；tile 模式下不需要
+    #[serde(default)]
     pub chunk_size: usize,
     #[serde(default)]
     pub session_id: Option<String>,
+    /// 为 true 时固定创建的任务（不会过期），需要携带管理令牌
+    #[serde(default)]
+    pub pinned: bool,
+    /// 上一次预处理得到的 `file_mtime`（与可选的 `data_hash`）；文件未变化且之前的任务仍然存在时直接返回该任务，不再解析
+    #[serde(default)]
+    pub if_unchanged: Option<CacheValidator>,
+    #[serde(flatten)]
+    pub options: PreprocessOptions,
+}
+
+/// 分块方式
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ChunkMode {
+    /// 按 `chunk_size` 切分一维数据（默认）
+    #[default]
+    Linear,
+    /// 按 `tile_size` 把网格划分为长方体 tile，每个 chunk 是一个 tile
+    Tile,
+}
+
+/// `resample_shape` 的重采样方法
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ResampleMethod {
+    /// FFT 带限插值（默认），需要以 `fft` feature 编译
+    #[default]
+    Fft,
+    /// 守恒重分箱：按重叠体积分配每个体素的值，所有值之和保持不变
+    Rebin,
+}
+
+/// 预处理的可选参数，均有默认值
+#[derive(Deserialize, Serialize, Default, Clone)]
+pub struct PreprocessOptions {
+    /// 后台解析优先级（`low` / `normal` / `high`），默认 `normal`
+    #[serde(default)]
+    pub priority: ParsePriority,
+    /// 解析后将每个值舍入到 N 位小数（0..=15），默认不舍入
+    #[serde(default)]
+    pub round_decimals: Option<u32>,
+    /// 解析后逐元素应用的数值变换（`none` / `abs` / `log` / `log1p` / `sqrt`），默认 `none`
+    #[serde(default)]
+    pub value_transform: ValueTransform,
+    /// 为 true 时响应不内联 `chunks` 数组，分块布局可通过 `/voxel-grid/layout` 按需获取
+    #[serde(default)]
+    pub omit_chunks: bool,
+    /// 为 true 时响应内联每个 chunk 的取值摘要（最小 / 最大值、是否均匀），只有在返回前 chunk 已全部就绪
+    /// （需要完整解析的预处理）时才有；后台解析的任务通过 `/voxel-grid/layout?metadata=true` 获取
+    #[serde(default)]
+    pub chunk_metadata: bool,
+    /// 为 true 时解析后裁剪掉四周低于阈值的空白边界，再按裁剪后的网格分块
+    /// 需要先完成解析才能确定 shape，因此预处理会等待解析结束后才返回
+    #[serde(default)]
+    pub autocrop: bool,
+    /// 自动裁剪的阈值：绝对值大于该值的体素视为非空，默认 0
+    #[serde(default)]
+    pub autocrop_threshold: f64,
+    /// 在响应中内联低分辨率预览，值为每个轴的最大采样数（1..=64），默认不生成
+    #[serde(default)]
+    pub preview: Option<usize>,
+    /// 分块方式（`linear` / `tile`），默认 `linear`
+    #[serde(default)]
+    pub chunk_mode: ChunkMode,
+    /// tile 模式下每个 tile 的大小 [x, y, z]，tile 模式必填
+    #[serde(default)]
+    pub tile_size: Option<[usize; 3]>,
+    /// 解析后做高斯模糊降噪的 sigma（单位：体素，0..=10），0 或缺省时不模糊
+    #[serde(default)]
+    pub smooth_sigma: Option<f64>,
+    /// 每个 chunk 的目标字节数：指定时由服务端按 `auto_chunk_bytes / 8` 推导 `chunk_size`，
+    /// 此时不能再指定 `chunk_size`，也不能用于 tile 模式
+    #[serde(default)]
+    pub auto_chunk_bytes: Option<usize>,
+    /// 解析后用 FFT 重采样到的 shape [x, y, z]，再按新 shape 分块（需要以 `fft` feature 编译）
+    /// 与 `autocrop` 一样需要先完成解析，预处理会等待解析结束后才返回
+    #[serde(default)]
+    pub resample_shape: Option<[usize; 3]>,
+    /// `resample_shape` 使用的方法（`fft` / `rebin`），默认 `fft`
+    #[serde(default)]
+    pub resample_method: ResampleMethod,
+    /// 文件包含多个网格时要创建任务的网格（名称或序号），只解析一次文件，为每个网格各创建一个任务
+    /// 指定后预处理会等待解析结束，响应为按该顺序排列的 `PreprocessResponse` 数组
+    #[serde(default)]
+    pub grids: Option<Vec<GridSelector>>,
+    /// 在后台解析时、分割 chunk 之前预先计算的分析（`stats` / `histogram` / `laplacian`），默认不预计算
+    /// 预计算会推迟第一个 chunk 就绪的时间，但之后的分析请求直接返回缓存；未预计算的分析在首次请求时计算
+    #[serde(default)]
+    pub precompute: Vec<Analysis>,
+    /// 把每个轴裁剪（`crop`）或填充（`pad_zero` / `pad_edge`）到 2 的幂，在其他处理与自动裁剪之后进行，默认不调整
+    /// 与 `autocrop` 一样需要先完成解析，预处理会等待解析结束后才返回
+    #[serde(default)]
+    pub pow2: Option<PadMode>,
+    /// 直方图均衡化的 bin 数（1..=4096）：按累积分布把数值重新映射到 `[0, 1]`，提高低对比度数据的可见度，
+    /// 在数值变换、舍入与自动裁剪之后、`pow2` 之前进行，默认不均衡化。需要完整的数值分布，预处理会等待解析结束后才返回
+    #[serde(default)]
+    pub equalize: Option<usize>,
+}
+
+/// `grids` 中的一项：按序号（从 0 开始）或解析器给出的名称（如 `total` / `diff`）选择网格
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(untagged)]
+pub enum GridSelector {
+    Index(usize),
+    Name(String),
+}
+
+impl PreprocessOptions {
+    /// tile 模式下生效的 tile 大小（已通过 `validate_and_locate` 校验）
+    fn effective_tile_size(&self) -> Option<[usize; 3]> {
+        match self.chunk_mode {
+            ChunkMode::Linear => None,
+            ChunkMode::Tile => self.tile_size,
+        }
+    }
+
+    /// 排序去重后的预计算分析
+    fn effective_precompute(&self) -> Vec<Analysis> {
+        normalize_analyses(&self.precompute)
+    }
+
+    /// 实际生效的模糊 sigma（已通过 `validate_and_locate` 校验），0 视为不模糊
+    fn effective_smooth_sigma(&self) -> Option<f64> {
+        self.smooth_sigma.filter(|&sigma| sigma > 0.0)
+    }
+
+    /// 线性分块实际使用的 chunk_size：指定了 `auto_chunk_bytes` 时按字节预算推导，否则为请求中的值
+    ///
+    /// 推导出的分块数超过 `MAX_AUTO_CHUNK_COUNT` 时返回 400，提示客户端增大字节预算
+    fn resolve_chunk_size(
+        &self,
+        chunk_size: usize,
+        data_length: usize,
+    ) -> Result<usize, HttpResponse> {
+        let Some(bytes) = self.auto_chunk_bytes else {
+            return Ok(chunk_size);
+        };
+        let chunk_size = bytes / AUTO_CHUNK_ELEMENT_BYTES;
+        let chunk_count = data_length.div_ceil(chunk_size);
+        if chunk_count > MAX_AUTO_CHUNK_COUNT {
+            let min_bytes = data_length.div_ceil(MAX_AUTO_CHUNK_COUNT) * AUTO_CHUNK_ELEMENT_BYTES;
+            return Err(HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "auto_chunk_bytes 过小，推导出的分块数超过上限",
+                "auto_chunk_bytes": bytes,
+                "data_length": data_length,
+                "chunk_size": chunk_size,
+                "chunk_count": chunk_count,
+                "max_chunk_count": MAX_AUTO_CHUNK_COUNT,
+                "min_auto_chunk_bytes": min_bytes,
+            })));
+        }
+        Ok(chunk_size)
+    }
+}
+
+/// 自动裁剪的范围，各轴为 `[min, max)`，坐标相对原始网格
+#[derive(Serialize, Clone, Copy)]
+pub struct CropBounds {
+    pub min: [usize; 3],
+    pub max: [usize; 3],
+}
+
+/// `pow2` 调整的结果
+#[derive(Serialize, Clone, Copy)]
+pub struct Pow2Adjustment {
+    pub mode: PadMode,
+    /// 调整前（自动裁剪后）的 shape
+    pub source_shape: [usize; 3],
+    /// 新网格中的体素 `c` 对应调整前网格中的体素 `c + offset`（裁剪时为正，填充时为负）
+    pub offset: [isize; 3],
 }
 
+/// 服务过载（解析队列已满）时建议客户端的重试间隔（秒）
+const OVERLOAD_RETRY_AFTER_SECS: u64 = 5;
+
+/// `round_decimals` 允许的最大值：f64 约有 15~17 位有效数字，更多位数没有意义
+pub const MAX_ROUND_DECIMALS: u32 = 15;
+
+/// `smooth_sigma` 允许的最大值：卷积核半径为 3σ，过大的 sigma 开销高且会抹平所有细节
+pub const MAX_SMOOTH_SIGMA: f64 = 10.0;
+
+/// `resample_shape` 的体素总数上限：FFT 重采样的中间结果按复数存储，占用约为目标网格的 2 倍
+pub const MAX_RESAMPLE_VOXELS: usize = 1 << 26;
+
+/// `pow2` 填充后的体素总数上限：每个轴最多接近翻倍，体素数最多约为原来的 8 倍
+pub const MAX_POW2_VOXELS: usize = 1 << 27;
+
+/// `auto_chunk_bytes` 推导 chunk_size 时每个元素的字节数：chunk 按 f64 存储，默认的二进制格式也是 f64
+pub const AUTO_CHUNK_ELEMENT_BYTES: usize = std::mem::size_of::<f64>();
+
+/// `auto_chunk_bytes` 推导出的分块数上限：分块过细时请求次数与 chunks 数组都会失控
+pub const MAX_AUTO_CHUNK_COUNT: usize = 65_536;
+
 #[derive(Serialize, Clone)]
 pub struct PreprocessResponse {
     pub task_id: String,
     pub file: String,
     pub file_size: u64,
+    /// 文件的修改时间（Unix 毫秒），可作为之后预处理请求的 `if_unchanged.file_mtime`；无法获取时省略
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file_mtime: Option<u64>,
     pub shape: [usize; 3],
     pub data_length: usize,
+    /// 实际使用的 chunk_size（指定 `auto_chunk_bytes` 时为推导出的值）
     pub chunk_size: usize,
-    pub chunks: Vec<ChunkDescriptor>,
+    /// 请求的每 chunk 字节预算（仅在指定 `auto_chunk_bytes` 时返回）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auto_chunk_bytes: Option<usize>,
+    /// 分块描述列表；请求 `omit_chunks` 时省略
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chunks: Option<Vec<ChunkDescriptor>>,
+    /// 与 chunk 一一对应的取值摘要（仅在请求 `chunk_metadata` 且 chunk 已全部就绪时返回）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chunk_metadata: Option<Vec<Option<ChunkSummary>>>,
+    pub chunk_count: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub round_decimals: Option<u32>,
+    #[serde(skip_serializing_if = "ValueTransform::is_none")]
+    pub value_transform: ValueTransform,
+    /// 驻留窗口大小（仅在驻留窗口模式下返回）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resident_window: Option<usize>,
+    /// 自动裁剪、重采样前的原始 shape（仅在 autocrop 或 resample_shape 时返回）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub original_shape: Option<[usize; 3]>,
+    /// 使用的重采样方法（仅在 resample_shape 时返回）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resample_method: Option<ResampleMethod>,
+    /// 自动裁剪的范围（仅在 autocrop 时返回；没有可裁剪的边界时为整个网格）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crop: Option<CropBounds>,
+    /// 2 的幂调整（仅在请求 `pow2` 时返回；shape 已经是 2 的幂时偏移为 0）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pow2: Option<Pow2Adjustment>,
+    /// 是否加入了进行中的相同解析（与其他任务共享一次解析）
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub joined_parse: bool,
+    /// tile 大小（仅在 tile 模式下返回），此时 `chunk_size` 为完整 tile 的体素数
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tile_size: Option<[usize; 3]>,
+    /// 低分辨率预览（仅在请求 `preview` 且能快速采样时返回）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preview: Option<Preview>,
+    /// 实际应用的高斯模糊 sigma（仅在模糊时返回）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub smooth_sigma: Option<f64>,
+    /// 直方图均衡化使用的 bin 数（仅在均衡化时返回）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub equalize: Option<usize>,
+    /// 晶胞体积（仅在文件包含晶格信息时返回），体素体积为 `cell_volume / data_length`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cell_volume: Option<f64>,
+    /// 任务对应的网格名称（仅在请求 `grids` 时返回）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub grid: Option<String>,
+    /// 任务是否已固定（仅在请求 `pinned` 时返回）
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub pinned: bool,
+    /// 实际生效的预计算分析（仅在请求 `precompute` 且生效时返回）
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub precompute: Vec<Analysis>,
 }
 
 #[post("/voxel-grid/preprocess")]
 pub async fn preprocess_voxel_grid(
+    req: HttpRequest,
     data: web::Data<AppState>,
     payload: web::Json<PreprocessRequest>,
 ) -> impl Responder {
-    let session_id = payload.session_id.clone();
+    // 固定的任务不受 TTL 约束，只允许持有管理令牌的调用方创建
+    if payload.pinned
+        && let Err(response) =
+            check_admin_token(data.config.admin_token.as_deref(), req.headers(), req.path())
+    {
+        return response;
+    }
+
+    // 客户端未指定 session_id 时按配置生成一个，通过 X-Session-Id 返回，之后的请求可以复用
+    let (session_id, assigned_session) =
+        resolve_session_id(payload.session_id.as_deref(), None, data.config.auto_session_id);
     let start_time = get_unix_timestamp_ms();
     let thread_id = get_thread_id();
     let channel_index = format!("preprocess_{}", thread_id);
 
-    let result = run_preprocess(
-        data.get_ref(),
-        &payload.file,
-        payload.chunk_size,
-        session_id.clone(),
-    );
+    // handler 的 future 在响应发出前被丢弃时（如 HTTP/2 流被客户端重置、服务关闭）取消后台解析；
+    // HTTP/1.1 下 actix 不会因客户端断开而丢弃 future，此时只能通过取消接口显式取消
+    let cancel = CancelToken::new();
+    let cancel_guard = cancel.cancel_on_drop();
+
+    // 客户端携带上次预处理的校验值、文件与任务都没有变化时直接返回之前的任务，不再解析
+    let cache_key = preprocess_cache_key(&data, &payload.file, payload.chunk_size, &payload.options);
+    let cached = match (&payload.if_unchanged, &cache_key) {
+        (Some(validator), Some(key)) => data.preprocess_cache.validate(
+            key,
+            validator,
+            file_mtime_ms(&key.path),
+            &data.task_store,
+        ),
+        _ => None,
+    };
+    let cache_hit = cached.is_some();
+    if cache_hit {
+        println!("[预处理] 文件 {} 未变化，返回缓存的任务", payload.file);
+    }
+
+    // 指定 grids 时一次解析创建多个任务，响应为数组
+    let multi_grid = payload.options.grids.is_some();
+    let result = if let Some(mut responses) = cached {
+        // 命中缓存时任务早已存在，只需按本次请求固定
+        if payload.pinned {
+            for resp in &mut responses {
+                if let Some(task) = data.task_store.get(&resp.task_id) {
+                    task.set_pinned(true);
+                    resp.pinned = true;
+                }
+            }
+        }
+        Ok(responses)
+    } else if multi_grid {
+        match validate_and_locate(&data, &payload.file, payload.chunk_size, &payload.options) {
+            Ok(located) => {
+                run_full_parse_preprocess(
+                    &data,
+                    located,
+                    payload.chunk_size,
+                    session_id.clone(),
+                    payload.pinned,
+                    payload.options.clone(),
+                    cancel,
+                )
+                .await
+            }
+            Err(response) => Err(response),
+        }
+    } else {
+        preprocess_file(
+            &data,
+            &payload.file,
+            payload.chunk_size,
+            session_id.clone(),
+            payload.pinned,
+            payload.options.clone(),
+            cancel,
+        )
+        .await
+        .map(|resp| vec![resp])
+    };
 
     let end_time = get_unix_timestamp_ms();
 
@@ -57,12 +399,112 @@ pub async fn preprocess_voxel_grid(
         eprintln!("[性能数据记录] 预处理接口 - session_id: {}, channel_index: {}", sid, channel_index);
         data.performance_store.add_record(sid, record);
     } else {
-        eprintln!("[性能数据记录] 预处理接口 - session_id 为空，未记录性能数据");
+        eprintln!("[性能数据记录] 预处理接口 - session_id 为空且未开启自动生成，未记录性能数据");
     }
 
-    match result {
-        Ok(resp) => HttpResponse::Ok().json(resp),
+    let mut response = match result {
+        Ok(mut responses) => {
+            if let Some(resolution) = payload.options.preview.filter(|_| !cache_hit) {
+                // 自动裁剪、重采样后的网格坐标与文件不一致，模糊后的值也无法从文件直接读出，只能从内存中的 chunk 采样
+                let from_file = !payload.options.autocrop
+                    && payload.options.resample_shape.is_none()
+                    && payload.options.pow2.is_none()
+                    && payload.options.equalize.is_none()
+                    && payload.options.effective_smooth_sigma().is_none();
+                for resp in &mut responses {
+                    resp.preview =
+                        build_preview(&data, &resp.task_id, resolution, from_file).await;
+                }
+            }
+            if !cache_hit && let Some(key) = cache_key {
+                data.preprocess_cache.insert(key, &responses, &data.task_store);
+            }
+            let mut response = if multi_grid {
+                HttpResponse::Ok().json(responses)
+            } else {
+                HttpResponse::Ok().json(&responses[0])
+            };
+            if payload.if_unchanged.is_some() {
+                response.headers_mut().insert(
+                    HeaderName::from_static(PREPROCESS_CACHE_HEADER),
+                    HeaderValue::from_static(if cache_hit { "hit" } else { "miss" }),
+                );
+            }
+            response
+        }
         Err(err) => err,
+    };
+    if assigned_session
+        && let Some(value) = session_id.and_then(|sid| HeaderValue::from_str(&sid).ok())
+    {
+        response
+            .headers_mut()
+            .insert(HeaderName::from_static(SESSION_ID_HEADER), value);
+    }
+    cancel_guard.disarm();
+    response
+}
+
+/// 标明预处理是否命中缓存（`hit` / `miss`）的响应头，只在请求携带 `if_unchanged` 时返回
+const PREPROCESS_CACHE_HEADER: &str = "x-preprocess-cache";
+
+/// 预处理结果缓存的键；文件路径无效或不存在时返回 None（交给正常流程返回错误）
+///
+/// 优先级只影响调度，不影响结果，不属于键的一部分
+fn preprocess_cache_key(
+    app_state: &AppState,
+    file: &str,
+    chunk_size: usize,
+    options: &PreprocessOptions,
+) -> Option<PreprocessCacheKey> {
+    let relative_path = sanitize_relative_path(file).ok()?;
+    let file_path = format!("{}/{}", app_state.config.resource_dir, relative_path.display());
+    let path = std::fs::canonicalize(&file_path).ok()?;
+    let options = PreprocessOptions {
+        priority: ParsePriority::default(),
+        ..options.clone()
+    };
+    let request = serde_json::to_string(&(chunk_size, options)).ok()?;
+    Some(PreprocessCacheKey {
+        path: path.to_string_lossy().into_owned(),
+        request,
+    })
+}
+
+/// 文件的修改时间（Unix 毫秒），文件系统不支持时返回 None
+pub(crate) fn file_mtime_ms(path: &str) -> Option<u64> {
+    let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+    let since_epoch = modified.duration_since(std::time::UNIX_EPOCH).ok()?;
+    u64::try_from(since_epoch.as_millis()).ok()
+}
+
+/// 预处理入口：按请求与解析器能力选择流程
+///
+/// 开启 `autocrop`、`resample_shape`、`pow2` 或 `equalize`，或解析器的 `shape_is_cheap` 为 false 时，先完整解析再创建任务（`run_full_parse_preprocess`）；
+/// 否则快速读取 shape 后立即返回，在后台解析（`run_preprocess`）。
+/// 指定 `grids` 时只返回第一个网格的任务，需要全部任务时先调用 `validate_and_locate`，再直接调用 `run_full_parse_preprocess`
+pub async fn preprocess_file(
+    app_state: &web::Data<AppState>,
+    file: &str,
+    chunk_size: usize,
+    session_id: Option<String>,
+    pinned: bool,
+    options: PreprocessOptions,
+    cancel: CancelToken,
+) -> Result<PreprocessResponse, HttpResponse> {
+    let located = validate_and_locate(app_state, file, chunk_size, &options)?;
+    let full_parse = options.autocrop
+        || options.resample_shape.is_some()
+        || options.pow2.is_some()
+        || options.equalize.is_some()
+        || options.grids.is_some()
+        || !located.parser.shape_is_cheap();
+    if full_parse {
+        run_full_parse_preprocess(app_state, located, chunk_size, session_id, pinned, options, cancel)
+            .await
+            .map(|mut responses| responses.swap_remove(0))
+    } else {
+        run_preprocess(app_state, located, chunk_size, session_id, pinned, options, cancel)
     }
 }
 
@@ -78,52 +520,35 @@ pub async fn preprocess_voxel_grid(
 ///
 /// ## 参数
 /// - `app_state`: 应用全局状态，包含解析器注册表、资源目录、任务存储等
-/// - `file`: 资源目录下的文件名（如 "CHGDIFF.vasp"）
+/// - `located`: `validate_and_locate` 校验并定位的文件（资源目录下的文件名如 "CHGDIFF.vasp"、完整路径、解析器与文件大小）
 /// - `chunk_size`: 每个分块包含的元素数量（Float64 个数）
+/// - `options`: 可选参数（解析优先级等）
+/// - `cancel`: 请求的取消标记，保存在任务上；取消后后台解析在下一个阶段边界停止
 ///
 /// ## 返回
 /// - `Ok(PreprocessResponse)`: 预处理成功，返回 task_id、shape、chunks 等信息
 /// - `Err(HttpResponse)`: 预处理失败，返回相应的 HTTP 错误响应
 pub fn run_preprocess(
-    app_state: &AppState,
-    file: &str,
+    app_state: &web::Data<AppState>,
+    located: LocatedFile<'_>,
     chunk_size: usize,
     session_id: Option<String>,
+    pinned: bool,
+    options: PreprocessOptions,
+    cancel: CancelToken,
 ) -> Result<PreprocessResponse, HttpResponse> {
-    // ==================== 步骤 1: 参数验证与文件路径构建 ====================
-    // 确保分块大小至少为 1，避免除零或无效分块
-    let chunk_size = chunk_size.max(1);
-    // 构建完整文件路径：{资源目录}/{文件名}
-    let file_path = format!("{}/{}", app_state.resource_dir, file);
-
-    // ==================== 步骤 2: 查找匹配的解析器 ====================
-    // 根据文件扩展名（如 .vasp）从注册表中查找对应的解析器
-    let parser = match app_state.parser_registry.find_parser_for_file(&file_path) {
-        Some((p, _)) => p,
-        None => {
-            let supported = app_state.parser_registry.supported_extensions();
-            return Err(HttpResponse::BadRequest().json(serde_json::json!({
-                "error": "不支持的文件格式",
-                "file": file,
-                "supported_extensions": supported,
-            })));
-        }
-    };
-
-    // ==================== 步骤 3: 获取文件大小 ====================
-    let file_size = match std::fs::metadata(&file_path) {
-        Ok(metadata) => metadata.len(),
-        Err(e) => {
-            return Err(HttpResponse::NotFound().json(serde_json::json!({
-                "error": "文件不存在或无法访问",
-                "file": file,
-                "details": e.to_string(),
-            })));
-        }
-    };
+    // ==================== 步骤 1~3: 参数验证、查找解析器、获取文件大小 ====================
+    // 已由调用方通过 `validate_and_locate` 完成
+    let LocatedFile {
+        name: file,
+        path: file_path,
+        parser,
+        size: file_size,
+    } = located;
+    let file_mtime = file_mtime_ms(&file_path);
 
     // ==================== 步骤 4: 快速获取 shape（只读取元数据） ====================
-    // 使用解析器的轻量级方法，只读取文件的元数据部分（如 VASP 的前 29 行）
+    // 使用解析器的轻量级方法，只读取文件的元数据部分（如 VASP 的头部，到 shape 行为止）
     // 不解析完整的体素数据，快速返回
     let shape = match parser.get_shape_from_file(&file_path) {
         Ok(s) => s,
@@ -137,23 +562,78 @@ pub fn run_preprocess(
         }
     };
 
+    let cell_volume = read_cell_volume(parser, &file_path, file);
+
+    let tile_size = options.effective_tile_size();
+    let smooth_sigma = options.effective_smooth_sigma();
+
+    // 驻留窗口模式只对支持随机读取的文件生效，否则每次按需读取都相当于完整解析一次文件
+    // tile 在文件中不是连续的一段，模糊需要相邻体素，同样无法按 chunk 单独读取
+    let resident_window = match app_state.config.resident_window {
+        0 => None,
+        window
+            if tile_size.is_none()
+                && smooth_sigma.is_none()
+                && parser.supports_range_read(&file_path) =>
+        {
+            Some(window)
+        }
+        _ => {
+            println!("[预处理] 文件 {file} 不支持按 chunk 随机读取，驻留窗口不生效，按完整解析处理");
+            None
+        }
+    };
+
+    // 驻留窗口模式不做完整解析，没有完整网格可供预计算，分析只能在首次请求时计算
+    let mut precompute = options.effective_precompute();
+    if resident_window.is_some() && !precompute.is_empty() {
+        println!("[预处理] 文件 {file} 使用驻留窗口模式，预计算 {precompute:?} 不生效");
+        precompute.clear();
+    }
+
     // ==================== 步骤 5: 计算分块信息 ====================
     // 根据 shape 计算总元素数，然后按照 chunk_size 划分
-    let data_length = shape[0] * shape[1] * shape[2];
-    let mut chunks = Vec::new();
-    let mut start = 0usize;
-    let mut index = 0usize;
-    while start < data_length {
-        let end = (start + chunk_size).min(data_length);
-        chunks.push(ChunkDescriptor { index, start, end });
-        start = end;
-        index += 1;
-    }
+    // 解析器一般已在读取 shape 时校验过，这里仍按溢出检查，避免回绕成很小的数后分块与数据不一致
+    let data_length = match checked_grid_len(shape) {
+        Ok(length) => length,
+        Err(e) => {
+            return Err(HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "文件 shape 过大",
+                "file": file,
+                "shape": shape,
+                "details": e,
+            })));
+        }
+    };
+    let (chunks, chunk_size) = match tile_size {
+        Some(tile_size) => (compute_tiles(shape, tile_size), tile_size.iter().product()),
+        None => {
+            let chunk_size = options.resolve_chunk_size(chunk_size, data_length)?;
+            (compute_chunks(data_length, chunk_size), chunk_size)
+        }
+    };
+    check_layout(file, shape, &chunks)?;
+    let chunk_count = chunks.len();
+    // 细粒度分块时 chunks 数组可能非常大，客户端不需要时不复制、不序列化
+    let response_chunks = (!options.omit_chunks).then(|| chunks.clone());
 
     // ==================== 步骤 6: 创建任务存储 ====================
     // 创建 TaskData（此时 chunk 还未解析，chunk_data 中都是 None）
-    let task_data = TaskData::new(shape, chunks.clone(), file_path.clone());
-    let task_id = app_state.task_store.insert(task_data);
+    let mut task_data = TaskData::new(shape, chunks, file_path.clone());
+    task_data.round_decimals = options.round_decimals;
+    task_data.value_transform = options.value_transform;
+    task_data.resident_window = resident_window;
+    task_data.smooth_sigma = smooth_sigma;
+    task_data.precompute = precompute.clone();
+    task_data.voxel_volume = cell_volume.map(|volume| volume / data_length as f64);
+    task_data.cancel = cancel;
+    task_data.session_id = session_id.clone();
+    // 插入之前固定，任务一进入存储就不会被过期清理或按上限移除
+    task_data.set_pinned(pinned);
+    let task_id = app_state
+        .task_store
+        .insert(task_data)
+        .map_err(|e| task_limit_response(e.limit))?;
 
     // 获取任务引用，用于后台解析
     let Some(task) = app_state.task_store.get(&task_id) else {
@@ -165,108 +645,46 @@ pub fn run_preprocess(
     // ==================== 步骤 7: 启动后台任务并行解析文件 ====================
     // 完整解析文件，然后使用多个任务并行分割成多个 chunk 并存储
     // 使用 actix_web::rt::spawn 在后台异步执行，不阻塞预处理响应
-    let parser_registry = app_state.parser_registry.clone();
-    let task_clone = task.clone();
-    let file_path_clone = file_path.clone();
-    let chunks_clone = chunks.clone();
-    let task_id_clone = task_id.clone();
-    let performance_store = app_state.performance_store.clone();
-    let session_id_clone = session_id.clone();
-    
-    actix_web::rt::spawn(async move {
-        let parse_start = get_unix_timestamp_ms();
-        let parse_thread_id = get_thread_id();
-        let parse_channel_index = format!("parse_file_{}", parse_thread_id);
-        
-        // 步骤 7.1: 解析完整文件（顺序执行，因为文件格式是顺序的）
-        let parser = match parser_registry.find_parser_for_file(&file_path_clone) {
-            Some((p, _)) => p,
-            None => {
-                eprintln!("[后台解析] 任务 {task_id_clone} 解析失败：找不到解析器");
-                return;
-            }
-        };
+    // 相同文件 + 相同 chunk_size 的解析正在进行时，只登记为订阅者，共享同一次解析
+    let subscriber = ParseSubscriber {
+        task_id: task_id.clone(),
+        task,
+        session_id,
+    };
+    // 驻留窗口模式不做完整解析，各任务的窗口位置也不同，因此不参与去重
+    let dedup = app_state.config.parse_dedup && resident_window.is_none();
+    let dedup_key = dedup.then(|| ParseKey {
+        path: std::fs::canonicalize(&file_path)
+            .map(|p| p.to_string_lossy().into_owned())
+            .unwrap_or_else(|_| file_path.clone()),
+        chunk_size,
+        tile_size,
+        round_decimals: options.round_decimals,
+        value_transform: options.value_transform,
+        smooth_sigma: smooth_sigma.map(f64::to_bits),
+    });
+    let joined_parse = match &dedup_key {
+        Some(key) => !app_state
+            .in_flight_parses
+            .join_or_start(key.clone(), subscriber.clone()),
+        None => false,
+    };
 
-        let voxel_grid = match parser.parse_from_file(&file_path_clone) {
-            Ok(grid) => grid,
-            Err(e) => {
-                eprintln!("[后台解析] 任务 {task_id_clone} 解析文件失败: {e}");
-                return;
-            }
+    if joined_parse {
+        println!("[后台解析] 任务 {task_id} 加入进行中的相同解析，不再重复解析");
+    } else if resident_window.is_some() {
+        // 后台只读取第一个窗口，之后的 chunk 在被请求时按需读取
+        let priority = options.priority;
+        actix_web::rt::spawn(run_window_job(app_state.clone(), subscriber, priority));
+    } else {
+        let job = ParseJob {
+            file_path,
+            priority: options.priority,
+            dedup_key,
+            owner: subscriber,
         };
-
-        let parse_end = get_unix_timestamp_ms();
-        
-        // 记录文件解析性能数据
-        if let Some(ref sid) = session_id_clone {
-            let record = PerformanceRecord {
-                start_time: parse_start,
-                end_time: parse_end,
-                channel_group: "backend".to_string(),
-                channel_index: parse_channel_index.clone(),
-                msg: format!("后台解析文件: {}", task_id_clone),
-            };
-            eprintln!("[性能数据记录] 后台任务 - 解析文件 - session_id: {}, channel_index: {}", sid, parse_channel_index);
-            performance_store.add_record(sid, record);
-        }
-
-        println!(
-            "[后台解析] 任务 {} 文件解析完成，耗时 {:.2}ms",
-            task_id_clone,
-            parse_end - parse_start
-        );
-
-        // 步骤 7.2: 并行分割成多个 chunk（可以并行执行）
-        let data = voxel_grid.get_data();
-        let split_start = get_unix_timestamp_ms();
-
-        // 使用多个后台任务并行分割和存储 chunk
-        let mut handles = Vec::new();
-        for descriptor in chunks_clone.iter() {
-            let task_ref = task_clone.clone();
-            let perf_store = performance_store.clone();
-            let sid = session_id_clone.clone();
-            // 为每个 chunk 复制对应的数据切片（因为多个任务需要并发读取不同部分）
-            let chunk_values: Vec<f64> = data[descriptor.start..descriptor.end].to_vec();
-            let chunk_index = descriptor.index;
-            let split_thread_id = get_thread_id();
-            let split_channel_index = format!("split_chunk_{}", split_thread_id);
-
-            // 为每个 chunk 启动一个任务来存储数据
-            let handle = actix_web::rt::spawn(async move {
-                let chunk_start = get_unix_timestamp_ms();
-                task_ref.set_chunk(chunk_index, chunk_values);
-                let chunk_end = get_unix_timestamp_ms();
-                
-                // 记录分割 chunk 性能数据
-                if let Some(ref session_id) = sid {
-                    let record = PerformanceRecord {
-                        start_time: chunk_start,
-                        end_time: chunk_end,
-                        channel_group: "backend".to_string(),
-                        channel_index: split_channel_index.clone(),
-                        msg: format!("后台分割 Chunk {}", chunk_index),
-                    };
-                    eprintln!("[性能数据记录] 后台任务 - 分割Chunk - session_id: {}, channel_index: {}", session_id, split_channel_index);
-                    perf_store.add_record(session_id, record);
-                }
-            });
-            handles.push(handle);
-        }
-
-        // 等待所有分割任务完成
-        for handle in handles {
-            let _ = handle.await;
-        }
-
-        let split_end = get_unix_timestamp_ms();
-        println!(
-            "[后台解析] 任务 {} 分割完成，共 {} 个 chunk，耗时 {:.2}ms",
-            task_id_clone,
-            task_clone.chunks.len(),
-            split_end - split_start
-        );
-    });
+        actix_web::rt::spawn(run_parse_job(app_state.clone(), job));
+    }
 
     // ==================== 步骤 8: 构造并返回预处理响应 ====================
     // 立即返回，不等待文件解析完成
@@ -275,9 +693,1417 @@ pub fn run_preprocess(
         task_id,
         file: file.to_string(),
         file_size,
+        file_mtime,
         shape,
         data_length,
         chunk_size,
-        chunks,
+        auto_chunk_bytes: options.auto_chunk_bytes,
+        chunks: response_chunks,
+        // chunk 尚未就绪，取值摘要只能之后通过 layout 接口获取
+        chunk_metadata: None,
+        chunk_count,
+        round_decimals: options.round_decimals,
+        value_transform: options.value_transform,
+        resident_window,
+        original_shape: None,
+        resample_method: None,
+        crop: None,
+        pow2: None,
+        joined_parse,
+        tile_size,
+        preview: None,
+        smooth_sigma,
+        equalize: None,
+        cell_volume,
+        grid: None,
+        pinned,
+        precompute,
+    })
+}
+
+/// 先完整解析再创建任务的预处理，用于自动裁剪、重采样、多网格与读取 shape 代价高的格式
+///
+/// 裁剪后的 shape 与分块取决于数据内容，必须先解析完整文件才能确定；重采样需要完整网格做 FFT 或重分箱；
+/// 解析器的 `shape_is_cheap` 为 false 时，快速读取 shape 本身就接近一次完整解析，两阶段流程会读两遍文件。
+/// 因此与普通预处理不同，这里在返回响应前完成解析（仍受解析队列调度），
+/// 得到的网格（依次重采样或重分箱、模糊、变换、舍入，开启 `autocrop` 时再裁剪，然后直方图均衡化，最后按 `pow2` 调整到 2 的幂）直接通过 `TaskData::from_grid` 创建任务，所有 chunk 立即就绪。
+/// 指定 `grids` 时只解析一次文件，为每个选中的网格各创建一个任务，按 `grids` 的顺序返回；否则只返回一个任务。
+/// 等待解析名额后与解析完成后检查 `cancel`，已取消时不再继续
+pub async fn run_full_parse_preprocess(
+    app_state: &web::Data<AppState>,
+    located: LocatedFile<'_>,
+    chunk_size: usize,
+    session_id: Option<String>,
+    pinned: bool,
+    options: PreprocessOptions,
+    cancel: CancelToken,
+) -> Result<Vec<PreprocessResponse>, HttpResponse> {
+    let LocatedFile {
+        name: file,
+        path: file_path,
+        parser,
+        size: file_size,
+    } = located;
+    let file_mtime = file_mtime_ms(&file_path);
+    let cell_volume = read_cell_volume(parser, &file_path, file);
+
+    let autocrop = options.autocrop;
+    let threshold = options.autocrop_threshold;
+    if autocrop && (!threshold.is_finite() || threshold < 0.0) {
+        return Err(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "autocrop_threshold 必须是非负的有限数",
+            "autocrop_threshold": threshold,
+        })));
+    }
+
+    let _permit = app_state.parse_queue.acquire(options.priority).await;
+    if cancel.is_cancelled() {
+        return Err(cancelled_response(file));
+    }
+
+    // 解析、变换、裁剪与校验和都是 CPU 密集操作，放到阻塞线程池执行
+    let parser_registry = app_state.parser_registry.clone();
+    let parse_path = file_path.clone();
+    let multi_grid = options.grids.is_some();
+    let parse_result = web::block(move || {
+        let (parser, _) = parser_registry
+            .find_parser_for_file(&parse_path)
+            .ok_or_else(|| "找不到解析器".to_string())?;
+        if multi_grid {
+            parser.parse_grids_from_file(&parse_path).map_err(|e| e.to_string())
+        } else {
+            let grid = parser.parse_from_file(&parse_path).map_err(|e| e.to_string())?;
+            Ok(vec![(DEFAULT_GRID_NAME.to_string(), grid)])
+        }
+    })
+    .await;
+    let parsed = match parse_result {
+        Ok(Ok(grids)) => grids,
+        Ok(Err(e)) => {
+            return Err(HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "解析文件失败",
+                "file": file,
+                "details": e,
+            })));
+        }
+        Err(e) => {
+            return Err(HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "解析线程异常",
+                "file": file,
+                "details": e.to_string(),
+            })));
+        }
+    };
+    if cancel.is_cancelled() {
+        return Err(cancelled_response(file));
+    }
+    let selected = match &options.grids {
+        Some(selectors) => select_grids(file, parsed, selectors)?,
+        None => parsed,
+    };
+    // 自动裁剪只会缩小网格，按裁剪前的 shape 检查填充后的体素数即可
+    if let Some(mode) = options.pow2 {
+        for (_, grid) in &selected {
+            let shape = options.resample_shape.unwrap_or(grid.shape);
+            let padded = pow2_shape(shape, mode);
+            if checked_grid_len(padded).is_ok_and(|length| length <= MAX_POW2_VOXELS) {
+                continue;
+            }
+            return Err(HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "pow2 调整后的体素总数超过上限",
+                "shape": shape,
+                "pow2_shape": padded,
+                "max_voxels": MAX_POW2_VOXELS,
+            })));
+        }
+    }
+
+    let value_transform = options.value_transform;
+    let round_decimals = options.round_decimals;
+    let smooth_sigma = options.effective_smooth_sigma();
+    let resample_shape = options.resample_shape;
+    let resample_method = options.resample_method;
+    let pow2 = options.pow2;
+    let equalize = options.equalize;
+    let precompute = options.effective_precompute();
+    let process_precompute = precompute.clone();
+    let process_cancel = cancel.clone();
+    let process_result = web::block(move || {
+        let mut processed = Vec::with_capacity(selected.len());
+        for (name, mut grid) in selected {
+            if process_cancel.is_cancelled() {
+                return Err(CANCELLED_REASON.to_string());
+            }
+            let original_shape = grid.shape;
+            if let Some(target) = resample_shape {
+                grid = match resample_method {
+                    ResampleMethod::Fft => resample_grid(&grid, target)?,
+                    ResampleMethod::Rebin => grid.rebin_conservative(target),
+                };
+            }
+            if let Some(sigma) = smooth_sigma {
+                grid = grid.gaussian_blur(sigma);
+            }
+            if !value_transform.is_none() {
+                grid.map_values(|value| value_transform.apply(value));
+            }
+            if let Some(decimals) = round_decimals {
+                grid.round_to_decimals(decimals);
+            }
+
+            // 未开启自动裁剪，或全部体素都低于阈值时不裁剪，返回整个网格
+            let uncropped_shape = grid.shape;
+            let (min, max) = autocrop
+                .then(|| grid.nonzero_bounds(threshold))
+                .flatten()
+                .unwrap_or(([0; 3], uncropped_shape));
+            if (min, max) != ([0; 3], uncropped_shape) {
+                grid = grid.crop(min, max)?;
+            }
+            // 均衡化只统计裁剪后的数据，也不统计 pow2 填充的值；均衡化后的值需要重新舍入
+            if let Some(bins) = equalize {
+                grid = grid.histogram_equalize(bins);
+                if let Some(decimals) = round_decimals {
+                    grid.round_to_decimals(decimals);
+                }
+            }
+            // shape 已经是 2 的幂时不复制网格
+            let pow2 = pow2.map(|mode| {
+                let source_shape = grid.shape;
+                let mut offset = [0; 3];
+                if pow2_shape(source_shape, mode) != source_shape {
+                    (grid, offset) = grid.pad_or_crop_to_pow2(mode);
+                }
+                Pow2Adjustment {
+                    mode,
+                    source_shape,
+                    offset,
+                }
+            });
+            let data_hash = grid.checksum();
+            let data_sum = grid.sum();
+            let analyses = PrecomputedAnalyses::compute(&grid, &process_precompute);
+            processed.push(ProcessedGrid {
+                name,
+                grid,
+                original_shape,
+                uncropped_shape,
+                crop: CropBounds { min, max },
+                pow2,
+                data_hash,
+                data_sum,
+                analyses,
+            });
+        }
+        Ok::<_, String>(processed)
     })
+    .await;
+
+    let processed = match process_result {
+        Ok(Ok(processed)) => processed,
+        Ok(Err(_)) if cancel.is_cancelled() => return Err(cancelled_response(file)),
+        Ok(Err(e)) => {
+            return Err(HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "解析文件失败",
+                "file": file,
+                "details": e,
+            })));
+        }
+        Err(e) => {
+            return Err(HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "解析线程异常",
+                "file": file,
+                "details": e.to_string(),
+            })));
+        }
+    };
+
+    // 先为所有网格构建任务，再一次性插入：任务数上限不足以容纳全部网格时一个任务都不创建，不会留下无人引用的任务
+    let mut tasks = Vec::with_capacity(processed.len());
+    let mut responses = Vec::with_capacity(processed.len());
+    for entry in processed {
+        let ProcessedGrid {
+            name,
+            grid,
+            original_shape,
+            uncropped_shape,
+            crop,
+            pow2,
+            data_hash,
+            data_sum,
+            analyses,
+        } = entry;
+        let shape = grid.shape;
+        let data_length = grid.data.len();
+        let label = if multi_grid {
+            format!("{file} 的网格 {name}")
+        } else {
+            file.to_string()
+        };
+        if let Some(target) = resample_shape {
+            let method = match resample_method {
+                ResampleMethod::Fft => "FFT 重采样",
+                ResampleMethod::Rebin => "守恒重分箱",
+            };
+            println!("[预处理] 文件 {label} {method}: shape {original_shape:?} -> {target:?}");
+        }
+        if autocrop {
+            println!(
+                "[预处理] 文件 {label} 自动裁剪: shape {uncropped_shape:?} -> {shape:?}，范围 {:?}..{:?}",
+                crop.min, crop.max
+            );
+        } else if multi_grid {
+            println!("[预处理] 文件 {label} 已随同一次解析创建任务: shape {shape:?}");
+        } else if resample_shape.is_none() && pow2.is_none() && equalize.is_none() {
+            println!("[预处理] 文件 {file} 读取 shape 的代价高，已直接完整解析: shape {shape:?}");
+        }
+        if let Some(bins) = equalize {
+            println!("[预处理] 文件 {label} 直方图均衡化: {bins} 个 bin");
+        }
+        if let Some(pow2) = &pow2 {
+            let method = match pow2.mode {
+                PadMode::Crop => "裁剪",
+                PadMode::PadZero => "填充 0",
+                PadMode::PadEdge => "填充边界值",
+            };
+            println!(
+                "[预处理] 文件 {label} 调整到 2 的幂（{method}）: shape {:?} -> {shape:?}，偏移 {:?}",
+                pow2.source_shape, pow2.offset
+            );
+        }
+
+        let tile_size = options.effective_tile_size();
+        let (mut task_data, chunk_size) = match tile_size {
+            Some(tile_size) => (
+                TaskData::from_grid_tiled(grid, tile_size, file_path.clone()),
+                tile_size.iter().product(),
+            ),
+            None => {
+                // 按裁剪后的体素数推导，字节预算针对的是实际传输的 chunk
+                let chunk_size = options.resolve_chunk_size(chunk_size, grid.data.len())?;
+                (
+                    TaskData::from_grid(grid, chunk_size, file_path.clone()),
+                    chunk_size,
+                )
+            }
+        };
+        task_data.round_decimals = round_decimals;
+        task_data.value_transform = value_transform;
+        task_data.smooth_sigma = smooth_sigma;
+        task_data.equalize_bins = equalize;
+        // 裁剪不改变体素间距，体素体积按裁剪前（重采样后）的体素数换算；
+        // 守恒重分箱保持的是数值之和，按原始体素数换算才能使积分与原网格一致
+        let volume_shape = match (resample_shape, resample_method) {
+            (Some(_), ResampleMethod::Rebin) => original_shape,
+            _ => uncropped_shape,
+        };
+        let volume_length: usize = volume_shape.iter().product();
+        task_data.voxel_volume = cell_volume.map(|volume| volume / volume_length as f64);
+        let _ = task_data.data_hash.set(data_hash);
+        let _ = task_data.data_sum.set(data_sum);
+        task_data.precompute = precompute.clone();
+        task_data.store_analyses(&analyses);
+        // 只有任务的网格与文件逐层对应（未裁剪、重采样、模糊、均衡化或调整 shape，按线性分块）时才能追加新的层
+        let appendable = !autocrop
+            && resample_shape.is_none()
+            && smooth_sigma.is_none()
+            && equalize.is_none()
+            && pow2.is_none()
+            && tile_size.is_none()
+            && !multi_grid
+            && parser.supports_append(&file_path);
+        if appendable {
+            *task_data.append.get_mut() = Some(AppendState {
+                file_size,
+                file_mtime,
+                cursor: None,
+                chunk_size,
+                appends: Vec::new(),
+            });
+        }
+        task_data.cancel = cancel.clone();
+        task_data.session_id = session_id.clone();
+        task_data.set_pinned(pinned);
+        check_layout(file, shape, &task_data.chunks)?;
+        let chunk_count = task_data.chunks.len();
+        let chunks = (!options.omit_chunks).then(|| task_data.chunks.clone());
+        let chunk_metadata = options.chunk_metadata.then(|| {
+            task_data
+                .chunks
+                .iter()
+                .map(|descriptor| task_data.chunk_summary(descriptor.index))
+                .collect()
+        });
+        tasks.push(task_data);
+
+        responses.push(PreprocessResponse {
+            task_id: String::new(),
+            file: file.to_string(),
+            file_size,
+            file_mtime,
+            shape,
+            data_length,
+            chunk_size,
+            auto_chunk_bytes: options.auto_chunk_bytes,
+            chunks,
+            chunk_metadata,
+            chunk_count,
+            round_decimals,
+            value_transform,
+            resident_window: None,
+            original_shape: (autocrop || resample_shape.is_some()).then_some(original_shape),
+            resample_method: resample_shape.map(|_| resample_method),
+            crop: autocrop.then_some(crop),
+            pow2,
+            joined_parse: false,
+            tile_size,
+            preview: None,
+            smooth_sigma,
+            equalize,
+            cell_volume,
+            grid: multi_grid.then_some(name),
+            pinned,
+            precompute: precompute.clone(),
+        });
+    }
+
+    let task_ids = app_state
+        .task_store
+        .insert_all(tasks)
+        .map_err(|e| task_limit_response(e.limit))?;
+    for (response, task_id) in responses.iter_mut().zip(task_ids) {
+        response.task_id = task_id;
+    }
+    Ok(responses)
+}
+
+/// 完成重采样、变换、舍入与裁剪后的单个网格
+struct ProcessedGrid {
+    name: String,
+    grid: VoxelGrid,
+    /// 文件中的 shape
+    original_shape: [usize; 3],
+    /// 裁剪前（重采样后）的 shape
+    uncropped_shape: [usize; 3],
+    crop: CropBounds,
+    /// 2 的幂调整（仅在请求 `pow2` 时存在）
+    pow2: Option<Pow2Adjustment>,
+    data_hash: u64,
+    data_sum: f64,
+    /// 请求的预计算分析
+    analyses: PrecomputedAnalyses,
+}
+
+/// 按 `grids` 的顺序从解析结果中取出网格；名称或序号不存在、同一网格被重复选择时返回 400
+fn select_grids(
+    file: &str,
+    parsed: Vec<(String, VoxelGrid)>,
+    selectors: &[GridSelector],
+) -> Result<Vec<(String, VoxelGrid)>, HttpResponse> {
+    let available: Vec<String> = parsed.iter().map(|(name, _)| name.clone()).collect();
+    let mut slots: Vec<Option<(String, VoxelGrid)>> = parsed.into_iter().map(Some).collect();
+    let mut selected = Vec::with_capacity(selectors.len());
+    for selector in selectors {
+        let index = match selector {
+            GridSelector::Index(index) => Some(*index).filter(|&index| index < slots.len()),
+            GridSelector::Name(name) => available.iter().position(|candidate| candidate == name),
+        };
+        let Some(index) = index else {
+            return Err(HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "文件中没有请求的网格",
+                "file": file,
+                "grid": selector,
+                "available": available,
+            })));
+        };
+        let Some(entry) = slots[index].take() else {
+            return Err(HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "grids 中的网格重复",
+                "grid": selector,
+            })));
+        };
+        selected.push(entry);
+    }
+    Ok(selected)
+}
+
+/// 把解析得到的网格重采样到 `target`（已通过 `validate_and_locate` 校验）
+#[cfg(feature = "fft")]
+fn resample_grid(grid: &VoxelGrid, target: [usize; 3]) -> Result<VoxelGrid, String> {
+    grid.fft_resample(target)
+}
+
+/// 未启用 `fft` feature 时 `validate_and_locate` 已拒绝 `resample_shape`，不会走到这里
+#[cfg(not(feature = "fft"))]
+fn resample_grid(_grid: &VoxelGrid, target: [usize; 3]) -> Result<VoxelGrid, String> {
+    Err(format!("未启用 fft feature，无法重采样到 {target:?}"))
+}
+
+/// 通过 `validate_and_locate` 校验并定位的资源文件
+pub struct LocatedFile<'a> {
+    /// 请求中的文件名（资源目录下的相对路径）
+    pub name: &'a str,
+    /// 完整文件路径
+    pub path: String,
+    pub parser: &'a dyn VoxelGridParser,
+    /// 文件大小（字节）
+    pub size: u64,
+}
+
+/// 预处理的公共前置步骤：参数验证、解析队列背压检查、查找解析器、获取文件大小
+///
+/// 每个请求只调用一次，结果交给 `run_preprocess` / `run_full_parse_preprocess`，
+/// 避免两次检查之间状态变化（如解析队列长度）得出不同的结论
+pub fn validate_and_locate<'a>(
+    app_state: &'a AppState,
+    file: &'a str,
+    chunk_size: usize,
+    options: &PreprocessOptions,
+) -> Result<LocatedFile<'a>, HttpResponse> {
+    // ==================== 步骤 1: 参数验证与文件路径构建 ====================
+    if let Some(bytes) = options.auto_chunk_bytes {
+        if options.chunk_mode == ChunkMode::Tile || chunk_size != 0 {
+            return Err(HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "auto_chunk_bytes 不能与 chunk_size 或 tile 模式同时使用",
+                "auto_chunk_bytes": bytes,
+                "chunk_size": chunk_size,
+            })));
+        }
+        if bytes < AUTO_CHUNK_ELEMENT_BYTES {
+            return Err(HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "auto_chunk_bytes 至少要容纳一个元素",
+                "auto_chunk_bytes": bytes,
+                "min": AUTO_CHUNK_ELEMENT_BYTES,
+            })));
+        }
+    }
+    // chunk_size 为 0 时明确拒绝，而不是静默改成 1（那样会为每个元素创建一个 chunk）
+    match options.chunk_mode {
+        ChunkMode::Linear if chunk_size == 0 && options.auto_chunk_bytes.is_none() => {
+            return Err(HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "chunk_size 必须大于 0",
+                "chunk_size": chunk_size,
+            })));
+        }
+        ChunkMode::Tile if !options.tile_size.is_some_and(|size| size.iter().all(|&n| n > 0)) => {
+            return Err(HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "tile 模式需要 tile_size，且每个维度都必须大于 0",
+                "tile_size": options.tile_size,
+            })));
+        }
+        ChunkMode::Tile => {
+            // tile 的体素数即 chunk_size，同样不能溢出
+            if let Some(size) = options.tile_size
+                && let Err(e) = checked_grid_len(size)
+            {
+                return Err(HttpResponse::BadRequest().json(serde_json::json!({
+                    "error": "tile_size 过大",
+                    "tile_size": size,
+                    "details": e,
+                })));
+            }
+        }
+        _ => {}
+    }
+    if let Some(decimals) = options.round_decimals
+        && decimals > MAX_ROUND_DECIMALS
+    {
+        return Err(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "round_decimals 超出范围",
+            "round_decimals": decimals,
+            "max": MAX_ROUND_DECIMALS,
+        })));
+    }
+
+    if let Some(sigma) = options.smooth_sigma
+        && !(0.0..=MAX_SMOOTH_SIGMA).contains(&sigma)
+    {
+        return Err(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "smooth_sigma 超出范围",
+            "smooth_sigma": sigma,
+            "max": MAX_SMOOTH_SIGMA,
+        })));
+    }
+
+    if let Some(bins) = options.equalize
+        && !(1..=MAX_HISTOGRAM_BINS).contains(&bins)
+    {
+        return Err(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("equalize 的 bin 数必须在 1..={MAX_HISTOGRAM_BINS} 之间"),
+            "equalize": bins,
+        })));
+    }
+
+    if let Some(target) = options.resample_shape {
+        if options.resample_method == ResampleMethod::Fft && !cfg!(feature = "fft") {
+            return Err(HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "resample_shape 需要以 fft feature 编译服务端",
+                "resample_shape": target,
+                "message": "可改用 resample_method=rebin",
+            })));
+        }
+        let valid = !target.contains(&0)
+            && checked_grid_len(target).is_ok_and(|length| length <= MAX_RESAMPLE_VOXELS);
+        if !valid {
+            return Err(HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "resample_shape 的每个维度都必须大于 0，且体素总数不能超过上限",
+                "resample_shape": target,
+                "max_voxels": MAX_RESAMPLE_VOXELS,
+            })));
+        }
+    }
+
+    if options.grids.as_ref().is_some_and(|grids| grids.is_empty()) {
+        return Err(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "grids 不能为空",
+        })));
+    }
+
+    if let Some(resolution) = options.preview
+        && !(1..=MAX_PREVIEW_RESOLUTION).contains(&resolution)
+    {
+        return Err(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "preview 超出范围",
+            "preview": resolution,
+            "max": MAX_PREVIEW_RESOLUTION,
+        })));
+    }
+
+    // 解析队列已满时直接拒绝，给客户端明确的背压信号，而不是无限排队
+    let max_queue_depth = app_state.config.max_parse_queue_depth;
+    let queued = app_state.parse_queue.queued();
+    if max_queue_depth > 0 && queued >= max_queue_depth {
+        return Err(HttpResponse::ServiceUnavailable()
+            .append_header(("Retry-After", OVERLOAD_RETRY_AFTER_SECS.to_string()))
+            .json(serde_json::json!({
+                "error": "服务繁忙，解析队列已满，请稍后重试",
+                "queued": queued,
+                "max_queue_depth": max_queue_depth,
+                "retry_after_secs": OVERLOAD_RETRY_AFTER_SECS,
+            })));
+    }
+
+    // 任务数已达上限（拒绝策略）时提前拒绝，避免白白读取文件
+    if app_state.task_store.is_full() {
+        return Err(task_limit_response(app_state.task_store.max_tasks()));
+    }
+
+    // 构建完整文件路径：{资源目录}/{相对路径}，相对路径可以包含子目录，但不能离开资源目录
+    let relative_path = match sanitize_relative_path(file) {
+        Ok(path) => path,
+        Err(e) => {
+            return Err(HttpResponse::BadRequest().json(serde_json::json!({
+                "error": e,
+                "file": file,
+            })));
+        }
+    };
+    let file_path = format!("{}/{}", app_state.config.resource_dir, relative_path.display());
+
+    // ==================== 步骤 2: 查找匹配的解析器 ====================
+    // 根据文件扩展名（如 .vasp）从注册表中查找对应的解析器
+    let parser = match app_state.parser_registry.find_parser_for_file(&file_path) {
+        Some((p, _)) => p,
+        None => {
+            let supported = app_state.parser_registry.supported_extensions();
+            return Err(HttpResponse::BadRequest().json(serde_json::json!({
+                "error": "不支持的文件格式",
+                "file": file,
+                "supported_extensions": supported,
+            })));
+        }
+    };
+
+    // ==================== 步骤 3: 获取文件大小 ====================
+    let file_size = match std::fs::metadata(&file_path) {
+        Ok(metadata) => metadata.len(),
+        Err(e) => {
+            return Err(HttpResponse::NotFound().json(serde_json::json!({
+                "error": "文件不存在或无法访问",
+                "file": file,
+                "details": e.to_string(),
+            })));
+        }
+    };
+
+    Ok(LocatedFile {
+        name: file,
+        path: file_path,
+        parser,
+        size: file_size,
+    })
+}
+
+/// 分块布局违反不变量（有空 chunk 或长度之和与 data_length 不一致）时直接失败，
+/// 不创建任务，避免客户端拿到永远无法完整拼接的网格
+fn check_layout(
+    file: &str,
+    shape: [usize; 3],
+    chunks: &[ChunkDescriptor],
+) -> Result<(), HttpResponse> {
+    let data_length = shape.iter().product();
+    check_chunk_layout(chunks, data_length).map_err(|details| {
+        eprintln!("[预处理] 文件 {file} 的分块布局无效: {details}");
+        HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "分块布局无效",
+            "file": file,
+            "shape": shape,
+            "data_length": data_length,
+            "chunk_count": chunks.len(),
+            "details": details,
+        }))
+    })
+}
+
+/// 读取文件的晶胞体积；晶格信息无法解析时只记录警告，不影响预处理
+fn read_cell_volume(parser: &dyn VoxelGridParser, file_path: &str, file: &str) -> Option<f64> {
+    match parser.get_cell_volume_from_file(file_path) {
+        Ok(volume) => volume,
+        Err(e) => {
+            eprintln!("[预处理] 警告: 文件 {file} 的晶格信息无法解析，不计算晶胞体积: {e}");
+            None
+        }
+    }
+}
+
+/// 请求已取消时记录的原因
+const CANCELLED_REASON: &str = "请求已取消";
+
+/// 请求在返回响应前被取消（handler 已被丢弃，响应通常不会被读取）
+fn cancelled_response(file: &str) -> HttpResponse {
+    println!("[预处理] 文件 {file} 的请求已取消，停止处理");
+    HttpResponse::Conflict().json(serde_json::json!({
+        "error": CANCELLED_REASON,
+        "file": file,
+    }))
+}
+
+/// 任务数达到上限（拒绝策略）时的错误响应
+fn task_limit_response(limit: usize) -> HttpResponse {
+    HttpResponse::ServiceUnavailable().json(serde_json::json!({
+        "error": "任务数已达上限，请稍后重试或先释放已有任务",
+        "max_tasks": limit,
+    }))
+}
+
+/// 为任务生成低分辨率预览
+///
+/// 所需的 chunk 都已在内存中时（如自动裁剪）直接采样；否则在 `from_file` 为 true 且文件支持
+/// 随机读取时，按采样行从文件中跨步读取，无需等待完整解析。两者都不可行时不生成预览
+async fn build_preview(
+    app_state: &AppState,
+    task_id: &str,
+    resolution: usize,
+    from_file: bool,
+) -> Option<Preview> {
+    let task = app_state.task_store.get(task_id)?;
+    let plan = PreviewPlan::new(task.shape, resolution);
+    if plan.is_empty() {
+        return None;
+    }
+
+    if let Some(values) = sample_preview_from_chunks(&task, &plan) {
+        return Some(plan.encode(&values));
+    }
+    if !from_file {
+        println!("[预处理] 任务 {task_id} 的 chunk 不在内存中，跳过预览");
+        return None;
+    }
+
+    let parser_registry = app_state.parser_registry.clone();
+    let file_path = task.file_path.clone();
+    let sampled = web::block(move || {
+        let (parser, _) = parser_registry
+            .find_parser_for_file(&file_path)
+            .ok_or_else(|| "找不到解析器".to_string())?;
+        if !parser.supports_range_read(&file_path) {
+            return Err("文件不支持随机读取".to_string());
+        }
+        let row_span = plan.row_span();
+        let mut values = Vec::with_capacity(plan.len());
+        for (row_start, xs) in plan.rows() {
+            let row = parser
+                .parse_chunk_from_file(&file_path, row_start, row_start + row_span)
+                .map_err(|e| e.to_string())?;
+            values.extend(xs.map(|x| row[x]));
+        }
+        Ok((plan, values))
+    })
+    .await;
+
+    match sampled {
+        Ok(Ok((plan, mut values))) => {
+            if !task.value_transform.is_none() {
+                values.iter_mut().for_each(|v| *v = task.value_transform.apply(*v));
+            }
+            if let Some(decimals) = task.round_decimals {
+                round_values_to_decimals(&mut values, decimals);
+            }
+            Some(plan.encode(&values))
+        }
+        Ok(Err(e)) => {
+            println!("[预处理] 任务 {task_id} 无法快速生成预览: {e}");
+            None
+        }
+        Err(e) => {
+            eprintln!("[预处理] 任务 {task_id} 生成预览的线程异常: {e}");
+            None
+        }
+    }
+}
+
+/// 从已就绪的 chunk 中采样预览，任一所需 chunk 未就绪时返回 None
+fn sample_preview_from_chunks(task: &TaskData, plan: &PreviewPlan) -> Option<Vec<f64>> {
+    let mut values = Vec::with_capacity(plan.len());
+    for (row_start, xs) in plan.rows() {
+        for x in xs {
+            let (chunk_index, offset) = task.locate(row_start + x)?;
+            values.push(task.peek_chunk(chunk_index, |data| data[offset])?);
+        }
+    }
+    Some(values)
+}
+
+/// 一次后台解析所需的全部信息
+struct ParseJob {
+    file_path: String,
+    priority: ParsePriority,
+    /// 去重键；为 None 时不参与去重，只服务于发起者自己的任务
+    dedup_key: Option<ParseKey>,
+    /// 发起解析的任务
+    owner: ParseSubscriber,
+}
+
+/// 后台解析：解析完整文件，然后把数据分割到所有订阅该解析的任务中
+async fn run_parse_job(app_state: web::Data<AppState>, job: ParseJob) {
+    let ParseJob {
+        file_path,
+        priority,
+        dedup_key,
+        owner,
+    } = job;
+    let task_id = owner.task_id.clone();
+    // 订阅者的模糊、舍入与变换设置都相同（属于去重键的一部分）
+    let round_decimals = owner.task.round_decimals;
+    let value_transform = owner.task.value_transform;
+    let smooth_sigma = owner.task.smooth_sigma;
+
+    // 名额在整个后台任务结束（解析 + 分割）后自动归还
+    // 空闲名额优先分配给高优先级任务
+    let _permit = app_state.parse_queue.acquire(priority).await;
+
+    // 排队期间所有订阅者都已取消时不再解析
+    let abandoned = match &dedup_key {
+        Some(key) => app_state.in_flight_parses.abandon_if_cancelled(key),
+        None => owner.task.cancel.is_cancelled(),
+    };
+    if abandoned {
+        println!("[后台解析] 任务 {task_id} 已取消，跳过解析");
+        return;
+    }
+
+    let parse_start = get_unix_timestamp_ms();
+    let parse_thread_id = get_thread_id();
+    let parse_channel_index = format!("parse_file_{}", parse_thread_id);
+
+    // 步骤 7.1: 解析完整文件（顺序执行，因为文件格式是顺序的）
+    // 解析是 CPU 密集的同步操作，放到阻塞线程池执行，避免占用 actix worker
+    // 解析进度同步写入所有订阅者的任务（包括解析中途加入的）
+    let parser_registry = app_state.parser_registry.clone();
+    let parse_path = file_path.clone();
+    let in_flight_parses = app_state.in_flight_parses.clone();
+    let progress_key = dedup_key.clone();
+    let owner_task = owner.task.clone();
+    let parse_result = web::block(move || {
+        let (parser, _) = parser_registry
+            .find_parser_for_file(&parse_path)
+            .ok_or_else(|| "找不到解析器".to_string())?;
+        let report_progress = |values_read: usize| match &progress_key {
+            Some(key) => in_flight_parses.for_each_subscriber(key, |subscriber| {
+                subscriber.task.progress.set_values_read(values_read)
+            }),
+            None => owner_task.progress.set_values_read(values_read),
+        };
+        parser
+            .parse_from_file_with_progress(&parse_path, &report_progress)
+            .map_err(|e| e.to_string())
+    })
+    .await;
+
+    // 解析结束后立即从进行中列表移除，此后到达的相同请求会发起新的解析
+    let mut subscribers = match &dedup_key {
+        Some(key) => app_state.in_flight_parses.finish(key),
+        None => vec![owner],
+    };
+    // 解析期间取消的任务不再接收结果；全部取消时跳过后续的变换与分割
+    subscribers.retain(|subscriber| !subscriber.task.cancel.is_cancelled());
+    if subscribers.is_empty() {
+        println!("[后台解析] 任务 {task_id} 已取消，丢弃解析结果");
+        return;
+    }
+
+    // 解析失败时把原因记录到所有订阅者的任务上，客户端可通过状态 / chunk 接口看到具体错误
+    let mut voxel_grid = match parse_result {
+        Ok(Ok(grid)) => grid,
+        Ok(Err(e)) => {
+            eprintln!("[后台解析] 任务 {task_id} 解析文件失败: {e}");
+            for subscriber in &subscribers {
+                subscriber.task.mark_failed(e.clone());
+            }
+            return;
+        }
+        Err(e) => {
+            eprintln!("[后台解析] 任务 {task_id} 解析线程异常: {e}");
+            for subscriber in &subscribers {
+                subscriber.task.mark_failed(format!("解析线程异常: {e}"));
+            }
+            return;
+        }
+    };
+
+    let parse_end = get_unix_timestamp_ms();
+
+    // 记录文件解析性能数据（每个订阅者的会话都记录一份）
+    for subscriber in &subscribers {
+        if let Some(ref sid) = subscriber.session_id {
+            let record = PerformanceRecord {
+                start_time: parse_start,
+                end_time: parse_end,
+                channel_group: "backend".to_string(),
+                channel_index: parse_channel_index.clone(),
+                msg: format!("后台解析文件: {}", subscriber.task_id),
+            };
+            eprintln!("[性能数据记录] 后台任务 - 解析文件 - session_id: {}, channel_index: {}", sid, parse_channel_index);
+            app_state.performance_store.add_record(sid, record);
+        }
+    }
+
+    println!(
+        "[后台解析] 任务 {} 文件解析完成，耗时 {:.2}ms",
+        task_id,
+        parse_end - parse_start
+    );
+
+    if app_state.config.ordering_check {
+        check_data_ordering(&task_id, &voxel_grid);
+    }
+
+    if let Some(sigma) = smooth_sigma {
+        voxel_grid = voxel_grid.gaussian_blur(sigma);
+    }
+    if !value_transform.is_none() {
+        voxel_grid.map_values(|value| value_transform.apply(value));
+    }
+    if let Some(decimals) = round_decimals {
+        voxel_grid.round_to_decimals(decimals);
+    }
+
+    // 校验和与数据之和只在后台计算一次，之后通过状态接口与 chunk 响应头返回
+    let data_hash = voxel_grid.checksum();
+    let data_sum = voxel_grid.sum();
+
+    // 预计算在分割之前完成，订阅者看到第一个 chunk 就绪时分析也已就绪
+    // 各订阅者的数据相同，按请求的并集计算一次，结果写入所有订阅者
+    let precompute: Vec<Analysis> = normalize_analyses(
+        &subscribers
+            .iter()
+            .flat_map(|subscriber| subscriber.task.precompute.iter().copied())
+            .collect::<Vec<_>>(),
+    );
+    if !precompute.is_empty() {
+        let precompute_start = get_unix_timestamp_ms();
+        let requested = precompute.clone();
+        let computed = web::block(move || {
+            let analyses = PrecomputedAnalyses::compute(&voxel_grid, &requested);
+            (voxel_grid, analyses)
+        })
+        .await;
+        let analyses;
+        (voxel_grid, analyses) = match computed {
+            Ok(computed) => computed,
+            Err(e) => {
+                eprintln!("[后台解析] 任务 {task_id} 预计算线程异常: {e}");
+                for subscriber in &subscribers {
+                    subscriber.task.mark_failed(format!("预计算线程异常: {e}"));
+                }
+                return;
+            }
+        };
+        for subscriber in &subscribers {
+            subscriber.task.store_analyses(&analyses);
+        }
+        println!(
+            "[后台解析] 任务 {task_id} 预计算 {precompute:?} 完成，耗时 {:.2}ms",
+            get_unix_timestamp_ms() - precompute_start
+        );
+    }
+
+    // 步骤 7.2: 并行分割成多个 chunk（可以并行执行），分发给所有订阅者
+    let data = voxel_grid.get_data();
+    for subscriber in &subscribers {
+        let _ = subscriber.task.data_hash.set(data_hash);
+        let _ = subscriber.task.data_sum.set(data_sum);
+        split_into_task(&app_state, subscriber, data).await;
+    }
+}
+
+/// 驻留窗口模式的后台任务：读取第一个窗口的 chunk
+async fn run_window_job(
+    app_state: web::Data<AppState>,
+    owner: ParseSubscriber,
+    priority: ParsePriority,
+) {
+    let _permit = app_state.parse_queue.acquire(priority).await;
+    if owner.task.cancel.is_cancelled() {
+        println!("[后台解析] 任务 {} 已取消，跳过读取驻留窗口", owner.task_id);
+        return;
+    }
+
+    let load_start = get_unix_timestamp_ms();
+    let load_channel_index = format!("load_window_{}", get_thread_id());
+    match load_resident_window(&app_state, &owner.task, 0).await {
+        Ok(loaded) => println!(
+            "[后台解析] 任务 {} 驻留窗口模式，已读取前 {} 个 chunk",
+            owner.task_id, loaded
+        ),
+        Err(e) => {
+            eprintln!("[后台解析] 任务 {} 读取驻留窗口失败: {e}", owner.task_id);
+            owner.task.mark_failed(e);
+            return;
+        }
+    }
+    let load_end = get_unix_timestamp_ms();
+
+    if let Some(ref sid) = owner.session_id {
+        let record = PerformanceRecord {
+            start_time: load_start,
+            end_time: load_end,
+            channel_group: "backend".to_string(),
+            channel_index: load_channel_index.clone(),
+            msg: format!("后台读取驻留窗口: {}", owner.task_id),
+        };
+        eprintln!("[性能数据记录] 后台任务 - 读取驻留窗口 - session_id: {}, channel_index: {}", sid, load_channel_index);
+        app_state.performance_store.add_record(sid, record);
+    }
+}
+
+/// 驻留窗口模式：把任务的窗口移动到从 `first` 开始的位置
+///
+/// 窗口外已就绪的 chunk 被释放，窗口内缺失的 chunk 通过 `parse_chunk_from_file` 从文件读取。
+/// 顺序请求的客户端每次请求都会使窗口前移，因此内存中最多只有一个窗口的数据。
+/// 读取失败的 chunk 被标记为失败（之后不再重试），窗口内的其他 chunk 照常就绪。
+/// 返回本次成功读取的 chunk 数量
+pub async fn load_resident_window(
+    app_state: &AppState,
+    task: &Arc<TaskData>,
+    first: usize,
+) -> Result<usize, String> {
+    let Some(window) = task.resident_window else {
+        return Ok(0);
+    };
+    // 已读取失败的 chunk 不再重试
+    let ranges: Vec<(usize, usize, usize)> = task
+        .slide_window(first, window.max(1))
+        .into_iter()
+        .filter(|&index| task.chunk_failure(index).is_none())
+        .filter_map(|index| task.chunks.get(index))
+        .map(|descriptor| (descriptor.index, descriptor.start, descriptor.end))
+        .collect();
+    if ranges.is_empty() {
+        return Ok(0);
+    }
+
+    // 读取文件是阻塞 IO，放到阻塞线程池执行
+    let parser_registry = app_state.parser_registry.clone();
+    let file_path = task.file_path.clone();
+    let loaded = web::block(move || {
+        let (parser, _) = parser_registry
+            .find_parser_for_file(&file_path)
+            .ok_or_else(|| "找不到解析器".to_string())?;
+        // 每个 chunk 独立读取：文件中某个区域损坏时只有对应的 chunk 失败
+        Ok::<_, String>(
+            ranges
+                .into_iter()
+                .map(|(index, start, end)| {
+                    let values = parser
+                        .parse_chunk_from_file(&file_path, start, end)
+                        .map_err(|e| e.to_string());
+                    (index, values)
+                })
+                .collect::<Vec<_>>(),
+        )
+    })
+    .await
+    .map_err(|e| format!("读取线程异常: {e}"))??;
+
+    let mut count = 0;
+    for (index, values) in loaded {
+        let mut values = match values {
+            Ok(values) => values,
+            Err(e) => {
+                let reason = format!("读取 chunk 失败: {e}");
+                eprintln!("[驻留窗口] 文件 {} 的 chunk {index} {reason}", task.file_path);
+                task.mark_chunk_failed(index, reason);
+                continue;
+            }
+        };
+        count += 1;
+        // 驻留窗口模式下的读取进度表示已读到文件中的哪个位置
+        if let Some(descriptor) = task.chunks.get(index) {
+            task.progress.set_values_read(descriptor.end);
+        }
+        if !task.value_transform.is_none() {
+            values.iter_mut().for_each(|v| *v = task.value_transform.apply(*v));
+        }
+        if let Some(decimals) = task.round_decimals {
+            round_values_to_decimals(&mut values, decimals);
+        }
+        task.set_pending_chunk(index, values);
+    }
+    Ok(count)
+}
+
+/// 将完整数据按任务的分块描述切分并存入任务
+///
+/// 每个 chunk 的切分与存储互相隔离：某个 chunk 发生 panic（如切片越界）时只把该 chunk 标记为失败，
+/// 其余 chunk 照常就绪。持有 `chunk_data` 写锁的临界区只做 HashMap 的插入与计数，切分在锁外完成，
+/// parking_lot 的锁不会因 panic 中毒，展开时守卫释放，共享状态不会停留在写了一半的状态
+async fn split_into_task(app_state: &AppState, subscriber: &ParseSubscriber, data: &[f64]) {
+    split_chunks(app_state, subscriber, data, ChunkDescriptor::extract, TaskData::set_chunk).await;
+}
+
+/// 用 `extract` 切出每个 chunk 的数据，再在各自的后台任务中用 `store` 存入任务
+///
+/// 任一步骤 panic 时只把对应的 chunk 标记为失败，其余 chunk 照常就绪
+async fn split_chunks<E, S>(
+    app_state: &AppState,
+    subscriber: &ParseSubscriber,
+    data: &[f64],
+    extract: E,
+    store: S,
+) where
+    E: Fn(&ChunkDescriptor, &[f64], [usize; 3]) -> Vec<f64> + RefUnwindSafe,
+    S: Fn(&TaskData, usize, Vec<f64>) + Copy + Send + 'static,
+{
+    let split_start = get_unix_timestamp_ms();
+
+    // 使用多个后台任务并行分割和存储 chunk
+    let mut handles = Vec::new();
+    for descriptor in subscriber.task.chunks.iter() {
+        if subscriber.task.cancel.is_cancelled() {
+            println!("[后台解析] 任务 {} 已取消，停止分割", subscriber.task_id);
+            break;
+        }
+        let task_ref = subscriber.task.clone();
+        let perf_store = app_state.performance_store.clone();
+        let sid = subscriber.session_id.clone();
+        let chunk_index = descriptor.index;
+        // 为每个 chunk 复制对应的数据（因为多个任务需要并发读取不同部分）
+        let shape = subscriber.task.shape;
+        let chunk_values = match panic::catch_unwind(|| extract(descriptor, data, shape)) {
+            Ok(values) => values,
+            Err(payload) => {
+                let reason = format!("分割 chunk 时发生 panic: {}", panic_message(&*payload));
+                eprintln!("[后台解析] 任务 {} 的 chunk {chunk_index} {reason}", subscriber.task_id);
+                subscriber.task.mark_chunk_failed(chunk_index, reason);
+                continue;
+            }
+        };
+        let split_channel_id = app_state.config.perf_channel_ids.channel_id(Some(chunk_index));
+        let split_channel_index = format!("split_chunk_{}", split_channel_id);
+
+        // 为每个 chunk 启动一个任务来存储数据
+        let handle = actix_web::rt::spawn(async move {
+            let chunk_start = get_unix_timestamp_ms();
+            store(&task_ref, chunk_index, chunk_values);
+            let chunk_end = get_unix_timestamp_ms();
+
+            // 记录分割 chunk 性能数据
+            if let Some(ref session_id) = sid {
+                let record = PerformanceRecord {
+                    start_time: chunk_start,
+                    end_time: chunk_end,
+                    channel_group: "backend".to_string(),
+                    channel_index: split_channel_index.clone(),
+                    msg: format!("后台分割 Chunk {}", chunk_index),
+                };
+                eprintln!("[性能数据记录] 后台任务 - 分割Chunk - session_id: {}, channel_index: {}", session_id, split_channel_index);
+                perf_store.add_record(session_id, record);
+            }
+        });
+        handles.push((chunk_index, handle));
+    }
+
+    // 等待所有分割任务完成；存储时 panic 的 chunk 同样标记为失败
+    for (chunk_index, handle) in handles {
+        if let Err(e) = handle.await {
+            let reason = format!("存储 chunk 时发生 panic: {e}");
+            eprintln!("[后台解析] 任务 {} 的 chunk {chunk_index} {reason}", subscriber.task_id);
+            subscriber.task.mark_chunk_failed(chunk_index, reason);
+        }
+    }
+
+    let split_end = get_unix_timestamp_ms();
+    println!(
+        "[后台解析] 任务 {} 分割完成，共 {} 个 chunk，耗时 {:.2}ms",
+        subscriber.task_id,
+        subscriber.task.chunks.len(),
+        split_end - split_start
+    );
+}
+
+/// panic 携带的消息（`panic!` 的格式化字符串或字面量），其他类型的载荷返回占位描述
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "未知的 panic".to_string()
+    }
+}
+
+/// 数据顺序自检的采样数量上限（每个轴）
+const ORDERING_CHECK_SAMPLES: usize = 1_000_000;
+
+/// 解析后对数据顺序做启发式自检，发现可疑时只记录警告
+///
+/// 如果某个轴的相邻体素明显连续（比值小），而另一个轴的"相邻"体素差异接近随机样本，
+/// 说明场本身是连续的，但沿后者的步长很可能算错了，即声明的维度顺序与实际存储不一致
+fn check_data_ordering(task_id: &str, grid: &crate::utils::voxel_grid::VoxelGrid) {
+    let Some(ratios) = grid.axis_continuity(ORDERING_CHECK_SAMPLES) else {
+        return;
+    };
+    let valid: Vec<f64> = ratios.iter().copied().filter(|r| r.is_finite()).collect();
+    let smoothest = valid.iter().copied().fold(f64::INFINITY, f64::min);
+    let roughest = valid.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+
+    if smoothest < 0.5 && roughest > 0.8 {
+        eprintln!(
+            "[后台解析] 警告: 任务 {task_id} 的数据顺序可能与声明不一致 \
+             (shape {:?}，x/y/z 轴连续性比值 {:.3}/{:.3}/{:.3}，接近 1 表示该轴相邻体素不连续)",
+            grid.shape, ratios[0], ratios[1], ratios[2]
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use actix_web::http::StatusCode;
+    use actix_web::{App, test};
+
+    use super::*;
+    use crate::config::Config;
+    use crate::handlers::voxel_grid::get_voxel_grid;
+    use crate::task::TaskLimitPolicy;
+
+    /// 把 4×2×2 的网格按 4 个元素分块，用给定的 `extract` / `store` 分割，返回分割后的任务
+    async fn split_with<E, S>(extract: E, store: S) -> Arc<TaskData>
+    where
+        E: Fn(&ChunkDescriptor, &[f64], [usize; 3]) -> Vec<f64> + RefUnwindSafe,
+        S: Fn(&TaskData, usize, Vec<f64>) + Copy + Send + 'static,
+    {
+        let app_state = AppState::builder().build();
+        let shape = [4, 2, 2];
+        let data: Vec<f64> = (0..16).map(f64::from).collect();
+        let task = Arc::new(TaskData::new(
+            shape,
+            compute_chunks(data.len(), 4),
+            "test".to_string(),
+        ));
+        let subscriber = ParseSubscriber {
+            task_id: "test".to_string(),
+            task: task.clone(),
+            session_id: None,
+        };
+
+        split_chunks(&app_state, &subscriber, &data, extract, store).await;
+        task
+    }
+
+    fn assert_only_chunk_1_failed(task: &TaskData) {
+        assert_eq!(task.failed_chunk_indices(), vec![1]);
+        assert!(task.chunk_failure(1).unwrap().contains("panic"));
+        assert_eq!(task.stored_chunk_indices(), vec![0, 2, 3]);
+        assert_eq!(task.take_chunk(2), Some(vec![8.0, 9.0, 10.0, 11.0]));
+        assert_eq!(task.status_label(), "ready");
+    }
+
+    #[actix_web::test]
+    async fn panic_while_extracting_fails_only_that_chunk() {
+        let extract = |descriptor: &ChunkDescriptor, data: &[f64], shape: [usize; 3]| {
+            if descriptor.index == 1 {
+                panic!("测试注入的 panic");
+            }
+            descriptor.extract(data, shape)
+        };
+        let task = split_with(extract, TaskData::set_chunk).await;
+        assert_only_chunk_1_failed(&task);
+    }
+
+    #[actix_web::test]
+    async fn panic_while_storing_fails_only_that_chunk() {
+        let store = |task: &TaskData, chunk_index: usize, values: Vec<f64>| {
+            if chunk_index == 1 {
+                panic!("测试注入的 panic");
+            }
+            task.set_chunk(chunk_index, values);
+        };
+        let task = split_with(ChunkDescriptor::extract, store).await;
+        assert_only_chunk_1_failed(&task);
+    }
+
+    #[actix_web::test]
+    async fn truncated_file_fails_task_with_specific_error() {
+        // 默认配置的资源目录为 test/resource
+        let app_state = web::Data::new(AppState::builder().build());
+        let response = preprocess_file(
+            &app_state,
+            "truncated.vasp",
+            16,
+            None,
+            false,
+            PreprocessOptions::default(),
+            CancelToken::new(),
+        )
+        .await
+        .unwrap_or_else(|_| panic!("预处理应当成功，截断在后台解析时才会发现"));
+        let task = app_state.task_store.get(&response.task_id).unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while task.status_label() == "processing" {
+            assert!(Instant::now() < deadline, "后台解析没有在 10 秒内结束");
+            task.wait_for_event(Duration::from_millis(100), || {
+                task.status_label() != "processing"
+            })
+            .await;
+        }
+        assert_eq!(task.status_label(), "failed");
+        let failure = task.failure().unwrap();
+        assert!(failure.contains("数据被截断"), "{failure}");
+    }
+
+    #[actix_web::test]
+    async fn zero_chunk_size_is_rejected_by_get_and_post() {
+        let app_state = web::Data::new(AppState::builder().build());
+        let app = test::init_service(
+            App::new()
+                .app_data(app_state.clone())
+                .service(get_voxel_grid)
+                .service(preprocess_voxel_grid),
+        )
+        .await;
+
+        let get = test::TestRequest::get()
+            .uri("/voxel-grid?file=small.vasp&chunk_size=0")
+            .to_request();
+        let post = test::TestRequest::post()
+            .uri("/voxel-grid/preprocess")
+            .set_json(serde_json::json!({ "file": "small.vasp", "chunk_size": 0 }))
+            .to_request();
+        for request in [get, post] {
+            let response = test::call_service(&app, request).await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+            let body: serde_json::Value = test::read_body_json(response).await;
+            assert_eq!(body["error"], "chunk_size 必须大于 0");
+        }
+        // 被拒绝的请求不会创建任务
+        assert_eq!(app_state.task_store.task_count(), 0);
+    }
+
+    #[actix_web::test]
+    async fn chunk_size_boundaries_produce_expected_chunk_counts() {
+        let app_state = web::Data::new(AppState::builder().build());
+        // small.vasp 为 4×4×4，共 64 个元素
+        for (chunk_size, expected) in [(1, 64), (63, 2), (64, 1), (65, 1)] {
+            let response = preprocess_file(
+                &app_state,
+                "small.vasp",
+                chunk_size,
+                None,
+                false,
+                PreprocessOptions::default(),
+                CancelToken::new(),
+            )
+            .await
+            .unwrap_or_else(|_| panic!("chunk_size={chunk_size} 应当被接受"));
+            assert_eq!(response.data_length, 64);
+            assert_eq!(response.chunk_size, chunk_size);
+            assert_eq!(response.chunk_count, expected, "chunk_size={chunk_size}");
+        }
+    }
+
+    #[actix_web::test]
+    async fn multi_grid_preprocess_creates_no_tasks_when_limit_is_short() {
+        let app_state = web::Data::new(
+            AppState::builder()
+                .config(Config {
+                    max_tasks: 1,
+                    ..Config::default()
+                })
+                .build(),
+        );
+        let options = PreprocessOptions {
+            grids: Some(vec![
+                GridSelector::Name("total".to_string()),
+                GridSelector::Name("diff".to_string()),
+            ]),
+            ..Default::default()
+        };
+        let response = preprocess_file(
+            &app_state,
+            "spin.vasp",
+            16,
+            None,
+            false,
+            options,
+            CancelToken::new(),
+        )
+        .await
+        .err()
+        .expect("两个网格超过任务数上限，应当被拒绝");
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(app_state.task_store.task_count(), 0);
+    }
+
+    #[actix_web::test]
+    async fn pinned_task_is_pinned_when_inserted() {
+        // 任务数已满且按最早创建移除：固定的任务一进入存储就不会被之后的插入移除
+        let app_state = web::Data::new(
+            AppState::builder()
+                .config(Config {
+                    max_tasks: 1,
+                    task_limit_policy: TaskLimitPolicy::EvictOldest,
+                    ..Config::default()
+                })
+                .build(),
+        );
+        for full_parse in [false, true] {
+            let options = PreprocessOptions {
+                autocrop: full_parse,
+                ..Default::default()
+            };
+            let response = preprocess_file(
+                &app_state,
+                "small.vasp",
+                16,
+                None,
+                true,
+                options,
+                CancelToken::new(),
+            )
+            .await
+            .unwrap_or_else(|_| panic!("预处理应当成功"));
+            assert!(response.pinned);
+            let task = app_state.task_store.get(&response.task_id).unwrap();
+            assert!(task.is_pinned());
+
+            let other = TaskData::new([1, 1, 1], compute_chunks(1, 1), "other".to_string());
+            assert!(app_state.task_store.insert(other).is_err());
+            assert!(app_state.task_store.get(&response.task_id).is_some());
+            app_state.task_store.clear_all();
+        }
+    }
 }
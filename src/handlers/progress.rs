@@ -0,0 +1,197 @@
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use actix_web::web::Bytes;
+use actix_web::{HttpResponse, Responder, get, web};
+use futures_core::Stream;
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+use crate::app_state::AppState;
+use crate::stream_limit::StreamSubscription;
+use crate::task::TaskData;
+
+/// 检查解析进度的间隔：进度没有变化时不发送事件
+const PROGRESS_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+#[derive(Deserialize)]
+pub struct ProgressQuery {
+    pub task_id: String,
+}
+
+/// 一行 NDJSON 进度事件
+#[derive(Serialize, PartialEq)]
+struct ProgressEvent {
+    /// queued / reading / splitting，终止状态为 ready / failed / cancelled
+    stage: &'static str,
+    /// 已存入任务的 chunk 数量（包括之后被请求走的）
+    chunks_ready: usize,
+    total_chunks: usize,
+    /// 与状态接口的 `progress.percent` 相同
+    percent: f64,
+    /// 分割失败、不会再就绪的 chunk 数量（为 0 时省略）
+    #[serde(skip_serializing_if = "is_zero")]
+    chunks_failed: usize,
+    /// 解析失败的原因（仅 `failed`）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+fn is_zero(count: &usize) -> bool {
+    *count == 0
+}
+
+impl ProgressEvent {
+    fn from_task(task: &TaskData) -> Self {
+        let chunks_ready = task.progress.chunks_stored();
+        let stage = match task.status_label() {
+            "processing"
+                if chunks_ready > 0 || task.progress.values_read() >= task.data_length() =>
+            {
+                "splitting"
+            }
+            "processing" if task.progress.values_read() > 0 => "reading",
+            "processing" => "queued",
+            terminal => terminal,
+        };
+        Self {
+            stage,
+            chunks_ready,
+            total_chunks: task.chunks.len(),
+            chunks_failed: task.failed_chunk_indices().len(),
+            percent: task.progress_percent(),
+            error: task.failure().map(str::to_string),
+        }
+    }
+
+    fn is_terminal(&self) -> bool {
+        matches!(self.stage, "ready" | "failed" | "cancelled")
+    }
+
+    fn to_line(&self) -> Bytes {
+        let mut line = serde_json::to_vec(self).unwrap_or_default();
+        line.push(b'\n');
+        Bytes::from(line)
+    }
+}
+
+/// 进度事件的响应体
+struct ProgressStream {
+    receiver: mpsc::Receiver<Bytes>,
+}
+
+impl Stream for ProgressStream {
+    type Item = Result<Bytes, actix_web::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx).map(|line| line.map(Ok))
+    }
+}
+
+/// 以 NDJSON 流推送单个任务的解析进度
+///
+/// 无法使用 WebSocket 的客户端可以通过普通 HTTP 流式读取进度，不必轮询状态接口。
+/// 立即发送一次当前进度，之后每当阶段、已就绪 chunk 数或百分比变化时发送一行；
+/// 任务进入终止状态（ready / failed / cancelled）后发送最后一行并结束响应。
+/// 每个连接在整个推送期间占用一个流式订阅名额（见 `StreamLimits`），超出上限时返回 429
+#[get("/voxel-grid/progress")]
+pub async fn stream_progress(
+    data: web::Data<AppState>,
+    query: web::Query<ProgressQuery>,
+) -> impl Responder {
+    let Some(task) = data.task_store.get(&query.task_id) else {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "无效的 task_id",
+            "task_id": query.task_id,
+        }));
+    };
+
+    let subscription = match data.stream_limits.try_subscribe(&task) {
+        Ok(subscription) => subscription,
+        Err(e) => {
+            return HttpResponse::TooManyRequests().json(serde_json::json!({
+                "error": e.to_string(),
+                "task_id": query.task_id,
+            }));
+        }
+    };
+
+    let (sender, receiver) = mpsc::channel(1);
+    actix_web::rt::spawn(produce_progress(task, sender, subscription));
+
+    HttpResponse::Ok()
+        .content_type("application/x-ndjson")
+        .streaming(ProgressStream { receiver })
+}
+
+/// 轮询任务进度，变化时发送事件，直到任务结束或客户端断开
+///
+/// `_subscription` 在推送结束时释放，归还订阅名额；进度没有变化时也检查客户端是否已断开，及时归还名额
+async fn produce_progress(
+    task: Arc<TaskData>,
+    sender: mpsc::Sender<Bytes>,
+    _subscription: StreamSubscription,
+) {
+    let mut last: Option<ProgressEvent> = None;
+    loop {
+        if sender.is_closed() {
+            return;
+        }
+        let event = ProgressEvent::from_task(&task);
+        let terminal = event.is_terminal();
+        if last.as_ref() != Some(&event) {
+            if sender.send(event.to_line()).await.is_err() {
+                return;
+            }
+            last = Some(event);
+        }
+        if terminal {
+            return;
+        }
+        actix_web::rt::time::sleep(PROGRESS_POLL_INTERVAL).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::StatusCode;
+    use actix_web::{App, test};
+
+    use super::*;
+    use crate::config::Config;
+    use crate::task::compute_chunks;
+
+    #[actix_web::test]
+    async fn progress_streams_count_against_stream_limits() {
+        let app_state = AppState::builder()
+            .config(Config {
+                max_task_streams: 1,
+                ..Config::default()
+            })
+            .build();
+        // 没有 chunk 就绪的任务：进度流保持打开，持续占用订阅名额
+        let task = TaskData::new([2, 2, 2], compute_chunks(8, 4), "test".to_string());
+        let task_id = app_state.task_store.insert(task).unwrap();
+        let app_state = web::Data::new(app_state);
+        let app = test::init_service(
+            App::new()
+                .app_data(app_state.clone())
+                .service(stream_progress),
+        )
+        .await;
+        let uri = format!("/voxel-grid/progress?task_id={task_id}");
+
+        let first = test::call_service(&app, test::TestRequest::get().uri(&uri).to_request()).await;
+        assert_eq!(first.status(), StatusCode::OK);
+        let second =
+            test::call_service(&app, test::TestRequest::get().uri(&uri).to_request()).await;
+        assert_eq!(second.status(), StatusCode::TOO_MANY_REQUESTS);
+
+        // 客户端断开后，即使进度没有变化名额也会在下一次轮询时归还
+        drop(first);
+        actix_web::rt::time::sleep(PROGRESS_POLL_INTERVAL * 3).await;
+        assert_eq!(app_state.stream_limits.active(), 0);
+    }
+}
@@ -0,0 +1,153 @@
+use actix_web::{HttpResponse, Responder, get, http::header::ContentType, web};
+use serde::Deserialize;
+
+use crate::app_state::AppState;
+use crate::task::TaskData;
+
+#[derive(Deserialize)]
+pub struct RangeQuery {
+    pub task_id: String,
+    /// 开始位置（包含），单位：浮点元素索引
+    pub start: usize,
+    /// 结束位置（不包含），单位：浮点元素索引
+    pub end: usize,
+}
+
+/// 按元素范围 `[start, end)` 获取数据（小端序 Float64Array）
+///
+/// 数据从覆盖该范围的各个 chunk 中拼接而来，与预处理时选择的 chunk_size（或 tile 分块）无关；
+/// 读取是非破坏性的，不会消费 chunk
+#[get("/voxel-grid/range-data")]
+pub async fn get_range_data(
+    data: web::Data<AppState>,
+    query: web::Query<RangeQuery>,
+) -> impl Responder {
+    let Some(task) = data.task_store.get(&query.task_id) else {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "无效的 task_id",
+            "task_id": query.task_id,
+        }));
+    };
+
+    let data_length = task.shape[0] * task.shape[1] * task.shape[2];
+    if query.start >= query.end || query.end > data_length {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "无效的范围，要求 start < end <= data_length",
+            "start": query.start,
+            "end": query.end,
+            "data_length": data_length,
+        }));
+    }
+
+    let bytes = match read_range_bytes(&task, &query.task_id, query.start, query.end) {
+        Ok(bytes) => bytes,
+        Err(response) => return response,
+    };
+
+    HttpResponse::Ok()
+        .content_type(ContentType::octet_stream())
+        .append_header(("X-Range-Start", query.start.to_string()))
+        .append_header(("X-Range-End", query.end.to_string()))
+        .append_header(("X-Range-Length", (query.end - query.start).to_string()))
+        .append_header(("X-Chunk-Task", query.task_id.clone()))
+        .body(bytes)
+}
+
+/// 从覆盖 `[start, end)` 的各个 chunk 中拼接出小端序 f64 字节（非破坏性）
+/// 调用方需保证范围有效；任务失败、chunk 未就绪或已被请求时返回对应的错误响应
+pub(crate) fn read_range_bytes(
+    task: &TaskData,
+    task_id: &str,
+    start: usize,
+    end: usize,
+) -> Result<Vec<u8>, HttpResponse> {
+    if let Some(reason) = task.failure() {
+        return Err(HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "任务解析失败",
+            "task_id": task_id,
+            "status": "failed",
+            "details": reason,
+        })));
+    }
+
+    let mut bytes = Vec::with_capacity((end - start) * std::mem::size_of::<f64>());
+
+    // tile 模式下网格中连续的一段分散在多个 tile 中，逐个体素定位
+    if task.is_tiled() {
+        for index in start..end {
+            let Some((chunk_index, offset)) = task.locate(index) else {
+                return Err(chunk_unavailable(task, task_id, index));
+            };
+            let copied = task.peek_chunk(chunk_index, |values| {
+                bytes.extend_from_slice(&values[offset].to_le_bytes());
+            });
+            if copied.is_none() {
+                return Err(chunk_unavailable(task, task_id, chunk_index));
+            }
+        }
+        return Ok(bytes);
+    }
+
+    // chunk 按 start 升序排列，只遍历与范围相交的部分
+    for descriptor in task
+        .chunks
+        .iter()
+        .filter(|d| d.end > start && d.start < end)
+    {
+        let from = start.max(descriptor.start) - descriptor.start;
+        let to = end.min(descriptor.end) - descriptor.start;
+        let copied = task.peek_chunk(descriptor.index, |values| {
+            for value in &values[from..to] {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+        });
+
+        if copied.is_none() {
+            return Err(chunk_unavailable(task, task_id, descriptor.index));
+        }
+    }
+
+    Ok(bytes)
+}
+
+/// 按网格顺序读取全部数据（非破坏性），失败时返回无法读取的 chunk 索引
+pub(crate) fn read_grid_values(task: &TaskData) -> Result<Vec<f64>, usize> {
+    let chunk_data = task.chunk_data.read();
+    let data_length = task.shape[0] * task.shape[1] * task.shape[2];
+    (0..data_length)
+        .map(|voxel| {
+            let (chunk_index, offset) = task.locate(voxel).ok_or(voxel)?;
+            match chunk_data.get(&chunk_index) {
+                Some(Some(values)) => Ok(values[offset]),
+                _ => Err(chunk_index),
+            }
+        })
+        .collect()
+}
+
+/// 范围内的 chunk 无法读取时的错误响应
+/// chunk 仍在 chunk_data 中说明还在解析；否则已被请求，数据已释放
+pub(crate) fn chunk_unavailable(task: &TaskData, task_id: &str, chunk_index: usize) -> HttpResponse {
+    if let Some(reason) = task.chunk_failure(chunk_index) {
+        HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "范围内的 chunk 分割失败",
+            "task_id": task_id,
+            "chunk_index": chunk_index,
+            "status": "failed",
+            "details": reason,
+        }))
+    } else if task.chunk_data.read().contains_key(&chunk_index) {
+        HttpResponse::Accepted().json(serde_json::json!({
+            "error": "范围内的 chunk 正在解析中，请稍后重试",
+            "task_id": task_id,
+            "chunk_index": chunk_index,
+            "status": "processing",
+        }))
+    } else {
+        HttpResponse::NotFound().json(serde_json::json!({
+            "error": "范围内的 chunk 已被请求，数据已释放",
+            "task_id": task_id,
+            "chunk_index": chunk_index,
+        }))
+    }
+}
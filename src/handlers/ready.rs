@@ -0,0 +1,54 @@
+use actix_web::{HttpResponse, Responder, get, web};
+use serde::Deserialize;
+
+use crate::app_state::AppState;
+
+/// 未指定 `limit` 时最多返回的 chunk 数量
+const DEFAULT_READY_LIMIT: usize = 64;
+
+#[derive(Deserialize)]
+pub struct ReadyQuery {
+    pub task_id: String,
+    /// 只返回索引大于该值的 chunk，缺省时从第一个 chunk 开始
+    #[serde(default)]
+    pub after: Option<usize>,
+    /// 最多返回多少个 chunk，默认 64
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+/// 获取当前可以立即下载（已就绪且尚未被请求）的 chunk 索引
+///
+/// 按顺序下载的客户端可据此在解析过程中流水线式地请求 chunk，
+/// 而不必逐个探测并收到 202
+#[get("/voxel-grid/ready")]
+pub async fn get_ready_chunks(
+    data: web::Data<AppState>,
+    query: web::Query<ReadyQuery>,
+) -> impl Responder {
+    let Some(task) = data.task_store.get(&query.task_id) else {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "无效的 task_id",
+            "task_id": query.task_id,
+        }));
+    };
+
+    if let Some(reason) = task.failure() {
+        return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "任务解析失败",
+            "task_id": query.task_id,
+            "status": "failed",
+            "details": reason,
+        }));
+    }
+
+    let limit = query.limit.unwrap_or(DEFAULT_READY_LIMIT);
+    let ready = task.ready_chunk_indices(query.after, limit);
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "task_id": query.task_id,
+        "status": task.status_label(),
+        "after": query.after,
+        "ready": ready,
+    }))
+}
@@ -0,0 +1,14 @@
+use actix_web::{HttpResponse, Responder, get, web};
+
+use crate::app_state::AppState;
+
+/// 获取按路由统计的 HTTP 请求数与延迟分位数（p50 / p95 / p99）
+///
+/// 与 `/performance` 的会话级解析耗时不同，这里反映的是 HTTP 层的整体健康状况
+#[get("/request-metrics")]
+pub async fn get_request_metrics(data: web::Data<AppState>) -> impl Responder {
+    HttpResponse::Ok().json(serde_json::json!({
+        "uptime_secs": data.started_at.elapsed().as_secs(),
+        "routes": data.request_metrics.snapshot(),
+    }))
+}
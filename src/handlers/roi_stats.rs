@@ -0,0 +1,90 @@
+use actix_web::{HttpResponse, Responder, get, web};
+use serde::Deserialize;
+
+use crate::app_state::AppState;
+use crate::handlers::range::{chunk_unavailable, read_grid_values};
+use crate::utils::voxel_grid::VoxelGrid;
+
+#[derive(Deserialize)]
+pub struct RoiStatsQuery {
+    pub task_id: String,
+    /// 值域下界（含），缺省时不限制
+    pub min: Option<f64>,
+    /// 值域上界（含），缺省时不限制
+    pub max: Option<f64>,
+}
+
+/// 统计值落在 `[min, max]` 内的体素（值域 ROI）的 min/max/mean/count
+///
+/// 读取是非破坏性的，所有 chunk 必须已就绪且未被请求。没有体素被选中时返回 count 为 0、
+/// min/max/mean 为 null 且 `empty` 为 true 的结果，而不是 NaN
+#[get("/voxel-grid/roi-stats")]
+pub async fn get_roi_stats(
+    data: web::Data<AppState>,
+    query: web::Query<RoiStatsQuery>,
+) -> impl Responder {
+    let Some(task) = data.task_store.get(&query.task_id) else {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "无效的 task_id",
+            "task_id": query.task_id,
+        }));
+    };
+
+    let lower = query.min.unwrap_or(f64::NEG_INFINITY);
+    let upper = query.max.unwrap_or(f64::INFINITY);
+    if lower.is_nan() || upper.is_nan() || lower > upper {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "无效的值域范围，要求 min <= max 且均不为 NaN",
+            "min": query.min,
+            "max": query.max,
+        }));
+    }
+
+    if let Some(reason) = task.failure() {
+        return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "任务解析失败",
+            "task_id": query.task_id,
+            "status": "failed",
+            "details": reason,
+        }));
+    }
+
+    let values = match read_grid_values(&task) {
+        Ok(values) => values,
+        Err(chunk_index) => return chunk_unavailable(&task, &query.task_id, chunk_index),
+    };
+    let grid = match VoxelGrid::new(task.shape, values) {
+        Ok(grid) => grid,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": e,
+                "task_id": query.task_id,
+            }));
+        }
+    };
+
+    let total_voxels = grid.get_data().len();
+    let stats =
+        match web::block(move || grid.masked_statistics(|value| value >= lower && value <= upper))
+            .await
+        {
+            Ok(stats) => stats,
+            Err(e) => {
+                return HttpResponse::InternalServerError().json(serde_json::json!({
+                    "error": "ROI 统计失败",
+                    "details": e.to_string(),
+                }));
+            }
+        };
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "task_id": query.task_id,
+        "range": { "min": query.min, "max": query.max },
+        "min": stats.min,
+        "max": stats.max,
+        "mean": stats.mean,
+        "count": stats.count,
+        "total_voxels": total_voxels,
+        "empty": stats.count == 0,
+    }))
+}
@@ -0,0 +1,92 @@
+use actix_web::{HttpResponse, Responder, get, web};
+use serde::Deserialize;
+
+use crate::app_state::AppState;
+use crate::handlers::range::{chunk_unavailable, read_grid_values};
+use crate::utils::voxel_grid::VoxelGrid;
+
+#[derive(Deserialize)]
+pub struct SampleQuery {
+    pub task_id: String,
+    /// 采样点坐标，单位为体素索引（体素 `(i, j, k)` 位于 `(i, j, k)`），可以是小数
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    /// 为 true 时按周期边界插值（坐标按 shape 取模，`nx` 与 `0` 等价），默认 false
+    #[serde(default)]
+    pub periodic: bool,
+}
+
+/// 在任意点三线性插值采样，并返回该点的梯度（每体素的变化量）
+///
+/// 读取是非破坏性的，所有 chunk 必须已就绪且未被请求。非周期模式下坐标超出 `[0, n - 1]` 返回 400，
+/// 边界处的梯度改用单侧差分；周期模式下插值与梯度都跨越晶胞边界取值
+#[get("/voxel-grid/sample")]
+pub async fn get_sample(
+    data: web::Data<AppState>,
+    query: web::Query<SampleQuery>,
+) -> impl Responder {
+    let Some(task) = data.task_store.get(&query.task_id) else {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "无效的 task_id",
+            "task_id": query.task_id,
+        }));
+    };
+
+    let point = [query.x, query.y, query.z];
+    if point.iter().any(|coordinate| !coordinate.is_finite()) {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "采样点坐标必须是有限数",
+            "point": point,
+        }));
+    }
+
+    if let Some(reason) = task.failure() {
+        return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "任务解析失败",
+            "task_id": query.task_id,
+            "status": "failed",
+            "details": reason,
+        }));
+    }
+
+    let values = match read_grid_values(&task) {
+        Ok(values) => values,
+        Err(chunk_index) => return chunk_unavailable(&task, &query.task_id, chunk_index),
+    };
+    let grid = match VoxelGrid::new(task.shape, values) {
+        Ok(grid) => grid,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": e,
+                "task_id": query.task_id,
+            }));
+        }
+    };
+
+    let (value, gradient) = if query.periodic {
+        (
+            grid.sample_trilinear_periodic(point),
+            grid.gradient_periodic(point),
+        )
+    } else {
+        match (grid.sample_trilinear(point), grid.gradient(point)) {
+            (Some(value), Some(gradient)) => (value, gradient),
+            _ => {
+                return HttpResponse::BadRequest().json(serde_json::json!({
+                    "error": "采样点超出网格范围，周期性数据可指定 periodic=true",
+                    "point": point,
+                    "shape": task.shape,
+                }));
+            }
+        }
+    };
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "task_id": query.task_id,
+        "point": point,
+        "periodic": query.periodic,
+        "value": value,
+        "gradient": gradient,
+    }))
+}
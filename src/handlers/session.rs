@@ -0,0 +1,57 @@
+use actix_web::{HttpResponse, Responder, get, web};
+use serde::Deserialize;
+
+use crate::app_state::AppState;
+
+#[derive(Deserialize)]
+pub struct SessionSummaryQuery {
+    pub session_id: String,
+}
+
+/// 会话概况：该会话创建的所有任务（shape 与就绪情况）以及会话的性能数据汇总
+///
+/// 供运维面板与排查问题使用，一次请求即可看到某个用户正在加载哪些文件、进度如何
+#[get("/session/summary")]
+pub async fn get_session_summary(
+    data: web::Data<AppState>,
+    query: web::Query<SessionSummaryQuery>,
+) -> impl Responder {
+    let resource_prefix = format!("{}/", data.config.resource_dir);
+    let tasks: Vec<_> = data
+        .task_store
+        .session_tasks(&query.session_id)
+        .into_iter()
+        .map(|(task_id, task)| {
+            let total_chunks = task.chunks.len();
+            let remaining_chunks = task.remaining_chunk_count();
+            let ready_chunks = task.ready_chunk_count();
+            // 资源目录下的文件显示相对路径，与预处理请求中的 `file` 一致
+            let file = task
+                .file_path
+                .strip_prefix(&resource_prefix)
+                .unwrap_or(&task.file_path);
+            serde_json::json!({
+                "task_id": task_id,
+                "file": file,
+                "status": task.status_label(),
+                "pinned": task.is_pinned(),
+                "shape": task.shape,
+                "age_secs": task.created_at.elapsed().as_secs(),
+                "total_chunks": total_chunks,
+                "ready_chunks": ready_chunks,
+                "pending_chunks": remaining_chunks - ready_chunks,
+                "consumed_chunks": total_chunks - remaining_chunks,
+                "percent": task.progress_percent(),
+                "error": task.failure(),
+            })
+        })
+        .collect();
+
+    // 与性能接口一致：未知的会话也返回 200，任务列表为空、性能汇总为 null
+    HttpResponse::Ok().json(serde_json::json!({
+        "session_id": query.session_id,
+        "task_count": tasks.len(),
+        "tasks": tasks,
+        "performance": data.performance_store.summary(&query.session_id),
+    }))
+}
@@ -0,0 +1,142 @@
+use actix_web::{HttpResponse, Responder, get, web};
+use serde::Deserialize;
+
+use crate::app_state::AppState;
+use crate::handlers::range::chunk_unavailable;
+use crate::task::TaskData;
+use crate::utils::png_slice::{Colormap, SliceAxis, render_png};
+use crate::utils::stats::GridStats;
+
+#[derive(Deserialize)]
+pub struct SlicePngQuery {
+    pub task_id: String,
+    /// 切片的法向轴，默认 z
+    #[serde(default)]
+    pub axis: SliceAxis,
+    /// 切片在该轴上的位置
+    pub index: usize,
+    /// 颜色映射，默认 viridis
+    #[serde(default)]
+    pub cmap: Colormap,
+    /// 归一化范围的下界，默认取网格的最小值
+    #[serde(default)]
+    pub vmin: Option<f64>,
+    /// 归一化范围的上界，默认取网格的最大值
+    #[serde(default)]
+    pub vmax: Option<f64>,
+}
+
+/// 把单层切片渲染为 PNG，便于在浏览器中直接查看数据（调试 / 运维用）
+///
+/// 数值按网格的整体范围归一化后着色；部分 chunk 已被请求或未驻留内存而无法得到整体范围时，
+/// 退化为按该切片自身的范围归一化。读取是非破坏性的，覆盖切片的 chunk 必须已就绪且未被请求
+#[get("/voxel-grid/slice.png")]
+pub async fn get_slice_png(
+    data: web::Data<AppState>,
+    query: web::Query<SlicePngQuery>,
+) -> impl Responder {
+    let Some(task) = data.task_store.get(&query.task_id) else {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "无效的 task_id",
+            "task_id": query.task_id,
+        }));
+    };
+
+    let axis_length = task.shape[query.axis.number()];
+    if query.index >= axis_length {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "无效的切片位置，要求 index < 该轴的长度",
+            "axis": query.axis.label(),
+            "index": query.index,
+            "axis_length": axis_length,
+        }));
+    }
+
+    let invalid_bound = [query.vmin, query.vmax]
+        .into_iter()
+        .flatten()
+        .any(|bound| !bound.is_finite());
+    if invalid_bound || matches!((query.vmin, query.vmax), (Some(min), Some(max)) if min >= max) {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "无效的归一化范围，要求 vmin < vmax 且均为有限值",
+            "vmin": query.vmin,
+            "vmax": query.vmax,
+        }));
+    }
+
+    if let Some(reason) = task.failure() {
+        return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "任务解析失败",
+            "task_id": query.task_id,
+            "status": "failed",
+            "details": reason,
+        }));
+    }
+
+    let values = match read_slice(&task, query.axis, query.index) {
+        Ok(values) => values,
+        Err(chunk_index) => return chunk_unavailable(&task, &query.task_id, chunk_index),
+    };
+
+    let (stats, range_source) = match grid_stats(&task) {
+        Some(stats) => (stats, "grid"),
+        None => (GridStats::from_values(values.iter().copied()), "slice"),
+    };
+    let min = query.vmin.or(stats.min).unwrap_or(0.0);
+    let max = query.vmax.or(stats.max).unwrap_or(min);
+    let range_source = if query.vmin.is_some() && query.vmax.is_some() {
+        "query"
+    } else {
+        range_source
+    };
+
+    let (width, height) = query.axis.plane_dims(task.shape);
+    let png = match render_png(&values, width, height, (min, max), query.cmap) {
+        Ok(png) => png,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": e,
+                "task_id": query.task_id,
+            }));
+        }
+    };
+
+    HttpResponse::Ok()
+        .content_type("image/png")
+        .append_header(("X-Slice-Axis", query.axis.label()))
+        .append_header(("X-Slice-Index", query.index.to_string()))
+        .append_header(("X-Slice-Width", width.to_string()))
+        .append_header(("X-Slice-Height", height.to_string()))
+        .append_header(("X-Value-Min", min.to_string()))
+        .append_header(("X-Value-Max", max.to_string()))
+        .append_header(("X-Value-Range", range_source))
+        .append_header(("X-Chunk-Task", query.task_id.clone()))
+        .body(png)
+}
+
+/// 按图像像素顺序读取切片数据（非破坏性），失败时返回无法读取的 chunk 索引
+fn read_slice(task: &TaskData, axis: SliceAxis, index: usize) -> Result<Vec<f64>, usize> {
+    let chunk_data = task.chunk_data.read();
+    axis.voxel_indices(task.shape, index)
+        .map(|voxel| {
+            let (chunk_index, offset) = task.locate(voxel).ok_or(voxel)?;
+            match chunk_data.get(&chunk_index) {
+                Some(Some(values)) => Ok(values[offset]),
+                _ => Err(chunk_index),
+            }
+        })
+        .collect()
+}
+
+/// 整个网格的统计量；任一 chunk 不在内存中（已被请求、解析中或在驻留窗口外）时返回 None
+fn grid_stats(task: &TaskData) -> Option<GridStats> {
+    let chunk_data = task.chunk_data.read();
+    let mut chunks = Vec::with_capacity(task.chunks.len());
+    for descriptor in &task.chunks {
+        match chunk_data.get(&descriptor.index) {
+            Some(Some(values)) => chunks.push(values.as_slice()),
+            _ => return None,
+        }
+    }
+    Some(GridStats::from_values(chunks.into_iter().flatten().copied()))
+}
@@ -0,0 +1,60 @@
+use actix_web::{HttpResponse, Responder, get, http::header::ContentType, web};
+use serde::Deserialize;
+
+use crate::app_state::AppState;
+use crate::handlers::range::read_range_bytes;
+
+#[derive(Deserialize)]
+pub struct SlicesQuery {
+    pub task_id: String,
+    /// 起始 z 平面（包含）
+    pub from: usize,
+    /// 结束 z 平面（不包含）
+    pub to: usize,
+}
+
+/// 获取连续的 z 平面 `[from, to)`（小端序 Float64Array，按平面依次拼接）
+///
+/// 每个平面内 x 变化最快、y 其次，长度为 `nx * ny`。由于 z 变化最慢，
+/// 连续的多个平面在数据中也是连续的一段，一次请求即可返回，适合逐层浏览的 2D 界面；
+/// 读取是非破坏性的，覆盖这些平面的 chunk 必须已就绪且未被请求
+#[get("/voxel-grid/slices")]
+pub async fn get_slices(
+    data: web::Data<AppState>,
+    query: web::Query<SlicesQuery>,
+) -> impl Responder {
+    let Some(task) = data.task_store.get(&query.task_id) else {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "无效的 task_id",
+            "task_id": query.task_id,
+        }));
+    };
+
+    let [nx, ny, nz] = task.shape;
+    if query.from >= query.to || query.to > nz {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "无效的平面范围，要求 from < to <= nz",
+            "from": query.from,
+            "to": query.to,
+            "nz": nz,
+        }));
+    }
+
+    let plane_length = nx * ny;
+    let start = query.from * plane_length;
+    let end = query.to * plane_length;
+    let bytes = match read_range_bytes(&task, &query.task_id, start, end) {
+        Ok(bytes) => bytes,
+        Err(response) => return response,
+    };
+
+    HttpResponse::Ok()
+        .content_type(ContentType::octet_stream())
+        .append_header(("X-Slice-From", query.from.to_string()))
+        .append_header(("X-Slice-To", query.to.to_string()))
+        .append_header(("X-Slice-Count", (query.to - query.from).to_string()))
+        .append_header(("X-Slice-Width", nx.to_string()))
+        .append_header(("X-Slice-Height", ny.to_string()))
+        .append_header(("X-Chunk-Task", query.task_id.clone()))
+        .body(bytes)
+}
@@ -0,0 +1,99 @@
+use actix_web::{HttpResponse, Responder, get, http::header::ContentType, web};
+use serde::Deserialize;
+
+use crate::app_state::AppState;
+use crate::handlers::range::{chunk_unavailable, read_grid_values};
+use crate::utils::voxel_grid::VoxelGrid;
+
+#[derive(Deserialize)]
+pub struct SparseQuery {
+    pub task_id: String,
+    /// 绝对值大于该值的体素被保留，默认 0（保留所有非零体素）
+    #[serde(default)]
+    pub threshold: f64,
+}
+
+/// 以稀疏形式返回网格：只包含绝对值大于 `threshold` 的体素，客户端据此还原稠密网格（其余体素为 0）
+///
+/// 响应体依次为 `count` 个小端序 f64 取值与 `count` 个小端序 u32 坐标三元组 `[x, y, z]`（取值在前，
+/// 保证 Float64Array 按 8 字节对齐），体素按 x 最快、z 最慢的顺序排列。
+/// 读取是非破坏性的，所有 chunk 必须已就绪且未被请求
+#[get("/voxel-grid/sparse")]
+pub async fn get_sparse(
+    data: web::Data<AppState>,
+    query: web::Query<SparseQuery>,
+) -> impl Responder {
+    let Some(task) = data.task_store.get(&query.task_id) else {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "无效的 task_id",
+            "task_id": query.task_id,
+        }));
+    };
+
+    if !(query.threshold.is_finite() && query.threshold >= 0.0) {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "threshold 必须是非负的有限数",
+            "threshold": query.threshold,
+        }));
+    }
+    // 坐标以 u32 传输，维度超出范围时无法表示
+    if task.shape.iter().any(|&n| n > u32::MAX as usize) {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "网格过大，坐标无法以 u32 表示",
+            "shape": task.shape,
+        }));
+    }
+
+    if let Some(reason) = task.failure() {
+        return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "任务解析失败",
+            "task_id": query.task_id,
+            "status": "failed",
+            "details": reason,
+        }));
+    }
+
+    let values = match read_grid_values(&task) {
+        Ok(values) => values,
+        Err(chunk_index) => return chunk_unavailable(&task, &query.task_id, chunk_index),
+    };
+    let grid = match VoxelGrid::new(task.shape, values) {
+        Ok(grid) => grid,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": e,
+                "task_id": query.task_id,
+            }));
+        }
+    };
+
+    // 遍历整个网格，放到阻塞线程池执行
+    let threshold = query.threshold;
+    let sparse = match web::block(move || grid.to_sparse(threshold)).await {
+        Ok(sparse) => sparse,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "生成稀疏网格失败",
+                "details": e.to_string(),
+            }));
+        }
+    };
+
+    let mut body = Vec::with_capacity(sparse.len() * (8 + 12));
+    for value in &sparse.values {
+        body.extend_from_slice(&value.to_le_bytes());
+    }
+    for coord in sparse.coords.iter().flatten() {
+        body.extend_from_slice(&coord.to_le_bytes());
+    }
+
+    let [nx, ny, nz] = sparse.shape;
+    HttpResponse::Ok()
+        .content_type(ContentType::octet_stream())
+        .append_header(("X-Sparse-Count", sparse.len().to_string()))
+        .append_header(("X-Occupancy", sparse.occupancy().to_string()))
+        .append_header(("X-Threshold", threshold.to_string()))
+        .append_header(("X-Grid-Shape", format!("{nx},{ny},{nz}")))
+        .append_header(("X-Chunk-Task", query.task_id.clone()))
+        .body(body)
+}
@@ -0,0 +1,71 @@
+use actix_web::{HttpResponse, Responder, get, web};
+use serde::Deserialize;
+
+use crate::app_state::AppState;
+
+#[derive(Deserialize)]
+pub struct StatusQuery {
+    pub task_id: String,
+}
+
+/// 查询任务状态：chunk 就绪情况与数据校验和
+#[get("/voxel-grid/status")]
+pub async fn get_task_status(
+    data: web::Data<AppState>,
+    query: web::Query<StatusQuery>,
+) -> impl Responder {
+    let Some(task) = data.task_store.get(&query.task_id) else {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "无效的 task_id",
+            "task_id": query.task_id,
+        }));
+    };
+
+    let total_chunks = task.chunks.len();
+    let remaining_chunks = task.remaining_chunk_count();
+    let ready_chunks = task.ready_chunk_count();
+    // 仍在 chunk_data 中但数据为 None 的 chunk 还在解析中（分割失败的 chunk 除外）
+    let chunk_errors = task.chunk_failures();
+    let failed_chunks: Vec<usize> = chunk_errors.keys().copied().collect();
+    let pending_chunks = remaining_chunks - ready_chunks - failed_chunks.len();
+    // 配置了填充值时失败的 chunk 以填充值返回（降级），否则请求这些 chunk 返回错误
+    let degraded_chunks = match data.config.chunk_failure_fill {
+        Some(_) => failed_chunks.len(),
+        None => 0,
+    };
+    let failure = task.failure();
+    let status = task.status_label();
+
+    HttpResponse::Ok().json(serde_json::json!({
+        "task_id": query.task_id,
+        "status": status,
+        "pinned": task.is_pinned(),
+        "shape": task.shape,
+        "total_chunks": total_chunks,
+        "ready_chunks": ready_chunks,
+        "pending_chunks": pending_chunks,
+        "consumed_chunks": total_chunks - remaining_chunks,
+        "failed_chunks": failed_chunks,
+        "chunk_errors": chunk_errors,
+        "degraded_chunks": degraded_chunks,
+        "chunk_failure_fill": data.config.chunk_failure_fill,
+        "data_hash": task.data_hash_hex(),
+        "integral": task.integral(),
+        "voxel_volume": task.voxel_volume,
+        "progress": {
+            "values_read": task.progress.values_read(),
+            "values_total": task.data_length(),
+            "chunks_stored": task.progress.chunks_stored(),
+            "chunks_total": total_chunks,
+            "percent": task.progress_percent(),
+        },
+        "value_transform": task.value_transform,
+        "resident_window": task.resident_window,
+        "smooth_sigma": task.smooth_sigma,
+        "equalize": task.equalize_bins,
+        "precompute": task.precompute,
+        "analyses_ready": task.ready_analyses(),
+        "stream_subscribers": task.stream_subscribers.load(std::sync::atomic::Ordering::Acquire),
+        "error": failure,
+    }))
+}
@@ -0,0 +1,105 @@
+use actix_web::{HttpResponse, Responder, get, http::header::ContentType, web};
+use serde::Deserialize;
+
+use crate::app_state::AppState;
+use crate::handlers::range::{chunk_unavailable, read_grid_values};
+use crate::utils::voxel_grid::VoxelGrid;
+
+/// 导出的 grid 名称的默认值
+const DEFAULT_GRID_NAME: &str = "density";
+
+/// grid 名称的最大长度
+const MAX_GRID_NAME_LEN: usize = 256;
+
+#[derive(Deserialize)]
+pub struct VdbExportQuery {
+    pub task_id: String,
+    /// 稀疏导出的阈值：只写入绝对值大于该值的体素所在的 8³ 块；缺省时稠密导出
+    #[serde(default)]
+    pub threshold: Option<f64>,
+    /// grid 名称，默认 `density`
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+/// 把网格导出为 OpenVDB 文件（单个 FloatGrid），供 Houdini / Blender 等基于 VDB 的工具直接读取
+///
+/// 读取是非破坏性的，所有 chunk 必须已就绪且未被请求
+#[get("/voxel-grid/export.vdb")]
+pub async fn export_vdb(
+    data: web::Data<AppState>,
+    query: web::Query<VdbExportQuery>,
+) -> impl Responder {
+    let Some(task) = data.task_store.get(&query.task_id) else {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "无效的 task_id",
+            "task_id": query.task_id,
+        }));
+    };
+
+    if let Some(threshold) = query.threshold
+        && !(threshold.is_finite() && threshold >= 0.0)
+    {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "threshold 必须是非负的有限数",
+            "threshold": threshold,
+        }));
+    }
+
+    let name = query.name.as_deref().unwrap_or(DEFAULT_GRID_NAME);
+    if name.is_empty() || name.len() > MAX_GRID_NAME_LEN || name.chars().any(char::is_control) {
+        return HttpResponse::BadRequest().json(serde_json::json!({
+            "error": "无效的 grid 名称，要求非空、不含控制字符且不超过最大长度",
+            "name": name,
+            "max_length": MAX_GRID_NAME_LEN,
+        }));
+    }
+
+    if let Some(reason) = task.failure() {
+        return HttpResponse::InternalServerError().json(serde_json::json!({
+            "error": "任务解析失败",
+            "task_id": query.task_id,
+            "status": "failed",
+            "details": reason,
+        }));
+    }
+
+    let values = match read_grid_values(&task) {
+        Ok(values) => values,
+        Err(chunk_index) => return chunk_unavailable(&task, &query.task_id, chunk_index),
+    };
+    let grid = match VoxelGrid::new(task.shape, values) {
+        Ok(grid) => grid,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": e,
+                "task_id": query.task_id,
+            }));
+        }
+    };
+
+    // 构建树结构与序列化与网格大小成正比，放到阻塞线程池执行
+    let threshold = query.threshold;
+    let grid_name = name.to_string();
+    let (bytes, summary) = match web::block(move || grid.to_vdb(&grid_name, threshold)).await {
+        Ok(result) => result,
+        Err(e) => {
+            return HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "导出 VDB 失败",
+                "details": e.to_string(),
+            }));
+        }
+    };
+
+    HttpResponse::Ok()
+        .content_type(ContentType::octet_stream())
+        .append_header((
+            "Content-Disposition",
+            format!("attachment; filename=\"{}.vdb\"", query.task_id),
+        ))
+        .append_header(("X-Vdb-Grid-Name", name.to_string()))
+        .append_header(("X-Vdb-Leaf-Count", summary.leaf_count.to_string()))
+        .append_header(("X-Vdb-Active-Voxels", summary.active_voxels.to_string()))
+        .append_header(("X-Chunk-Task", query.task_id.clone()))
+        .body(bytes)
+}
@@ -2,7 +2,8 @@ use actix_web::{HttpResponse, Responder, get, web};
 use serde::Deserialize;
 
 use crate::app_state::AppState;
-use crate::handlers::preprocess::run_preprocess;
+use crate::cancel::CancelToken;
+use crate::handlers::preprocess::{PreprocessOptions, preprocess_file};
 
 #[derive(Deserialize)]
 pub struct VoxelGridQuery {
@@ -10,6 +11,9 @@ pub struct VoxelGridQuery {
     pub file: String,
     /// 分块大小（元素数量），必须指定
     pub chunk_size: Option<usize>,
+    /// 为 true 时响应不内联 chunks 数组
+    #[serde(default)]
+    pub omit_chunks: bool,
 }
 
 /// 体素网格接口，根据文件名自动识别文件格式并解析
@@ -26,7 +30,20 @@ pub async fn get_voxel_grid(
         }));
     };
 
-    match run_preprocess(data.get_ref(), &query.file, chunk_size, None) {
+    match preprocess_file(
+        &data,
+        &query.file,
+        chunk_size,
+        None,
+        false,
+        PreprocessOptions {
+            omit_chunks: query.omit_chunks,
+            ..Default::default()
+        },
+        CancelToken::new(),
+    )
+    .await
+    {
         Ok(resp) => HttpResponse::Ok().json(resp),
         Err(err) => err,
     }
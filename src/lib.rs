@@ -0,0 +1,29 @@
+//! 体素网格数据服务的核心库
+//!
+//! 二进制入口 `main.rs` 只负责启动流程；解析器、任务存储与 HTTP handler 都在这里导出，
+//! 供基准测试（`benches/`）等外部目标直接调用
+
+pub mod app_state;
+pub mod append_watch;
+pub mod cancel;
+pub mod config;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod handlers;
+pub mod parse_dedup;
+pub mod parse_queue;
+pub mod parser_health;
+pub mod parsers;
+pub mod performance;
+pub mod preprocess_cache;
+pub mod request_metrics;
+pub mod routes;
+pub mod self_check;
+pub mod stream_limit;
+pub mod task;
+pub mod task_persist;
+#[cfg(test)]
+mod test_support;
+#[cfg(feature = "tls")]
+pub mod tls;
+pub mod utils;
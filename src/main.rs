@@ -1,46 +1,103 @@
-mod app_state;
-mod handlers;
-mod parsers;
-mod performance;
-mod routes;
-mod task;
-mod utils;
-
-use std::sync::Arc;
-
+use actix_web::http::KeepAlive;
+use actix_web::middleware::from_fn;
 use actix_web::{App, HttpServer, web};
 
-use crate::performance::PerformanceStore;
-use crate::utils::parser_registry::ParserRegistry;
-use app_state::AppState;
-use task::TaskStore;
+use demos_3d_backend::app_state::AppState;
+use demos_3d_backend::config::Config;
+#[cfg(feature = "grpc")]
+use demos_3d_backend::grpc;
+#[cfg(feature = "tls")]
+use demos_3d_backend::tls;
+use demos_3d_backend::utils::parser_registry::ParserRegistry;
+use demos_3d_backend::{
+    append_watch, parser_health, request_metrics, routes, self_check, task_persist,
+};
+
+/// 以 `mimalloc` feature 编译时替换全局分配器，默认仍使用系统分配器
+#[cfg(feature = "mimalloc")]
+#[global_allocator]
+static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    // 初始化解析器注册表
-    let parser_registry = Arc::new(ParserRegistry::new());
-    let resource_dir = "test/resource".to_string();
+    let config = Config::from_env();
+
+    // `--check [--full]`：部署前用真实解析器检查资源目录中的文件，不启动服务；有文件失败时以非零状态码退出
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "--check") {
+        let full = args.iter().any(|arg| arg == "--full");
+        let resource_dir = std::path::Path::new(&config.resource_dir);
+        let failed = self_check::run(&ParserRegistry::from_config(&config), resource_dir, full).map_err(|e| {
+            std::io::Error::new(
+                e.kind(),
+                format!("无法读取资源目录 {}: {e}", config.resource_dir),
+            )
+        })?;
+        if failed > 0 {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    // 证书与私钥必须同时设置；都未设置时以明文 HTTP/1.1 提供服务
+    let tls_paths = match (&config.tls_cert, &config.tls_key) {
+        (Some(cert), Some(key)) => Some((cert.clone(), key.clone())),
+        (None, None) => None,
+        _ => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "DEMOS3D_TLS_CERT 与 DEMOS3D_TLS_KEY 必须同时设置",
+            ));
+        }
+    };
+    if tls_paths.is_some() && !cfg!(feature = "tls") {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "已配置 TLS 证书，但服务端未启用 TLS，请以 --features tls 重新编译",
+        ));
+    }
+    // 启动前加载证书，路径或内容有误时直接退出
+    #[cfg(feature = "tls")]
+    let tls_config = tls_paths
+        .as_ref()
+        .map(|(cert, key)| tls::load_server_config(cert, key))
+        .transpose()?;
 
-    let supported_extensions = parser_registry.supported_extensions();
+    let app_state = web::Data::new(AppState::from_config(config.clone()));
+
+    // 资源目录缺失时所有文件请求都会以 NotFound 失败，启动时就明确提示
+    if app_state.resource_root.is_none() {
+        let message = format!("资源目录 {} 不存在或不是目录", config.resource_dir);
+        if config.require_resource_dir {
+            return Err(std::io::Error::new(std::io::ErrorKind::NotFound, message));
+        }
+        eprintln!(
+            "警告: {message}，所有文件请求都会失败（设置 DEMOS3D_REQUIRE_RESOURCE_DIR=true 可在此时拒绝启动）"
+        );
+    }
+
+    let supported_extensions = app_state.parser_registry.supported_extensions();
     println!("已注册的解析器:");
     for ext in &supported_extensions {
         println!("  - .{ext}");
     }
 
-    let task_store = Arc::new(TaskStore::new());
-    let performance_store = Arc::new(PerformanceStore::new());
-    let app_state = web::Data::new(AppState {
-        parser_registry,
-        resource_dir: resource_dir.clone(),
-        task_store: task_store.clone(),
-        performance_store: performance_store.clone(),
-    });
+    // 恢复上次正常关闭时保存的任务
+    if let Some(dir) = &config.task_persist_dir {
+        match task_persist::load_tasks(&app_state.task_store, dir) {
+            Ok((loaded, dropped)) => {
+                println!("[任务持久化] 从 {dir} 恢复了 {loaded} 个任务，丢弃 {dropped} 个过期或损坏的任务")
+            }
+            Err(e) => eprintln!("[任务持久化] 读取目录 {dir} 失败: {e}"),
+        }
+    }
 
     // 启动后台清理任务：定期清理过期的任务
-    // 每 5 分钟执行一次清理，避免长期占用内存
-    let cleanup_store = task_store.clone();
+    // 默认每 5 分钟执行一次清理，避免长期占用内存
+    let cleanup_store = app_state.task_store.clone();
+    let cleanup_interval = config.cleanup_interval;
     actix_web::rt::spawn(async move {
-        let mut interval = actix_web::rt::time::interval(std::time::Duration::from_secs(5 * 60));
+        let mut interval = actix_web::rt::time::interval(cleanup_interval);
         loop {
             interval.tick().await;
             let cleaned_count = cleanup_store.cleanup_expired();
@@ -53,16 +110,84 @@ async fn main() -> std::io::Result<()> {
         }
     });
 
-    println!("\n服务器启动在 http://127.0.0.1:8080");
-    println!("资源目录: {resource_dir}");
-    println!("任务 TTL: {} 分钟", task_store.default_ttl().as_secs() / 60);
-  
-    HttpServer::new(move || {
+    // 启动后台解析器自检：结果缓存在 `ParserHealth` 中，由根路径的 `parsers` 返回
+    parser_health::spawn_checks(
+        app_state.parser_health.clone(),
+        app_state.parser_registry.clone(),
+        config.parser_fixture_dir.clone(),
+        config.parser_check_interval,
+    );
+
+    // 启动追加检查：固定的堆栈任务随文件增长追加新的层
+    append_watch::spawn_watch(app_state.clone(), config.append_poll_interval);
+
+    let scheme = if tls_paths.is_some() { "https" } else { "http" };
+    println!("\n服务器启动在 {scheme}://{}:{}", config.host, config.port);
+    match &tls_paths {
+        Some((cert, _)) => println!("协议: HTTPS（TLS，ALPN 协商 HTTP/2 与 HTTP/1.1），证书: {cert}"),
+        None => println!("协议: HTTP/1.1（明文，未配置 TLS）"),
+    }
+    match &app_state.resource_root {
+        Some(root) => println!("资源目录: {}", root.display()),
+        None => println!("资源目录: {}（不存在）", config.resource_dir),
+    }
+    println!(
+        "任务 TTL: {} 分钟",
+        app_state.task_store.default_ttl().as_secs() / 60
+    );
+    println!("后台解析并发数: {}", app_state.parse_queue.workers());
+    // 0 表示关闭 / 不限制
+    let describe = |timeout: std::time::Duration, zero: &str| {
+        if timeout.is_zero() {
+            zero.to_string()
+        } else {
+            format!("{timeout:?}")
+        }
+    };
+    println!(
+        "连接超时: keep-alive {}，请求头 {}，断开 {}",
+        describe(config.keep_alive, "关闭"),
+        describe(config.client_request_timeout, "不限制"),
+        describe(config.client_disconnect_timeout, "不限制")
+    );
+    if cfg!(feature = "mimalloc") {
+        println!("全局分配器: mimalloc");
+    }
+
+    #[cfg(feature = "grpc")]
+    grpc::spawn_server(app_state.clone())?;
+
+    let server_state = app_state.clone();
+    let keep_alive = if config.keep_alive.is_zero() {
+        KeepAlive::Disabled
+    } else {
+        KeepAlive::Timeout(config.keep_alive)
+    };
+    let server = HttpServer::new(move || {
         App::new()
-            .app_data(app_state.clone())
+            .app_data(server_state.clone())
+            .wrap(from_fn(request_metrics::track_requests))
             .configure(routes::configure)
     })
-    .bind(("127.0.0.1", 8080))?
-    .run()
-    .await
+    .keep_alive(keep_alive)
+    .client_request_timeout(config.client_request_timeout)
+    .client_disconnect_timeout(config.client_disconnect_timeout);
+    let address = (config.host.as_str(), config.port);
+    #[cfg(feature = "tls")]
+    let server = match tls_config {
+        Some(tls_config) => server.bind_rustls_0_23(address, tls_config)?,
+        None => server.bind(address)?,
+    };
+    #[cfg(not(feature = "tls"))]
+    let server = server.bind(address)?;
+    server.run().await?;
+
+    // 正常关闭（SIGINT / SIGTERM）后保存未过期的任务，重启后客户端无需重新预处理
+    if let Some(dir) = &config.task_persist_dir {
+        match task_persist::save_tasks(&app_state.task_store, dir) {
+            Ok(saved) => println!("[任务持久化] 已保存 {saved} 个任务到 {dir}"),
+            Err(e) => eprintln!("[任务持久化] 保存任务失败: {e}"),
+        }
+    }
+    Ok(())
 }
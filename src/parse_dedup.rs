@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+
+use crate::task::TaskData;
+use crate::utils::value_transform::ValueTransform;
+
+/// 进行中的后台解析的去重键
+/// 只有解析结果完全相同的请求才能共享同一次解析
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ParseKey {
+    /// 规范化后的文件路径
+    pub path: String,
+    pub chunk_size: usize,
+    /// tile 模式的 tile 大小（线性分块为 None）
+    pub tile_size: Option<[usize; 3]>,
+    /// 舍入位数与数值变换会改变 chunk 内容，因此也属于键的一部分
+    pub round_decimals: Option<u32>,
+    pub value_transform: ValueTransform,
+    /// 高斯模糊的 sigma（f64 无法作为 Hash 键，保存其位模式）
+    pub smooth_sigma: Option<u64>,
+}
+
+/// 等待某次解析结果的任务
+#[derive(Clone)]
+pub struct ParseSubscriber {
+    pub task_id: String,
+    pub task: Arc<TaskData>,
+    pub session_id: Option<String>,
+}
+
+/// 进行中的后台解析表
+///
+/// 同一文件、同一 chunk_size 的预处理请求在解析完成前到达时，不再启动新的解析，
+/// 而是登记为订阅者；解析完成后由发起者把结果分发给所有订阅者的任务
+pub struct InFlightParses {
+    parses: Mutex<HashMap<ParseKey, Vec<ParseSubscriber>>>,
+}
+
+impl InFlightParses {
+    pub fn new() -> Self {
+        Self {
+            parses: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 登记订阅者
+    /// 返回 true 表示当前没有相同的解析在进行，调用方需要启动后台解析；
+    /// 返回 false 表示已加入进行中的解析，无需再启动
+    pub fn join_or_start(&self, key: ParseKey, subscriber: ParseSubscriber) -> bool {
+        let mut parses = self.parses.lock();
+        match parses.get_mut(&key) {
+            Some(subscribers) => {
+                subscribers.push(subscriber);
+                false
+            }
+            None => {
+                parses.insert(key, vec![subscriber]);
+                true
+            }
+        }
+    }
+
+    /// 解析结束（无论成功与否）时调用，移除记录并返回所有订阅者
+    pub fn finish(&self, key: &ParseKey) -> Vec<ParseSubscriber> {
+        self.parses.lock().remove(key).unwrap_or_default()
+    }
+
+    /// 所有订阅者的任务都已取消时移除记录并返回 true，调用方应放弃这次解析
+    /// 在同一把锁内判断与移除，不会漏掉刚刚加入的订阅者
+    pub fn abandon_if_cancelled(&self, key: &ParseKey) -> bool {
+        let mut parses = self.parses.lock();
+        let cancelled = parses.get(key).is_none_or(|subscribers| {
+            subscribers
+                .iter()
+                .all(|subscriber| subscriber.task.cancel.is_cancelled())
+        });
+        if cancelled {
+            parses.remove(key);
+        }
+        cancelled
+    }
+
+    /// 对进行中解析的每个订阅者执行 `f`（用于把解析进度同步到所有任务）
+    pub fn for_each_subscriber(&self, key: &ParseKey, mut f: impl FnMut(&ParseSubscriber)) {
+        if let Some(subscribers) = self.parses.lock().get(key) {
+            subscribers.iter().for_each(&mut f);
+        }
+    }
+
+    /// 当前进行中的解析数量
+    pub fn len(&self) -> usize {
+        self.parses.lock().len()
+    }
+
+    /// 是否没有进行中的解析
+    pub fn is_empty(&self) -> bool {
+        self.parses.lock().is_empty()
+    }
+}
+
+impl Default for InFlightParses {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,244 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::sync::Arc;
+
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
+
+/// 后台解析优先级
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ParsePriority {
+    /// 批量任务
+    Low,
+    /// 默认优先级
+    #[default]
+    Normal,
+    /// 交互式请求，优先调度
+    High,
+}
+
+/// 等待中的解析任务
+struct Waiter {
+    priority: ParsePriority,
+    /// 入队序号，同优先级按先来先服务
+    seq: u64,
+    sender: oneshot::Sender<()>,
+}
+
+impl PartialEq for Waiter {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Waiter {}
+
+impl PartialOrd for Waiter {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Waiter {
+    /// BinaryHeap 是大顶堆：优先级高的在前，同优先级序号小的在前
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+struct QueueState {
+    /// 当前空闲的 worker 数量
+    available: usize,
+    waiters: BinaryHeap<Waiter>,
+    next_seq: u64,
+}
+
+/// 后台解析调度队列
+///
+/// 限制同时进行的后台解析数量（相当于一个固定大小的 worker 池），
+/// 空闲名额按优先级分配给等待中的解析任务，避免小的交互式请求被大批量任务阻塞
+pub struct ParseQueue {
+    workers: usize,
+    state: Mutex<QueueState>,
+}
+
+impl ParseQueue {
+    pub fn new(workers: usize) -> Self {
+        let workers = workers.max(1);
+        Self {
+            workers,
+            state: Mutex::new(QueueState {
+                available: workers,
+                waiters: BinaryHeap::new(),
+                next_seq: 0,
+            }),
+        }
+    }
+
+    /// 等待一个解析名额，名额在返回的 `ParsePermit` 被释放时归还
+    pub async fn acquire(self: &Arc<Self>, priority: ParsePriority) -> ParsePermit {
+        let mut pending = {
+            let mut state = self.state.lock();
+            if state.available > 0 && state.waiters.is_empty() {
+                state.available -= 1;
+                return ParsePermit {
+                    queue: self.clone(),
+                };
+            }
+            let (sender, receiver) = oneshot::channel();
+            let seq = state.next_seq;
+            state.next_seq += 1;
+            state.waiters.push(Waiter {
+                priority,
+                seq,
+                sender,
+            });
+            PendingAcquire {
+                queue: self.clone(),
+                seq,
+                receiver,
+                granted: false,
+            }
+        };
+
+        // 发送端只会在移交名额时使用；等待者被移出队列时 pending 已被丢弃，不会在未发送的情况下结束
+        let _ = (&mut pending.receiver).await;
+        pending.granted = true;
+        ParsePermit {
+            queue: self.clone(),
+        }
+    }
+
+    /// 归还名额：优先移交给等待中优先级最高的任务
+    ///
+    /// 移交（发送）在持锁时完成，`PendingAcquire` 持锁后不在队列中即说明名额已经移交给它
+    fn release(&self) {
+        let mut state = self.state.lock();
+        while let Some(waiter) = state.waiters.pop() {
+            // 等待方已取消（例如 future 被丢弃）时继续尝试下一个
+            if waiter.sender.send(()).is_ok() {
+                return;
+            }
+        }
+        state.available += 1;
+    }
+
+    /// worker 总数
+    pub fn workers(&self) -> usize {
+        self.workers
+    }
+
+    /// 当前排队等待的解析任务数量
+    pub fn queued(&self) -> usize {
+        self.state.lock().waiters.len()
+    }
+}
+
+/// 排队中的 `acquire`
+///
+/// `acquire` 的 future 可能在移交名额前后被丢弃（如客户端断开时丢弃 handler），
+/// 此时把自己移出队列；名额已经移交但尚未被取走时转交给下一个等待者，避免名额永久丢失
+struct PendingAcquire {
+    queue: Arc<ParseQueue>,
+    seq: u64,
+    receiver: oneshot::Receiver<()>,
+    granted: bool,
+}
+
+impl Drop for PendingAcquire {
+    fn drop(&mut self) {
+        if self.granted {
+            return;
+        }
+        let handed_off = {
+            let mut state = self.queue.state.lock();
+            let queued = state.waiters.len();
+            state.waiters.retain(|waiter| waiter.seq != self.seq);
+            state.waiters.len() == queued
+        };
+        if handed_off && self.receiver.try_recv().is_ok() {
+            self.queue.release();
+        }
+    }
+}
+
+/// 解析名额，离开作用域时自动归还
+pub struct ParsePermit {
+    queue: Arc<ParseQueue>,
+}
+
+impl Drop for ParsePermit {
+    fn drop(&mut self) {
+        self.queue.release();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::pin::{Pin, pin};
+    use std::task::{Context, Poll, Waker};
+
+    use super::*;
+
+    fn poll_once<F: Future>(future: Pin<&mut F>) -> Poll<F::Output> {
+        future.poll(&mut Context::from_waker(Waker::noop()))
+    }
+
+    fn acquire_now(queue: &Arc<ParseQueue>) -> ParsePermit {
+        match poll_once(pin!(queue.acquire(ParsePriority::Normal))) {
+            Poll::Ready(permit) => permit,
+            Poll::Pending => panic!("应当立即获得名额"),
+        }
+    }
+
+    #[test]
+    fn dropped_waiter_leaves_queue() {
+        let queue = Arc::new(ParseQueue::new(1));
+        let permit = acquire_now(&queue);
+        {
+            let waiter = pin!(queue.acquire(ParsePriority::Normal));
+            assert!(poll_once(waiter).is_pending());
+            assert_eq!(queue.queued(), 1);
+        }
+        assert_eq!(queue.queued(), 0);
+        drop(permit);
+        drop(acquire_now(&queue));
+    }
+
+    #[test]
+    fn permit_handed_to_unpolled_waiter_is_not_lost() {
+        let queue = Arc::new(ParseQueue::new(1));
+        let permit = acquire_now(&queue);
+        {
+            let mut waiter = pin!(queue.acquire(ParsePriority::Normal));
+            assert!(poll_once(waiter.as_mut()).is_pending());
+            // 名额移交给等待者，但等待者在取走之前被丢弃
+            drop(permit);
+            assert_eq!(queue.queued(), 0);
+        }
+        drop(acquire_now(&queue));
+    }
+
+    #[test]
+    fn higher_priority_waiter_is_served_first() {
+        let queue = Arc::new(ParseQueue::new(1));
+        let permit = acquire_now(&queue);
+        let mut low = pin!(queue.acquire(ParsePriority::Low));
+        let mut high = pin!(queue.acquire(ParsePriority::High));
+        assert!(poll_once(low.as_mut()).is_pending());
+        assert!(poll_once(high.as_mut()).is_pending());
+
+        drop(permit);
+        assert!(poll_once(low.as_mut()).is_pending());
+        let Poll::Ready(high_permit) = poll_once(high.as_mut()) else {
+            panic!("高优先级的等待者应当先获得名额");
+        };
+        drop(high_permit);
+        assert!(poll_once(low.as_mut()).is_ready());
+    }
+}
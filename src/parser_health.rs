@@ -0,0 +1,213 @@
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use parking_lot::RwLock;
+use serde::Serialize;
+
+use crate::performance::get_unix_timestamp_ms;
+use crate::utils::parser::VoxelGridParser;
+use crate::utils::parser_registry::ParserRegistry;
+
+/// 单个解析器的自检状态
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ParserStatus {
+    /// 服务刚启动，首次检查尚未完成
+    Pending,
+    /// 样例解析成功且 shape 符合预期
+    Ok,
+    /// 样例解析失败（或 shape 不符合预期）
+    Failed,
+    /// 解析器没有内置样例，配置的样例目录中也没有匹配的文件
+    Untested,
+}
+
+/// 单个解析器最近一次自检的结果
+#[derive(Serialize, Clone, Debug)]
+pub struct ParserReport {
+    pub name: &'static str,
+    pub extensions: Vec<&'static str>,
+    pub status: ParserStatus,
+    /// 使用的样例：`bundled`（内置样例）或样例目录中的文件路径
+    pub fixture: Option<String>,
+    pub error: Option<String>,
+    /// 最近一次检查的时间（Unix 毫秒）
+    pub checked_at: Option<u64>,
+    /// 最近一次检查的耗时（毫秒）
+    pub duration_ms: Option<f64>,
+}
+
+/// 各解析器的自检结果缓存：后台定期刷新，健康检查只读取缓存，不在请求中解析文件
+pub struct ParserHealth {
+    reports: RwLock<Vec<ParserReport>>,
+}
+
+impl ParserHealth {
+    /// 为注册表中的每个解析器创建 `pending` 状态的记录
+    pub fn new(registry: &ParserRegistry) -> Self {
+        let reports = registry
+            .parsers()
+            .map(|parser| ParserReport {
+                name: parser.name(),
+                extensions: parser.supported_extensions(),
+                status: ParserStatus::Pending,
+                fixture: None,
+                error: None,
+                checked_at: None,
+                duration_ms: None,
+            })
+            .collect();
+        Self {
+            reports: RwLock::new(reports),
+        }
+    }
+
+    /// 最近一次检查的结果（按解析器的注册顺序）
+    pub fn snapshot(&self) -> Vec<ParserReport> {
+        self.reports.read().clone()
+    }
+
+    /// 依次自检所有解析器并更新缓存（会读取并解析样例文件，应在阻塞线程池中调用）
+    ///
+    /// 配置了样例目录时优先使用其中第一个（按文件名排序）扩展名匹配的文件，否则使用解析器的内置样例。
+    /// 状态变为失败时输出一条日志
+    pub fn refresh(&self, registry: &ParserRegistry, fixture_dir: Option<&Path>) {
+        let reports: Vec<ParserReport> = registry
+            .parsers()
+            .enumerate()
+            .map(|(index, parser)| check_parser(parser, index, fixture_dir))
+            .collect();
+
+        let mut current = self.reports.write();
+        for report in &reports {
+            let was_failed = current
+                .iter()
+                .any(|old| old.name == report.name && old.status == ParserStatus::Failed);
+            if report.status == ParserStatus::Failed && !was_failed {
+                eprintln!(
+                    "[解析器自检] {} 自检失败: {}",
+                    report.name,
+                    report.error.as_deref().unwrap_or_default()
+                );
+            }
+        }
+        *current = reports;
+    }
+}
+
+/// 启动后台自检：立即检查一次，之后每隔 `interval` 检查一次（为 0 时只检查一次）
+pub fn spawn_checks(
+    health: Arc<ParserHealth>,
+    registry: Arc<ParserRegistry>,
+    fixture_dir: Option<String>,
+    interval: Duration,
+) {
+    actix_web::rt::spawn(async move {
+        loop {
+            let (health, registry, fixture_dir) =
+                (health.clone(), registry.clone(), fixture_dir.clone());
+            let refreshed = actix_web::web::block(move || {
+                health.refresh(&registry, fixture_dir.as_deref().map(Path::new))
+            })
+            .await;
+            if let Err(e) = refreshed {
+                eprintln!("[解析器自检] 执行自检失败: {e}");
+            }
+            if interval.is_zero() {
+                break;
+            }
+            actix_web::rt::time::sleep(interval).await;
+        }
+    });
+}
+
+/// 用样例完整解析一次，并核对 shape：内置样例与期望的 shape 比较，样例目录中的文件与文件头中读取的 shape 比较
+fn check_parser(
+    parser: &dyn VoxelGridParser,
+    index: usize,
+    fixture_dir: Option<&Path>,
+) -> ParserReport {
+    let started = Instant::now();
+    let mut report = ParserReport {
+        name: parser.name(),
+        extensions: parser.supported_extensions(),
+        status: ParserStatus::Untested,
+        fixture: None,
+        error: None,
+        checked_at: Some(get_unix_timestamp_ms()),
+        duration_ms: None,
+    };
+
+    let outcome = if let Some(path) = fixture_dir.and_then(|dir| find_fixture(parser, dir)) {
+        report.fixture = Some(path.display().to_string());
+        let path = path.to_string_lossy();
+        parser
+            .get_shape_from_file(&path)
+            .map_err(|e| format!("读取 shape 失败: {e}"))
+            .and_then(|shape| parse_and_compare(parser, &path, shape))
+    } else if let Some(fixture) = parser.self_test_fixture() {
+        report.fixture = Some("bundled".to_string());
+        // 解析器只接受文件路径，内置样例先写入临时文件
+        let path = std::env::temp_dir().join(format!(
+            "demos3d-selftest-{}-{index}.{}",
+            std::process::id(),
+            fixture.extension
+        ));
+        let outcome = std::fs::write(&path, fixture.bytes)
+            .map_err(|e| format!("写入临时样例失败: {e}"))
+            .and_then(|()| parse_and_compare(parser, &path.to_string_lossy(), fixture.shape));
+        let _ = std::fs::remove_file(&path);
+        outcome
+    } else {
+        return report;
+    };
+
+    report.duration_ms = Some(started.elapsed().as_secs_f64() * 1000.0);
+    match outcome {
+        Ok(()) => report.status = ParserStatus::Ok,
+        Err(error) => {
+            report.status = ParserStatus::Failed;
+            report.error = Some(error);
+        }
+    }
+    report
+}
+
+/// 完整解析样例并核对 shape；解析器 panic 时同样视为失败，不影响服务
+fn parse_and_compare(
+    parser: &dyn VoxelGridParser,
+    path: &str,
+    expected: [usize; 3],
+) -> Result<(), String> {
+    let parsed = catch_unwind(AssertUnwindSafe(|| {
+        parser.parse_from_file(path).map_err(|e| e.to_string())
+    }))
+    .map_err(|_| "解析时发生 panic".to_string())?;
+    let grid = parsed.map_err(|e| format!("完整解析失败: {e}"))?;
+    if grid.get_shape() != expected {
+        return Err(format!(
+            "解析得到的 shape {:?} 与期望的 shape {:?} 不一致",
+            grid.get_shape(),
+            expected
+        ));
+    }
+    Ok(())
+}
+
+/// 样例目录中第一个（按文件名排序）扩展名被该解析器支持的文件，不递归子目录
+fn find_fixture(parser: &dyn VoxelGridParser, dir: &Path) -> Option<PathBuf> {
+    let mut candidates: Vec<PathBuf> = std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file())
+        .filter(|path| {
+            path.extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| parser.supports(ext))
+        })
+        .collect();
+    candidates.sort();
+    candidates.into_iter().next()
+}
@@ -1,8 +1,32 @@
+mod npy;
+mod plugins;
+mod plt;
+mod text_grid;
 mod vasp;
 
+pub use npy::NpyParser;
+pub use plt::PltParser;
+pub use text_grid::{TextGridParser, TextGridSpec};
 pub use vasp::VaspParser;
 
-/// 获取所有可用的解析器
+/// 获取所有可用的解析器（使用默认配置）
 pub fn get_all_parsers() -> Vec<Box<dyn crate::utils::parser::VoxelGridParser>> {
-    vec![Box::new(VaspParser::new())]
+    get_configured_parsers(&crate::config::Config::default())
+}
+
+/// 按配置创建所有可用的解析器（读取缓冲区大小等）
+/// 配置了插件目录时，插件解析器排在内置解析器之后
+pub fn get_configured_parsers(
+    config: &crate::config::Config,
+) -> Vec<Box<dyn crate::utils::parser::VoxelGridParser>> {
+    let mut parsers: Vec<Box<dyn crate::utils::parser::VoxelGridParser>> = vec![
+        Box::new(VaspParser::with_read_buffer(config.parse_buffer_bytes)),
+        Box::new(PltParser::new()),
+        Box::new(NpyParser::new()),
+    ];
+    if let Some(dir) = &config.parser_plugin_dir {
+        let plugins = plugins::load_plugin_parsers(dir, &parsers);
+        parsers.extend(plugins);
+    }
+    parsers
 }
@@ -0,0 +1,308 @@
+use crate::utils::parser::{
+    SelfTestFixture, VoxelGridParser, read_range_by_full_parse, read_values_at,
+};
+use crate::utils::voxel_grid::{VoxelGrid, checked_grid_len, prealloc_len};
+use byteorder::{BigEndian, ByteOrder, LittleEndian};
+use std::fs::File;
+use std::io::{BufReader, Error, ErrorKind, Read, Seek};
+
+/// NumPy `.npy` 文件的魔数
+const NPY_MAGIC: &[u8] = b"\x93NUMPY";
+
+/// NumPy `.npy` 文件格式解析器
+///
+/// 只接受三维浮点数组（`<f8` / `>f8` / `<f4` / `>f4`）。
+/// 数组的三个轴依次解释为 x、y、z，即 `arr[i, j, k]` 对应体素 `(i, j, k)`：
+/// - `fortran_order: True` 时数据本身就是 x 变化最快，与 `VoxelGrid` 顺序一致
+/// - `fortran_order: False`（NumPy 默认的 C 顺序）时 z 变化最快，需要转置
+pub struct NpyParser;
+
+impl NpyParser {
+    pub fn new() -> Self {
+        NpyParser
+    }
+}
+
+impl Default for NpyParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 支持的元素类型
+#[derive(Clone, Copy)]
+enum Dtype {
+    F64Le,
+    F64Be,
+    F32Le,
+    F32Be,
+}
+
+impl Dtype {
+    fn parse(descr: &str) -> Result<Self, Error> {
+        match descr {
+            "<f8" => Ok(Dtype::F64Le),
+            ">f8" => Ok(Dtype::F64Be),
+            "<f4" => Ok(Dtype::F32Le),
+            ">f4" => Ok(Dtype::F32Be),
+            other => Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("不支持的 npy 元素类型 '{other}'，仅支持 <f8 / >f8 / <f4 / >f4"),
+            )),
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Dtype::F64Le | Dtype::F64Be => 8,
+            Dtype::F32Le | Dtype::F32Be => 4,
+        }
+    }
+
+    fn read(self, bytes: &[u8]) -> f64 {
+        match self {
+            Dtype::F64Le => LittleEndian::read_f64(bytes),
+            Dtype::F64Be => BigEndian::read_f64(bytes),
+            Dtype::F32Le => LittleEndian::read_f32(bytes) as f64,
+            Dtype::F32Be => BigEndian::read_f32(bytes) as f64,
+        }
+    }
+}
+
+/// 解析后的 npy 头部
+struct NpyHeader {
+    dtype: Dtype,
+    fortran_order: bool,
+    shape: [usize; 3],
+}
+
+/// 从头部字典中取出某个键对应的原始值文本（到下一个顶层逗号或右括号为止）
+fn dict_value<'a>(dict: &'a str, key: &str) -> Result<&'a str, Error> {
+    let pattern = format!("'{key}'");
+    let start = dict
+        .find(&pattern)
+        .and_then(|pos| {
+            dict[pos + pattern.len()..]
+                .find(':')
+                .map(|c| pos + pattern.len() + c + 1)
+        })
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, format!("npy 头部缺少 '{key}' 字段")))?;
+    let rest = dict[start..].trim_start();
+
+    // 元组值需要匹配到右括号，其余值到逗号或字典结尾为止
+    let end = if rest.starts_with('(') {
+        rest.find(')').map(|p| p + 1)
+    } else {
+        rest.find([',', '}'])
+    }
+    .unwrap_or(rest.len());
+    Ok(rest[..end].trim())
+}
+
+/// 读取并解析 npy 头部，读取完成后 reader 正好位于数据起始处
+fn read_header<R: Read>(reader: &mut R) -> Result<NpyHeader, Error> {
+    let mut preamble = [0u8; 8];
+    reader.read_exact(&mut preamble)?;
+    if &preamble[..6] != NPY_MAGIC {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "不是有效的 npy 文件（魔数不匹配）",
+        ));
+    }
+
+    // 1.x 版本头部长度为 u16，2.x / 3.x 为 u32
+    let major = preamble[6];
+    let header_len = if major == 1 {
+        let mut len = [0u8; 2];
+        reader.read_exact(&mut len)?;
+        LittleEndian::read_u16(&len) as usize
+    } else {
+        let mut len = [0u8; 4];
+        reader.read_exact(&mut len)?;
+        LittleEndian::read_u32(&len) as usize
+    };
+
+    let mut header = vec![0u8; header_len];
+    reader.read_exact(&mut header)?;
+    let dict = String::from_utf8_lossy(&header);
+
+    let descr = dict_value(&dict, "descr")?.trim_matches(|c| c == '\'' || c == '"');
+    let dtype = Dtype::parse(descr)?;
+
+    let fortran_order = match dict_value(&dict, "fortran_order")? {
+        "True" => true,
+        "False" => false,
+        other => {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("无法解析 fortran_order: {other}"),
+            ));
+        }
+    };
+
+    let dims: Vec<usize> = dict_value(&dict, "shape")?
+        .trim_matches(|c| c == '(' || c == ')')
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse::<usize>())
+        .collect::<Result<_, _>>()
+        .map_err(|e| Error::new(ErrorKind::InvalidData, format!("无法解析shape: {e}")))?;
+
+    if dims.len() != 3 {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("只支持三维数组作为体素网格，但数组维度为 {}", dims.len()),
+        ));
+    }
+
+    let shape = [dims[0], dims[1], dims[2]];
+    // 数据段的字节数同样不能溢出
+    checked_grid_len(shape)
+        .and_then(|total| {
+            total
+                .checked_mul(dtype.size())
+                .ok_or_else(|| format!("shape {shape:?} 过大，数据字节数超出 usize 范围"))
+        })
+        .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+
+    Ok(NpyHeader {
+        dtype,
+        fortran_order,
+        shape,
+    })
+}
+
+impl VoxelGridParser for NpyParser {
+    fn supported_extensions(&self) -> Vec<&'static str> {
+        vec!["npy"]
+    }
+
+    fn name(&self) -> &'static str {
+        "NumPy Parser"
+    }
+
+    fn self_test_fixture(&self) -> Option<SelfTestFixture> {
+        Some(SelfTestFixture {
+            extension: "npy",
+            bytes: include_bytes!("../../test/resource/potential.npy"),
+            shape: [4, 3, 2],
+        })
+    }
+
+    fn sniff(&self, header: &[u8]) -> bool {
+        header.starts_with(NPY_MAGIC)
+    }
+
+    fn get_shape_from_file(
+        &self,
+        file_path: &str,
+    ) -> Result<[usize; 3], Box<dyn std::error::Error>> {
+        // 只读取头部
+        let mut reader = BufReader::new(File::open(file_path)?);
+        let header = read_header(&mut reader)?;
+        Ok(header.shape)
+    }
+
+    fn parse_from_file(&self, file_path: &str) -> Result<VoxelGrid, Box<dyn std::error::Error>> {
+        let mut reader = BufReader::new(File::open(file_path)?);
+        let header = read_header(&mut reader)?;
+        let [nx, ny, nz] = header.shape;
+        // read_header 已校验过体素数与字节数不会溢出
+        let total_elements = checked_grid_len(header.shape)?;
+        let file_len = reader.get_ref().metadata()?.len();
+
+        let mut bytes = Vec::with_capacity(prealloc_len(
+            total_elements * header.dtype.size(),
+            file_len,
+            1,
+        ));
+        reader.read_to_end(&mut bytes)?;
+        let raw: Vec<f64> = bytes
+            .chunks_exact(header.dtype.size())
+            .take(total_elements)
+            .map(|b| header.dtype.read(b))
+            .collect();
+
+        let data = if header.fortran_order || raw.len() != total_elements {
+            // Fortran 顺序无需转置；数据不足时交给 VoxelGrid::new 报告截断
+            raw
+        } else {
+            // C 顺序：raw[(i * ny + j) * nz + k] -> data[(k * ny + j) * nx + i]
+            let mut data = vec![0.0; total_elements];
+            for i in 0..nx {
+                for j in 0..ny {
+                    let src = (i * ny + j) * nz;
+                    for k in 0..nz {
+                        data[(k * ny + j) * nx + i] = raw[src + k];
+                    }
+                }
+            }
+            data
+        };
+
+        VoxelGrid::new(header.shape, data).map_err(|e| {
+            Box::new(Error::new(ErrorKind::InvalidData, e)) as Box<dyn std::error::Error>
+        })
+    }
+    fn supports_range_read(&self, file_path: &str) -> bool {
+        // C 顺序需要转置，连续的元素范围在文件中并不连续
+        File::open(file_path)
+            .and_then(|file| read_header(&mut BufReader::new(file)))
+            .map(|header| header.fortran_order)
+            .unwrap_or(false)
+    }
+
+    fn parse_chunk_from_file(
+        &self,
+        file_path: &str,
+        start: usize,
+        end: usize,
+    ) -> Result<Vec<f64>, Box<dyn std::error::Error>> {
+        let mut reader = BufReader::new(File::open(file_path)?);
+        let header = read_header(&mut reader)?;
+        if !header.fortran_order {
+            return read_range_by_full_parse(self, file_path, start, end);
+        }
+        // Fortran 顺序的存储顺序与 VoxelGrid 一致，直接定位读取
+        let data_offset = reader.stream_position()?;
+        let element_size = header.dtype.size();
+        let offset = data_offset + (start * element_size) as u64;
+        Ok(read_values_at(file_path, offset, end - start, element_size, |b| {
+            header.dtype.read(b)
+        })?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempFile;
+
+    /// 1.0 版本的 npy 文件：f8、C 顺序，头部之后为 `data`
+    fn npy_file(shape: &str, data: &[u8]) -> TempFile {
+        let dict = format!("{{'descr': '<f8', 'fortran_order': False, 'shape': {shape}, }}\n");
+        let mut bytes = NPY_MAGIC.to_vec();
+        bytes.extend_from_slice(&[1, 0]);
+        bytes.extend_from_slice(&(dict.len() as u16).to_le_bytes());
+        bytes.extend_from_slice(dict.as_bytes());
+        bytes.extend_from_slice(data);
+        TempFile::new("npy", &bytes)
+    }
+
+    #[test]
+    fn overflowing_shape_is_rejected() {
+        let file = npy_file(&format!("({}, 2, 1)", usize::MAX), &[]);
+        let error = NpyParser::new()
+            .get_shape_from_file(file.path())
+            .unwrap_err();
+        assert!(error.to_string().contains("过大"), "{error}");
+    }
+
+    #[test]
+    fn shape_larger_than_file_reports_truncation() {
+        let file = npy_file("(100000, 100000, 100000)", &1.0f64.to_le_bytes());
+        let error = NpyParser::new().parse_from_file(file.path()).unwrap_err();
+        assert!(error.to_string().contains("数据被截断"), "{error}");
+    }
+}
@@ -0,0 +1,270 @@
+use crate::utils::parser::{
+    SelfTestFixture, VoxelGridParser, read_range_by_full_parse, read_values_at,
+};
+use crate::utils::voxel_grid::{MIN_TEXT_VALUE_BYTES, VoxelGrid, checked_grid_len, prealloc_len};
+use byteorder::{BigEndian, ByteOrder, LittleEndian, ReadBytesExt};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Error, ErrorKind, Read};
+
+/// 二进制 PLT 头部长度：rank、surface type、nz、ny、nx 共 5 个 int32，
+/// 加上 zmin、zmax、ymin、ymax、xmin、xmax 共 6 个 float32
+const BINARY_HEADER_LEN: usize = 5 * 4 + 6 * 4;
+
+/// PLT（gOpenMol / APBS 势能网格）文件格式解析器
+///
+/// 支持两种变体：
+/// - 二进制：`rank(=3) type nz ny nx` 五个 int32，随后 6 个 float32 的坐标范围，
+///   然后是 float32 数据（大小端通过 rank 字段自动判断）
+/// - 格式化文本：第一行 `3 type`，第二行 `nz ny nx`，第三行坐标范围，之后为数据
+///
+/// 两种变体的数据均为 x 变化最快、z 最慢，与 `VoxelGrid` 的存储顺序一致；
+/// 头部给出的维度顺序是 `nz ny nx`，返回时转换为 `[nx, ny, nz]`
+pub struct PltParser;
+
+impl PltParser {
+    pub fn new() -> Self {
+        PltParser
+    }
+}
+
+impl Default for PltParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 二进制头部的字节序
+#[derive(Clone, Copy)]
+enum Endian {
+    Little,
+    Big,
+}
+
+impl Endian {
+    fn read_i32(self, buf: &[u8]) -> i32 {
+        match self {
+            Endian::Little => LittleEndian::read_i32(buf),
+            Endian::Big => BigEndian::read_i32(buf),
+        }
+    }
+
+    fn read_f32(self, buf: &[u8]) -> f32 {
+        match self {
+            Endian::Little => LittleEndian::read_f32(buf),
+            Endian::Big => BigEndian::read_f32(buf),
+        }
+    }
+}
+
+/// 通过 rank 字段（必须为 3）判断文件是否为二进制 PLT 以及其字节序
+fn detect_binary(header: &[u8]) -> Option<Endian> {
+    if header.len() < 4 {
+        return None;
+    }
+    if LittleEndian::read_i32(&header[0..4]) == 3 {
+        Some(Endian::Little)
+    } else if BigEndian::read_i32(&header[0..4]) == 3 {
+        Some(Endian::Big)
+    } else {
+        None
+    }
+}
+
+/// 将头部中的 `nz ny nx` 转换为 `[nx, ny, nz]`
+fn shape_from_dims(dims: [i64; 3]) -> Result<[usize; 3], Error> {
+    let [nz, ny, nx] = dims;
+    if nx <= 0 || ny <= 0 || nz <= 0 {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("PLT 维度必须为正数，但得到 nz={nz} ny={ny} nx={nx}"),
+        ));
+    }
+    let shape = [nx as usize, ny as usize, nz as usize];
+    checked_grid_len(shape).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+    Ok(shape)
+}
+
+/// 从二进制头部读取 shape
+fn read_binary_shape(header: &[u8], endian: Endian) -> Result<[usize; 3], Error> {
+    if header.len() < BINARY_HEADER_LEN {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "PLT 文件头部长度不足，无法读取shape信息",
+        ));
+    }
+    let nz = endian.read_i32(&header[8..12]) as i64;
+    let ny = endian.read_i32(&header[12..16]) as i64;
+    let nx = endian.read_i32(&header[16..20]) as i64;
+    shape_from_dims([nz, ny, nx])
+}
+
+/// 从文本头部第二行（`nz ny nx`）读取 shape
+fn read_text_shape(line: &str) -> Result<[usize; 3], Error> {
+    let dims: Vec<i64> = line
+        .split_whitespace()
+        .map(|s| s.parse::<i64>())
+        .collect::<Result<_, _>>()
+        .map_err(|e| Error::new(ErrorKind::InvalidData, format!("无法解析shape: {e}")))?;
+
+    if dims.len() != 3 {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("shape应该包含3个维度，但得到{}个", dims.len()),
+        ));
+    }
+    shape_from_dims([dims[0], dims[1], dims[2]])
+}
+
+/// 读取文件开头的二进制头部长度的字节（文件更短时返回实际读取的部分）
+fn read_header_bytes(file_path: &str) -> Result<Vec<u8>, Error> {
+    let mut header = Vec::with_capacity(BINARY_HEADER_LEN);
+    File::open(file_path)?
+        .take(BINARY_HEADER_LEN as u64)
+        .read_to_end(&mut header)?;
+    Ok(header)
+}
+
+impl VoxelGridParser for PltParser {
+    fn supported_extensions(&self) -> Vec<&'static str> {
+        vec!["plt"]
+    }
+
+    fn name(&self) -> &'static str {
+        "PLT Parser"
+    }
+
+    fn self_test_fixture(&self) -> Option<SelfTestFixture> {
+        Some(SelfTestFixture {
+            extension: "plt",
+            bytes: include_bytes!("../../test/resource/potential.plt"),
+            shape: [4, 3, 2],
+        })
+    }
+
+    fn get_shape_from_file(
+        &self,
+        file_path: &str,
+    ) -> Result<[usize; 3], Box<dyn std::error::Error>> {
+        let header = read_header_bytes(file_path)?;
+        if let Some(endian) = detect_binary(&header) {
+            return Ok(read_binary_shape(&header, endian)?);
+        }
+
+        // 文本变体：只读取前 2 行
+        let reader = BufReader::new(File::open(file_path)?);
+        let lines: Vec<String> = reader.lines().take(2).collect::<Result<_, _>>()?;
+        if lines.len() < 2 {
+            return Err(Box::new(Error::new(
+                ErrorKind::InvalidData,
+                "文件行数不足，无法读取shape信息",
+            )));
+        }
+        Ok(read_text_shape(&lines[1])?)
+    }
+
+    fn parse_from_file(&self, file_path: &str) -> Result<VoxelGrid, Box<dyn std::error::Error>> {
+        let mut bytes = Vec::new();
+        File::open(file_path)?.read_to_end(&mut bytes)?;
+
+        let (shape, data) = match detect_binary(&bytes) {
+            Some(endian) => {
+                let shape = read_binary_shape(&bytes, endian)?;
+                let total_elements = checked_grid_len(shape)?;
+                let mut body = &bytes[BINARY_HEADER_LEN..];
+                let mut data =
+                    Vec::with_capacity(prealloc_len(total_elements, body.len() as u64, 4));
+                while body.len() >= 4 && data.len() < total_elements {
+                    let value = match endian {
+                        Endian::Little => body.read_f32::<LittleEndian>()?,
+                        Endian::Big => body.read_f32::<BigEndian>()?,
+                    };
+                    data.push(value as f64);
+                }
+                (shape, data)
+            }
+            None => {
+                let text = String::from_utf8(bytes).map_err(|_| {
+                    Error::new(ErrorKind::InvalidData, "PLT 文件既不是二进制格式也不是文本格式")
+                })?;
+                let lines: Vec<&str> = text.lines().collect();
+                if lines.len() < 3 {
+                    return Err(Box::new(Error::new(
+                        ErrorKind::InvalidData,
+                        "文件行数不足，无法读取shape信息",
+                    )));
+                }
+                let shape = read_text_shape(lines[1])?;
+                let total_elements = checked_grid_len(shape)?;
+
+                // 从第4行（索引3）开始解析数据
+                let mut data = Vec::with_capacity(prealloc_len(
+                    total_elements,
+                    text.len() as u64,
+                    MIN_TEXT_VALUE_BYTES,
+                ));
+                for line in lines.iter().skip(3) {
+                    for token in line.split_whitespace() {
+                        match token.parse::<f64>() {
+                            Ok(value) => data.push(value),
+                            Err(_) => eprintln!("警告: 无法解析值 '{token}'，已跳过"),
+                        }
+                    }
+                }
+                (shape, data)
+            }
+        };
+
+        VoxelGrid::new(shape, data).map_err(|e| {
+            Box::new(Error::new(ErrorKind::InvalidData, e)) as Box<dyn std::error::Error>
+        })
+    }
+    fn supports_range_read(&self, file_path: &str) -> bool {
+        read_header_bytes(file_path)
+            .map(|header| detect_binary(&header).is_some())
+            .unwrap_or(false)
+    }
+
+    fn parse_chunk_from_file(
+        &self,
+        file_path: &str,
+        start: usize,
+        end: usize,
+    ) -> Result<Vec<f64>, Box<dyn std::error::Error>> {
+        // 二进制变体的数据为定长 float32，直接定位读取；文本变体只能完整解析
+        let header = read_header_bytes(file_path)?;
+        let Some(endian) = detect_binary(&header) else {
+            return read_range_by_full_parse(self, file_path, start, end);
+        };
+        let element_size = std::mem::size_of::<f32>();
+        let offset = (BINARY_HEADER_LEN + start * element_size) as u64;
+        Ok(read_values_at(file_path, offset, end - start, element_size, |b| {
+            endian.read_f32(b) as f64
+        })?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempFile;
+
+    #[test]
+    fn binary_shape_larger_than_file_reports_truncation() {
+        let mut bytes = Vec::new();
+        for value in [3i32, 0, 100000, 100000, 100000] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes.extend_from_slice(&[0; 6 * 4]);
+        bytes.extend_from_slice(&1.0f32.to_le_bytes());
+        let file = TempFile::new("plt", &bytes);
+        let error = PltParser::new().parse_from_file(file.path()).unwrap_err();
+        assert!(error.to_string().contains("数据被截断"), "{error}");
+    }
+
+    #[test]
+    fn text_shape_larger_than_file_reports_truncation() {
+        let file = TempFile::new("plt", b"3 0\n100000 100000 100000\n0 1 0 1 0 1\n1.0 2.0\n");
+        let error = PltParser::new().parse_from_file(file.path()).unwrap_err();
+        assert!(error.to_string().contains("数据被截断"), "{error}");
+    }
+}
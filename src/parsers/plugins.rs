@@ -0,0 +1,80 @@
+use super::text_grid::{TextGridParser, TextGridSpec};
+use crate::utils::parser::VoxelGridParser;
+use serde::Deserialize;
+use std::path::Path;
+
+/// 插件描述文件的内容，按 `type` 区分插件类型
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum PluginSpec {
+    TextGrid(TextGridSpec),
+}
+
+/// 从插件目录加载声明式解析器（`DEMOS3D_PARSER_PLUGIN_DIR`）
+///
+/// 目录中每个 `*.json` 文件描述一个解析器，按文件名顺序加载；无法读取或描述无效的文件会被跳过并记录日志，
+/// 不影响服务启动。`builtin` 为已注册的解析器：插件声明的扩展名已被其占用时记录警告，
+/// 按注册顺序匹配时该扩展名仍由先注册的解析器处理
+pub fn load_plugin_parsers(
+    dir: &str,
+    builtin: &[Box<dyn VoxelGridParser>],
+) -> Vec<Box<dyn VoxelGridParser>> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!("[解析器插件] 读取目录 {dir} 失败: {e}");
+            return Vec::new();
+        }
+    };
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    paths.sort();
+
+    let mut parsers: Vec<Box<dyn VoxelGridParser>> = Vec::new();
+    for path in paths {
+        let parser = match load_plugin(&path) {
+            Ok(parser) => parser,
+            Err(e) => {
+                eprintln!("[解析器插件] 跳过 {}: {e}", path.display());
+                continue;
+            }
+        };
+        let extensions = parser.supported_extensions();
+        println!(
+            "[解析器插件] 已加载 {} ({}): {}",
+            parser.name(),
+            path.display(),
+            extensions
+                .iter()
+                .map(|ext| format!(".{ext}"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        for ext in extensions {
+            if let Some(owner) = builtin
+                .iter()
+                .chain(&parsers)
+                .find(|existing| existing.supports(ext))
+            {
+                eprintln!(
+                    "[解析器插件] 警告: 扩展名 .{ext} 已由 {} 处理，{} 的该扩展名不会生效",
+                    owner.name(),
+                    parser.name()
+                );
+            }
+        }
+        parsers.push(parser);
+    }
+    parsers
+}
+
+/// 读取并校验单个插件描述文件
+fn load_plugin(path: &Path) -> Result<Box<dyn VoxelGridParser>, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| format!("读取失败: {e}"))?;
+    let spec: PluginSpec = serde_json::from_str(&content).map_err(|e| format!("描述无效: {e}"))?;
+    match spec {
+        PluginSpec::TextGrid(spec) => Ok(Box::new(TextGridParser::from_spec(spec)?)),
+    }
+}
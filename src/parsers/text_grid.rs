@@ -0,0 +1,434 @@
+use crate::utils::axis_order::{AxisOrder, untranspose};
+use crate::utils::parser::{AppendCursor, AppendedLayers, VoxelGridParser};
+use crate::utils::voxel_grid::{MIN_TEXT_VALUE_BYTES, VoxelGrid, checked_grid_len, prealloc_len};
+use serde::Deserialize;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Error, ErrorKind, Seek, SeekFrom};
+
+/// 声明式文本网格格式的描述（插件描述文件中 `"type": "text_grid"` 的部分）
+///
+/// 文件布局依次为：`skip_lines` 行任意文件头、一行 shape（给定 `shape` 时没有这一行）、
+/// `skip_after_shape` 行任意内容，之后是按 `data_order` 顺序排列的全部数值。
+/// `stack` 为 true 时文件是不断追加 xy 层的堆栈（如模拟程序逐步写出的结果），z 方向的层数由已写入的完整层数决定
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TextGridSpec {
+    /// 解析器名称（日志、健康检查中显示）
+    pub name: String,
+    /// 支持的扩展名（不含点号）
+    pub extensions: Vec<String>,
+    /// 文件开头跳过的行数
+    #[serde(default)]
+    pub skip_lines: usize,
+    /// 固定的 shape `[nx, ny, nz]`；不给出时从跳过文件头后的第一行读取三个整数
+    #[serde(default)]
+    pub shape: Option<[usize; 3]>,
+    /// shape 行之后、数据之前跳过的行数
+    #[serde(default)]
+    pub skip_after_shape: usize,
+    /// 分隔符（单个字符），默认任意空白；分隔符两侧的空白会被忽略
+    #[serde(default)]
+    pub delimiter: Option<char>,
+    /// 以该前缀开头的行视为注释并忽略（只作用于 shape 行与数据段）
+    #[serde(default)]
+    pub comment: Option<String>,
+    /// 数据的轴顺序（从快到慢），默认 `xyz`，与 VASP 相同
+    #[serde(default)]
+    pub data_order: Option<String>,
+    /// 文件开头的固定文本，用于扩展名无法识别时按内容识别格式
+    #[serde(default)]
+    pub magic: Option<String>,
+    /// 为 true 时按堆栈读取：shape 只取 nx、ny（shape 行可以只有两个整数），z 方向的层数为文件中完整层的数量，
+    /// 末尾未写完的层与没有以换行结尾的最后一行被忽略；固定任务会随文件增长追加新的层
+    #[serde(default)]
+    pub stack: bool,
+}
+
+/// 按 `TextGridSpec` 描述解析文本网格的通用解析器
+///
+/// 每个插件描述文件生成一个实例，在启动时创建并在进程的整个生命周期内使用，
+/// 名称与扩展名因此被泄漏为 `&'static str` 以满足 `VoxelGridParser` 的接口
+pub struct TextGridParser {
+    name: &'static str,
+    extensions: Vec<&'static str>,
+    skip_lines: usize,
+    shape: Option<[usize; 3]>,
+    skip_after_shape: usize,
+    delimiter: Option<char>,
+    comment: Option<String>,
+    data_order: AxisOrder,
+    magic: Option<String>,
+    stack: bool,
+}
+
+impl TextGridParser {
+    /// 校验描述并创建解析器
+    pub fn from_spec(spec: TextGridSpec) -> Result<Self, String> {
+        if spec.name.trim().is_empty() {
+            return Err("name 不能为空".to_string());
+        }
+        if spec.extensions.is_empty() {
+            return Err("extensions 不能为空".to_string());
+        }
+        if let Some(ext) = spec
+            .extensions
+            .iter()
+            .find(|ext| ext.is_empty() || ext.starts_with('.'))
+        {
+            return Err(format!("无效的扩展名 '{ext}'（不能为空，且不含点号）"));
+        }
+        if let Some(shape) = spec.shape {
+            checked_grid_len(shape)?;
+        }
+        let data_order = match &spec.data_order {
+            Some(order) => order.parse()?,
+            None => AxisOrder::XYZ,
+        };
+        // 追加的数据只有在 z 最慢时才是完整的 xy 层
+        if spec.stack && data_order.slowest_axis() != 2 {
+            return Err(format!(
+                "stack 模式要求 z 为最慢的轴，但 data_order 为 {data_order}"
+            ));
+        }
+        let leak = |s: String| -> &'static str { Box::leak(s.into_boxed_str()) };
+        Ok(TextGridParser {
+            name: leak(spec.name),
+            extensions: spec.extensions.into_iter().map(leak).collect(),
+            skip_lines: spec.skip_lines,
+            shape: spec.shape,
+            skip_after_shape: spec.skip_after_shape,
+            delimiter: spec.delimiter,
+            comment: spec.comment.filter(|c| !c.is_empty()),
+            data_order,
+            magic: spec.magic.filter(|m| !m.is_empty()),
+            stack: spec.stack,
+        })
+    }
+
+    /// 把一行拆分为非空的字段
+    fn tokens<'a>(&self, line: &'a str) -> Box<dyn Iterator<Item = &'a str> + 'a> {
+        match self.delimiter {
+            Some(delimiter) => Box::new(
+                line.split(delimiter)
+                    .map(str::trim)
+                    .filter(|token| !token.is_empty()),
+            ),
+            None => Box::new(line.split_whitespace()),
+        }
+    }
+
+    fn is_comment(&self, line: &str) -> bool {
+        self.comment
+            .as_deref()
+            .is_some_and(|prefix| line.trim_start().starts_with(prefix))
+    }
+
+    /// 读取文件头，返回 shape、已读取的行数与数据段起始的字节偏移，读取完成后 reader 正好位于数据段起始处
+    ///
+    /// stack 模式下返回的 shape 中 nz 为 0，层数需要读取数据段才能确定
+    fn read_header<R: BufRead>(&self, reader: &mut R) -> Result<([usize; 3], usize, u64), Error> {
+        let mut line = String::new();
+        let mut line_no = 0;
+        let mut offset = 0u64;
+        let mut next_line = |line: &mut String, line_no: &mut usize| -> Result<bool, Error> {
+            line.clear();
+            *line_no += 1;
+            let read = reader.read_line(line)?;
+            offset += read as u64;
+            Ok(read > 0)
+        };
+
+        for _ in 0..self.skip_lines {
+            if !next_line(&mut line, &mut line_no)? {
+                return Err(Error::new(ErrorKind::InvalidData, "文件头不完整"));
+            }
+        }
+
+        let shape = match self.shape {
+            Some(shape) => shape,
+            None => loop {
+                if !next_line(&mut line, &mut line_no)? {
+                    return Err(Error::new(ErrorKind::InvalidData, "文件缺少 shape 行"));
+                }
+                if line.trim().is_empty() || self.is_comment(&line) {
+                    continue;
+                }
+                let dims: Vec<usize> = self
+                    .tokens(&line)
+                    .map(str::parse)
+                    .collect::<Result<_, _>>()
+                    .map_err(|e| {
+                        Error::new(
+                            ErrorKind::InvalidData,
+                            format!("第 {line_no} 行: 无法解析shape: {e}"),
+                        )
+                    })?;
+                match dims[..] {
+                    [nx, ny, nz] => break [nx, ny, nz],
+                    [nx, ny] if self.stack => break [nx, ny, 0],
+                    _ => {
+                        let expected = if self.stack { "2 或 3" } else { "3" };
+                        return Err(Error::new(
+                            ErrorKind::InvalidData,
+                            format!(
+                                "第 {line_no} 行: shape 行应包含 {expected} 个整数，实际为 {}",
+                                dims.len()
+                            ),
+                        ));
+                    }
+                }
+            },
+        };
+        let shape = if self.stack {
+            [shape[0], shape[1], 0]
+        } else {
+            shape
+        };
+        // stack 模式至少要能容纳一层
+        checked_grid_len([shape[0], shape[1], shape[2].max(self.stack as usize)])
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+
+        for _ in 0..self.skip_after_shape {
+            if !next_line(&mut line, &mut line_no)? {
+                return Err(Error::new(ErrorKind::InvalidData, "文件头不完整"));
+            }
+        }
+        Ok((shape, line_no, offset))
+    }
+
+    /// stack 模式：从 `cursor` 处读取数据段中的完整 z 层，每层 `layer_len` 个值
+    ///
+    /// 前 `discard` 个值（层大小的整数倍）只计数、不保留；`collect` 为 false 时只计数、不解析。
+    /// 没有以换行结尾的最后一行可能仍在写入，不读取。返回新读取的完整层数、这些层的数据（文件中的顺序）
+    /// 与最后一个完整层之后的读取位置
+    fn read_layers(
+        &self,
+        file_path: &str,
+        layer_len: usize,
+        cursor: AppendCursor,
+        discard: usize,
+        collect: bool,
+    ) -> Result<(usize, Vec<f64>, AppendCursor), Error> {
+        let mut reader = BufReader::new(File::open(file_path)?);
+        reader.seek(SeekFrom::Start(cursor.offset))?;
+
+        let mut line = String::new();
+        let mut line_start = cursor.offset;
+        let mut skip = cursor.skip_values;
+        let mut seen = 0usize;
+        let mut complete = 0usize;
+        let mut end = cursor;
+        let mut values = Vec::new();
+        loop {
+            line.clear();
+            let read = reader.read_line(&mut line)?;
+            if read == 0 || !line.ends_with('\n') {
+                break;
+            }
+            let line_end = line_start + read as u64;
+            if !self.is_comment(&line) {
+                let mut boundary_at_end = false;
+                for (position, token) in self.tokens(&line).enumerate().skip(skip) {
+                    seen += 1;
+                    if collect && seen > discard {
+                        let value = token.parse::<f64>().map_err(|_| {
+                            Error::new(
+                                ErrorKind::InvalidData,
+                                format!("字节偏移 {line_start} 处的行: 无法解析值 '{token}'"),
+                            )
+                        })?;
+                        values.push(value);
+                    }
+                    boundary_at_end = seen.is_multiple_of(layer_len);
+                    if boundary_at_end {
+                        complete = seen;
+                        end = AppendCursor {
+                            offset: line_start,
+                            skip_values: position + 1,
+                        };
+                    }
+                }
+                // 层恰好在行尾结束时，下一次从下一行开始
+                if boundary_at_end {
+                    end = AppendCursor {
+                        offset: line_end,
+                        skip_values: 0,
+                    };
+                }
+            }
+            skip = 0;
+            line_start = line_end;
+        }
+
+        if complete < discard {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "文件中的完整层只有 {complete} 个值，少于已解析的 {discard} 个（文件被截断或重写）"
+                ),
+            ));
+        }
+        let layers = (complete - discard) / layer_len;
+        values.truncate(layers * layer_len);
+        Ok((layers, values, end))
+    }
+
+    /// stack 模式：读取文件头，返回每层的 shape `[nx, ny]` 与数据段起始的读取位置
+    fn stack_start(&self, file_path: &str) -> Result<([usize; 2], AppendCursor), Error> {
+        let mut reader = BufReader::new(File::open(file_path)?);
+        let ([nx, ny, _], _, offset) = self.read_header(&mut reader)?;
+        let cursor = AppendCursor {
+            offset,
+            skip_values: 0,
+        };
+        Ok(([nx, ny], cursor))
+    }
+
+    /// stack 模式：解析文件中的全部完整层
+    fn parse_stack(&self, file_path: &str) -> Result<VoxelGrid, Box<dyn std::error::Error>> {
+        let ([nx, ny], cursor) = self.stack_start(file_path)?;
+        let (layers, values, _) = self.read_layers(file_path, nx * ny, cursor, 0, true)?;
+        if layers == 0 {
+            return Err(Box::new(Error::new(
+                ErrorKind::InvalidData,
+                "文件中还没有完整的 z 层",
+            )));
+        }
+        let shape = [nx, ny, layers];
+        let data = untranspose(&values, shape, self.data_order);
+        VoxelGrid::new(shape, data).map_err(|e| {
+            Box::new(Error::new(ErrorKind::InvalidData, e)) as Box<dyn std::error::Error>
+        })
+    }
+}
+
+impl VoxelGridParser for TextGridParser {
+    fn supported_extensions(&self) -> Vec<&'static str> {
+        self.extensions.clone()
+    }
+
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn sniff(&self, header: &[u8]) -> bool {
+        self.magic
+            .as_ref()
+            .is_some_and(|magic| header.starts_with(magic.as_bytes()))
+    }
+
+    fn get_shape_from_file(
+        &self,
+        file_path: &str,
+    ) -> Result<[usize; 3], Box<dyn std::error::Error>> {
+        if self.stack {
+            // 层数需要数出数据段中完整层的数量（只计数、不解析）
+            let ([nx, ny], cursor) = self.stack_start(file_path)?;
+            let (layers, _, _) = self.read_layers(file_path, nx * ny, cursor, 0, false)?;
+            return Ok([nx, ny, layers]);
+        }
+        // 只读取文件头
+        let mut reader = BufReader::new(File::open(file_path)?);
+        let (shape, _, _) = self.read_header(&mut reader)?;
+        Ok(shape)
+    }
+
+    fn shape_is_cheap(&self) -> bool {
+        !self.stack
+    }
+
+    fn supports_append(&self, _file_path: &str) -> bool {
+        self.stack
+    }
+
+    fn read_appended_layers(
+        &self,
+        file_path: &str,
+        parsed: usize,
+        cursor: Option<AppendCursor>,
+    ) -> Result<Option<AppendedLayers>, Box<dyn std::error::Error>> {
+        if !self.stack {
+            return Ok(None);
+        }
+        let ([nx, ny], start) = self.stack_start(file_path)?;
+        let (cursor, discard) = match cursor {
+            Some(cursor) => (cursor, 0),
+            None => (start, parsed),
+        };
+        let (layers, values, cursor) =
+            self.read_layers(file_path, nx * ny, cursor, discard, true)?;
+        Ok(Some(AppendedLayers {
+            layers,
+            values: untranspose(&values, [nx, ny, layers], self.data_order),
+            cursor,
+        }))
+    }
+
+    fn parse_from_file(&self, file_path: &str) -> Result<VoxelGrid, Box<dyn std::error::Error>> {
+        if self.stack {
+            return self.parse_stack(file_path);
+        }
+        let mut reader = BufReader::new(File::open(file_path)?);
+        let (shape, mut line_no, _) = self.read_header(&mut reader)?;
+        let total = checked_grid_len(shape)?;
+        let file_len = reader.get_ref().metadata()?.len();
+
+        let mut values = Vec::with_capacity(prealloc_len(total, file_len, MIN_TEXT_VALUE_BYTES));
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                break;
+            }
+            line_no += 1;
+            if self.is_comment(&line) {
+                continue;
+            }
+            for token in self.tokens(&line) {
+                if values.len() == total {
+                    return Err(Box::new(Error::new(
+                        ErrorKind::InvalidData,
+                        format!("第 {line_no} 行: 数据多于 shape {shape:?} 所需的 {total} 个值"),
+                    )));
+                }
+                let value = token.parse::<f64>().map_err(|_| {
+                    Error::new(
+                        ErrorKind::InvalidData,
+                        format!("第 {line_no} 行: 无法解析值 '{token}'"),
+                    )
+                })?;
+                values.push(value);
+            }
+        }
+        if values.len() < total {
+            return Err(Box::new(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "数据被截断: shape {shape:?} 需要 {total} 个值，但只读取到 {} 个",
+                    values.len()
+                ),
+            )));
+        }
+
+        let data = untranspose(&values, shape, self.data_order);
+        VoxelGrid::new(shape, data).map_err(|e| {
+            Box::new(Error::new(ErrorKind::InvalidData, e)) as Box<dyn std::error::Error>
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempFile;
+
+    #[test]
+    fn shape_larger_than_file_reports_truncation() {
+        let spec: TextGridSpec =
+            serde_json::from_str(r#"{"name": "Test", "extensions": ["tgrid"]}"#).unwrap();
+        let parser = TextGridParser::from_spec(spec).unwrap();
+        let file = TempFile::new("tgrid", b"100000 100000 100000\n1.0 2.0\n");
+        let error = parser.parse_from_file(file.path()).unwrap_err();
+        assert!(error.to_string().contains("数据被截断"), "{error}");
+    }
+}
@@ -1,14 +1,73 @@
-use crate::utils::parser::VoxelGridParser;
-use crate::utils::voxel_grid::VoxelGrid;
+use crate::utils::parser::{
+    DEFAULT_GRID_NAME, SelfTestFixture, VoxelGridParser, read_range_by_full_parse, read_values_at,
+};
+use crate::utils::voxel_grid::{MIN_TEXT_VALUE_BYTES, VoxelGrid, checked_grid_len, prealloc_len};
+use byteorder::{ByteOrder, LittleEndian};
 use std::fs::File;
-use std::io::{BufRead, BufReader, Error, ErrorKind};
+use std::io::{BufRead, BufReader, Error, ErrorKind, Read, Seek, SeekFrom};
 
 /// VASP 文件格式解析器
-pub struct VaspParser;
+///
+/// 数据段通过 `BufReader` 流式读取、逐行解析（行缓冲区复用），内存中不保留整个文件
+pub struct VaspParser {
+    /// 读取文件时的缓冲区大小（字节）
+    read_buffer: usize,
+}
 
 impl VaspParser {
+    /// 默认的读取缓冲区大小
+    pub const DEFAULT_READ_BUFFER_BYTES: usize = 256 * 1024;
+
+    /// 读取缓冲区的下限，保证判断数据段编码时能一次看到足够的字节
+    pub const MIN_READ_BUFFER_BYTES: usize = 4096;
+
     pub fn new() -> Self {
-        VaspParser
+        Self::with_read_buffer(Self::DEFAULT_READ_BUFFER_BYTES)
+    }
+
+    /// 指定读取缓冲区大小（字节），小于 `MIN_READ_BUFFER_BYTES` 时按下限处理
+    pub fn with_read_buffer(bytes: usize) -> Self {
+        VaspParser {
+            read_buffer: bytes.max(Self::MIN_READ_BUFFER_BYTES),
+        }
+    }
+
+    /// 打开文件并解析头部，返回定位到数据段开头的 reader、头部与数据段编码
+    fn open_data_section(
+        &self,
+        file_path: &str,
+    ) -> Result<(BufReader<File>, Header, DataEncoding), Error> {
+        let mut reader = BufReader::with_capacity(self.read_buffer, File::open(file_path)?);
+        let header = read_header(&mut reader)?;
+        // 回退到固定布局时可能已经读过了 shape 行，重新定位到数据段开头
+        reader.seek(SeekFrom::Start(header.data_offset))?;
+        let encoding = DataEncoding::sniff(reader.fill_buf()?);
+        Ok((reader, header, encoding))
+    }
+
+    /// 数据段每个网格预分配的元素数，不超过数据段的字节数能容纳的值数量（见 `prealloc_len`）
+    fn data_capacity(
+        reader: &BufReader<File>,
+        header: &Header,
+        encoding: DataEncoding,
+        total_elements: usize,
+    ) -> Result<usize, Error> {
+        let data_bytes = reader
+            .get_ref()
+            .metadata()?
+            .len()
+            .saturating_sub(header.data_offset);
+        let value_bytes = match encoding {
+            DataEncoding::Ascii => MIN_TEXT_VALUE_BYTES,
+            DataEncoding::Binary => std::mem::size_of::<f64>() as u64,
+        };
+        Ok(prealloc_len(total_elements, data_bytes, value_bytes))
+    }
+}
+
+impl Default for VaspParser {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -21,93 +80,676 @@ impl VoxelGridParser for VaspParser {
         "VASP Parser"
     }
 
+    fn self_test_fixture(&self) -> Option<SelfTestFixture> {
+        Some(SelfTestFixture {
+            extension: "vasp",
+            bytes: include_bytes!("../../test/resource/small.vasp"),
+            shape: [4, 4, 4],
+        })
+    }
+
+    fn sniff(&self, header: &[u8]) -> bool {
+        // 第 2 行为缩放系数，第 3-5 行为 3x3 晶格矢量，其后能定位到 shape 行
+        let text = String::from_utf8_lossy(header);
+        let lines: Vec<&str> = text.lines().take(LATTICE_LINES).collect();
+        if lines.len() < LATTICE_LINES {
+            return false;
+        }
+        let floats = |line: &str| {
+            line.split_whitespace()
+                .map(|token| token.parse::<f64>())
+                .collect::<Result<Vec<_>, _>>()
+                .map(|values| values.len())
+                .unwrap_or(0)
+        };
+        let shape_ok = read_header(&mut &header[..])
+            .is_ok_and(|header| header.shape.iter().all(|&n| n > 0));
+
+        floats(lines[1]) == 1 && lines[2..5].iter().all(|line| floats(line) == 3) && shape_ok
+    }
+
     fn get_shape_from_file(
         &self,
         file_path: &str,
     ) -> Result<[usize; 3], Box<dyn std::error::Error>> {
-        // 快速读取 shape：只读取前 29 行
+        // 快速读取 shape：只读取头部
+        let mut reader = BufReader::new(File::open(file_path)?);
+        Ok(read_header(&mut reader)?.shape)
+    }
+
+    fn get_cell_volume_from_file(
+        &self,
+        file_path: &str,
+    ) -> Result<Option<f64>, Box<dyn std::error::Error>> {
+        // 第 2 行为缩放系数，第 3-5 行为晶格矢量
         let file = File::open(file_path)?;
         let reader = BufReader::new(file);
-        let lines: Vec<String> = reader.lines().take(29).collect::<Result<_, _>>()?;
-
-        if lines.len() < 29 {
+        let lines: Vec<String> = reader.lines().take(5).collect::<Result<_, _>>()?;
+        if lines.len() < 5 {
             return Err(Box::new(Error::new(
                 ErrorKind::InvalidData,
-                "文件行数不足，无法读取shape信息",
+                "文件行数不足，无法读取晶格信息",
             )));
         }
+        Ok(Some(cell_volume(&lines[1], [&lines[2], &lines[3], &lines[4]])?))
+    }
 
-        // 解析shape: "112  112  108"（第29行，索引28）
-        let shape_line = &lines[28];
-        let shape: Vec<usize> = shape_line
-            .split_whitespace()
-            .map(|s| s.parse::<usize>())
-            .collect::<Result<_, _>>()
-            .map_err(|e| Error::new(ErrorKind::InvalidData, format!("无法解析shape: {e}")))?;
+    fn parse_from_file(&self, file_path: &str) -> Result<VoxelGrid, Box<dyn std::error::Error>> {
+        self.parse_from_file_with_progress(file_path, &|_| {})
+    }
 
-        if shape.len() != 3 {
-            return Err(Box::new(Error::new(
-                ErrorKind::InvalidData,
-                format!("shape应该包含3个维度，但得到{}个", shape.len()),
-            )));
+    fn parse_from_file_with_progress(
+        &self,
+        file_path: &str,
+        progress: &dyn Fn(usize),
+    ) -> Result<VoxelGrid, Box<dyn std::error::Error>> {
+        // 头部在文本和二进制变体中都是文本；shape 行之后是数据：根据内容自动判断是 ASCII 浮点数还是二进制 double
+        let (mut reader, header, encoding) = self.open_data_section(file_path)?;
+        let shape_array = header.shape;
+        let total_elements =
+            checked_grid_len(shape_array).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        let capacity = Self::data_capacity(&reader, &header, encoding, total_elements)?;
+
+        let data = match encoding {
+            DataEncoding::Ascii => parse_ascii_values(&mut reader, capacity, progress)?,
+            DataEncoding::Binary => parse_binary_values(&mut reader, total_elements, capacity)?,
+        };
+        progress(data.len());
+
+        // 创建体素网格
+        VoxelGrid::new(shape_array, data).map_err(|e| {
+            Box::new(Error::new(ErrorKind::InvalidData, e)) as Box<dyn std::error::Error>
+        })
+    }
+
+    fn parse_grids_from_file(
+        &self,
+        file_path: &str,
+    ) -> Result<Vec<(String, VoxelGrid)>, Box<dyn std::error::Error>> {
+        let (mut reader, header, encoding) = self.open_data_section(file_path)?;
+        let total_elements =
+            checked_grid_len(header.shape).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        let capacity = Self::data_capacity(&reader, &header, encoding, total_elements)?;
+
+        // 二进制数据段没有可用于定位后续网格的 shape 行，只返回第一个网格
+        let sections = match encoding {
+            DataEncoding::Ascii => {
+                parse_ascii_grids(&mut reader, header.shape, total_elements, capacity)?
+            }
+            DataEncoding::Binary => {
+                vec![parse_binary_values(&mut reader, total_elements, capacity)?]
+            }
+        };
+        let names = grid_names(sections.len());
+        sections
+            .into_iter()
+            .zip(names)
+            .map(|(data, name)| {
+                let grid = VoxelGrid::new(header.shape, data)
+                    .map_err(|e| Error::new(ErrorKind::InvalidData, format!("网格 {name}: {e}")))?;
+                Ok((name, grid))
+            })
+            .collect()
+    }
+
+    fn supports_range_read(&self, file_path: &str) -> bool {
+        matches!(
+            self.open_data_section(file_path),
+            Ok((_, _, DataEncoding::Binary))
+        )
+    }
+
+    fn parse_chunk_from_file(
+        &self,
+        file_path: &str,
+        start: usize,
+        end: usize,
+    ) -> Result<Vec<f64>, Box<dyn std::error::Error>> {
+        // 二进制数据段为定长 f64，直接定位读取；ASCII 数据段只能完整解析
+        match self.open_data_section(file_path)? {
+            (_, header, DataEncoding::Binary) => {
+                let element_size = std::mem::size_of::<f64>();
+                let offset = header.data_offset + (start * element_size) as u64;
+                Ok(read_values_at(
+                    file_path,
+                    offset,
+                    end - start,
+                    element_size,
+                    LittleEndian::read_f64,
+                )?)
+            }
+            (_, _, DataEncoding::Ascii) => read_range_by_full_parse(self, file_path, start, end),
         }
+    }
+}
+
+/// 固定布局下的头部行数（第 29 行为 shape），空行启发式失败时使用
+const HEADER_LINES: usize = 29;
+
+/// 注释、缩放系数与三行晶格矢量占据的行数，定位 shape 时从其后开始寻找空行
+const LATTICE_LINES: usize = 5;
+
+/// 空行启发式最多扫描的行数（含原子坐标，远大于常见文件的头部）
+const MAX_HEADER_SCAN_LINES: usize = 10_000;
+
+/// 头部单行的最大字节数；超过时视为已进入数据段（如没有换行的二进制数据），停止读取
+const MAX_HEADER_LINE_BYTES: u64 = 4096;
+
+/// ASCII 数据段每解析这么多行报告一次进度
+const PROGRESS_INTERVAL_LINES: usize = 4096;
+
+/// VASP 以 Fortran 定宽格式写出数据，值超出字段宽度时整个字段输出为星号（如 `*****************`）。
+/// 这类值以 NaN 代替，保持元素数量不变（JSON 中为 `null`，统计与积分时跳过）
+const OVERFLOW_VALUE: f64 = f64::NAN;
 
-        Ok([shape[0], shape[1], shape[2]])
+/// 判断数据段编码时检查的字节数
+const SNIFF_BYTES: usize = 256;
+
+/// 读取二进制数据段时每批转换的元素数量
+const BINARY_BATCH_VALUES: usize = 8192;
+
+/// 数据段编码
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DataEncoding {
+    /// 空白分隔的 ASCII 浮点数（标准 VASP 格式）
+    Ascii,
+    /// 连续的小端序 f64
+    Binary,
+}
+
+impl DataEncoding {
+    /// 检查数据段开头的字节：只包含数字、符号、指数标记、溢出星号与空白时视为 ASCII，否则为二进制
+    fn sniff(body: &[u8]) -> Self {
+        let is_ascii_float_byte = |b: &u8| {
+            b.is_ascii_digit()
+                || b.is_ascii_whitespace()
+                || matches!(b, b'.' | b'-' | b'+' | b'E' | b'e' | b'*')
+        };
+        if body.iter().take(SNIFF_BYTES).all(is_ascii_float_byte) {
+            DataEncoding::Ascii
+        } else {
+            DataEncoding::Binary
+        }
     }
+}
 
-    fn parse_from_file(&self, file_path: &str) -> Result<VoxelGrid, Box<dyn std::error::Error>> {
-        let file = File::open(file_path)?;
-        let reader = BufReader::new(file);
-        let lines: Vec<String> = reader.lines().collect::<Result<_, _>>()?;
+/// 由缩放系数行与三行晶格矢量计算晶胞体积
+///
+/// 按 VASP 的约定：缩放系数为正数时晶格矢量整体乘以该系数；为负数时其绝对值是晶胞的目标体积；
+/// 也可以给出三个正数，分别缩放晶格矢量的 x / y / z 分量
+fn cell_volume(scale_line: &str, lattice_lines: [&str; 3]) -> Result<f64, Error> {
+    let parse_floats = |line: &str, what: &str| {
+        line.split_whitespace()
+            .map(|token| token.parse::<f64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| Error::new(ErrorKind::InvalidData, format!("无法解析{what}: {e}")))
+    };
 
-        // 第29行（索引28）包含shape信息
-        if lines.len() < 29 {
-            return Err(Box::new(Error::new(
+    let mut lattice = [[0.0; 3]; 3];
+    for (vector, line) in lattice.iter_mut().zip(lattice_lines) {
+        let values = parse_floats(line, "晶格矢量")?;
+        if values.len() != 3 {
+            return Err(Error::new(
                 ErrorKind::InvalidData,
-                "文件行数不足，无法读取shape信息",
-            )));
+                format!("晶格矢量应该包含3个分量，但得到{}个", values.len()),
+            ));
         }
+        vector.copy_from_slice(&values);
+    }
+    let [a, b, c] = lattice;
+    // 混合积 a · (b × c)，取绝对值使左手系晶格也得到正的体积
+    let unscaled = (a[0] * (b[1] * c[2] - b[2] * c[1]) - a[1] * (b[0] * c[2] - b[2] * c[0])
+        + a[2] * (b[0] * c[1] - b[1] * c[0]))
+        .abs();
+    if !unscaled.is_finite() || unscaled == 0.0 {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "晶格矢量线性相关，晶胞体积为 0",
+        ));
+    }
 
-        // 解析shape: "112  112  108"
-        let shape_line = &lines[28]; // 第29行（0-indexed是28）
-        let shape: Vec<usize> = shape_line
-            .split_whitespace()
-            .map(|s| s.parse::<usize>())
-            .collect::<Result<_, _>>()
-            .map_err(|e| Error::new(ErrorKind::InvalidData, format!("无法解析shape: {e}")))?;
-
-        if shape.len() != 3 {
-            return Err(Box::new(Error::new(
+    let scale = parse_floats(scale_line, "缩放系数")?;
+    let volume = match *scale.as_slice() {
+        // 负数表示目标体积
+        [target] if target < 0.0 => -target,
+        [factor] if factor > 0.0 => unscaled * factor.powi(3),
+        [sx, sy, sz] if sx > 0.0 && sy > 0.0 && sz > 0.0 => unscaled * sx * sy * sz,
+        _ => {
+            return Err(Error::new(
                 ErrorKind::InvalidData,
-                format!("shape应该包含3个维度，但得到{}个", shape.len()),
-            )));
+                format!("无效的缩放系数: {}", scale_line.trim()),
+            ));
+        }
+    };
+    if !volume.is_finite() {
+        return Err(Error::new(ErrorKind::InvalidData, "晶胞体积不是有限值"));
+    }
+    Ok(volume)
+}
+
+/// 文件头部的解析结果
+#[derive(Debug, Clone, Copy)]
+struct Header {
+    shape: [usize; 3],
+    /// 数据段的起始字节偏移（shape 行之后）
+    data_offset: u64,
+}
+
+/// 解析头部，定位 shape 行与数据段
+///
+/// 优先使用空行启发式：晶格矢量之后的第一个空行把原子信息与体积数据分开，其后第一个非空行即 shape 行，
+/// 因此原子数量不同、头部行数不固定的文件也能解析。找不到空行、遇到非文本内容，或空行后的第一行不是
+/// 三个正整数时，回退到固定布局（第 29 行为 shape）
+fn read_header(reader: &mut impl BufRead) -> Result<Header, Error> {
+    let mut line = Vec::new();
+    let mut offset = 0u64;
+    let mut line_count = 0;
+    // 固定布局的 shape 行与其后的偏移
+    let mut fixed: Option<(Vec<u8>, u64)> = None;
+    let mut after_blank = false;
+    let mut scanning = true;
+
+    loop {
+        // 启发式已失败且已读到固定布局的 shape 行，无需继续
+        if !scanning && fixed.is_some() {
+            break;
+        }
+        line.clear();
+        let read = (&mut *reader)
+            .take(MAX_HEADER_LINE_BYTES)
+            .read_until(b'\n', &mut line)?;
+        if line.last() != Some(&b'\n') {
+            break;
+        }
+        offset += read as u64;
+        line_count += 1;
+        if line_count == HEADER_LINES {
+            fixed = Some((line.clone(), offset));
+        }
+        if !scanning {
+            continue;
+        }
+        if line_count > MAX_HEADER_SCAN_LINES || std::str::from_utf8(&line).is_err() {
+            scanning = false;
+            continue;
+        }
+        if line_count <= LATTICE_LINES {
+            continue;
+        }
+        let blank = line.iter().all(u8::is_ascii_whitespace);
+        if !after_blank {
+            after_blank = blank;
+            continue;
+        }
+        if blank {
+            continue;
+        }
+        match parse_shape_dims(&line) {
+            Ok(shape) if shape.iter().all(|&n| n > 0) => {
+                // 找到了 shape 行但体素总数溢出时直接报告，而不是回退到固定布局后给出无关的错误
+                checked_grid_len(shape).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+                return Ok(Header {
+                    shape,
+                    data_offset: offset,
+                });
+            }
+            _ => scanning = false,
+        }
+    }
+
+    let Some((shape_line, data_offset)) = fixed else {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "文件行数不足，无法读取shape信息",
+        ));
+    };
+    Ok(Header {
+        shape: parse_shape(&shape_line)?,
+        data_offset,
+    })
+}
+
+/// 解析 shape 行，如 "112  112  108"，并检查体素总数不会溢出
+fn parse_shape(line: &[u8]) -> Result<[usize; 3], Error> {
+    let shape = parse_shape_dims(line)?;
+    checked_grid_len(shape).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+    Ok(shape)
+}
+
+/// 把 shape 行解析为三个整数，不检查体素总数
+fn parse_shape_dims(line: &[u8]) -> Result<[usize; 3], Error> {
+    let line = std::str::from_utf8(line)
+        .map_err(|_| Error::new(ErrorKind::InvalidData, "文件头部不是有效的文本"))?;
+    let shape: Vec<usize> = line
+        .split_whitespace()
+        .map(|s| s.parse::<usize>())
+        .collect::<Result<_, _>>()
+        .map_err(|e| Error::new(ErrorKind::InvalidData, format!("无法解析shape: {e}")))?;
+
+    if shape.len() != 3 {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("shape应该包含3个维度，但得到{}个", shape.len()),
+        ));
+    }
+
+    Ok([shape[0], shape[1], shape[2]])
+}
+
+/// 解析 ASCII 数据段：每行可能有多个空白分隔的浮点数
+/// 每 `PROGRESS_INTERVAL_LINES` 行通过 `progress` 报告一次已读取的值数量，`capacity` 为预分配的元素数
+fn parse_ascii_values(
+    reader: &mut impl BufRead,
+    capacity: usize,
+    progress: &dyn Fn(usize),
+) -> Result<Vec<f64>, Error> {
+    let mut data = Vec::with_capacity(capacity);
+    let mut overflowed = 0;
+    let mut line = Vec::new();
+    let mut line_count = 0;
+    while read_data_line(reader, &mut line)? {
+        for token in ascii_tokens(&line) {
+            push_ascii_value(&mut data, token?, &mut overflowed);
+        }
+        line_count += 1;
+        if line_count % PROGRESS_INTERVAL_LINES == 0 {
+            progress(data.len());
         }
+    }
+    warn_overflowed(overflowed);
+    Ok(data)
+}
 
-        let shape_array = [shape[0], shape[1], shape[2]];
-        let total_elements = shape_array[0] * shape_array[1] * shape_array[2];
-
-        // 从第30行（索引29）开始解析数据
-        let mut data = Vec::with_capacity(total_elements);
-
-        for line in lines.iter().skip(29) {
-            // 解析每行的浮点数（可能有多个值，用空格分隔）
-            for token in line.split_whitespace() {
-                // 处理科学计数法（如 0.14631837E+00）
-                match token.parse::<f64>() {
-                    Ok(value) => data.push(value),
-                    Err(_) => {
-                        // 如果不是有效的浮点数，跳过（可能是行尾的空格或空行）
-                        if !token.trim().is_empty() {
-                            eprintln!("警告: 无法解析值 '{token}'，已跳过");
-                        }
-                    }
-                }
+/// 读取下一行到复用的缓冲区 `line`（先清空），文件结束时返回 false
+fn read_data_line(reader: &mut impl BufRead, line: &mut Vec<u8>) -> Result<bool, Error> {
+    line.clear();
+    Ok(reader.read_until(b'\n', line)? > 0)
+}
+
+/// 按 ASCII 空白切分一行，不为每一行或每个值分配字符串
+fn ascii_tokens(line: &[u8]) -> impl Iterator<Item = Result<&str, Error>> {
+    line.split(u8::is_ascii_whitespace)
+        .filter(|token| !token.is_empty())
+        .map(|token| {
+            std::str::from_utf8(token)
+                .map_err(|_| Error::new(ErrorKind::InvalidData, "数据段不是有效的文本"))
+        })
+}
+
+/// 解析单个值并追加到 `data`，无法解析时警告并跳过
+fn push_ascii_value(data: &mut Vec<f64>, token: &str, overflowed: &mut usize) {
+    match parse_ascii_value(token, overflowed) {
+        Some(value) => data.push(value),
+        None => eprintln!("警告: 无法解析值 '{token}'，已跳过"),
+    }
+}
+
+/// 解析数据段中的单个值（支持科学计数法，如 0.14631837E+00）
+///
+/// 溢出的星号字段返回 `OVERFLOW_VALUE` 并累加 `overflowed`；其他无法解析的内容返回 None
+fn parse_ascii_value(token: &str, overflowed: &mut usize) -> Option<f64> {
+    if token.bytes().all(|b| b == b'*') {
+        *overflowed += 1;
+        return Some(OVERFLOW_VALUE);
+    }
+    token.parse::<f64>().ok()
+}
+
+/// 汇总报告溢出值的数量，避免大量逐个警告
+fn warn_overflowed(overflowed: usize) {
+    if overflowed > 0 {
+        eprintln!("警告: {overflowed} 个值超出 VASP 定宽格式（写为星号），已替换为 NaN");
+    }
+}
+
+/// 解析可能包含多个网格的 ASCII 数据段（自旋极化 / 非共线计算的 CHGCAR 等）
+///
+/// 每个网格读取恰好 `total_elements` 个值；之后跳过增广占据数、原子磁矩等内容，
+/// 直到再次出现与文件头相同的 shape 行，其后为下一个网格；`capacity` 为每个网格预分配的元素数
+fn parse_ascii_grids(
+    reader: &mut impl BufRead,
+    shape: [usize; 3],
+    total_elements: usize,
+    capacity: usize,
+) -> Result<Vec<Vec<f64>>, Error> {
+    let mut grids = Vec::new();
+    let mut overflowed = 0;
+    let mut current = Some(Vec::with_capacity(capacity));
+    let mut line = Vec::new();
+    while read_data_line(reader, &mut line)? {
+        let Some(values) = current.as_mut() else {
+            if parse_shape(&line).is_ok_and(|next| next == shape) {
+                current = Some(Vec::with_capacity(capacity));
             }
+            continue;
+        };
+        for token in ascii_tokens(&line).take(total_elements - values.len()) {
+            push_ascii_value(values, token?, &mut overflowed);
         }
+        if values.len() == total_elements {
+            grids.extend(current.take());
+        }
+    }
+    warn_overflowed(overflowed);
 
-        // 创建体素网格
-        VoxelGrid::new(shape_array, data).map_err(|e| {
-            Box::new(Error::new(ErrorKind::InvalidData, e)) as Box<dyn std::error::Error>
+    if let Some(values) = current {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "第 {} 个网格的数据被截断: shape {:?} 需要 {} 个值，但只读取到 {} 个",
+                grids.len() + 1,
+                shape,
+                total_elements,
+                values.len()
+            ),
+        ));
+    }
+    Ok(grids)
+}
+
+/// 按文件中网格的数量命名：自旋极化（2 个）为总密度与磁化密度 `diff`（自旋向上减自旋向下），
+/// 非共线（4 个）为总密度与磁化密度的三个分量；其他数量按序号命名
+fn grid_names(count: usize) -> Vec<String> {
+    let names: &[&str] = match count {
+        2 => &[DEFAULT_GRID_NAME, "diff"],
+        4 => &[DEFAULT_GRID_NAME, "mx", "my", "mz"],
+        _ => &[DEFAULT_GRID_NAME],
+    };
+    (0..count)
+        .map(|index| match names.get(index) {
+            Some(name) => name.to_string(),
+            None => format!("grid{index}"),
         })
+        .collect()
+}
+
+/// 解析二进制数据段：小端序 f64，多余的尾部字节忽略
+/// 按批读取到复用的缓冲区再转换，最多读取 `total_elements` 个值，`capacity` 为预分配的元素数
+fn parse_binary_values(
+    reader: &mut impl Read,
+    total_elements: usize,
+    capacity: usize,
+) -> Result<Vec<f64>, Error> {
+    let element_size = std::mem::size_of::<f64>();
+    let mut data = Vec::with_capacity(capacity);
+    let mut batch = Vec::with_capacity(BINARY_BATCH_VALUES * element_size);
+    while data.len() < total_elements {
+        let wanted = (total_elements - data.len()).min(BINARY_BATCH_VALUES) * element_size;
+        batch.clear();
+        let read = (&mut *reader).take(wanted as u64).read_to_end(&mut batch)?;
+        data.extend(batch.chunks_exact(element_size).map(LittleEndian::read_f64));
+        if read < wanted {
+            break;
+        }
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::TempFile;
+
+    /// 19 个原子的最小 VASP 文件（shape 位于第 29 行），shape 行与数据段由调用方给出
+    fn vasp_file(shape_line: &str, data: &str) -> TempFile {
+        let positions = "0 0 0\n".repeat(19);
+        let text = format!(
+            "test\n1.0\n4 0 0\n0 4 0\n0 0 4\nO\n19\nDirect\n{positions}\n{shape_line}\n{data}\n"
+        );
+        TempFile::new("vasp", text.as_bytes())
+    }
+
+    #[test]
+    fn overflowing_shape_is_rejected() {
+        let file = vasp_file(&format!("{} 2 1", usize::MAX), "1.0");
+        let error = VaspParser::new()
+            .get_shape_from_file(file.path())
+            .unwrap_err();
+        assert!(error.to_string().contains("过大"), "{error}");
+    }
+
+    #[test]
+    fn shape_larger_than_file_reports_truncation() {
+        // 按 shape 预分配需要 8e15 字节，预分配按文件大小限制后应当返回截断错误而不是中止进程
+        let file = vasp_file("100000 100000 100000", "1.0 2.0");
+        let parser = VaspParser::new();
+        assert_eq!(
+            parser.get_shape_from_file(file.path()).unwrap(),
+            [100000; 3]
+        );
+        let error = parser.parse_from_file(file.path()).unwrap_err();
+        assert!(error.to_string().contains("数据被截断"), "{error}");
+    }
+
+    #[test]
+    fn asterisk_overflow_keeps_element_count() {
+        let grid = VaspParser::new()
+            .parse_from_file("test/resource/overflow.vasp")
+            .unwrap();
+        assert_eq!(grid.get_shape(), [4, 4, 4]);
+        let data = grid.get_data();
+        assert_eq!(data.len(), 64);
+        for (index, value) in data.iter().enumerate() {
+            let overflowed = [1, 30, 63].contains(&index);
+            assert_eq!(value.is_nan(), overflowed, "index {index}: {value}");
+        }
+        // 星号之后的值没有错位
+        assert_eq!(data[2], 3.0);
+        assert_eq!(data[31], 134.0);
+        assert_eq!(data[62], 333.0);
+    }
+
+    #[test]
+    fn asterisk_tokens_are_counted() {
+        let mut overflowed = 0;
+        assert!(
+            parse_ascii_value("*****************", &mut overflowed)
+                .unwrap()
+                .is_nan()
+        );
+        assert_eq!(parse_ascii_value("1.5E+00", &mut overflowed), Some(1.5));
+        assert_eq!(parse_ascii_value("**x", &mut overflowed), None);
+        assert_eq!(overflowed, 1);
+    }
+
+    #[test]
+    fn cell_volume_follows_the_scale_convention() {
+        // 晶格矢量 (3,0,0)、(1,2,0)、(0.5,0.5,4) 的混合积为 3 × 2 × 4 = 24
+        let parser = VaspParser::new();
+        let positive = parser
+            .get_cell_volume_from_file("test/resource/cell_positive.vasp")
+            .unwrap()
+            .unwrap();
+        assert!(
+            (positive - 24.0 * 2.5f64.powi(3)).abs() < 1e-9,
+            "{positive}"
+        );
+        // 负的缩放系数直接给出体积，与晶格矢量的长度无关
+        let negative = parser
+            .get_cell_volume_from_file("test/resource/cell_negative.vasp")
+            .unwrap()
+            .unwrap();
+        assert!((negative - 123.456).abs() < 1e-9, "{negative}");
+    }
+
+    #[test]
+    fn cell_volume_with_three_scale_factors() {
+        let lattice = ["1 0 0", "0 1 0", "0 0 1"];
+        assert_eq!(cell_volume("2 3 4", lattice).unwrap(), 24.0);
+        assert!(cell_volume("2 3", lattice).is_err());
+        assert!(cell_volume("2 -3 4", lattice).is_err());
+        assert!(cell_volume("0", lattice).is_err());
+    }
+
+    #[test]
+    fn coplanar_lattice_is_rejected() {
+        let error = cell_volume("1.0", ["1 0 0", "0 1 0", "1 1 0"]).unwrap_err();
+        assert!(error.to_string().contains("线性相关"), "{error}");
+    }
+
+    #[test]
+    fn text_and_binary_data_sections_give_the_same_grid() {
+        let parser = VaspParser::new();
+        let text = parser.parse_from_file("test/resource/small.vasp").unwrap();
+        let binary = parser
+            .parse_from_file("test/resource/small_binary.vasp")
+            .unwrap();
+        assert_eq!(text.get_shape(), binary.get_shape());
+        let bits = |values: &[f64]| values.iter().map(|v| v.to_bits()).collect::<Vec<_>>();
+        assert_eq!(bits(text.get_data()), bits(binary.get_data()));
+
+        assert!(!parser.supports_range_read("test/resource/small.vasp"));
+        assert!(parser.supports_range_read("test/resource/small_binary.vasp"));
+    }
+
+    /// 解析文件，返回 `get_shape_from_file` 的 shape 与完整解析得到的 shape 和数据（按位比较）
+    fn shape_and_bits(path: &str) -> ([usize; 3], [usize; 3], Vec<u64>) {
+        let parser = VaspParser::new();
+        let shape = parser.get_shape_from_file(path).unwrap();
+        let grid = parser.parse_from_file(path).unwrap();
+        let bits = grid.get_data().iter().map(|v| v.to_bits()).collect();
+        (shape, grid.get_shape(), bits)
+    }
+
+    /// 对 small.vasp 的第 `line`（从 1 开始）行做替换后写入临时文件
+    fn small_with_line(line: usize, replacement: &str) -> TempFile {
+        let text = std::fs::read_to_string("test/resource/small.vasp").unwrap();
+        let mut lines: Vec<&str> = text.lines().collect();
+        lines[line - 1] = replacement;
+        TempFile::new("vasp", (lines.join("\n") + "\n").as_bytes())
+    }
+
+    #[test]
+    fn atom_count_does_not_affect_header_detection() {
+        // 原子数较少 / 较多时 shape 行不在第 29 行，按空行定位
+        let expected = shape_and_bits("test/resource/small.vasp");
+        assert_eq!(expected.0, [4, 4, 4]);
+        assert_eq!(expected.1, [4, 4, 4]);
+        for path in [
+            "test/resource/header_short.vasp",
+            "test/resource/header_long.vasp",
+        ] {
+            assert_eq!(shape_and_bits(path), expected, "{path}");
+        }
+    }
+
+    #[test]
+    fn header_without_blank_line_uses_fixed_layout() {
+        // 第 28 行的空行换成非空内容，启发式找不到 shape 行，回退到第 29 行
+        let file = small_with_line(28, "  0.950000  0.090000  0.230000");
+        assert_eq!(
+            shape_and_bits(file.path()),
+            shape_and_bits("test/resource/small.vasp")
+        );
+    }
+
+    #[test]
+    fn non_shape_line_after_blank_falls_back_to_fixed_layout() {
+        // 第 9 行为空行，其后是坐标而不是 shape，启发式放弃，回退到第 29 行
+        let file = small_with_line(9, "");
+        assert_eq!(
+            shape_and_bits(file.path()),
+            shape_and_bits("test/resource/small.vasp")
+        );
     }
 }
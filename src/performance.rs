@@ -1,4 +1,5 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::str::FromStr;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use parking_lot::RwLock;
@@ -20,15 +21,109 @@ pub struct PerformanceRecord {
     pub msg: String,
 }
 
+/// 会话内某个行组的耗时汇总
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ChannelGroupSummary {
+    /// 记录数
+    pub records: usize,
+    /// 各记录耗时之和（毫秒），并行的记录会重复计算
+    pub total_ms: u64,
+    /// 单条记录的最长耗时（毫秒）
+    pub max_ms: u64,
+}
+
+/// 会话的性能数据汇总
+#[derive(Debug, Clone, Serialize)]
+pub struct PerformanceSummary {
+    /// 记录数
+    pub record_count: usize,
+    /// 最早的开始时间（Unix 时间戳，毫秒）
+    pub first_start_time: u64,
+    /// 最晚的结束时间（Unix 时间戳，毫秒）
+    pub last_end_time: u64,
+    /// 从最早开始到最晚结束的时长（毫秒）
+    pub span_ms: u64,
+    /// 按行组汇总
+    pub channel_groups: BTreeMap<String, ChannelGroupSummary>,
+}
+
+impl PerformanceSummary {
+    /// 汇总一组记录，没有记录时返回 None
+    pub fn from_records<'a>(
+        records: impl IntoIterator<Item = &'a PerformanceRecord>,
+    ) -> Option<Self> {
+        let mut record_count = 0;
+        let mut first_start_time = u64::MAX;
+        let mut last_end_time = 0;
+        let mut channel_groups: BTreeMap<String, ChannelGroupSummary> = BTreeMap::new();
+        for record in records {
+            record_count += 1;
+            first_start_time = first_start_time.min(record.start_time);
+            last_end_time = last_end_time.max(record.end_time);
+            let duration = record.end_time.saturating_sub(record.start_time);
+            let group = channel_groups.entry(record.channel_group.clone()).or_default();
+            group.records += 1;
+            group.total_ms += duration;
+            group.max_ms = group.max_ms.max(duration);
+        }
+        if record_count == 0 {
+            return None;
+        }
+        Some(Self {
+            record_count,
+            first_start_time,
+            last_end_time,
+            span_ms: last_end_time.saturating_sub(first_start_time),
+            channel_groups,
+        })
+    }
+}
+
+/// 单个会话的性能记录
+#[derive(Default)]
+pub struct SessionRecords {
+    /// 按添加顺序保存的记录，超出上限时从头部丢弃最早的记录
+    pub records: VecDeque<PerformanceRecord>,
+    /// 因超出上限而丢弃的记录数
+    pub dropped: u64,
+}
+
+impl SessionRecords {
+    /// 追加记录；`max_records` 大于 0 且已满时丢弃最早的记录（环形缓冲区）
+    fn push(&mut self, session_id: &str, record: PerformanceRecord, max_records: usize) {
+        if max_records > 0 && self.records.len() >= max_records {
+            if self.dropped == 0 {
+                eprintln!(
+                    "[性能数据记录] 警告: session_id {session_id} 的记录数达到上限 {max_records}，之后丢弃最早的记录"
+                );
+            }
+            self.records.pop_front();
+            self.dropped += 1;
+        }
+        self.records.push_back(record);
+    }
+}
+
+/// 会话记录数上限的状态，随性能接口返回
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct RecordCapStatus {
+    /// 每个会话的记录数上限，0 表示不限制
+    pub max_records: usize,
+    /// 因超出上限而丢弃的最早记录数
+    pub dropped_records: u64,
+}
+
 /// 性能数据存储
 /// 按 session_id 存储性能记录
 pub struct PerformanceStore {
-    /// session_id -> 性能记录列表
-    pub records: RwLock<HashMap<String, Vec<PerformanceRecord>>>,
+    /// session_id -> 性能记录
+    pub records: RwLock<HashMap<String, SessionRecords>>,
     /// TTL（Time-To-Live）默认过期时间：30 分钟
     default_ttl: Duration,
     /// session_id -> 创建时间
     session_times: RwLock<HashMap<String, SystemTime>>,
+    /// 每个会话最多保存的记录数，0 表示不限制
+    max_records_per_session: usize,
 }
 
 impl PerformanceStore {
@@ -37,14 +132,23 @@ impl PerformanceStore {
             records: RwLock::new(HashMap::new()),
             default_ttl: Duration::from_secs(30 * 60), // 30 分钟
             session_times: RwLock::new(HashMap::new()),
+            max_records_per_session: 0,
         }
     }
 
+    /// 设置每个会话最多保存的记录数（0 表示不限制），超出时丢弃最早的记录
+    ///
+    /// 会话 TTL 之外的另一道限制：长时间交互或异常客户端的会话在 TTL 内也可能产生大量记录
+    pub fn with_max_records_per_session(mut self, max_records: usize) -> Self {
+        self.max_records_per_session = max_records;
+        self
+    }
+
     /// 添加性能记录
     pub fn add_record(&self, session_id: &str, record: PerformanceRecord) {
         let mut records = self.records.write();
-        let entry = records.entry(session_id.to_string()).or_insert_with(Vec::new);
-        entry.push(record);
+        let entry = records.entry(session_id.to_string()).or_default();
+        entry.push(session_id, record, self.max_records_per_session);
 
         // 记录会话创建时间（如果还没有）
         let mut session_times = self.session_times.write();
@@ -56,10 +160,10 @@ impl PerformanceStore {
     /// 批量添加性能记录
     pub fn add_records(&self, session_id: &str, records: Vec<PerformanceRecord>) {
         let mut all_records = self.records.write();
-        let entry = all_records
-            .entry(session_id.to_string())
-            .or_insert_with(Vec::new);
-        entry.extend(records);
+        let entry = all_records.entry(session_id.to_string()).or_default();
+        for record in records {
+            entry.push(session_id, record, self.max_records_per_session);
+        }
 
         // 记录会话创建时间（如果还没有）
         let mut session_times = self.session_times.write();
@@ -70,7 +174,32 @@ impl PerformanceStore {
 
     /// 获取指定会话的所有性能记录
     pub fn get_records(&self, session_id: &str) -> Option<Vec<PerformanceRecord>> {
-        self.records.read().get(session_id).cloned()
+        self.records
+            .read()
+            .get(session_id)
+            .map(|session| session.records.iter().cloned().collect())
+    }
+
+    /// 指定会话的记录数上限状态（会话不存在时丢弃数为 0）
+    pub fn cap_status(&self, session_id: &str) -> RecordCapStatus {
+        RecordCapStatus {
+            max_records: self.max_records_per_session,
+            dropped_records: self
+                .records
+                .read()
+                .get(session_id)
+                .map_or(0, |session| session.dropped),
+        }
+    }
+
+    /// 获取指定会话的性能数据汇总，没有记录时返回 None
+    pub fn summary(&self, session_id: &str) -> Option<PerformanceSummary> {
+        PerformanceSummary::from_records(&self.records.read().get(session_id)?.records)
+    }
+
+    /// 当前保存的会话数量
+    pub fn session_count(&self) -> usize {
+        self.records.read().len()
     }
 
     /// 清理过期的会话
@@ -106,14 +235,20 @@ impl PerformanceStore {
     }
 }
 
-/// 获取当前线程 ID（用于标识）
-/// 使用线程本地存储的计数器生成唯一标识
+impl Default for PerformanceStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// 线程本地存储的计数器，用于为每个线程生成唯一标识
 thread_local! {
-    static THREAD_COUNTER: std::cell::Cell<usize> = std::cell::Cell::new(0);
+    static THREAD_COUNTER: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
 }
 
 static NEXT_THREAD_ID: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(1);
 
+/// 获取当前线程 ID（用于标识）
 pub fn get_thread_id() -> usize {
     THREAD_COUNTER.with(|counter| {
         let id = counter.get();
@@ -127,6 +262,39 @@ pub fn get_thread_id() -> usize {
     })
 }
 
+/// 性能记录 channel 编号（如 `split_chunk_3` 中的 3）的分配方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ChannelIdScheme {
+    /// 按线程首次记录的顺序编号（`get_thread_id`），编号在不同运行之间没有对应关系
+    #[default]
+    Thread,
+    /// 使用操作自身的稳定标识（如 chunk 索引），同一逻辑操作在不同运行中落在同一 channel，便于对比时间线；
+    /// 没有稳定标识的操作（预处理、整体解析等）仍按线程编号
+    Logical,
+}
+
+impl ChannelIdScheme {
+    /// 计算 channel 编号；`logical` 为操作的稳定标识，没有时传 None
+    pub fn channel_id(self, logical: Option<usize>) -> usize {
+        match (self, logical) {
+            (ChannelIdScheme::Logical, Some(id)) => id,
+            _ => get_thread_id(),
+        }
+    }
+}
+
+impl FromStr for ChannelIdScheme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "thread" => Ok(Self::Thread),
+            "logical" => Ok(Self::Logical),
+            other => Err(format!("未知的 channel 编号方式: {other}")),
+        }
+    }
+}
+
 /// 获取 Unix 时间戳（毫秒）
 pub fn get_unix_timestamp_ms() -> u64 {
     SystemTime::now()
@@ -135,3 +303,27 @@ pub fn get_unix_timestamp_ms() -> u64 {
         .as_millis() as u64
 }
 
+
+/// 服务端为请求生成（或沿用任务的）session_id 时，通过该响应头返回给客户端
+pub const SESSION_ID_HEADER: &str = "x-session-id";
+
+/// 确定请求实际使用的 session_id，返回 `(session_id, 是否由服务端指定)`
+///
+/// 客户端指定了 session_id 时直接使用；否则在开启 `auto_session_id` 时先沿用 `fallback`
+/// （如创建任务时使用的会话），没有时生成一个新的 UUID。关闭时返回 None，不记录性能数据
+pub fn resolve_session_id(
+    provided: Option<&str>,
+    fallback: Option<&str>,
+    auto: bool,
+) -> (Option<String>, bool) {
+    match provided {
+        Some(session_id) => (Some(session_id.to_string()), false),
+        None if auto => {
+            let session_id = fallback
+                .map(str::to_string)
+                .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+            (Some(session_id), true)
+        }
+        None => (None, false),
+    }
+}
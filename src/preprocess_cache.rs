@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+
+use parking_lot::Mutex;
+use serde::Deserialize;
+
+use crate::handlers::preprocess::PreprocessResponse;
+use crate::task::TaskStore;
+
+/// 预处理结果缓存的键：只有文件与影响结果的请求参数都相同，才能复用之前创建的任务
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PreprocessCacheKey {
+    /// 规范化后的文件路径
+    pub path: String,
+    /// 序列化后的 `chunk_size` 与预处理选项（不含优先级等不影响结果的参数）
+    pub request: String,
+}
+
+/// 客户端提供的缓存校验值：上一次预处理响应中的 `file_mtime`，以及可选的任务 `data_hash`
+#[derive(Deserialize, Clone, Debug)]
+pub struct CacheValidator {
+    /// 文件修改时间（Unix 毫秒）
+    pub file_mtime: u64,
+    /// 任务数据的 xxh3 校验和（16 位十六进制）；提供时缓存的任务数据也必须一致
+    #[serde(default)]
+    pub data_hash: Option<String>,
+}
+
+/// 最近一次预处理的结果表，用于客户端驱动的缓存校验（预处理的 `if_unchanged`）
+///
+/// 每个键只保留最近一次成功预处理的响应；写入时顺带移除任务已不存在的记录，表的大小不超过存活任务数
+pub struct PreprocessCache {
+    entries: Mutex<HashMap<PreprocessCacheKey, Vec<PreprocessResponse>>>,
+}
+
+impl PreprocessCache {
+    pub fn new() -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 记录一次成功预处理的响应；响应中没有文件修改时间时无法校验，不记录
+    pub fn insert(
+        &self,
+        key: PreprocessCacheKey,
+        responses: &[PreprocessResponse],
+        task_store: &TaskStore,
+    ) {
+        if responses.is_empty() || responses.iter().any(|resp| resp.file_mtime.is_none()) {
+            return;
+        }
+        let mut entries = self.entries.lock();
+        entries.retain(|_, cached| tasks_alive(cached, task_store));
+        entries.insert(key, responses.to_vec());
+    }
+
+    /// 校验缓存：文件当前的修改时间与客户端提供的、缓存记录的都一致，所有任务仍然存在且没有失败或被取消，
+    /// 并且（提供 `data_hash` 时）第一个任务的数据校验和一致时，返回缓存的响应（`pinned` 按任务当前状态更新）
+    pub fn validate(
+        &self,
+        key: &PreprocessCacheKey,
+        validator: &CacheValidator,
+        current_mtime: Option<u64>,
+        task_store: &TaskStore,
+    ) -> Option<Vec<PreprocessResponse>> {
+        if current_mtime != Some(validator.file_mtime) {
+            return None;
+        }
+        let mut responses = self.entries.lock().get(key)?.clone();
+        if responses
+            .iter()
+            .any(|resp| resp.file_mtime != Some(validator.file_mtime))
+        {
+            return None;
+        }
+        for (index, resp) in responses.iter_mut().enumerate() {
+            let task = task_store.get(&resp.task_id)?;
+            if task.failure().is_some() {
+                return None;
+            }
+            if index == 0
+                && let Some(expected) = &validator.data_hash
+                && task.data_hash_hex().as_ref() != Some(expected)
+            {
+                return None;
+            }
+            resp.pinned = task.is_pinned();
+        }
+        Some(responses)
+    }
+}
+
+impl Default for PreprocessCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 记录中的任务是否都还存在（未过期、未被淘汰）
+fn tasks_alive(responses: &[PreprocessResponse], task_store: &TaskStore) -> bool {
+    responses
+        .iter()
+        .all(|resp| task_store.get(&resp.task_id).is_some())
+}
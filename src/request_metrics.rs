@@ -0,0 +1,211 @@
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::middleware::Next;
+use actix_web::{Error, web};
+use parking_lot::Mutex;
+use serde::Serialize;
+
+use crate::app_state::AppState;
+
+/// 延迟直方图各 bucket 的上界（微秒），最后一个 bucket 之外的请求计入溢出 bucket
+const BUCKET_BOUNDS_US: [u64; 18] = [
+    100, 250, 500, 1_000, 2_500, 5_000, 10_000, 25_000, 50_000, 100_000, 250_000, 500_000,
+    1_000_000, 2_500_000, 5_000_000, 10_000_000, 30_000_000, 60_000_000,
+];
+
+/// handler 写入响应扩展的序列化耗时（如 chunk 的编码），中间件从该路由的延迟中扣除并单独统计
+#[derive(Debug, Clone, Copy)]
+pub struct EncodeTime(pub Duration);
+
+/// 固定 bucket 的延迟直方图
+#[derive(Debug, Clone)]
+struct LatencyHistogram {
+    /// 与 `BUCKET_BOUNDS_US` 对应，多出的最后一项为溢出 bucket
+    counts: [u64; BUCKET_BOUNDS_US.len() + 1],
+    count: u64,
+    sum_us: u64,
+    max_us: u64,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self {
+            counts: [0; BUCKET_BOUNDS_US.len() + 1],
+            count: 0,
+            sum_us: 0,
+            max_us: 0,
+        }
+    }
+}
+
+impl LatencyHistogram {
+    fn record(&mut self, latency: Duration) {
+        let us = u64::try_from(latency.as_micros()).unwrap_or(u64::MAX);
+        let bucket = BUCKET_BOUNDS_US.partition_point(|&bound| bound < us);
+        self.counts[bucket] += 1;
+        self.count += 1;
+        self.sum_us = self.sum_us.saturating_add(us);
+        self.max_us = self.max_us.max(us);
+    }
+
+    /// 第 `q` 分位数（毫秒）：取该分位所在 bucket 的上界，不超过观测到的最大值
+    fn quantile_ms(&self, q: f64) -> Option<f64> {
+        if self.count == 0 {
+            return None;
+        }
+        let rank = ((q * self.count as f64).ceil() as u64).clamp(1, self.count);
+        let mut seen = 0;
+        for (bucket, &count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                let bound = BUCKET_BOUNDS_US.get(bucket).copied().unwrap_or(self.max_us);
+                return Some(bound.min(self.max_us) as f64 / 1000.0);
+            }
+        }
+        Some(self.max_us as f64 / 1000.0)
+    }
+
+    fn summary(&self) -> LatencySummary {
+        let buckets = BUCKET_BOUNDS_US
+            .iter()
+            .map(|&bound| Some(bound as f64 / 1000.0))
+            .chain(std::iter::once(None))
+            .zip(self.counts)
+            .map(|(le_ms, count)| LatencyBucket { le_ms, count })
+            .collect();
+        LatencySummary {
+            count: self.count,
+            mean_ms: (self.count > 0).then(|| self.sum_us as f64 / self.count as f64 / 1000.0),
+            max_ms: (self.count > 0).then(|| self.max_us as f64 / 1000.0),
+            p50_ms: self.quantile_ms(0.50),
+            p95_ms: self.quantile_ms(0.95),
+            p99_ms: self.quantile_ms(0.99),
+            buckets,
+        }
+    }
+}
+
+/// 直方图的一个 bucket：延迟不超过 `le_ms` 的请求数（不累计），`le_ms` 为 null 的是溢出 bucket
+#[derive(Debug, Clone, Serialize)]
+pub struct LatencyBucket {
+    pub le_ms: Option<f64>,
+    pub count: u64,
+}
+
+/// 延迟汇总，分位数按直方图估计（所在 bucket 的上界），没有记录时为 null
+#[derive(Debug, Clone, Serialize)]
+pub struct LatencySummary {
+    pub count: u64,
+    pub mean_ms: Option<f64>,
+    pub max_ms: Option<f64>,
+    pub p50_ms: Option<f64>,
+    pub p95_ms: Option<f64>,
+    pub p99_ms: Option<f64>,
+    pub buckets: Vec<LatencyBucket>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct RouteStats {
+    /// 按状态码类别（"2xx"、"4xx" 等）的请求数
+    status: BTreeMap<String, u64>,
+    latency: LatencyHistogram,
+    encode: LatencyHistogram,
+}
+
+/// 单个路由的请求指标
+#[derive(Debug, Clone, Serialize)]
+pub struct RouteMetrics {
+    pub count: u64,
+    pub status: BTreeMap<String, u64>,
+    /// 从收到请求到返回响应头的延迟，已扣除序列化耗时
+    pub latency: LatencySummary,
+    /// 序列化耗时（只有报告了 `EncodeTime` 的路由才有）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encode: Option<LatencySummary>,
+}
+
+/// 按路由统计的 HTTP 请求数与延迟直方图（服务启动后累计）
+///
+/// 路由按 `方法 + 路由模板` 区分（如 `GET /voxel-grid/chunk`），查询参数不影响分组；
+/// 未匹配任何路由的请求统一计入 `unmatched`，避免任意路径产生无限多的分组
+#[derive(Default)]
+pub struct RequestMetrics {
+    routes: Mutex<BTreeMap<String, RouteStats>>,
+}
+
+impl RequestMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 记录一次请求：`latency` 为总耗时，`encode` 为其中的序列化耗时
+    pub fn record(&self, route: &str, status: u16, latency: Duration, encode: Option<Duration>) {
+        let mut routes = self.routes.lock();
+        let stats = routes.entry(route.to_string()).or_default();
+        *stats
+            .status
+            .entry(format!("{}xx", status / 100))
+            .or_default() += 1;
+        match encode {
+            Some(encode) => {
+                stats.latency.record(latency.saturating_sub(encode));
+                stats.encode.record(encode);
+            }
+            None => stats.latency.record(latency),
+        }
+    }
+
+    /// 所有路由的指标，按路由名排序
+    pub fn snapshot(&self) -> BTreeMap<String, RouteMetrics> {
+        self.routes
+            .lock()
+            .iter()
+            .map(|(route, stats)| {
+                let metrics = RouteMetrics {
+                    count: stats.latency.count,
+                    status: stats.status.clone(),
+                    latency: stats.latency.summary(),
+                    encode: (stats.encode.count > 0).then(|| stats.encode.summary()),
+                };
+                (route.clone(), metrics)
+            })
+            .collect()
+    }
+}
+
+/// 请求指标中间件：记录每个请求的路由、状态码与延迟
+///
+/// 延迟统计到 handler 返回响应（响应头）为止，流式响应体的传输时间不计入
+pub async fn track_requests(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let started = Instant::now();
+    let metrics = req
+        .app_data::<web::Data<AppState>>()
+        .map(|data| data.request_metrics.clone());
+    let method = req.method().clone();
+    let response = next.call(req).await?;
+
+    if let Some(metrics) = metrics {
+        let route = match response.request().match_pattern() {
+            Some(pattern) => format!("{method} {pattern}"),
+            None => "unmatched".to_string(),
+        };
+        let encode = response
+            .response()
+            .extensions()
+            .get::<EncodeTime>()
+            .map(|encode| encode.0);
+        metrics.record(
+            &route,
+            response.status().as_u16(),
+            started.elapsed(),
+            encode,
+        );
+    }
+    Ok(response)
+}
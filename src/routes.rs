@@ -1,12 +1,48 @@
-use actix_web::web;
+use actix_web::{middleware::from_fn, web};
 
 use crate::handlers;
 
 /// 统一注册 HTTP 路由，方便集中管理
 pub fn configure(cfg: &mut web::ServiceConfig) {
-    cfg.service(handlers::hello)
+    // 所有 JSON 请求体共用：Content-Type 不对返回 415，JSON 无效返回 400，均为 JSON 错误信息
+    cfg.app_data(handlers::json_config())
+        .service(handlers::hello)
+        .service(handlers::list_resource_files)
         .service(handlers::get_voxel_grid)
         .service(handlers::preprocess_voxel_grid)
+        .service(handlers::cancel_task)
         .service(handlers::get_voxel_chunk)
-        .service(handlers::get_performance);
+        .service(handlers::get_voxel_chunk_stats)
+        .service(handlers::get_paired_chunk)
+        .service(handlers::get_task_status)
+        .service(handlers::get_layout)
+        .service(handlers::get_ready_chunks)
+        .service(handlers::get_appends)
+        .service(handlers::stream_progress)
+        .service(handlers::stream_events)
+        .service(handlers::multi_stream_chunks)
+        .service(handlers::get_range_data)
+        .service(handlers::get_slices)
+        .service(handlers::get_slice_png)
+        .service(handlers::get_isosurface)
+        .service(handlers::export_vdb)
+        .service(handlers::get_grid_stats)
+        .service(handlers::get_histogram)
+        .service(handlers::get_roi_stats)
+        .service(handlers::get_components)
+        .service(handlers::get_sparse)
+        .service(handlers::get_sample)
+        .service(handlers::get_line_profile)
+        .service(handlers::get_laplacian)
+        .service(handlers::get_performance)
+        .service(handlers::get_request_metrics)
+        .service(handlers::get_session_summary)
+        // 管理接口统一经过令牌鉴权
+        .service(
+            web::scope("/admin")
+                .wrap(from_fn(handlers::admin::require_admin_token))
+                .service(handlers::admin_cleanup)
+                .service(handlers::admin_pin)
+                .service(handlers::admin_unpin),
+        );
 }
@@ -0,0 +1,95 @@
+use std::path::Path;
+use std::time::Instant;
+
+use crate::utils::parser_registry::ParserRegistry;
+use crate::utils::resource_files::list_files;
+
+/// 自检模式下单个文件的检查结果
+pub enum FileCheck {
+    /// 解析成功
+    Passed {
+        parser: &'static str,
+        shape: [usize; 3],
+    },
+    /// 解析失败
+    Failed { parser: &'static str, error: String },
+    /// 没有解析器认领该文件（扩展名与内容都无法识别）
+    Skipped,
+}
+
+/// 检查单个文件：读取 shape，`full` 为 true 时再完整解析一次，并核对两次得到的 shape 一致
+pub fn check_file(registry: &ParserRegistry, file_path: &str, full: bool) -> FileCheck {
+    let Some((parser, _)) = registry.find_parser_for_file(file_path) else {
+        return FileCheck::Skipped;
+    };
+    let failed = |error: String| FileCheck::Failed {
+        parser: parser.name(),
+        error,
+    };
+
+    let shape = match parser.get_shape_from_file(file_path) {
+        Ok(shape) => shape,
+        Err(e) => return failed(format!("读取 shape 失败: {e}")),
+    };
+    if full {
+        match parser.parse_from_file(file_path) {
+            Ok(grid) if grid.get_shape() != shape => {
+                return failed(format!(
+                    "完整解析得到的 shape {:?} 与文件头中的 shape {:?} 不一致",
+                    grid.get_shape(),
+                    shape
+                ));
+            }
+            Ok(_) => {}
+            Err(e) => return failed(format!("完整解析失败: {e}")),
+        }
+    }
+    FileCheck::Passed {
+        parser: parser.name(),
+        shape,
+    }
+}
+
+/// 启动自检（`--check`）：用已注册的解析器逐个检查资源目录下的文件并输出报告，不启动 HTTP 服务
+///
+/// 默认只读取 shape；`full` 为 true（`--check --full`）时对每个文件做一次完整解析。
+/// 返回失败的文件数量，资源目录无法读取时返回错误
+pub fn run(registry: &ParserRegistry, resource_dir: &Path, full: bool) -> std::io::Result<usize> {
+    let (files, truncated) = list_files(resource_dir)?;
+    let mode = if full { "完整解析" } else { "读取 shape" };
+    println!(
+        "[自检] 检查资源目录 {}（{} 个文件，模式: {mode}）",
+        resource_dir.display(),
+        files.len()
+    );
+    if truncated {
+        println!(
+            "[自检] 警告: 文件数量超过上限，只检查了前 {} 个",
+            files.len()
+        );
+    }
+
+    let (mut passed, mut failed, mut skipped) = (0, 0, 0);
+    for file in &files {
+        let started = Instant::now();
+        let path = resource_dir.join(&file.path);
+        match check_file(registry, &path.to_string_lossy(), full) {
+            FileCheck::Passed { parser, shape } => {
+                passed += 1;
+                println!(
+                    "  通过  {}  [{parser}] shape {:?}，耗时 {:.2?}",
+                    file.path,
+                    shape,
+                    started.elapsed()
+                );
+            }
+            FileCheck::Failed { parser, error } => {
+                failed += 1;
+                println!("  失败  {}  [{parser}] {error}", file.path);
+            }
+            FileCheck::Skipped => skipped += 1,
+        }
+    }
+    println!("[自检] 通过 {passed} 个，失败 {failed} 个，跳过 {skipped} 个不支持的文件");
+    Ok(failed)
+}
@@ -0,0 +1,95 @@
+use std::fmt;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::task::TaskData;
+
+/// 流式订阅（gRPC `StreamChunks`、HTTP 多任务流）的并发上限
+///
+/// 每个流式订阅在整个传输期间占用一个名额，同时计入所属任务与全局计数；
+/// 任一计数达到上限时拒绝新的订阅，避免大量客户端订阅同一个大任务耗尽资源
+pub struct StreamLimits {
+    /// 单个任务的上限，0 表示不限制
+    per_task: usize,
+    /// 全局上限，0 表示不限制
+    global: usize,
+    /// 当前全局的订阅数量
+    active: AtomicUsize,
+}
+
+/// 超出订阅上限的原因
+#[derive(Debug, Clone, Copy)]
+pub enum StreamLimitExceeded {
+    Task { limit: usize },
+    Global { limit: usize },
+}
+
+impl fmt::Display for StreamLimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StreamLimitExceeded::Task { limit } => {
+                write!(f, "该任务的流式订阅数已达上限 {limit}，请稍后重试")
+            }
+            StreamLimitExceeded::Global { limit } => {
+                write!(f, "服务的流式订阅总数已达上限 {limit}，请稍后重试")
+            }
+        }
+    }
+}
+
+impl StreamLimits {
+    pub fn new(per_task: usize, global: usize) -> Self {
+        Self {
+            per_task,
+            global,
+            active: AtomicUsize::new(0),
+        }
+    }
+
+    /// 为 `task` 占用一个订阅名额，名额在返回的 `StreamSubscription` 被释放时归还
+    pub fn try_subscribe(
+        self: &Arc<Self>,
+        task: &Arc<TaskData>,
+    ) -> Result<StreamSubscription, StreamLimitExceeded> {
+        if !try_increment(&self.active, self.global) {
+            return Err(StreamLimitExceeded::Global { limit: self.global });
+        }
+        if !try_increment(&task.stream_subscribers, self.per_task) {
+            self.active.fetch_sub(1, Ordering::AcqRel);
+            return Err(StreamLimitExceeded::Task {
+                limit: self.per_task,
+            });
+        }
+        Ok(StreamSubscription {
+            limits: self.clone(),
+            task: task.clone(),
+        })
+    }
+
+    /// 当前全局的订阅数量
+    pub fn active(&self) -> usize {
+        self.active.load(Ordering::Acquire)
+    }
+}
+
+/// 计数未达到 `limit`（0 表示不限制）时加一，返回是否成功
+fn try_increment(counter: &AtomicUsize, limit: usize) -> bool {
+    counter
+        .fetch_update(Ordering::AcqRel, Ordering::Acquire, |count| {
+            (limit == 0 || count < limit).then_some(count + 1)
+        })
+        .is_ok()
+}
+
+/// 流式订阅名额，离开作用域时自动归还
+pub struct StreamSubscription {
+    limits: Arc<StreamLimits>,
+    task: Arc<TaskData>,
+}
+
+impl Drop for StreamSubscription {
+    fn drop(&mut self) {
+        self.task.stream_subscribers.fetch_sub(1, Ordering::AcqRel);
+        self.limits.active.fetch_sub(1, Ordering::AcqRel);
+    }
+}
@@ -1,25 +1,315 @@
-use std::collections::HashMap;
-use std::sync::Arc;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::pin::pin;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::str::FromStr;
+use std::sync::{Arc, OnceLock};
 use std::time::{Duration, Instant};
 
-use parking_lot::RwLock;
-use serde::Serialize;
+use actix_web::rt::time;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
 use uuid::Uuid;
 
-#[derive(Debug, Clone, Serialize)]
+use crate::cancel::CancelToken;
+use crate::performance::get_unix_timestamp_ms;
+use crate::utils::analysis::{Analysis, PrecomputedAnalyses};
+use crate::utils::parser::AppendCursor;
+use crate::utils::stats::{GridStats, Histogram};
+use crate::utils::value_transform::ValueTransform;
+use crate::utils::voxel_grid::{VoxelGrid, round_values_to_decimals};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ChunkDescriptor {
     pub index: usize,
     /// 开始位置（包含），单位：浮点元素索引
+    /// tile 模式下为该 tile 在所有 tile 依次拼接后的数据中的偏移
     pub start: usize,
     /// 结束位置（不包含），单位：浮点元素索引
     pub end: usize,
+    /// tile 模式下 chunk 覆盖的长方体区域；线性分块时为 None
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tile: Option<TileRegion>,
+}
+
+/// tile 模式下一个 chunk 覆盖的长方体区域，chunk 数据为区域内的体素（x 最快，z 最慢）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TileRegion {
+    /// 区域起点 [x, y, z]
+    pub origin: [usize; 3],
+    /// 区域大小 [dx, dy, dz]，边界处的 tile 可能小于 tile_size
+    pub dims: [usize; 3],
+}
+
+impl ChunkDescriptor {
+    /// 从完整网格数据（`VoxelGrid` 顺序）中取出该 chunk 的数据
+    pub fn extract(&self, data: &[f64], shape: [usize; 3]) -> Vec<f64> {
+        let Some(tile) = self.tile else {
+            return data[self.start..self.end].to_vec();
+        };
+        let [nx, ny, _] = shape;
+        let [ox, oy, oz] = tile.origin;
+        let [dx, dy, dz] = tile.dims;
+        let mut values = Vec::with_capacity(self.end - self.start);
+        for k in oz..oz + dz {
+            for j in oy..oy + dy {
+                let row = k * nx * ny + j * nx + ox;
+                values.extend_from_slice(&data[row..row + dx]);
+            }
+        }
+        values
+    }
+}
+
+/// chunk 的取值摘要（存入 chunk 时计算），客户端可以在下载前跳过均匀 chunk、优先加载取值范围大的 chunk
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct ChunkSummary {
+    /// 有限值中的最小值，chunk 中没有有限值时为 None
+    pub min: Option<f64>,
+    /// 有限值中的最大值，chunk 中没有有限值时为 None
+    pub max: Option<f64>,
+    /// 所有元素是否都相同（按位比较，全部为 NaN 也算）
+    pub uniform: bool,
+    /// 均匀 chunk 的取值
+    #[serde(skip)]
+    fill: Option<f64>,
+}
+
+impl ChunkSummary {
+    /// 遍历一次 chunk 数据计算摘要；空 chunk 返回 None
+    pub fn of(values: &[f64]) -> Option<Self> {
+        let first = values.first()?;
+        let mut uniform = true;
+        let mut range: Option<(f64, f64)> = None;
+        for &value in values {
+            uniform &= value.to_bits() == first.to_bits();
+            if value.is_finite() {
+                range = Some(range.map_or((value, value), |(min, max)| {
+                    (min.min(value), max.max(value))
+                }));
+            }
+        }
+        Some(Self {
+            min: range.map(|(min, _)| min),
+            max: range.map(|(_, max)| max),
+            uniform,
+            fill: uniform.then_some(*first),
+        })
+    }
+}
+
+/// 按 chunk_size 将长度为 data_length 的一维数组划分为连续的分块
+/// chunk_size 必须大于 0：请求参数由 `validate_and_locate` 校验，0 不会到达这里
+pub fn compute_chunks(data_length: usize, chunk_size: usize) -> Vec<ChunkDescriptor> {
+    debug_assert!(chunk_size > 0, "chunk_size 必须大于 0");
+    let mut chunks = Vec::with_capacity(data_length.div_ceil(chunk_size));
+    let mut start = 0usize;
+    let mut index = 0usize;
+    while start < data_length {
+        let end = (start + chunk_size).min(data_length);
+        chunks.push(ChunkDescriptor {
+            index,
+            start,
+            end,
+            tile: None,
+        });
+        start = end;
+        index += 1;
+    }
+    chunks
+}
+
+/// 按 `tile_size` 将网格划分为长方体 tile（tile 按 x、y、z 顺序排列，x 最快）
+/// 边界处的 tile 截断到网格范围内；tile_size 中的 0 按 1 处理
+pub fn compute_tiles(shape: [usize; 3], tile_size: [usize; 3]) -> Vec<ChunkDescriptor> {
+    let tile_size = tile_size.map(|n| n.max(1));
+    let counts = [0, 1, 2].map(|axis| shape[axis].div_ceil(tile_size[axis]));
+    let mut chunks = Vec::with_capacity(counts.iter().product());
+    let mut start = 0usize;
+    for tz in 0..counts[2] {
+        for ty in 0..counts[1] {
+            for tx in 0..counts[0] {
+                let origin = [tx * tile_size[0], ty * tile_size[1], tz * tile_size[2]];
+                let dims = [0, 1, 2].map(|axis| tile_size[axis].min(shape[axis] - origin[axis]));
+                let end = start + dims.iter().product::<usize>();
+                chunks.push(ChunkDescriptor {
+                    index: chunks.len(),
+                    start,
+                    end,
+                    tile: Some(TileRegion { origin, dims }),
+                });
+                start = end;
+            }
+        }
+    }
+    chunks
+}
+
+/// 检查分块布局的不变量：chunk 按索引顺序首尾相接、从 0 开始覆盖到 `data_length`，且没有空 chunk
+///
+/// 线性分块与 tile 分块都满足这些条件（tile 的 `start` / `end` 是拼接后数据中的偏移），
+/// 因此所有 chunk 的长度之和等于 `data_length`。违反时返回描述第一个问题的诊断信息
+pub fn check_chunk_layout(chunks: &[ChunkDescriptor], data_length: usize) -> Result<(), String> {
+    let mut expected_start = 0usize;
+    for (position, descriptor) in chunks.iter().enumerate() {
+        if descriptor.index != position {
+            return Err(format!(
+                "第 {position} 个 chunk 的 index 为 {}，与其位置不一致",
+                descriptor.index
+            ));
+        }
+        if descriptor.start >= descriptor.end {
+            return Err(format!(
+                "chunk {position} 为空或范围颠倒: [{}, {})",
+                descriptor.start, descriptor.end
+            ));
+        }
+        if descriptor.start != expected_start {
+            return Err(format!(
+                "chunk {position} 的 start 为 {}，应紧接上一个 chunk 的 end {expected_start}",
+                descriptor.start
+            ));
+        }
+        if let Some(tile) = descriptor.tile
+            && tile.dims.iter().product::<usize>() != descriptor.end - descriptor.start
+        {
+            return Err(format!(
+                "chunk {position} 的 tile 大小 {:?} 与其长度 {} 不一致",
+                tile.dims,
+                descriptor.end - descriptor.start
+            ));
+        }
+        expected_start = descriptor.end;
+    }
+    if expected_start != data_length {
+        return Err(format!(
+            "所有 chunk 的长度之和为 {expected_start}，与 data_length {data_length} 不一致"
+        ));
+    }
+    Ok(())
+}
+
+/// 后台解析进度
+/// 解析线程通过原子计数写入，状态接口随时读取，无需加锁
+#[derive(Debug, Default)]
+pub struct ParseProgress {
+    /// 已从文件读取的值数量
+    values_read: AtomicUsize,
+    /// 已存入任务的 chunk 数量（包括之后被请求走的）
+    chunks_stored: AtomicUsize,
+}
+
+impl ParseProgress {
+    /// 更新已读取的值数量（只会增大）
+    pub fn set_values_read(&self, values_read: usize) {
+        self.values_read.fetch_max(values_read, Ordering::Relaxed);
+    }
+
+    pub fn values_read(&self) -> usize {
+        self.values_read.load(Ordering::Relaxed)
+    }
+
+    /// 记录一个 chunk 已存入任务
+    pub fn add_chunk_stored(&self) {
+        self.chunks_stored.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn chunks_stored(&self) -> usize {
+        self.chunks_stored.load(Ordering::Relaxed)
+    }
+}
+
+/// 随文件增长追加数据的任务状态（只有支持追加的文件才有，见 `VoxelGridParser::supports_append`）
+#[derive(Debug, Clone)]
+pub struct AppendState {
+    /// 最近一次检查时文件的大小
+    pub file_size: u64,
+    /// 最近一次检查时文件的修改时间（Unix 毫秒）
+    pub file_mtime: Option<u64>,
+    /// 下一次继续解析的位置；None 表示尚未确定，下一次从数据段开头计数
+    pub cursor: Option<AppendCursor>,
+    /// 新追加的数据切分 chunk 时使用的 chunk_size
+    pub chunk_size: usize,
+    /// 已发生的追加，第 n 次追加的 `generation` 为 n
+    pub appends: Vec<AppendRecord>,
+}
+
+impl AppendState {
+    /// 已发生的追加次数
+    pub fn generation(&self) -> usize {
+        self.appends.len()
+    }
+}
+
+/// 一次追加：新增的 z 层及其对应的 chunk
+#[derive(Debug, Clone, Serialize)]
+pub struct AppendRecord {
+    pub generation: usize,
+    /// 新增的第一层的 z 坐标（即追加前的层数）
+    pub z_start: usize,
+    /// 新增的层数
+    pub layers: usize,
+    /// 新增的 chunk 为 `[first_chunk, first_chunk + chunk_count)`
+    pub first_chunk: usize,
+    pub chunk_count: usize,
+    /// 追加的时间（Unix 毫秒）
+    pub appended_at: u64,
+}
+
+/// 读取数据在整体进度中所占的比例，其余为分割存储 chunk
+const READ_PROGRESS_WEIGHT: f64 = 0.9;
+
+/// 最近被请求的 chunk（LRU），数据被 `take_chunk` 移除后仍保留一份副本，
+/// 客户端重复请求刚取走的 chunk（例如重复渲染）时仍能返回数据
+///
+/// 容量为 0 时不保留任何数据，行为与没有缓存时相同
+#[derive(Default)]
+pub struct RecentChunks {
+    capacity: usize,
+    /// 按最近使用时间排序，末尾为最近使用
+    entries: Mutex<VecDeque<(usize, Arc<[f64]>)>>,
+}
+
+impl RecentChunks {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    /// 记录刚被请求的 chunk，超出容量时淘汰最久未使用的
+    fn insert(&self, chunk_index: usize, values: &[f64]) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock();
+        entries.retain(|(index, _)| *index != chunk_index);
+        if entries.len() >= self.capacity {
+            entries.pop_front();
+        }
+        entries.push_back((chunk_index, Arc::from(values)));
+    }
+
+    /// 查找缓存的 chunk，命中时标记为最近使用
+    fn get(&self, chunk_index: usize) -> Option<Arc<[f64]>> {
+        let mut entries = self.entries.lock();
+        let position = entries.iter().position(|(index, _)| *index == chunk_index)?;
+        let entry = entries.remove(position)?;
+        let values = entry.1.clone();
+        entries.push_back(entry);
+        Some(values)
+    }
+
+    fn clear(&self) {
+        self.entries.lock().clear();
+    }
 }
 
 /// 任务数据，存储分块的体素网格数据
 /// 使用 HashMap 独立存储每个 chunk，允许单独释放
 pub struct TaskData {
     /// 网格维度 [nx, ny, nz]
-    #[allow(dead_code)]
     pub shape: [usize; 3],
     /// 分块描述列表
     pub chunks: Vec<ChunkDescriptor>,
@@ -30,8 +320,55 @@ pub struct TaskData {
     /// 任务创建时间，用于 TTL 过期检查
     pub created_at: Instant,
     /// 文件路径，用于后台解析
-    #[allow(dead_code)]
     pub file_path: String,
+    /// 解析后应用的小数位舍入（None 表示不舍入）
+    pub round_decimals: Option<u32>,
+    /// 解析后逐元素应用的数值变换（在舍入之前）
+    pub value_transform: ValueTransform,
+    /// 驻留窗口大小：Some 时只在内存中保留一个窗口的 chunk，其余按需从文件读取
+    pub resident_window: Option<usize>,
+    /// 解析后应用的高斯模糊 sigma（在数值变换之前；None 表示不模糊）
+    pub smooth_sigma: Option<f64>,
+    /// 直方图均衡化使用的 bin 数（在数值变换、舍入与自动裁剪之后；None 表示未均衡化）
+    pub equalize_bins: Option<usize>,
+    /// 网格数据校验和（后台解析完成后写入一次）
+    pub data_hash: OnceLock<u64>,
+    /// 网格中所有有限值之和（后台解析完成后写入一次）
+    pub data_sum: OnceLock<f64>,
+    /// 拉普拉斯量（`[非周期, 周期]` 两种边界），首次请求时计算并缓存，之后不再依赖 chunk 数据
+    pub laplacian: [OnceLock<Arc<[f64]>>; 2],
+    /// 全网格统计量，后台解析时预计算或首次请求时计算并缓存
+    pub stats: OnceLock<GridStats>,
+    /// 默认 bin 数的直方图，后台解析时预计算或首次请求时计算并缓存
+    pub histogram: OnceLock<Histogram>,
+    /// 预处理请求的预计算分析（已排序去重），后台解析在分割 chunk 之前计算
+    pub precompute: Vec<Analysis>,
+    /// 单个体素的体积（由晶格 / 网格间距得出），未知时为 None
+    pub voxel_volume: Option<f64>,
+    /// 后台解析进度
+    pub progress: ParseProgress,
+    /// 当前进行中的流式订阅数量（由 `StreamLimits` 维护）
+    pub stream_subscribers: AtomicUsize,
+    /// 取消标记：预处理请求创建，后台解析在各阶段检查；取消后不再产生新的 chunk
+    pub cancel: CancelToken,
+    /// 创建任务的预处理请求所属的会话，未指定时为 None
+    pub session_id: Option<String>,
+    /// 固定的任务不会因 TTL 过期被清理，也不会因任务数上限被移除，由管理接口切换
+    pinned: AtomicBool,
+    /// 最近被请求的 chunk 的副本，容量由 `TaskStore` 在插入任务时设置
+    pub recent_chunks: RecentChunks,
+    /// 已存入的 chunk 的取值摘要（最小 / 最大值、是否均匀），chunk 接口据此可以省略均匀 chunk 的数据
+    chunk_summaries: RwLock<HashMap<usize, ChunkSummary>>,
+    /// 每个 chunk 首次就绪的时间（Unix 毫秒），尚未就绪的 chunk 不在其中
+    chunk_ready_at: RwLock<HashMap<usize, u64>>,
+    /// 分割失败的 chunk 及原因：这些 chunk 不会再就绪，其余 chunk 不受影响
+    chunk_failures: RwLock<HashMap<usize, String>>,
+    /// chunk 就绪、放回或失败，以及任务失败时通知等待者，见 `wait_for_chunk`
+    chunk_events: Notify,
+    /// 后台解析失败的原因（写入后任务进入失败状态，chunk 不会再就绪）
+    failure: OnceLock<String>,
+    /// 随文件增长追加数据的状态（不支持追加时为 None）
+    pub append: Mutex<Option<AppendState>>,
 }
 
 impl TaskData {
@@ -49,12 +386,278 @@ impl TaskData {
             chunk_data: RwLock::new(chunk_data),
             created_at: Instant::now(),
             file_path,
+            round_decimals: None,
+            value_transform: ValueTransform::None,
+            resident_window: None,
+            smooth_sigma: None,
+            equalize_bins: None,
+            data_hash: OnceLock::new(),
+            data_sum: OnceLock::new(),
+            laplacian: [OnceLock::new(), OnceLock::new()],
+            stats: OnceLock::new(),
+            histogram: OnceLock::new(),
+            precompute: Vec::new(),
+            voxel_volume: None,
+            progress: ParseProgress::default(),
+            stream_subscribers: AtomicUsize::new(0),
+            cancel: CancelToken::new(),
+            session_id: None,
+            pinned: AtomicBool::new(false),
+            recent_chunks: RecentChunks::default(),
+            chunk_summaries: RwLock::new(HashMap::new()),
+            chunk_ready_at: RwLock::new(HashMap::new()),
+            chunk_failures: RwLock::new(HashMap::new()),
+            chunk_events: Notify::new(),
+            failure: OnceLock::new(),
+            append: Mutex::new(None),
+        }
+    }
+
+    /// 直接由内存中已计算好的网格创建任务，所有 chunk 立即就绪
+    ///
+    /// 用于差分、裁剪、重采样等在内存中生成新网格的场景，不需要再经过 `parse_from_file`。
+    /// 网格数据按 chunk 从尾部依次切出，不会产生整份网格的额外拷贝。
+    /// `source` 仅用于记录来源（例如原始文件路径或操作描述）
+    pub fn from_grid(grid: VoxelGrid, chunk_size: usize, source: String) -> Self {
+        let shape = grid.shape;
+        let mut data = grid.data;
+        let chunks = compute_chunks(data.len(), chunk_size);
+        let task = Self::new(shape, chunks, source);
+        {
+            let mut chunk_data = task.chunk_data.write();
+            for descriptor in task.chunks.iter().rev() {
+                let values = data.split_off(descriptor.start);
+                task.record_summary(descriptor.index, &values);
+                task.record_ready(descriptor.index);
+                chunk_data.insert(descriptor.index, Some(values));
+                task.progress.add_chunk_stored();
+            }
+        }
+        task.progress.set_values_read(task.data_length());
+        task
+    }
+
+    /// 由内存中的网格创建 tile 模式的任务，所有 chunk 立即就绪
+    pub fn from_grid_tiled(grid: VoxelGrid, tile_size: [usize; 3], source: String) -> Self {
+        let chunks = compute_tiles(grid.shape, tile_size);
+        let task = Self::new(grid.shape, chunks, source);
+        {
+            let mut chunk_data = task.chunk_data.write();
+            for descriptor in &task.chunks {
+                let values = descriptor.extract(&grid.data, grid.shape);
+                task.record_summary(descriptor.index, &values);
+                task.record_ready(descriptor.index);
+                chunk_data.insert(descriptor.index, Some(values));
+                task.progress.add_chunk_stored();
+            }
+        }
+        task.progress.set_values_read(task.data_length());
+        task
+    }
+
+    /// 追加新解析的 `layers` 个 z 层，返回包含原有 chunk 与新 chunk 的任务，由调用方通过 `TaskStore::replace` 以相同的 task_id 替换本任务
+    ///
+    /// 原有 chunk 的索引与范围保持不变，其数据在替换成功时才移动到新任务（见 `take_chunks_from`），在此之前本任务照常提供；
+    /// 新数据应用任务的数值变换与舍入后按 `state.chunk_size` 切分为新的 chunk 并立即就绪。依赖全部数据的缓存（校验和、统计量等）不会带到新任务
+    pub fn with_appended_layers(
+        &self,
+        mut values: Vec<f64>,
+        layers: usize,
+        mut state: AppendState,
+    ) -> TaskData {
+        if !self.value_transform.is_none() {
+            for value in values.iter_mut() {
+                *value = self.value_transform.apply(*value);
+            }
+        }
+        if let Some(decimals) = self.round_decimals {
+            round_values_to_decimals(&mut values, decimals);
+        }
+
+        let [nx, ny, nz] = self.shape;
+        let data_start = self.data_length();
+        let first_chunk = self.chunks.len();
+        let new_chunks: Vec<ChunkDescriptor> = compute_chunks(values.len(), state.chunk_size)
+            .into_iter()
+            .map(|descriptor| ChunkDescriptor {
+                index: first_chunk + descriptor.index,
+                start: data_start + descriptor.start,
+                end: data_start + descriptor.end,
+                tile: None,
+            })
+            .collect();
+        let mut chunks = self.chunks.clone();
+        chunks.extend(new_chunks.iter().cloned());
+
+        let mut task = TaskData::new([nx, ny, nz + layers], chunks, self.file_path.clone());
+        task.created_at = self.created_at;
+        task.round_decimals = self.round_decimals;
+        task.value_transform = self.value_transform;
+        task.voxel_volume = self.voxel_volume;
+        task.precompute = self.precompute.clone();
+        task.cancel = self.cancel.clone();
+        task.session_id = self.session_id.clone();
+        task.set_pinned(self.is_pinned());
+        {
+            let mut chunk_data = task.chunk_data.write();
+            for descriptor in new_chunks.iter().rev() {
+                let chunk = values.split_off(descriptor.start - data_start);
+                task.record_summary(descriptor.index, &chunk);
+                task.record_ready(descriptor.index);
+                chunk_data.insert(descriptor.index, Some(chunk));
+                task.progress.add_chunk_stored();
+            }
+        }
+        task.progress.set_values_read(task.data_length());
+
+        state.appends.push(AppendRecord {
+            generation: state.generation() + 1,
+            z_start: nz,
+            layers,
+            first_chunk,
+            chunk_count: new_chunks.len(),
+            appended_at: get_unix_timestamp_ms(),
+        });
+        *task.append.get_mut() = Some(state);
+        task
+    }
+
+    /// 把 `previous`（被本任务替换的任务）中原有 chunk 的数据与状态移动到本任务
+    ///
+    /// 由 `TaskStore::replace` 在任务表的写锁内确认可以替换后调用，替换失败时 `previous` 保持不变；
+    /// 已被取走的 chunk 在本任务中同样视为已取走
+    fn take_chunks_from(&mut self, previous: &TaskData) {
+        let previous_count = previous.chunks.len();
+        let chunk_data = self.chunk_data.get_mut();
+        chunk_data.retain(|&index, _| index >= previous_count);
+        chunk_data.extend(std::mem::take(&mut *previous.chunk_data.write()));
+        self.chunk_summaries
+            .get_mut()
+            .extend(previous.chunk_summaries.read().iter().map(|(&k, &v)| (k, v)));
+        self.chunk_ready_at
+            .get_mut()
+            .extend(previous.chunk_ready_at.read().iter().map(|(&k, &v)| (k, v)));
+        self.chunk_failures
+            .get_mut()
+            .extend(previous.chunk_failures.read().clone());
+        self.progress
+            .chunks_stored
+            .fetch_add(previous.progress.chunks_stored(), Ordering::Relaxed);
+    }
+
+    /// 任务已被追加了数据的新任务替换：唤醒所有等待本任务事件的请求，由它们重新查找任务
+    pub fn notify_replaced(&self) {
+        self.chunk_events.notify_waiters();
+    }
+
+    /// 等待任务的下一个事件（chunk 就绪、失败、被替换等），最多等待 `timeout`，超时返回 false
+    ///
+    /// `ready` 在注册等待之后检查，为 true 时立即返回，检查之后发生的事件不会被错过
+    pub async fn wait_for_event(&self, timeout: Duration, ready: impl Fn() -> bool) -> bool {
+        let mut notified = pin!(self.chunk_events.notified());
+        notified.as_mut().enable();
+        if ready() {
+            return true;
         }
+        time::timeout(timeout, notified).await.is_ok()
+    }
+
+    /// 是否按 3D tile 分块
+    pub fn is_tiled(&self) -> bool {
+        self.chunks.first().is_some_and(|descriptor| descriptor.tile.is_some())
+    }
+
+    /// 网格中线性索引为 `index` 的体素位于哪个 chunk 及其在 chunk 内的偏移
+    pub fn locate(&self, index: usize) -> Option<(usize, usize)> {
+        let first = self.chunks.first()?;
+        let Some(first_tile) = first.tile else {
+            // 追加数据后最后一个原有 chunk 之后的 chunk 不一定对齐，按范围二分查找
+            let position = self.chunks.partition_point(|descriptor| descriptor.end <= index);
+            let descriptor = self.chunks.get(position)?;
+            return Some((descriptor.index, index - descriptor.start));
+        };
+
+        // 第一个 tile 总是完整大小（或等于整个网格），据此推算 tile 网格
+        let [nx, ny, _] = self.shape;
+        let position = [index % nx, (index / nx) % ny, index / (nx * ny)];
+        let tile_size = first_tile.dims;
+        let counts = [0, 1].map(|axis| self.shape[axis].div_ceil(tile_size[axis]));
+        let t = [0, 1, 2].map(|axis| position[axis] / tile_size[axis]);
+        let descriptor = self.chunks.get(t[2] * counts[0] * counts[1] + t[1] * counts[0] + t[0])?;
+        let tile = descriptor.tile?;
+        let local = [0, 1, 2].map(|axis| position[axis] - tile.origin[axis]);
+        let offset = local[2] * tile.dims[0] * tile.dims[1] + local[1] * tile.dims[0] + local[0];
+        Some((descriptor.index, offset))
     }
 
     /// 设置指定 chunk 的数据（后台解析完成后调用）
     pub fn set_chunk(&self, chunk_index: usize, data: Vec<f64>) {
-        self.chunk_data.write().insert(chunk_index, Some(data));
+        let mut chunk_data = self.chunk_data.write();
+        // 取消时会清空 chunk_data，之后仍在进行的分割不再写入
+        if self.cancel.is_cancelled() {
+            return;
+        }
+        self.record_summary(chunk_index, &data);
+        self.record_ready(chunk_index);
+        chunk_data.insert(chunk_index, Some(data));
+        self.progress.add_chunk_stored();
+        self.chunk_events.notify_waiters();
+    }
+
+    /// 网格的总元素数量
+    pub fn data_length(&self) -> usize {
+        self.shape.iter().product()
+    }
+
+    /// 解析进度百分比（0–100，保留一位小数）：读取数据占 90%，分割存储 chunk 占 10%
+    pub fn progress_percent(&self) -> f64 {
+        let fraction = |done: usize, total: usize| {
+            if total == 0 {
+                1.0
+            } else {
+                (done as f64 / total as f64).min(1.0)
+            }
+        };
+        let read = fraction(self.progress.values_read(), self.data_length());
+        let stored = fraction(self.progress.chunks_stored(), self.chunks.len());
+        let percent = (read * READ_PROGRESS_WEIGHT + stored * (1.0 - READ_PROGRESS_WEIGHT)) * 100.0;
+        (percent * 10.0).round() / 10.0
+    }
+
+    /// 写入仍在等待中的 chunk（已被请求的 chunk 不会被重新写入）
+    /// 返回是否写入成功
+    pub fn set_pending_chunk(&self, chunk_index: usize, data: Vec<f64>) -> bool {
+        let mut chunk_data = self.chunk_data.write();
+        match chunk_data.get_mut(&chunk_index) {
+            Some(slot @ None) => {
+                self.record_summary(chunk_index, &data);
+                self.record_ready(chunk_index);
+                *slot = Some(data);
+                self.progress.add_chunk_stored();
+                self.chunk_events.notify_waiters();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// 驻留窗口模式：把窗口移动到 `[first, first + window)`
+    /// 释放窗口外已就绪的 chunk（之后可重新读取），返回窗口内尚未就绪、需要读取的 chunk 索引
+    pub fn slide_window(&self, first: usize, window: usize) -> Vec<usize> {
+        let window_end = first.saturating_add(window);
+        let mut chunk_data = self.chunk_data.write();
+        let mut missing = Vec::new();
+        for (index, slot) in chunk_data.iter_mut() {
+            if (first..window_end).contains(index) {
+                if slot.is_none() {
+                    missing.push(*index);
+                }
+            } else {
+                *slot = None;
+            }
+        }
+        missing.sort_unstable();
+        missing
     }
 
     /// 获取并移除指定 chunk 的数据（用于请求后释放内存）
@@ -62,15 +665,147 @@ impl TaskData {
     /// - chunk 不存在
     /// - chunk 正在解析中（还未就绪）
     /// - chunk 已被请求
+    ///
+    /// 取出的数据会在 `recent_chunks` 中保留一份副本，见 `recent_chunk`
     pub fn take_chunk(&self, chunk_index: usize) -> Option<Vec<f64>> {
         let mut chunk_data = self.chunk_data.write();
         if let Some(Some(data)) = chunk_data.remove(&chunk_index) {
+            self.recent_chunks.insert(chunk_index, &data);
             Some(data)
         } else {
             None
         }
     }
 
+    /// 把已取出但没有送达的 chunk 放回（如客户端在响应写完之前断开），之后可以重新请求
+    /// 任务已取消或该 chunk 已被重新存入时忽略
+    pub fn restore_chunk(&self, chunk_index: usize, data: Vec<f64>) {
+        let mut chunk_data = self.chunk_data.write();
+        if self.cancel.is_cancelled() {
+            return;
+        }
+        chunk_data.entry(chunk_index).or_insert(Some(data));
+        self.chunk_events.notify_waiters();
+    }
+
+    /// 已被请求、但仍保留在最近请求缓存中的 chunk
+    pub fn recent_chunk(&self, chunk_index: usize) -> Option<Arc<[f64]>> {
+        self.recent_chunks.get(chunk_index)
+    }
+
+    /// 在不移除数据的情况下读取指定 chunk（非破坏性）
+    /// chunk 未就绪或已被请求时返回 None
+    pub fn peek_chunk<R>(&self, chunk_index: usize, f: impl FnOnce(&[f64]) -> R) -> Option<R> {
+        let chunk_data = self.chunk_data.read();
+        match chunk_data.get(&chunk_index) {
+            Some(Some(data)) => Some(f(data)),
+            _ => None,
+        }
+    }
+
+    /// 计算并记录 chunk 的取值摘要；驻留窗口模式下重新读取的 chunk 会得到相同的摘要
+    pub fn record_summary(&self, chunk_index: usize, values: &[f64]) {
+        if let Some(summary) = ChunkSummary::of(values) {
+            self.chunk_summaries.write().insert(chunk_index, summary);
+        }
+    }
+
+    /// chunk 的取值摘要；chunk 尚未存入（或分割失败）时返回 None
+    pub fn chunk_summary(&self, chunk_index: usize) -> Option<ChunkSummary> {
+        self.chunk_summaries.read().get(&chunk_index).copied()
+    }
+
+    /// 均匀 chunk 的取值；chunk 不均匀或尚未存入时返回 None
+    pub fn chunk_fill(&self, chunk_index: usize) -> Option<f64> {
+        self.chunk_summary(chunk_index).and_then(|summary| summary.fill)
+    }
+
+    /// 记录 chunk 的就绪时间；驻留窗口模式下 chunk 会被释放后重新读取，只保留首次就绪的时间
+    fn record_ready(&self, chunk_index: usize) {
+        self.chunk_ready_at
+            .write()
+            .entry(chunk_index)
+            .or_insert_with(get_unix_timestamp_ms);
+    }
+
+    /// chunk 首次就绪的时间（Unix 毫秒），尚未就绪时返回 None
+    pub fn chunk_ready_at(&self, chunk_index: usize) -> Option<u64> {
+        self.chunk_ready_at.read().get(&chunk_index).copied()
+    }
+
+    /// 已就绪过的 chunk 索引（包括之后被请求走的），升序
+    pub fn stored_chunk_indices(&self) -> Vec<usize> {
+        let mut indices: Vec<usize> = self.chunk_ready_at.read().keys().copied().collect();
+        indices.sort_unstable();
+        indices
+    }
+
+    /// 标记任务解析失败（只记录第一次的原因）
+    pub fn mark_failed(&self, reason: String) {
+        let _ = self.failure.set(reason);
+        self.chunk_events.notify_waiters();
+    }
+
+    /// 获取解析失败原因，None 表示未失败
+    pub fn failure(&self) -> Option<&str> {
+        self.failure.get().map(String::as_str)
+    }
+
+    /// 标记单个 chunk 分割失败（只记录第一次的原因），该 chunk 保持未就绪，任务的其余 chunk 照常就绪
+    pub fn mark_chunk_failed(&self, chunk_index: usize, reason: String) {
+        self.chunk_failures
+            .write()
+            .entry(chunk_index)
+            .or_insert(reason);
+        self.chunk_events.notify_waiters();
+    }
+
+    /// 单个 chunk 分割失败的原因，None 表示未失败
+    pub fn chunk_failure(&self, chunk_index: usize) -> Option<String> {
+        self.chunk_failures.read().get(&chunk_index).cloned()
+    }
+
+    /// 分割失败的 chunk 索引（升序）
+    pub fn failed_chunk_indices(&self) -> Vec<usize> {
+        let mut indices: Vec<usize> = self.chunk_failures.read().keys().copied().collect();
+        indices.sort_unstable();
+        indices
+    }
+
+    /// 分割失败的 chunk 及原因（按索引升序）
+    pub fn chunk_failures(&self) -> BTreeMap<usize, String> {
+        self.chunk_failures
+            .read()
+            .iter()
+            .map(|(index, reason)| (*index, reason.clone()))
+            .collect()
+    }
+
+    /// 等待仍在解析中的 chunk 就绪，最多等待 `timeout`，返回 chunk 是否已就绪
+    ///
+    /// chunk 分割失败、任务失败或被取消、chunk 已被请求，或超时都会立即返回 false，由调用方按原有流程响应
+    pub async fn wait_for_chunk(&self, chunk_index: usize, timeout: Duration) -> bool {
+        let deadline = Instant::now() + timeout;
+        loop {
+            // 先注册再检查状态，检查之后发生的变化不会被错过
+            let mut notified = pin!(self.chunk_events.notified());
+            notified.as_mut().enable();
+            if self.is_chunk_ready(chunk_index) {
+                return true;
+            }
+            if self.failure().is_some()
+                || self.chunk_failures.read().contains_key(&chunk_index)
+                || !self.chunk_data.read().contains_key(&chunk_index)
+            {
+                return false;
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() || time::timeout(remaining, notified).await.is_err() {
+                return false;
+            }
+        }
+    }
+
     /// 检查指定 chunk 是否已就绪
     pub fn is_chunk_ready(&self, chunk_index: usize) -> bool {
         self.chunk_data
@@ -80,69 +815,344 @@ impl TaskData {
             .unwrap_or(false)
     }
 
+    /// 已就绪（解析完成且尚未被请求）的 chunk 数量
+    pub fn ready_chunk_count(&self) -> usize {
+        self.chunk_data
+            .read()
+            .values()
+            .filter(|opt| opt.is_some())
+            .count()
+    }
+
+    /// 索引大于 `after`（None 表示从头开始）的已就绪 chunk，按索引升序，最多 `limit` 个
+    /// 在同一次读锁内完成筛选，结果是某一时刻的一致快照
+    pub fn ready_chunk_indices(&self, after: Option<usize>, limit: usize) -> Vec<usize> {
+        let mut indices: Vec<usize> = self
+            .chunk_data
+            .read()
+            .iter()
+            .filter(|(index, opt)| opt.is_some() && after.is_none_or(|after| **index > after))
+            .map(|(index, _)| *index)
+            .collect();
+        indices.sort_unstable();
+        indices.truncate(limit);
+        indices
+    }
+
+    /// 取消任务：通知后台解析停止，释放已就绪的 chunk，之后的请求按失败处理
+    /// 返回 false 表示任务之前已被取消
+    pub fn cancel(&self) -> bool {
+        if !self.cancel.cancel() {
+            return false;
+        }
+        self.mark_failed("任务已被取消".to_string());
+        self.chunk_data.write().clear();
+        self.recent_chunks.clear();
+        true
+    }
+
+    /// 是否已固定（不会过期）
+    pub fn is_pinned(&self) -> bool {
+        self.pinned.load(Ordering::Acquire)
+    }
+
+    /// 固定或取消固定任务
+    pub fn set_pinned(&self, pinned: bool) {
+        self.pinned.store(pinned, Ordering::Release);
+    }
+
+    /// 任务状态：`cancelled`（已取消）、`failed`（后台解析失败）、`processing`（仍有 chunk 在解析中）或 `ready`
+    /// 驻留窗口模式下窗口外的 chunk 按需读取，随时可以请求，因此不会处于 `processing`；
+    /// 分割失败的 chunk 不会再就绪，不计入解析中的 chunk
+    pub fn status_label(&self) -> &'static str {
+        if self.cancel.is_cancelled() {
+            "cancelled"
+        } else if self.failure().is_some() {
+            "failed"
+        } else if self.resident_window.is_none()
+            && self.remaining_chunk_count()
+                > self.ready_chunk_count() + self.chunk_failures.read().len()
+        {
+            "processing"
+        } else {
+            "ready"
+        }
+    }
+
+    /// 数据校验和的十六进制表示（尚未计算时为 None）
+    /// 以字符串形式返回，避免 u64 超出 JavaScript 安全整数范围
+    pub fn data_hash_hex(&self) -> Option<String> {
+        self.data_hash.get().map(|hash| format!("{hash:016x}"))
+    }
+
+    /// 网格的积分 `sum(data) * voxel_volume`（电荷密度文件即总电子数）
+    /// 体素体积未知时返回原始的数据之和；后台解析完成前为 None
+    pub fn integral(&self) -> Option<f64> {
+        let sum = *self.data_sum.get()?;
+        Some(sum * self.voxel_volume.unwrap_or(1.0))
+    }
+
+    /// 写入预计算的分析结果（已有缓存的分析保持不变）
+    pub fn store_analyses(&self, analyses: &PrecomputedAnalyses) {
+        if let Some(stats) = analyses.stats {
+            let _ = self.stats.set(stats);
+        }
+        if let Some(histogram) = &analyses.histogram {
+            let _ = self.histogram.set(histogram.clone());
+        }
+        if let Some(laplacian) = &analyses.laplacian {
+            let _ = self.laplacian[0].set(laplacian.clone());
+        }
+    }
+
+    /// 已计算好、可以直接返回的分析（无论是预计算还是之前的请求计算的）
+    pub fn ready_analyses(&self) -> Vec<Analysis> {
+        [
+            (Analysis::Stats, self.stats.get().is_some()),
+            (Analysis::Histogram, self.histogram.get().is_some()),
+            (Analysis::Laplacian, self.laplacian[0].get().is_some()),
+        ]
+        .into_iter()
+        .filter_map(|(analysis, ready)| ready.then_some(analysis))
+        .collect()
+    }
+
     /// 检查是否还有未请求的 chunk
-    #[allow(dead_code)]
     pub fn has_remaining_chunks(&self) -> bool {
         !self.chunk_data.read().is_empty()
     }
 
     /// 获取剩余的 chunk 数量
-    #[allow(dead_code)]
     pub fn remaining_chunk_count(&self) -> usize {
         self.chunk_data.read().len()
     }
 }
 
+/// 任务数达到上限时的处理策略
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TaskLimitPolicy {
+    /// 拒绝新任务（预处理返回 503）
+    #[default]
+    Reject,
+    /// 移除最早创建的任务，为新任务腾出位置
+    EvictOldest,
+}
+
+impl FromStr for TaskLimitPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reject" => Ok(Self::Reject),
+            "evict_oldest" => Ok(Self::EvictOldest),
+            other => Err(format!("未知的任务上限策略: {other}")),
+        }
+    }
+}
+
+/// 任务数已达上限且策略为拒绝
+#[derive(Debug, Clone, Copy)]
+pub struct TaskLimitExceeded {
+    pub limit: usize,
+}
+
 pub struct TaskStore {
     tasks: RwLock<HashMap<String, Arc<TaskData>>>,
     /// TTL（Time-To-Live）默认过期时间：30 分钟
     default_ttl: Duration,
+    /// 同时存在的任务数上限，0 表示不限制
+    max_tasks: usize,
+    limit_policy: TaskLimitPolicy,
+    /// 每个任务保留的最近请求 chunk 数量
+    recent_chunk_cache: usize,
 }
 
 impl TaskStore {
     pub fn new() -> Self {
-        Self {
-            tasks: RwLock::new(HashMap::new()),
-            default_ttl: Duration::from_secs(30 * 60), // 30 分钟
-        }
+        Self::with_ttl(Duration::from_secs(30 * 60)) // 30 分钟
     }
 
     /// 创建带自定义 TTL 的 TaskStore
-    #[allow(dead_code)]
     pub fn with_ttl(ttl: Duration) -> Self {
         Self {
             tasks: RwLock::new(HashMap::new()),
             default_ttl: ttl,
+            max_tasks: 0,
+            limit_policy: TaskLimitPolicy::Reject,
+            recent_chunk_cache: 0,
         }
     }
 
-    pub fn insert(&self, data: TaskData) -> String {
+    /// 设置任务数上限（0 表示不限制）及达到上限时的策略
+    pub fn with_limit(mut self, max_tasks: usize, policy: TaskLimitPolicy) -> Self {
+        self.max_tasks = max_tasks;
+        self.limit_policy = policy;
+        self
+    }
+
+    /// 设置每个任务保留的最近请求 chunk 数量（0 表示不保留），对之后插入的任务生效
+    pub fn with_recent_chunk_cache(mut self, capacity: usize) -> Self {
+        self.recent_chunk_cache = capacity;
+        self
+    }
+
+    /// 插入新任务并返回 task_id
+    ///
+    /// 任务数达到上限时先清理已过期的任务；仍然已满时按策略拒绝或移除最早创建的任务（固定的任务不会被移除，
+    /// 全部任务都已固定时同样拒绝）
+    pub fn insert(&self, mut data: TaskData) -> Result<String, TaskLimitExceeded> {
+        data.recent_chunks = RecentChunks::new(self.recent_chunk_cache);
         let task_id = Uuid::new_v4().to_string();
-        self.tasks.write().insert(task_id.clone(), Arc::new(data));
-        task_id
+        let mut tasks = self.tasks.write();
+        self.make_room(&mut tasks, 1)?;
+        tasks.insert(task_id.clone(), Arc::new(data));
+        Ok(task_id)
+    }
+
+    /// 一次插入多个任务，按顺序返回各自的 task_id
+    ///
+    /// 要么全部插入，要么一个都不插入：容量不足以容纳全部任务时返回错误，不会留下一部分任务；
+    /// 腾出空间时也不会移除本次插入的其他任务。上限策略与 `insert` 相同
+    pub fn insert_all(&self, data: Vec<TaskData>) -> Result<Vec<String>, TaskLimitExceeded> {
+        let mut tasks = self.tasks.write();
+        self.make_room(&mut tasks, data.len())?;
+        let task_ids = data
+            .into_iter()
+            .map(|mut data| {
+                data.recent_chunks = RecentChunks::new(self.recent_chunk_cache);
+                let task_id = Uuid::new_v4().to_string();
+                tasks.insert(task_id.clone(), Arc::new(data));
+                task_id
+            })
+            .collect();
+        Ok(task_ids)
+    }
+
+    /// 为 `count` 个新任务腾出空间：先清理已过期的任务，仍然不够时按策略拒绝或依次移除最早创建的任务
+    fn make_room(
+        &self,
+        tasks: &mut HashMap<String, Arc<TaskData>>,
+        count: usize,
+    ) -> Result<(), TaskLimitExceeded> {
+        if self.max_tasks == 0 {
+            return Ok(());
+        }
+        let exceeded = TaskLimitExceeded {
+            limit: self.max_tasks,
+        };
+        if count > self.max_tasks {
+            return Err(exceeded);
+        }
+        if tasks.len() + count > self.max_tasks {
+            let now = Instant::now();
+            tasks.retain(|_, task| !self.is_expired(task, now));
+        }
+        while tasks.len() + count > self.max_tasks {
+            if self.limit_policy == TaskLimitPolicy::Reject {
+                return Err(exceeded);
+            }
+            let Some(oldest) = tasks
+                .iter()
+                .filter(|(_, task)| !task.is_pinned())
+                .min_by_key(|(_, task)| task.created_at)
+                .map(|(task_id, _)| task_id.clone())
+            else {
+                return Err(exceeded);
+            };
+            tasks.remove(&oldest);
+            println!("[任务存储] 任务数达到上限 {}，移除最早的任务 {oldest}", self.max_tasks);
+        }
+        Ok(())
+    }
+
+    /// 按拒绝策略是否已无法再创建任务（仅作预检，避免在必然被拒绝的请求上做解析等耗时工作）
+    pub fn is_full(&self) -> bool {
+        if self.max_tasks == 0 || self.limit_policy != TaskLimitPolicy::Reject {
+            return false;
+        }
+        let now = Instant::now();
+        let tasks = self.tasks.read();
+        tasks.values().filter(|task| !self.is_expired(task, now)).count() >= self.max_tasks
+    }
+
+    /// 任务数上限，0 表示不限制
+    pub fn max_tasks(&self) -> usize {
+        self.max_tasks
+    }
+
+    /// 用 `data` 替换仍然是 `current` 的任务（例如追加数据后），返回是否已替换
+    ///
+    /// 任务在此期间已被删除或替换时不做任何事，避免把已取消、已清理的任务重新加回来。
+    /// `current` 中原有 chunk 的数据在持锁时移动到新任务，替换失败时 `current` 保持不变
+    pub fn replace(&self, task_id: &str, current: &Arc<TaskData>, mut data: TaskData) -> bool {
+        data.recent_chunks = RecentChunks::new(self.recent_chunk_cache);
+        let mut tasks = self.tasks.write();
+        match tasks.get_mut(task_id) {
+            Some(task) if Arc::ptr_eq(task, current) => {
+                data.take_chunks_from(current);
+                *task = Arc::new(data);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// 以指定的 task_id 插入任务（例如重启后恢复持久化的任务），不受任务数上限限制
+    pub fn insert_with_id(&self, task_id: String, mut data: TaskData) {
+        data.recent_chunks = RecentChunks::new(self.recent_chunk_cache);
+        self.tasks.write().insert(task_id, Arc::new(data));
+    }
+
+    /// 当前所有未过期任务的快照
+    pub fn snapshot(&self) -> Vec<(String, Arc<TaskData>)> {
+        let now = Instant::now();
+        self.tasks
+            .read()
+            .iter()
+            .filter(|(_, task)| !self.is_expired(task, now))
+            .map(|(task_id, task)| (task_id.clone(), task.clone()))
+            .collect()
+    }
+
+    /// 属于指定会话的所有未过期任务，按创建时间排序
+    pub fn session_tasks(&self, session_id: &str) -> Vec<(String, Arc<TaskData>)> {
+        let now = Instant::now();
+        let mut tasks: Vec<_> = self
+            .tasks
+            .read()
+            .iter()
+            .filter(|(_, task)| {
+                task.session_id.as_deref() == Some(session_id) && !self.is_expired(task, now)
+            })
+            .map(|(task_id, task)| (task_id.clone(), task.clone()))
+            .collect();
+        tasks.sort_by_key(|(_, task)| task.created_at);
+        tasks
     }
 
     pub fn get(&self, task_id: &str) -> Option<Arc<TaskData>> {
         self.tasks.read().get(task_id).cloned()
     }
 
-    /// 清理过期的任务
+    /// 任务是否已超过 TTL；固定的任务永不过期
+    fn is_expired(&self, task: &TaskData, now: Instant) -> bool {
+        !task.is_pinned() && now.duration_since(task.created_at) >= self.default_ttl
+    }
+
+    /// 清理过期的任务（跳过固定的任务）
     /// 返回清理的任务数量
     pub fn cleanup_expired(&self) -> usize {
         let now = Instant::now();
         let mut tasks = self.tasks.write();
         let before_count = tasks.len();
 
-        tasks.retain(|_, task| {
-            // 保留未过期的任务
-            now.duration_since(task.created_at) < self.default_ttl
-        });
+        // 保留未过期的任务
+        tasks.retain(|_, task| !self.is_expired(task, now));
 
         before_count - tasks.len()
     }
 
     /// 清理所有任务（通常在服务关闭时调用）
-    #[allow(dead_code)]
     pub fn clear_all(&self) {
         self.tasks.write().clear();
     }
@@ -157,3 +1167,224 @@ impl TaskStore {
         self.default_ttl
     }
 }
+
+impl Default for TaskStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 值为线性索引的网格，按 `chunk_size` 分块，所有 chunk 立即就绪
+    fn ramp_task(shape: [usize; 3], chunk_size: usize) -> TaskData {
+        let values = (0..shape.iter().product::<usize>())
+            .map(|i| i as f64)
+            .collect();
+        let grid = VoxelGrid::new(shape, values).unwrap();
+        TaskData::from_grid(grid, chunk_size, "ramp".to_string())
+    }
+
+    fn append_state(chunk_size: usize) -> AppendState {
+        AppendState {
+            file_size: 0,
+            file_mtime: None,
+            cursor: None,
+            chunk_size,
+            appends: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn appended_task_keeps_unconsumed_chunks_until_replaced() {
+        let store = TaskStore::new();
+        let task_id = store.insert(ramp_task([2, 2, 2], 4)).unwrap();
+        let current = store.get(&task_id).unwrap();
+        assert!(current.take_chunk(0).is_some());
+
+        let replacement =
+            current.with_appended_layers(vec![8.0, 9.0, 10.0, 11.0], 1, append_state(4));
+        // 替换之前原任务照常提供尚未取走的 chunk
+        assert_eq!(
+            current.peek_chunk(1, |values| values.to_vec()),
+            Some(vec![4.0, 5.0, 6.0, 7.0])
+        );
+
+        assert!(store.replace(&task_id, &current, replacement));
+        let replaced = store.get(&task_id).unwrap();
+        assert_eq!(replaced.shape, [2, 2, 3]);
+        assert_eq!(replaced.take_chunk(0), None);
+        assert_eq!(replaced.take_chunk(1), Some(vec![4.0, 5.0, 6.0, 7.0]));
+        assert_eq!(replaced.take_chunk(2), Some(vec![8.0, 9.0, 10.0, 11.0]));
+        assert!(replaced.chunk_ready_at(1).is_some());
+        assert_eq!(replaced.progress.chunks_stored(), 3);
+    }
+
+    #[test]
+    fn failed_replace_leaves_task_intact() {
+        let store = TaskStore::new();
+        let task_id = store.insert(ramp_task([2, 2, 2], 4)).unwrap();
+        let current = store.get(&task_id).unwrap();
+        let replacement = current.with_appended_layers(vec![0.0; 4], 1, append_state(4));
+
+        // 任务在追加期间被删除（如过期清理），替换失败
+        store.clear_all();
+        assert!(!store.replace(&task_id, &current, replacement));
+        assert_eq!(current.take_chunk(0), Some(vec![0.0, 1.0, 2.0, 3.0]));
+        assert_eq!(current.take_chunk(1), Some(vec![4.0, 5.0, 6.0, 7.0]));
+    }
+
+    #[test]
+    fn expired_tasks_are_removed_by_cleanup() {
+        let store = TaskStore::with_ttl(Duration::from_millis(50));
+        let task_id = store.insert(ramp_task([2, 2, 2], 4)).unwrap();
+        assert_eq!(store.task_count(), 1);
+        assert_eq!(store.cleanup_expired(), 0);
+
+        std::thread::sleep(Duration::from_millis(80));
+        assert_eq!(store.cleanup_expired(), 1);
+        assert_eq!(store.task_count(), 0);
+        assert!(store.get(&task_id).is_none());
+    }
+
+    #[test]
+    fn pinned_tasks_survive_cleanup() {
+        let store = TaskStore::with_ttl(Duration::from_millis(50));
+        let task_id = store.insert(ramp_task([2, 2, 2], 4)).unwrap();
+        store.get(&task_id).unwrap().set_pinned(true);
+
+        std::thread::sleep(Duration::from_millis(80));
+        assert_eq!(store.cleanup_expired(), 0);
+        assert!(store.get(&task_id).is_some());
+    }
+
+    fn ranges(chunks: &[ChunkDescriptor]) -> Vec<(usize, usize)> {
+        chunks
+            .iter()
+            .map(|chunk| (chunk.start, chunk.end))
+            .collect()
+    }
+
+    #[test]
+    fn exact_multiple_has_no_empty_trailing_chunk() {
+        let chunks = compute_chunks(12, 4);
+        assert_eq!(ranges(&chunks), vec![(0, 4), (4, 8), (8, 12)]);
+        assert_eq!(check_chunk_layout(&chunks, 12), Ok(()));
+    }
+
+    #[test]
+    fn remainder_goes_into_a_shorter_final_chunk() {
+        let chunks = compute_chunks(10, 4);
+        assert_eq!(ranges(&chunks), vec![(0, 4), (4, 8), (8, 10)]);
+        assert_eq!(check_chunk_layout(&chunks, 10), Ok(()));
+
+        let tiles = compute_tiles([5, 3, 2], [2, 2, 2]);
+        assert_eq!(tiles.len(), 6);
+        assert_eq!(check_chunk_layout(&tiles, 30), Ok(()));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "chunk_size 必须大于 0")]
+    fn zero_chunk_size_is_a_precondition_violation() {
+        compute_chunks(8, 0);
+    }
+
+    #[test]
+    fn check_chunk_layout_rejects_broken_layouts() {
+        let mut empty_tail = compute_chunks(8, 4);
+        empty_tail.push(ChunkDescriptor {
+            index: 2,
+            start: 8,
+            end: 8,
+            tile: None,
+        });
+        assert!(
+            check_chunk_layout(&empty_tail, 8)
+                .unwrap_err()
+                .contains("为空")
+        );
+
+        let mut gap = compute_chunks(8, 4);
+        gap[1].start = 5;
+        assert!(check_chunk_layout(&gap, 8).is_err());
+
+        // 长度之和与 data_length 不一致
+        assert!(check_chunk_layout(&compute_chunks(8, 4), 9).is_err());
+        assert!(check_chunk_layout(&[], 1).is_err());
+    }
+
+    #[test]
+    fn recently_taken_chunk_can_be_served_again() {
+        let store = TaskStore::new().with_recent_chunk_cache(2);
+        let task_id = store.insert(ramp_task([2, 2, 2], 4)).unwrap();
+        let task = store.get(&task_id).unwrap();
+
+        assert_eq!(task.take_chunk(0), Some(vec![0.0, 1.0, 2.0, 3.0]));
+        assert_eq!(task.take_chunk(0), None);
+        assert_eq!(
+            task.recent_chunk(0).as_deref(),
+            Some(&[0.0, 1.0, 2.0, 3.0][..])
+        );
+        assert_eq!(task.recent_chunk(1), None);
+    }
+
+    #[test]
+    fn recent_chunks_evict_least_recently_used() {
+        let store = TaskStore::new().with_recent_chunk_cache(2);
+        let task_id = store.insert(ramp_task([4, 2, 2], 4)).unwrap();
+        let task = store.get(&task_id).unwrap();
+
+        task.take_chunk(0);
+        task.take_chunk(1);
+        // 命中会把 chunk 0 标记为最近使用，下一次淘汰的是 chunk 1
+        assert!(task.recent_chunk(0).is_some());
+        task.take_chunk(2);
+        assert!(task.recent_chunk(0).is_some());
+        assert_eq!(task.recent_chunk(1), None);
+        assert!(task.recent_chunk(2).is_some());
+    }
+
+    #[test]
+    fn zero_capacity_keeps_no_recent_chunks() {
+        let store = TaskStore::new();
+        let task_id = store.insert(ramp_task([2, 2, 2], 4)).unwrap();
+        let task = store.get(&task_id).unwrap();
+
+        assert!(task.take_chunk(0).is_some());
+        assert_eq!(task.recent_chunk(0), None);
+    }
+
+    #[test]
+    fn insert_all_rejects_without_partial_inserts() {
+        let store = TaskStore::new().with_limit(3, TaskLimitPolicy::Reject);
+        store.insert(ramp_task([2, 2, 2], 4)).unwrap();
+        store.insert(ramp_task([2, 2, 2], 4)).unwrap();
+
+        let batch = vec![ramp_task([2, 2, 2], 4), ramp_task([2, 2, 2], 4)];
+        assert!(store.insert_all(batch).is_err());
+        assert_eq!(store.task_count(), 2);
+
+        let task_ids = store.insert_all(vec![ramp_task([2, 2, 2], 4)]).unwrap();
+        assert_eq!(task_ids.len(), 1);
+        assert_eq!(store.task_count(), 3);
+    }
+
+    #[test]
+    fn insert_all_evicts_only_older_tasks() {
+        let store = TaskStore::new().with_limit(3, TaskLimitPolicy::EvictOldest);
+        let old = store.insert(ramp_task([2, 2, 2], 4)).unwrap();
+
+        let batch = (0..3).map(|_| ramp_task([2, 2, 2], 4)).collect();
+        let task_ids = store.insert_all(batch).unwrap();
+        assert!(store.get(&old).is_none());
+        assert!(task_ids.iter().all(|task_id| store.get(task_id).is_some()));
+
+        // 超过上限的一批任务无论如何都放不下，直接拒绝，不移除已有任务
+        let batch = (0..4).map(|_| ramp_task([2, 2, 2], 4)).collect();
+        assert!(store.insert_all(batch).is_err());
+        assert_eq!(store.task_count(), 3);
+    }
+}
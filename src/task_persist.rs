@@ -0,0 +1,224 @@
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Error, ErrorKind, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use serde::{Deserialize, Serialize};
+
+use crate::performance::get_unix_timestamp_ms;
+use crate::task::{ChunkDescriptor, TaskData, TaskStore};
+use crate::utils::value_transform::ValueTransform;
+
+/// 任务元数据文件扩展名（`<task_id>.json`）
+const META_EXTENSION: &str = "json";
+/// chunk 数据文件扩展名（`<task_id>.bin`）：按 `remaining` 顺序拼接已就绪 chunk 的小端序 f64
+const DATA_EXTENSION: &str = "bin";
+
+/// 持久化的任务元数据
+#[derive(Serialize, Deserialize)]
+struct PersistedTask {
+    shape: [usize; 3],
+    chunks: Vec<ChunkDescriptor>,
+    file_path: String,
+    round_decimals: Option<u32>,
+    value_transform: ValueTransform,
+    resident_window: Option<usize>,
+    #[serde(default)]
+    smooth_sigma: Option<f64>,
+    #[serde(default)]
+    equalize_bins: Option<usize>,
+    data_hash: Option<u64>,
+    #[serde(default)]
+    data_sum: Option<f64>,
+    #[serde(default)]
+    voxel_volume: Option<f64>,
+    #[serde(default)]
+    session_id: Option<String>,
+    #[serde(default)]
+    pinned: bool,
+    failure: Option<String>,
+    /// 任务创建时间（Unix 毫秒），`Instant` 无法跨进程保存
+    created_unix_ms: u64,
+    values_read: usize,
+    chunks_stored: usize,
+    /// 保存时尚未被请求的 chunk
+    remaining: Vec<PersistedChunk>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct PersistedChunk {
+    index: usize,
+    /// 已就绪的 chunk 数据写入数据文件；未就绪的（驻留窗口外）恢复后仍按需读取
+    ready: bool,
+}
+
+/// 把所有未过期的任务保存到 `dir`，返回保存的任务数量
+///
+/// 后台解析尚未完成的任务无法恢复解析进度，因此不保存（驻留窗口模式除外，其 chunk 本就按需读取）
+pub fn save_tasks(store: &TaskStore, dir: &str) -> Result<usize, Error> {
+    fs::create_dir_all(dir)?;
+    let now_ms = get_unix_timestamp_ms();
+    let mut saved = 0;
+    for (task_id, task) in store.snapshot() {
+        let chunk_data = task.chunk_data.read();
+        let pending = chunk_data.values().any(Option::is_none);
+        if pending && task.resident_window.is_none() && task.failure().is_none() {
+            println!("[任务持久化] 任务 {task_id} 仍在解析中，不保存");
+            continue;
+        }
+
+        let mut remaining: Vec<PersistedChunk> = chunk_data
+            .iter()
+            .map(|(index, values)| PersistedChunk {
+                index: *index,
+                ready: values.is_some(),
+            })
+            .collect();
+        remaining.sort_unstable_by_key(|chunk| chunk.index);
+
+        let mut writer = BufWriter::new(File::create(task_file(dir, &task_id, DATA_EXTENSION))?);
+        for chunk in remaining.iter().filter(|chunk| chunk.ready) {
+            if let Some(Some(values)) = chunk_data.get(&chunk.index) {
+                for value in values {
+                    writer.write_f64::<LittleEndian>(*value)?;
+                }
+            }
+        }
+        writer.flush()?;
+
+        let age_ms = task.created_at.elapsed().as_millis() as u64;
+        let meta = PersistedTask {
+            shape: task.shape,
+            chunks: task.chunks.clone(),
+            file_path: task.file_path.clone(),
+            round_decimals: task.round_decimals,
+            value_transform: task.value_transform,
+            resident_window: task.resident_window,
+            smooth_sigma: task.smooth_sigma,
+            equalize_bins: task.equalize_bins,
+            data_hash: task.data_hash.get().copied(),
+            data_sum: task.data_sum.get().copied(),
+            voxel_volume: task.voxel_volume,
+            session_id: task.session_id.clone(),
+            pinned: task.is_pinned(),
+            failure: task.failure().map(str::to_string),
+            created_unix_ms: now_ms.saturating_sub(age_ms),
+            values_read: task.progress.values_read(),
+            chunks_stored: task.progress.chunks_stored(),
+            remaining,
+        };
+        // 元数据最后写入：只有元数据存在的任务才会被加载，避免加载写了一半的数据
+        let meta_file = File::create(task_file(dir, &task_id, META_EXTENSION))?;
+        serde_json::to_writer(BufWriter::new(meta_file), &meta).map_err(Error::other)?;
+        saved += 1;
+    }
+    Ok(saved)
+}
+
+/// 从 `dir` 加载持久化的任务到 `store`，已超过 TTL 的任务直接丢弃
+/// 读取后删除对应文件，避免之后重复加载。返回（加载数量，丢弃数量）
+pub fn load_tasks(store: &TaskStore, dir: &str) -> Result<(usize, usize), Error> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok((0, 0)),
+        Err(e) => return Err(e),
+    };
+
+    let now_ms = get_unix_timestamp_ms();
+    let mut loaded = 0;
+    let mut dropped = 0;
+    for entry in entries {
+        let meta_path = entry?.path();
+        if meta_path.extension().and_then(|ext| ext.to_str()) != Some(META_EXTENSION) {
+            continue;
+        }
+        let Some(task_id) = meta_path.file_stem().and_then(|s| s.to_str()).map(str::to_string)
+        else {
+            continue;
+        };
+        let data_path = task_file(dir, &task_id, DATA_EXTENSION);
+
+        let result = read_meta(&meta_path).and_then(|meta| {
+            // 存活时长包括服务停机的时间；固定的任务不会过期
+            let age = Duration::from_millis(now_ms.saturating_sub(meta.created_unix_ms));
+            if !meta.pinned && age >= store.default_ttl() {
+                dropped += 1;
+                return Ok(());
+            }
+            let task = restore_task(meta, &data_path, age)?;
+            store.insert_with_id(task_id.clone(), task);
+            loaded += 1;
+            Ok(())
+        });
+        if let Err(e) = result {
+            eprintln!("[任务持久化] 恢复任务 {task_id} 失败: {e}");
+            dropped += 1;
+        }
+
+        let _ = fs::remove_file(&meta_path);
+        let _ = fs::remove_file(&data_path);
+    }
+    Ok((loaded, dropped))
+}
+
+fn task_file(dir: &str, task_id: &str, extension: &str) -> std::path::PathBuf {
+    Path::new(dir).join(format!("{task_id}.{extension}"))
+}
+
+fn read_meta(meta_path: &Path) -> Result<PersistedTask, Error> {
+    serde_json::from_reader(BufReader::new(File::open(meta_path)?))
+        .map_err(|e| Error::new(ErrorKind::InvalidData, e))
+}
+
+/// 根据元数据与数据文件重建任务，`age` 为任务已存在的时长
+fn restore_task(meta: PersistedTask, data_path: &Path, age: Duration) -> Result<TaskData, Error> {
+    let mut task = TaskData::new(meta.shape, meta.chunks, meta.file_path);
+    task.created_at = Instant::now().checked_sub(age).unwrap_or_else(Instant::now);
+    task.round_decimals = meta.round_decimals;
+    task.value_transform = meta.value_transform;
+    task.resident_window = meta.resident_window;
+    task.smooth_sigma = meta.smooth_sigma;
+    task.equalize_bins = meta.equalize_bins;
+    task.voxel_volume = meta.voxel_volume;
+    task.session_id = meta.session_id;
+    task.set_pinned(meta.pinned);
+    if let Some(hash) = meta.data_hash {
+        let _ = task.data_hash.set(hash);
+    }
+    if let Some(sum) = meta.data_sum {
+        let _ = task.data_sum.set(sum);
+    }
+    if let Some(reason) = meta.failure {
+        task.mark_failed(reason);
+    }
+    task.progress.set_values_read(meta.values_read);
+    for _ in 0..meta.chunks_stored {
+        task.progress.add_chunk_stored();
+    }
+
+    let mut reader = BufReader::new(File::open(data_path)?);
+    {
+        let mut chunk_data = task.chunk_data.write();
+        // TaskData::new 为所有 chunk 建立了未就绪的条目，只保留保存时尚未被请求的
+        chunk_data.clear();
+        for chunk in meta.remaining {
+            let Some(descriptor) = task.chunks.get(chunk.index) else {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!("无效的 chunk_index: {}", chunk.index),
+                ));
+            };
+            let values = if chunk.ready {
+                let mut values = vec![0.0; descriptor.end - descriptor.start];
+                reader.read_f64_into::<LittleEndian>(&mut values)?;
+                task.record_summary(chunk.index, &values);
+                Some(values)
+            } else {
+                None
+            };
+            chunk_data.insert(chunk.index, values);
+        }
+    }
+    Ok(task)
+}
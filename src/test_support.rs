@@ -0,0 +1,34 @@
+//! 单元测试共用的辅助函数
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// 测试用的临时文件，离开作用域时删除
+pub struct TempFile {
+    path: PathBuf,
+}
+
+impl TempFile {
+    /// 在系统临时目录下写入内容为 `bytes`、扩展名为 `extension` 的文件
+    pub fn new(extension: &str, bytes: &[u8]) -> Self {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let name = format!(
+            "demos3d-test-{}-{}.{extension}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        );
+        let path = std::env::temp_dir().join(name);
+        std::fs::write(&path, bytes).unwrap();
+        Self { path }
+    }
+
+    pub fn path(&self) -> &str {
+        self.path.to_str().unwrap()
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
@@ -0,0 +1,39 @@
+use std::fs::File;
+use std::io::{BufReader, Error, ErrorKind};
+
+use rustls::ServerConfig;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+
+/// 读取 PEM 格式的证书链与私钥，构造 rustls 服务端配置
+///
+/// ALPN（`h2` / `http/1.1`）由 actix 在绑定时追加，客户端支持时自动协商 HTTP/2
+pub fn load_server_config(cert_path: &str, key_path: &str) -> Result<ServerConfig, Error> {
+    let certs: Vec<CertificateDer<'static>> =
+        rustls_pemfile::certs(&mut BufReader::new(open(cert_path, "证书")?))
+            .collect::<Result<_, _>>()
+            .map_err(|e| invalid(format!("无法解析证书文件 {cert_path}: {e}")))?;
+    if certs.is_empty() {
+        return Err(invalid(format!("证书文件 {cert_path} 中没有 PEM 证书")));
+    }
+
+    let key: PrivateKeyDer<'static> =
+        rustls_pemfile::private_key(&mut BufReader::new(open(key_path, "私钥")?))
+            .map_err(|e| invalid(format!("无法解析私钥文件 {key_path}: {e}")))?
+            .ok_or_else(|| invalid(format!("私钥文件 {key_path} 中没有 PEM 私钥")))?;
+
+    let provider = rustls::crypto::ring::default_provider();
+    ServerConfig::builder_with_provider(provider.into())
+        .with_safe_default_protocol_versions()
+        .map_err(|e| invalid(format!("TLS 协议版本配置失败: {e}")))?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|e| invalid(format!("证书与私钥不匹配或无效: {e}")))
+}
+
+fn open(path: &str, what: &str) -> Result<File, Error> {
+    File::open(path).map_err(|e| Error::new(e.kind(), format!("无法打开{what}文件 {path}: {e}")))
+}
+
+fn invalid(message: String) -> Error {
+    Error::new(ErrorKind::InvalidInput, message)
+}
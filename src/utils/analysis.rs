@@ -0,0 +1,56 @@
+use std::sync::Arc;
+
+use serde::{Deserialize, Serialize};
+
+use crate::utils::stats::{DEFAULT_HISTOGRAM_BINS, GridStats, Histogram};
+use crate::utils::voxel_grid::VoxelGrid;
+
+/// 需要遍历整个网格的分析，可以在后台解析时预先计算（预处理的 `precompute`），也可以在首次请求时计算
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Analysis {
+    /// 全网格 min / max / mean / count（`/voxel-grid/stats`）
+    Stats,
+    /// 默认 bin 数的直方图（`/voxel-grid/histogram`）
+    Histogram,
+    /// 非周期边界的拉普拉斯量（`/voxel-grid/laplacian`）
+    Laplacian,
+}
+
+/// 规范化请求的分析列表：排序并去重
+pub fn normalize_analyses(analyses: &[Analysis]) -> Vec<Analysis> {
+    let mut analyses = analyses.to_vec();
+    analyses.sort();
+    analyses.dedup();
+    analyses
+}
+
+/// 对完整网格计算出的分析结果，只包含请求的分析
+#[derive(Default)]
+pub struct PrecomputedAnalyses {
+    pub stats: Option<GridStats>,
+    pub histogram: Option<Histogram>,
+    pub laplacian: Option<Arc<[f64]>>,
+}
+
+impl PrecomputedAnalyses {
+    /// 依次计算 `analyses` 中的每一项（遍历整个网格，应在阻塞线程池中调用）
+    pub fn compute(grid: &VoxelGrid, analyses: &[Analysis]) -> Self {
+        let mut computed = Self::default();
+        for analysis in analyses {
+            match analysis {
+                Analysis::Stats => {
+                    computed.stats = Some(GridStats::from_values(grid.get_data().iter().copied()));
+                }
+                Analysis::Histogram => {
+                    computed.histogram = Some(Histogram::from_values(
+                        grid.get_data(),
+                        DEFAULT_HISTOGRAM_BINS,
+                    ));
+                }
+                Analysis::Laplacian => computed.laplacian = Some(Arc::from(grid.laplacian())),
+            }
+        }
+        computed
+    }
+}
@@ -0,0 +1,77 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use arrow_array::{Float64Array, RecordBatch};
+use arrow_ipc::writer::StreamWriter;
+use arrow_schema::{ArrowError, DataType, Field, Schema, SchemaRef};
+
+use crate::task::ChunkDescriptor;
+
+/// 将 chunk 数据编码为 Arrow IPC stream
+///
+/// 输出只包含一个 RecordBatch，单列 `values`（Float64，不可为空），
+/// schema 元数据中带有 `chunk_index`、`start`、`end` 与 `shape`（JSON 数组），
+/// tile 模式下另有 `tile_origin` 与 `tile_dims`（JSON 数组）；
+/// 客户端可以直接用 `pyarrow.ipc.open_stream` 读取
+pub fn encode_chunk_arrow(
+    values: Vec<f64>,
+    descriptor: &ChunkDescriptor,
+    shape: [usize; 3],
+) -> Result<Vec<u8>, ArrowError> {
+    let mut writer = ArrowBatchWriter::try_new(descriptor, shape)?;
+    let mut buffer = writer.write_batch(values)?;
+    buffer.extend(writer.finish()?);
+    Ok(buffer)
+}
+
+/// 分批写出 chunk 的 Arrow IPC stream：schema 与 `encode_chunk_arrow` 相同，每次写入一个 RecordBatch，
+/// 并取出至今写出的字节，用于边序列化边发送
+pub struct ArrowBatchWriter {
+    writer: StreamWriter<Vec<u8>>,
+    schema: SchemaRef,
+}
+
+impl ArrowBatchWriter {
+    /// 创建写入器，schema 消息在第一次 `write_batch` 时一并取出
+    pub fn try_new(descriptor: &ChunkDescriptor, shape: [usize; 3]) -> Result<Self, ArrowError> {
+        let schema = chunk_schema(descriptor, shape);
+        let writer = StreamWriter::try_new(Vec::new(), &schema)?;
+        Ok(Self { writer, schema })
+    }
+
+    /// 写入一个 RecordBatch，返回尚未取出的字节
+    pub fn write_batch(&mut self, values: Vec<f64>) -> Result<Vec<u8>, ArrowError> {
+        let column = Arc::new(Float64Array::from(values));
+        let batch = RecordBatch::try_new(self.schema.clone(), vec![column])?;
+        self.writer.write(&batch)?;
+        Ok(std::mem::take(self.writer.get_mut()))
+    }
+
+    /// 写入 stream 结束标记，返回尚未取出的字节
+    pub fn finish(&mut self) -> Result<Vec<u8>, ArrowError> {
+        self.writer.finish()?;
+        Ok(std::mem::take(self.writer.get_mut()))
+    }
+}
+
+/// chunk 的 schema：单列 `values` 与描述 chunk 位置的元数据
+fn chunk_schema(descriptor: &ChunkDescriptor, shape: [usize; 3]) -> SchemaRef {
+    let mut metadata = HashMap::from([
+        ("chunk_index".to_string(), descriptor.index.to_string()),
+        ("start".to_string(), descriptor.start.to_string()),
+        ("end".to_string(), descriptor.end.to_string()),
+        (
+            "shape".to_string(),
+            format!("[{},{},{}]", shape[0], shape[1], shape[2]),
+        ),
+    ]);
+    if let Some(tile) = descriptor.tile {
+        let [x, y, z] = tile.origin;
+        metadata.insert("tile_origin".to_string(), format!("[{x},{y},{z}]"));
+        let [dx, dy, dz] = tile.dims;
+        metadata.insert("tile_dims".to_string(), format!("[{dx},{dy},{dz}]"));
+    }
+    Arc::new(
+        Schema::new(vec![Field::new("values", DataType::Float64, false)]).with_metadata(metadata),
+    )
+}
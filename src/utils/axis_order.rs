@@ -0,0 +1,96 @@
+use std::fmt;
+use std::str::FromStr;
+
+/// 体素在内存中的轴顺序：按变化从快到慢依次列出三个轴
+///
+/// 服务端的默认顺序为 `xyz`（x 最快、z 最慢）；`zyx` 即 z 最快、x 最慢（相当于 C 顺序的 `[x][y][z]` 数组）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AxisOrder([usize; 3]);
+
+impl AxisOrder {
+    /// 默认顺序：x 最快、z 最慢
+    pub const XYZ: AxisOrder = AxisOrder([0, 1, 2]);
+
+    /// 是否与默认顺序相同（无需重排）
+    pub fn is_identity(self) -> bool {
+        self == Self::XYZ
+    }
+
+    /// 变化最慢的轴（0 / 1 / 2 对应 x / y / z）
+    pub fn slowest_axis(self) -> usize {
+        self.0[2]
+    }
+}
+
+impl FromStr for AxisOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("无效的轴顺序 '{s}'，应为 x、y、z 的一个排列，如 xyz、zyx");
+        let axes: Vec<usize> = s
+            .chars()
+            .map(|c| match c {
+                'x' => Ok(0),
+                'y' => Ok(1),
+                'z' => Ok(2),
+                _ => Err(invalid()),
+            })
+            .collect::<Result<_, _>>()?;
+        let [a, b, c] = axes[..] else {
+            return Err(invalid());
+        };
+        if a == b || b == c || a == c {
+            return Err(invalid());
+        }
+        Ok(AxisOrder([a, b, c]))
+    }
+}
+
+impl fmt::Display for AxisOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for axis in self.0 {
+            write!(f, "{}", ['x', 'y', 'z'][axis])?;
+        }
+        Ok(())
+    }
+}
+
+/// 把按默认顺序（x 最快）排列、大小为 `dims`（`[dx, dy, dz]`）的长方体数据重排为 `order` 顺序
+pub fn transpose(values: &[f64], dims: [usize; 3], order: AxisOrder) -> Vec<f64> {
+    if order.is_identity() {
+        return values.to_vec();
+    }
+    let strides = [1, dims[0], dims[0] * dims[1]];
+    let [fast, middle, slow] = order.0;
+    let mut out = Vec::with_capacity(values.len());
+    for i_slow in 0..dims[slow] {
+        for i_middle in 0..dims[middle] {
+            let base = i_slow * strides[slow] + i_middle * strides[middle];
+            for i_fast in 0..dims[fast] {
+                out.push(values[base + i_fast * strides[fast]]);
+            }
+        }
+    }
+    out
+}
+
+/// `transpose` 的逆操作：把按 `order` 顺序排列、大小为 `dims` 的长方体数据重排回默认顺序（x 最快）
+/// 用于读取按其他轴顺序存储的文件；`values` 的长度必须等于 `dims` 的体素数
+pub fn untranspose(values: &[f64], dims: [usize; 3], order: AxisOrder) -> Vec<f64> {
+    if order.is_identity() {
+        return values.to_vec();
+    }
+    let strides = [1, dims[0], dims[0] * dims[1]];
+    let [fast, middle, slow] = order.0;
+    let mut out = vec![0.0; values.len()];
+    let mut source = values.iter().copied();
+    for i_slow in 0..dims[slow] {
+        for i_middle in 0..dims[middle] {
+            let base = i_slow * strides[slow] + i_middle * strides[middle];
+            for i_fast in 0..dims[fast] {
+                out[base + i_fast * strides[fast]] = source.next().unwrap_or(0.0);
+            }
+        }
+    }
+    out
+}
@@ -0,0 +1,291 @@
+use actix_web::http::header::{CONTENT_TYPE, HeaderMap, HeaderName, HeaderValue};
+use actix_web::mime::{self, Mime};
+use actix_web::web::Bytes;
+use xxhash_rust::xxh3::xxh3_64;
+
+use crate::task::ChunkDescriptor;
+#[cfg(feature = "arrow")]
+use crate::utils::arrow_ipc::{ArrowBatchWriter, encode_chunk_arrow};
+
+/// Arrow IPC stream 的 MIME 类型
+pub const ARROW_STREAM_CONTENT_TYPE: &str = "application/vnd.apache.arrow.stream";
+
+/// 标明 body 编码的响应头，客户端据此选择解码方式
+pub const CHUNK_ENCODING_HEADER: &str = "x-chunk-encoding";
+
+/// body 校验和的响应头，客户端可据此校验收到的字节是否完整
+pub const CHUNK_CHECKSUM_HEADER: &str = "x-chunk-checksum";
+
+/// 流式序列化时每段包含的元素数量（JSON 约 100–200 KiB，Arrow 约 64 KiB）
+const STREAM_SEGMENT_VALUES: usize = 8192;
+
+/// body 字节的校验和：xxh3-64，16 位小写十六进制
+///
+/// actix-web 不支持在响应体之后发送 HTTP trailer，因此校验和只用于完整缓冲的 body（放在响应头中）
+/// 或先组装完整再发送的帧（放在帧头中）
+pub fn chunk_checksum(bytes: &[u8]) -> String {
+    format!("{:016x}", xxh3_64(bytes))
+}
+
+/// chunk 数据的字节编码
+///
+/// 所有格式的序列化都集中在 `encode_chunk` 中，HTTP 接口只负责根据请求选择编码
+#[derive(Debug, Clone)]
+pub enum ChunkEncoding<'a> {
+    /// 小端序 f64，附带响应的 Content-Type
+    F64Le(Mime),
+    /// 小端序 f32（精度降低，体积减半），附带响应的 Content-Type
+    F32Le(Mime),
+    /// JSON 数组（NaN / ±inf 序列化为 null）
+    Json,
+    /// Arrow IPC stream，schema 元数据需要 chunk 描述与网格 shape；需要以 `arrow` feature 编译
+    #[cfg_attr(not(feature = "arrow"), allow(dead_code))]
+    Arrow {
+        descriptor: &'a ChunkDescriptor,
+        shape: [usize; 3],
+    },
+}
+
+impl ChunkEncoding<'_> {
+    /// 编码名称，写入 `X-Chunk-Encoding` 响应头
+    pub fn name(&self) -> &'static str {
+        match self {
+            ChunkEncoding::F64Le(_) => "f64le",
+            ChunkEncoding::F32Le(_) => "f32le",
+            ChunkEncoding::Json => "json",
+            ChunkEncoding::Arrow { .. } => "arrow",
+        }
+    }
+
+    /// 响应的 Content-Type
+    pub fn content_type(&self) -> Mime {
+        match self {
+            ChunkEncoding::F64Le(mime) | ChunkEncoding::F32Le(mime) => mime.clone(),
+            ChunkEncoding::Json => mime::APPLICATION_JSON,
+            #[cfg(feature = "arrow")]
+            ChunkEncoding::Arrow { .. } => ARROW_STREAM_CONTENT_TYPE.parse().unwrap(),
+            #[cfg(not(feature = "arrow"))]
+            ChunkEncoding::Arrow { .. } => mime::APPLICATION_OCTET_STREAM,
+        }
+    }
+}
+
+/// 按 `encoding` 序列化 chunk 数据，返回 body 与对应的响应头（`Content-Type`、`X-Chunk-Encoding`、`X-Chunk-Checksum`）
+pub fn encode_chunk(
+    values: &[f64],
+    encoding: ChunkEncoding,
+) -> Result<(Vec<u8>, HeaderMap), String> {
+    let bytes = match &encoding {
+        ChunkEncoding::F64Le(_) => values.iter().flat_map(|value| value.to_le_bytes()).collect(),
+        ChunkEncoding::F32Le(_) => values
+            .iter()
+            .flat_map(|&value| (value as f32).to_le_bytes())
+            .collect(),
+        ChunkEncoding::Json => serde_json::to_vec(values).map_err(|e| e.to_string())?,
+        #[cfg(feature = "arrow")]
+        ChunkEncoding::Arrow { descriptor, shape } => {
+            encode_chunk_arrow(values.to_vec(), descriptor, *shape).map_err(|e| e.to_string())?
+        }
+        #[cfg(not(feature = "arrow"))]
+        ChunkEncoding::Arrow { .. } => {
+            return Err("服务端未启用 Arrow 输出".to_string());
+        }
+    };
+
+    let mut headers = encoding_headers(&encoding)?;
+    headers.insert(
+        HeaderName::from_static(CHUNK_CHECKSUM_HEADER),
+        HeaderValue::from_str(&chunk_checksum(&bytes)).map_err(|e| e.to_string())?,
+    );
+    Ok((bytes, headers))
+}
+
+/// 与编码对应的 `Content-Type` 与 `X-Chunk-Encoding` 响应头
+fn encoding_headers(encoding: &ChunkEncoding) -> Result<HeaderMap, String> {
+    let mut headers = HeaderMap::new();
+    let content_type = HeaderValue::from_str(encoding.content_type().as_ref())
+        .map_err(|e| format!("无效的 Content-Type: {e}"))?;
+    headers.insert(CONTENT_TYPE, content_type);
+    headers.insert(
+        HeaderName::from_static(CHUNK_ENCODING_HEADER),
+        HeaderValue::from_static(encoding.name()),
+    );
+    Ok(headers)
+}
+
+/// 每段的序列化方式
+enum SegmentWriter {
+    F64Le,
+    F32Le,
+    Json,
+    #[cfg(feature = "arrow")]
+    Arrow(Box<ArrowBatchWriter>),
+}
+
+/// 边发送边序列化的 chunk 数据：每次只序列化 `STREAM_SEGMENT_VALUES` 个元素，
+/// 客户端不必等待整个 body 序列化完成就能开始接收，内存中也不会同时保留完整的 body
+///
+/// 拼接后的字节与 `encode_chunk` 的输出等价（Arrow 格式每段为一个 RecordBatch）。
+/// body 在发送前长度未知，也无法计算校验和，因此不带 `X-Chunk-Checksum`
+pub struct ChunkStream {
+    values: Vec<f64>,
+    /// 下一段的起始元素
+    position: usize,
+    writer: SegmentWriter,
+    finished: bool,
+}
+
+impl ChunkStream {
+    /// 按 `encoding` 创建流式序列化，返回 stream 与对应的响应头（`Content-Type`、`X-Chunk-Encoding`）
+    pub fn new(values: Vec<f64>, encoding: ChunkEncoding) -> Result<(Self, HeaderMap), String> {
+        let writer = match &encoding {
+            ChunkEncoding::F64Le(_) => SegmentWriter::F64Le,
+            ChunkEncoding::F32Le(_) => SegmentWriter::F32Le,
+            ChunkEncoding::Json => SegmentWriter::Json,
+            #[cfg(feature = "arrow")]
+            ChunkEncoding::Arrow { descriptor, shape } => SegmentWriter::Arrow(Box::new(
+                ArrowBatchWriter::try_new(descriptor, *shape).map_err(|e| e.to_string())?,
+            )),
+            #[cfg(not(feature = "arrow"))]
+            ChunkEncoding::Arrow { .. } => {
+                return Err("服务端未启用 Arrow 输出".to_string());
+            }
+        };
+        let stream = Self {
+            values,
+            position: 0,
+            writer,
+            finished: false,
+        };
+        Ok((stream, encoding_headers(&encoding)?))
+    }
+
+    /// 序列化下一段，全部输出完毕后返回 None
+    pub fn next_segment(&mut self) -> Option<Result<Bytes, String>> {
+        if self.finished {
+            return None;
+        }
+        let start = self.position;
+        let end = (start + STREAM_SEGMENT_VALUES).min(self.values.len());
+        let segment = &self.values[start..end];
+        self.position = end;
+        let last = end == self.values.len();
+
+        let bytes = match &mut self.writer {
+            SegmentWriter::F64Le => segment.iter().flat_map(|value| value.to_le_bytes()).collect(),
+            SegmentWriter::F32Le => segment
+                .iter()
+                .flat_map(|&value| (value as f32).to_le_bytes())
+                .collect(),
+            SegmentWriter::Json => match serde_json::to_vec(segment) {
+                // 去掉每段的方括号，段之间以逗号连接，首尾再补上方括号
+                Ok(array) => {
+                    let mut bytes = Vec::with_capacity(array.len() + 1);
+                    bytes.push(if start == 0 { b'[' } else { b',' });
+                    bytes.extend_from_slice(&array[1..array.len() - 1]);
+                    if last {
+                        bytes.push(b']');
+                    }
+                    bytes
+                }
+                Err(e) => return Some(Err(e.to_string())),
+            },
+            #[cfg(feature = "arrow")]
+            SegmentWriter::Arrow(writer) => {
+                let written = writer.write_batch(segment.to_vec()).and_then(|mut bytes| {
+                    if last {
+                        bytes.extend(writer.finish()?);
+                    }
+                    Ok(bytes)
+                });
+                match written {
+                    Ok(bytes) => bytes,
+                    Err(e) => return Some(Err(e.to_string())),
+                }
+            }
+        };
+        self.finished = last;
+        Some(Ok(Bytes::from(bytes)))
+    }
+
+    /// 取出 chunk 数据（例如在发送失败时放回任务），之后 stream 不再输出
+    pub fn take_values(&mut self) -> Vec<f64> {
+        self.finished = true;
+        std::mem::take(&mut self.values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 依次取出 stream 的所有段
+    fn segments(values: Vec<f64>, encoding: ChunkEncoding) -> Vec<Bytes> {
+        let (mut stream, _) = ChunkStream::new(values, encoding).unwrap();
+        std::iter::from_fn(|| stream.next_segment())
+            .map(Result::unwrap)
+            .collect()
+    }
+
+    /// 跨越两个完整段外加一个不满的尾段，其中含有序列化为 null 的 NaN
+    fn values() -> Vec<f64> {
+        let mut values: Vec<f64> = (0..STREAM_SEGMENT_VALUES * 2 + 5)
+            .map(|i| i as f64 * 0.5)
+            .collect();
+        values[STREAM_SEGMENT_VALUES] = f64::NAN;
+        values
+    }
+
+    #[test]
+    fn streamed_json_matches_buffered_encoding() {
+        let segments = segments(values(), ChunkEncoding::Json);
+        assert_eq!(segments.len(), 3);
+        let (buffered, _) = encode_chunk(&values(), ChunkEncoding::Json).unwrap();
+        assert_eq!(segments.concat(), buffered);
+    }
+
+    #[test]
+    fn streamed_empty_chunk_is_an_empty_array() {
+        assert_eq!(segments(Vec::new(), ChunkEncoding::Json).concat(), b"[]");
+    }
+
+    #[cfg(feature = "arrow")]
+    #[test]
+    fn streamed_arrow_decodes_to_the_same_values() {
+        use arrow_array::Float64Array;
+        use arrow_ipc::reader::StreamReader;
+
+        let values = values();
+        let descriptor = ChunkDescriptor {
+            index: 0,
+            start: 0,
+            end: values.len(),
+            tile: None,
+        };
+        let encoding = ChunkEncoding::Arrow {
+            descriptor: &descriptor,
+            shape: [values.len(), 1, 1],
+        };
+        let bytes = segments(values.clone(), encoding).concat();
+
+        let batches = StreamReader::try_new(bytes.as_slice(), None)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(batches.len(), 3);
+        let decoded: Vec<f64> = batches
+            .iter()
+            .flat_map(|batch| {
+                let column = batch.column(0).as_any().downcast_ref::<Float64Array>();
+                column.unwrap().values().to_vec()
+            })
+            .collect();
+        assert_eq!(decoded.len(), values.len());
+        assert!(
+            decoded
+                .iter()
+                .zip(&values)
+                .all(|(a, b)| a.to_bits() == b.to_bits())
+        );
+    }
+}
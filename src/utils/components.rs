@@ -0,0 +1,187 @@
+/// 26 连通时扫描顺序中位于当前体素之前的 13 个邻居偏移 `[dx, dy, dz]`
+/// （6 连通只使用其中的前 3 个：-x、-y、-z 方向的面邻居）
+const PREVIOUS_NEIGHBORS: [[isize; 3]; 13] = [
+    [-1, 0, 0],
+    [0, -1, 0],
+    [0, 0, -1],
+    [-1, -1, 0],
+    [1, -1, 0],
+    [-1, 0, -1],
+    [1, 0, -1],
+    [0, -1, -1],
+    [0, 1, -1],
+    [-1, -1, -1],
+    [1, -1, -1],
+    [-1, 1, -1],
+    [1, 1, -1],
+];
+
+/// 并查集：按临时标签合并，查找时压缩路径
+struct UnionFind {
+    parent: Vec<u32>,
+}
+
+impl UnionFind {
+    fn make_set(&mut self) -> u32 {
+        let label = self.parent.len() as u32;
+        self.parent.push(label);
+        label
+    }
+
+    fn find(&mut self, mut label: u32) -> u32 {
+        let mut root = label;
+        while self.parent[root as usize] != root {
+            root = self.parent[root as usize];
+        }
+        while self.parent[label as usize] != root {
+            let next = self.parent[label as usize];
+            self.parent[label as usize] = root;
+            label = next;
+        }
+        root
+    }
+
+    /// 合并两个集合，较小的根作为新根，返回新根
+    fn union(&mut self, a: u32, b: u32) -> u32 {
+        let (a, b) = (self.find(a), self.find(b));
+        let (root, child) = if a <= b { (a, b) } else { (b, a) };
+        self.parent[child as usize] = root;
+        root
+    }
+}
+
+/// 对 `value > threshold` 的体素做连通域标记（两遍扫描 + 并查集）
+///
+/// `data` 按 x 最快、z 最慢的顺序排列；`connectivity` 为 6（共面）或 26（共面、共边、共顶点）。
+/// 返回与 `data` 等长的标签数组与连通域数量：背景（含 NaN）为 0，连通域按扫描顺序中首次出现的位置依次编号为 `1..=count`
+pub fn label_components(
+    data: &[f64],
+    shape: [usize; 3],
+    threshold: f64,
+    connectivity: u8,
+) -> (Vec<u32>, usize) {
+    assert!(
+        connectivity == 6 || connectivity == 26,
+        "connectivity 只能为 6 或 26"
+    );
+    let neighbors = if connectivity == 6 {
+        &PREVIOUS_NEIGHBORS[..3]
+    } else {
+        &PREVIOUS_NEIGHBORS[..]
+    };
+    let [nx, ny, nz] = shape;
+
+    // 第一遍：为每个前景体素分配临时标签，与已扫描的前景邻居合并
+    // 临时标签从 1 开始，0 号集合占位表示背景
+    let mut labels = vec![0u32; data.len()];
+    let mut sets = UnionFind { parent: vec![0] };
+    for z in 0..nz {
+        for y in 0..ny {
+            for x in 0..nx {
+                let index = (z * ny + y) * nx + x;
+                let value = data[index];
+                if value.is_nan() || value <= threshold {
+                    continue;
+                }
+                let mut label = 0;
+                for &[dx, dy, dz] in neighbors {
+                    let (Some(px), Some(py), Some(pz)) = (
+                        x.checked_add_signed(dx).filter(|&v| v < nx),
+                        y.checked_add_signed(dy).filter(|&v| v < ny),
+                        z.checked_add_signed(dz),
+                    ) else {
+                        continue;
+                    };
+                    let neighbor = labels[(pz * ny + py) * nx + px];
+                    if neighbor == 0 {
+                        continue;
+                    }
+                    label = if label == 0 {
+                        sets.find(neighbor)
+                    } else {
+                        sets.union(label, neighbor)
+                    };
+                }
+                labels[index] = if label == 0 { sets.make_set() } else { label };
+            }
+        }
+    }
+
+    // 第二遍：临时标签替换为根，再按首次出现的顺序压缩为连续编号
+    let mut compact = vec![0u32; sets.parent.len()];
+    let mut count = 0u32;
+    for label in labels.iter_mut().filter(|label| **label != 0) {
+        let root = sets.find(*label) as usize;
+        if compact[root] == 0 {
+            count += 1;
+            compact[root] = count;
+        }
+        *label = compact[root];
+    }
+    (labels, count as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHAPE: [usize; 3] = [4, 3, 2];
+
+    /// 手工标注的 4×3×2 网格（x 最快），阈值为 0.5
+    ///
+    /// z = 0 层有三块：左上的一对共面体素、右侧的一列、左下的孤立体素；
+    /// 0.5 恰好等于阈值、NaN 都算背景。z = 1 层的体素与 z = 0 层的右侧一列只共边
+    fn grid() -> Vec<f64> {
+        #[rustfmt::skip]
+        let data = vec![
+            // z = 0
+            1.0, 2.0, f64::NAN, 0.0,
+            0.0, 0.5, 0.0, 3.0,
+            4.0, 0.0, 0.0, 5.0,
+            // z = 1
+            0.0, 0.0, 0.0, 6.0,
+            0.0, 0.0, 0.0, 0.0,
+            0.0, 0.0, 0.0, 0.0,
+        ];
+        data
+    }
+
+    #[test]
+    fn face_connectivity_keeps_edge_neighbors_apart() {
+        let (labels, count) = label_components(&grid(), SHAPE, 0.5, 6);
+        #[rustfmt::skip]
+        let expected = vec![
+            1, 1, 0, 0,
+            0, 0, 0, 2,
+            3, 0, 0, 2,
+            0, 0, 0, 4,
+            0, 0, 0, 0,
+            0, 0, 0, 0,
+        ];
+        assert_eq!(labels, expected);
+        assert_eq!(count, 4);
+    }
+
+    #[test]
+    fn full_connectivity_joins_edge_neighbors() {
+        let (labels, count) = label_components(&grid(), SHAPE, 0.5, 26);
+        #[rustfmt::skip]
+        let expected = vec![
+            1, 1, 0, 0,
+            0, 0, 0, 2,
+            3, 0, 0, 2,
+            0, 0, 0, 2,
+            0, 0, 0, 0,
+            0, 0, 0, 0,
+        ];
+        assert_eq!(labels, expected);
+        assert_eq!(count, 3);
+    }
+
+    #[test]
+    fn threshold_above_all_values_leaves_only_background() {
+        let (labels, count) = label_components(&grid(), SHAPE, 6.0, 26);
+        assert!(labels.iter().all(|&label| label == 0));
+        assert_eq!(count, 0);
+    }
+}
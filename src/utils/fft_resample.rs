@@ -0,0 +1,108 @@
+use rustfft::FftPlanner;
+use rustfft::num_complex::Complex;
+
+/// 用 FFT 把网格从 `shape` 重采样到 `target`，返回按 x 最快、z 最慢排列的新数据
+///
+/// 三维 DFT 可分离，依次沿 x、y、z 对每一条线做一维重采样：正变换后在频域补零（上采样）或截断（下采样），
+/// 再逆变换回空间域。偶数长度的 Nyquist 频率在上采样时平分到正负两个频率，下采样时合并，结果与
+/// `scipy.signal.resample` 一致。
+///
+/// 该方法假定数据沿每个轴是周期的（周期即网格边长），对 VASP 等周期性晶胞数据是精确的带限插值；
+/// 非周期数据在边界处会因首尾不连续产生振铃。NaN / ±inf 视为 0 参与变换
+pub fn fft_resample(data: &[f64], shape: [usize; 3], target: [usize; 3]) -> Vec<f64> {
+    let mut buffer: Vec<Complex<f64>> = data
+        .iter()
+        .map(|&value| Complex::new(if value.is_finite() { value } else { 0.0 }, 0.0))
+        .collect();
+    let mut current = shape;
+    let mut planner = FftPlanner::new();
+    for axis in 0..3 {
+        if current[axis] == target[axis] {
+            continue;
+        }
+        let mut next = current;
+        next[axis] = target[axis];
+        buffer = resample_axis(&buffer, current, next, axis, &mut planner);
+        current = next;
+    }
+    buffer.into_iter().map(|value| value.re).collect()
+}
+
+/// 沿 `axis` 把每一条线从 `shape[axis]` 重采样到 `target[axis]`，其余两个轴不变
+fn resample_axis(
+    data: &[Complex<f64>],
+    shape: [usize; 3],
+    target: [usize; 3],
+    axis: usize,
+    planner: &mut FftPlanner<f64>,
+) -> Vec<Complex<f64>> {
+    let (n, m) = (shape[axis], target[axis]);
+    let forward = planner.plan_fft_forward(n);
+    let inverse = planner.plan_fft_inverse(m);
+    // rustfft 不做归一化：逆变换结果除以原长度 n，使重合采样点上的值保持不变
+    let scale = 1.0 / n as f64;
+
+    let source_stride = [1, shape[0], shape[0] * shape[1]][axis];
+    let target_stride = [1, target[0], target[0] * target[1]][axis];
+    // 其余两个轴上的所有位置各对应一条线
+    let [other_a, other_b] = match axis {
+        0 => [1, 2],
+        1 => [0, 2],
+        _ => [0, 1],
+    };
+    let line_start = |dims: [usize; 3], a: usize, b: usize| {
+        let mut coords = [0; 3];
+        coords[other_a] = a;
+        coords[other_b] = b;
+        (coords[2] * dims[1] + coords[1]) * dims[0] + coords[0]
+    };
+
+    let mut output = vec![Complex::new(0.0, 0.0); target.iter().product()];
+    let mut line = vec![Complex::new(0.0, 0.0); n];
+    let mut spectrum = vec![Complex::new(0.0, 0.0); m];
+    for b in 0..shape[other_b] {
+        for a in 0..shape[other_a] {
+            let source = line_start(shape, a, b);
+            for (position, value) in line.iter_mut().enumerate() {
+                *value = data[source + position * source_stride];
+            }
+            forward.process(&mut line);
+            resize_spectrum(&line, &mut spectrum);
+            inverse.process(&mut spectrum);
+
+            let destination = line_start(target, a, b);
+            for (position, value) in spectrum.iter().enumerate() {
+                output[destination + position * target_stride] = value * scale;
+            }
+        }
+    }
+    output
+}
+
+/// 把长度为 n 的频谱补零或截断为 `resized.len()`，保留两者共有的低频部分
+fn resize_spectrum(spectrum: &[Complex<f64>], resized: &mut [Complex<f64>]) {
+    let (n, m) = (spectrum.len(), resized.len());
+    let common = n.min(m);
+    resized.fill(Complex::new(0.0, 0.0));
+
+    // 直流分量与正频率 1..=(common-1)/2、对应的负频率
+    let half = (common - 1) / 2;
+    resized[..=half].copy_from_slice(&spectrum[..=half]);
+    for k in 1..=half {
+        resized[m - k] = spectrum[n - k];
+    }
+
+    // 共有长度为偶数时还剩下 Nyquist 频率 common/2
+    if common % 2 == 0 {
+        let nyquist = common / 2;
+        if n < m {
+            // 上采样：原 Nyquist 分量平分到新频谱的正负两个频率
+            let value = spectrum[nyquist] * 0.5;
+            resized[nyquist] = value;
+            resized[m - nyquist] = value;
+        } else {
+            // 下采样：原频谱中 ±nyquist 两个频率都折叠到新的 Nyquist 频率上
+            resized[nyquist] = spectrum[nyquist] + spectrum[n - nyquist];
+        }
+    }
+}
@@ -1,3 +1,20 @@
+pub mod analysis;
+#[cfg(feature = "arrow")]
+pub mod arrow_ipc;
+pub mod axis_order;
+pub mod chunk_encoding;
+pub mod components;
+#[cfg(feature = "fft")]
+pub mod fft_resample;
+pub mod marching_cubes;
 pub mod parser;
 pub mod parser_registry;
+pub mod png_slice;
+pub mod preview;
+pub mod rebin;
+pub mod resource_files;
+pub mod sparse;
+pub mod stats;
+pub mod value_transform;
+pub mod vdb;
 pub mod voxel_grid;
@@ -1,5 +1,38 @@
+use std::fs::File;
+use std::io::{Error, ErrorKind, Read, Seek, SeekFrom};
+
 use crate::utils::voxel_grid::VoxelGrid;
 
+/// 只包含一个网格的文件中该网格的名称，多网格文件中第一个网格通常也使用该名称
+pub const DEFAULT_GRID_NAME: &str = "total";
+
+/// 解析器内置的自检样例，健康检查据此确认解析器可用
+pub struct SelfTestFixture {
+    /// 样例文件的扩展名（写入临时文件时使用）
+    pub extension: &'static str,
+    /// 样例文件的内容
+    pub bytes: &'static [u8],
+    /// 期望解析出的 shape
+    pub shape: [usize; 3],
+}
+
+/// 追加解析的读取位置：从文件的 `offset` 字节处（一行的开头）继续读取，并跳过该行开头 `skip_values` 个已解析的值
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AppendCursor {
+    pub offset: u64,
+    pub skip_values: usize,
+}
+
+/// 文件新追加的完整 z 层
+pub struct AppendedLayers {
+    /// 新增的层数
+    pub layers: usize,
+    /// 新增层的数据（按 `VoxelGrid` 的存储顺序，长度为 `nx * ny * layers`）
+    pub values: Vec<f64>,
+    /// 下一次继续读取的位置
+    pub cursor: AppendCursor,
+}
+
 /// 体素网格解析器 trait
 /// 不同文件格式需要实现这个 trait
 pub trait VoxelGridParser: Send + Sync {
@@ -13,9 +46,42 @@ pub trait VoxelGridParser: Send + Sync {
             .any(|ext| ext.eq_ignore_ascii_case(extension))
     }
 
+    /// 根据文件开头的字节判断内容是否为本解析器的格式
+    /// 扩展名无法识别时，注册表按注册顺序询问各解析器，默认不认领
+    fn sniff(&self, _header: &[u8]) -> bool {
+        false
+    }
+
     /// 从文件路径解析体素网格数据
     fn parse_from_file(&self, file_path: &str) -> Result<VoxelGrid, Box<dyn std::error::Error>>;
 
+    /// 解析文件，并通过 `progress` 报告已读取的值数量
+    ///
+    /// 默认实现在解析完成后一次性报告；逐行读取的格式可覆盖该方法，
+    /// 在解析过程中多次报告，让客户端看到平滑的进度
+    fn parse_from_file_with_progress(
+        &self,
+        file_path: &str,
+        progress: &dyn Fn(usize),
+    ) -> Result<VoxelGrid, Box<dyn std::error::Error>> {
+        let grid = self.parse_from_file(file_path)?;
+        progress(grid.data.len());
+        Ok(grid)
+    }
+
+    /// 解析文件中的全部网格，返回 `(名称, 网格)` 列表，第一个网格与 `parse_from_file` 的结果相同
+    ///
+    /// 部分格式在一个文件中包含多个网格（如自旋极化的 VASP CHGCAR：总密度与磁化密度），
+    /// 预处理的 `grids` 选项据此只解析一次文件、为每个网格各创建一个任务。
+    /// 默认实现只返回 `parse_from_file` 的结果，名称为 `DEFAULT_GRID_NAME`
+    fn parse_grids_from_file(
+        &self,
+        file_path: &str,
+    ) -> Result<Vec<(String, VoxelGrid)>, Box<dyn std::error::Error>> {
+        let grid = self.parse_from_file(file_path)?;
+        Ok(vec![(DEFAULT_GRID_NAME.to_string(), grid)])
+    }
+
     /// 快速获取文件的 shape（只读取元数据，不解析完整数据）
     /// 用于预处理阶段快速返回基本信息
     fn get_shape_from_file(
@@ -23,6 +89,113 @@ pub trait VoxelGridParser: Send + Sync {
         file_path: &str,
     ) -> Result<[usize; 3], Box<dyn std::error::Error>>;
 
+    /// `get_shape_from_file` 是否廉价（默认 true）
+    ///
+    /// 返回 true 表示读取 shape 的代价与文件大小无关（只读取一个小的文件头，如 VASP、PLT、npy），
+    /// 预处理会先读取 shape 立即返回，再在后台完整解析。
+    /// 元数据与数据无法分开读取的格式（例如整体压缩的文件，必须解压全部内容才能得到 shape）应返回 false：
+    /// 预处理会跳过快速读取 shape，直接完整解析一次后创建任务，避免把文件读两遍；
+    /// 代价是预处理要等解析完成才返回。此时 `get_shape_from_file` 仍需正确实现（其他接口可能调用）
+    fn shape_is_cheap(&self) -> bool {
+        true
+    }
+
+    /// 读取晶胞体积（晶格矢量的混合积），用于积分等需要体素体积的计算
+    /// 文件不包含晶格信息时返回 None（默认）
+    fn get_cell_volume_from_file(
+        &self,
+        _file_path: &str,
+    ) -> Result<Option<f64>, Box<dyn std::error::Error>> {
+        Ok(None)
+    }
+
+    /// 文件是否支持按元素范围随机读取（无需解析整个文件）
+    /// 只有返回 true 的文件才能以驻留窗口模式服务
+    fn supports_range_read(&self, _file_path: &str) -> bool {
+        false
+    }
+
+    /// 读取元素范围 `[start, end)` 的数据（按 `VoxelGrid` 的存储顺序）
+    ///
+    /// 默认实现解析整个文件后截取，代价与完整解析相同；
+    /// 数据段为定长二进制的格式应覆盖该方法，直接定位到对应偏移读取
+    fn parse_chunk_from_file(
+        &self,
+        file_path: &str,
+        start: usize,
+        end: usize,
+    ) -> Result<Vec<f64>, Box<dyn std::error::Error>> {
+        read_range_by_full_parse(self, file_path, start, end)
+    }
+
+    /// 文件是否是沿 z 轴（最慢的轴）不断追加完整 xy 层的堆栈，默认 false
+    ///
+    /// 返回 true 的文件在固定为任务后，服务端会检测文件增长并通过 `read_appended_layers` 只解析新追加的部分
+    fn supports_append(&self, _file_path: &str) -> bool {
+        false
+    }
+
+    /// 读取文件中前 `parsed` 个值之后新追加的完整 z 层
+    ///
+    /// `cursor` 为上一次返回的读取位置，可以跳过已解析的部分；为 None 时从数据段开头扫描
+    /// （只计数、不解析前 `parsed` 个值）。末尾尚未写完的层不返回，留到下一次读取。
+    /// `supports_append` 为 false 的文件返回 Ok(None)（默认）
+    fn read_appended_layers(
+        &self,
+        _file_path: &str,
+        _parsed: usize,
+        _cursor: Option<AppendCursor>,
+    ) -> Result<Option<AppendedLayers>, Box<dyn std::error::Error>> {
+        Ok(None)
+    }
+
+    /// 健康检查使用的内置自检样例（默认没有）
+    ///
+    /// 提供样例的解析器会被定期用该样例完整解析一次，结果显示在根路径的 `parsers` 中；
+    /// 样例应足够小，让检查的代价可以忽略
+    fn self_test_fixture(&self) -> Option<SelfTestFixture> {
+        None
+    }
+
     /// 获取解析器名称（用于日志和错误信息）
     fn name(&self) -> &'static str;
 }
+
+/// 从文件的 `offset` 处读取 `count` 个定长元素，`decode` 负责把单个元素的字节转换为 f64
+/// 供数据段为定长二进制的解析器实现 `parse_chunk_from_file`
+pub fn read_values_at(
+    file_path: &str,
+    offset: u64,
+    count: usize,
+    element_size: usize,
+    decode: impl Fn(&[u8]) -> f64,
+) -> Result<Vec<f64>, Error> {
+    let mut file = File::open(file_path)?;
+    file.seek(SeekFrom::Start(offset))?;
+    let mut bytes = vec![0u8; count * element_size];
+    file.read_exact(&mut bytes).map_err(|e| match e.kind() {
+        ErrorKind::UnexpectedEof => Error::new(
+            ErrorKind::InvalidData,
+            format!("数据被截断: 偏移 {offset} 处无法读取 {count} 个值"),
+        ),
+        _ => e,
+    })?;
+    Ok(bytes.chunks_exact(element_size).map(decode).collect())
+}
+
+/// 解析整个文件后截取元素范围 `[start, end)`，是 `parse_chunk_from_file` 的通用实现
+pub fn read_range_by_full_parse<P: VoxelGridParser + ?Sized>(
+    parser: &P,
+    file_path: &str,
+    start: usize,
+    end: usize,
+) -> Result<Vec<f64>, Box<dyn std::error::Error>> {
+    let grid = parser.parse_from_file(file_path)?;
+    match grid.data.get(start..end) {
+        Some(values) => Ok(values.to_vec()),
+        None => Err(Box::new(Error::new(
+            ErrorKind::InvalidInput,
+            format!("元素范围 {start}..{end} 超出数据长度 {}", grid.data.len()),
+        ))),
+    }
+}
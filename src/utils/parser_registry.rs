@@ -1,5 +1,11 @@
+use std::fs::File;
+use std::io::Read;
+
 use crate::utils::parser::VoxelGridParser;
 
+/// 按内容识别格式时读取的文件头部字节数
+const SNIFF_HEADER_BYTES: u64 = 4096;
+
 /// 解析器注册表
 /// 管理所有可用的体素网格解析器，并根据文件扩展名匹配对应的解析器
 pub struct ParserRegistry {
@@ -9,10 +15,24 @@ pub struct ParserRegistry {
 impl ParserRegistry {
     /// 创建新的解析器注册表，自动注册所有可用的解析器
     pub fn new() -> Self {
-        let parsers = crate::parsers::get_all_parsers();
+        Self::with_parsers(crate::parsers::get_all_parsers())
+    }
+
+    /// 按配置创建注册表（解析器的读取缓冲区大小等）
+    pub fn from_config(config: &crate::config::Config) -> Self {
+        Self::with_parsers(crate::parsers::get_configured_parsers(config))
+    }
+
+    /// 使用指定的解析器列表创建注册表（例如测试中只注册 mock 解析器）
+    pub fn with_parsers(parsers: Vec<Box<dyn VoxelGridParser>>) -> Self {
         Self { parsers }
     }
 
+    /// 按注册顺序遍历所有解析器
+    pub fn parsers(&self) -> impl Iterator<Item = &dyn VoxelGridParser> {
+        self.parsers.iter().map(|p| p.as_ref())
+    }
+
     /// 根据文件扩展名查找匹配的解析器
     /// extension: 文件扩展名（不含点号），例如 "vasp"
     pub fn find_parser(&self, extension: &str) -> Option<&dyn VoxelGridParser> {
@@ -23,16 +43,36 @@ impl ParserRegistry {
     }
 
     /// 根据文件路径查找匹配的解析器
-    /// 自动提取文件扩展名
+    /// 自动提取文件扩展名；扩展名缺失或无法识别时按文件内容识别，
+    /// 此时返回的扩展名为识别出的解析器的首个扩展名
     pub fn find_parser_for_file(&self, file_path: &str) -> Option<(&dyn VoxelGridParser, String)> {
         // 提取文件扩展名
         let extension = std::path::Path::new(file_path)
             .extension()
-            .and_then(|ext| ext.to_str())?
-            .to_string();
+            .and_then(|ext| ext.to_str());
 
-        self.find_parser(&extension)
-            .map(|parser| (parser, extension))
+        if let Some(parser) = extension.and_then(|ext| self.find_parser(ext)) {
+            return Some((parser, extension?.to_string()));
+        }
+
+        let parser = self.sniff_parser(file_path)?;
+        let extension = parser.supported_extensions().first()?.to_string();
+        Some((parser, extension))
+    }
+
+    /// 读取文件头部，返回第一个认领该内容的解析器
+    fn sniff_parser(&self, file_path: &str) -> Option<&dyn VoxelGridParser> {
+        let mut header = Vec::new();
+        File::open(file_path)
+            .ok()?
+            .take(SNIFF_HEADER_BYTES)
+            .read_to_end(&mut header)
+            .ok()?;
+
+        self.parsers
+            .iter()
+            .find(|parser| parser.sniff(&header))
+            .map(|p| p.as_ref())
     }
 
     /// 获取所有支持的扩展名列表
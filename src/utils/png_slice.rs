@@ -0,0 +1,175 @@
+use image::codecs::png::PngEncoder;
+use image::{ExtendedColorType, ImageEncoder};
+use serde::Deserialize;
+
+/// 切片的法向轴
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SliceAxis {
+    X,
+    Y,
+    #[default]
+    Z,
+}
+
+impl SliceAxis {
+    /// 轴在 shape 中的下标
+    pub fn number(self) -> usize {
+        match self {
+            SliceAxis::X => 0,
+            SliceAxis::Y => 1,
+            SliceAxis::Z => 2,
+        }
+    }
+
+    /// 轴名（x / y / z），用于响应头与错误信息
+    pub fn label(self) -> &'static str {
+        match self {
+            SliceAxis::X => "x",
+            SliceAxis::Y => "y",
+            SliceAxis::Z => "z",
+        }
+    }
+
+    /// 切片图像的（宽，高）：z 切片为 (nx, ny)，y 切片为 (nx, nz)，x 切片为 (ny, nz)
+    pub fn plane_dims(self, shape: [usize; 3]) -> (usize, usize) {
+        let [nx, ny, nz] = shape;
+        match self {
+            SliceAxis::X => (ny, nz),
+            SliceAxis::Y => (nx, nz),
+            SliceAxis::Z => (nx, ny),
+        }
+    }
+
+    /// 按图像像素顺序（逐行，从左上角开始）给出切片中每个像素对应体素的线性索引
+    ///
+    /// 图像横轴为切片平面的第一个轴，纵轴为第二个轴且向上递增（原点在左下角）
+    pub fn voxel_indices(self, shape: [usize; 3], index: usize) -> impl Iterator<Item = usize> {
+        let [nx, ny, _] = shape;
+        let (width, height) = self.plane_dims(shape);
+        (0..height).rev().flat_map(move |v| {
+            (0..width).map(move |u| match self {
+                SliceAxis::X => v * nx * ny + u * nx + index,
+                SliceAxis::Y => v * nx * ny + index * nx + u,
+                SliceAxis::Z => index * nx * ny + v * nx + u,
+            })
+        })
+    }
+}
+
+/// 切片着色使用的颜色映射
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Colormap {
+    Grayscale,
+    #[default]
+    Viridis,
+    Magma,
+    Inferno,
+}
+
+/// matplotlib 颜色映射在 t = 0, 0.1, ..., 1 处的取样，中间线性插值
+const VIRIDIS: [[u8; 3]; 11] = [
+    [0x44, 0x01, 0x54],
+    [0x48, 0x24, 0x75],
+    [0x41, 0x44, 0x87],
+    [0x35, 0x5f, 0x8d],
+    [0x2a, 0x78, 0x8e],
+    [0x21, 0x91, 0x8c],
+    [0x22, 0xa8, 0x84],
+    [0x44, 0xbf, 0x70],
+    [0x7a, 0xd1, 0x51],
+    [0xbd, 0xdf, 0x26],
+    [0xfd, 0xe7, 0x25],
+];
+
+const MAGMA: [[u8; 3]; 11] = [
+    [0x00, 0x00, 0x04],
+    [0x14, 0x0e, 0x36],
+    [0x3b, 0x0f, 0x70],
+    [0x64, 0x1a, 0x80],
+    [0x8c, 0x29, 0x81],
+    [0xb7, 0x37, 0x79],
+    [0xde, 0x49, 0x68],
+    [0xf7, 0x70, 0x5c],
+    [0xfe, 0x9f, 0x6d],
+    [0xfe, 0xcf, 0x92],
+    [0xfc, 0xfd, 0xbf],
+];
+
+const INFERNO: [[u8; 3]; 11] = [
+    [0x00, 0x00, 0x04],
+    [0x16, 0x0b, 0x39],
+    [0x42, 0x0a, 0x68],
+    [0x6a, 0x17, 0x6e],
+    [0x93, 0x26, 0x67],
+    [0xbc, 0x37, 0x54],
+    [0xdd, 0x51, 0x3a],
+    [0xf3, 0x78, 0x19],
+    [0xfc, 0xa5, 0x0a],
+    [0xf6, 0xd7, 0x46],
+    [0xfc, 0xff, 0xa4],
+];
+
+impl Colormap {
+    /// 把 `[0, 1]` 内的归一化值映射为 RGB 颜色（超出范围的值截断到两端）
+    pub fn rgb(self, t: f64) -> [u8; 3] {
+        let t = t.clamp(0.0, 1.0);
+        let table = match self {
+            Colormap::Grayscale => {
+                let gray = (t * 255.0).round() as u8;
+                return [gray; 3];
+            }
+            Colormap::Viridis => &VIRIDIS,
+            Colormap::Magma => &MAGMA,
+            Colormap::Inferno => &INFERNO,
+        };
+        let position = t * (table.len() - 1) as f64;
+        let lower = (position.floor() as usize).min(table.len() - 2);
+        let fraction = position - lower as f64;
+        [0, 1, 2].map(|channel| {
+            let a = table[lower][channel] as f64;
+            let b = table[lower + 1][channel] as f64;
+            (a + (b - a) * fraction).round() as u8
+        })
+    }
+}
+
+/// 把 `width * height` 个值（逐行，从左上角开始）按 `[min, max]` 归一化、着色后编码为 RGBA PNG
+///
+/// 非有限值（NaN / ±inf）渲染为透明像素；`min == max` 时所有有效像素取颜色映射的起点
+pub fn render_png(
+    values: &[f64],
+    width: usize,
+    height: usize,
+    (min, max): (f64, f64),
+    colormap: Colormap,
+) -> Result<Vec<u8>, String> {
+    if values.len() != width * height {
+        return Err(format!(
+            "切片数据量不匹配: {}x{} 需要 {} 个值，但提供了 {} 个",
+            width,
+            height,
+            width * height,
+            values.len()
+        ));
+    }
+
+    let span = max - min;
+    let mut pixels = Vec::with_capacity(values.len() * 4);
+    for value in values {
+        if !value.is_finite() {
+            pixels.extend_from_slice(&[0, 0, 0, 0]);
+            continue;
+        }
+        let t = if span > 0.0 { (value - min) / span } else { 0.0 };
+        pixels.extend_from_slice(&colormap.rgb(t));
+        pixels.push(u8::MAX);
+    }
+
+    let mut png = Vec::new();
+    PngEncoder::new(&mut png)
+        .write_image(&pixels, width as u32, height as u32, ExtendedColorType::Rgba8)
+        .map_err(|e| format!("PNG 编码失败: {e}"))?;
+    Ok(png)
+}
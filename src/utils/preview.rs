@@ -0,0 +1,83 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use serde::Serialize;
+
+/// 预览允许的最大分辨率（每个轴），64³ 的 f32 约 1 MB，base64 后约 1.4 MB
+pub const MAX_PREVIEW_RESOLUTION: usize = 64;
+
+/// 低分辨率预览：按固定步长对原网格逐点采样
+#[derive(Serialize, Clone, Debug)]
+pub struct Preview {
+    /// 预览网格维度 [nx, ny, nz]，每个轴不超过请求的分辨率
+    pub shape: [usize; 3],
+    /// 各轴的采样步长：预览体素 (i, j, k) 对应原网格 (i*sx, j*sy, k*sz)
+    pub stride: [usize; 3],
+    /// 数据编码，固定为 `base64-f32le`
+    pub encoding: &'static str,
+    /// 小端序 Float32Array 的 base64 编码，与 `VoxelGrid` 相同的 x 最快顺序
+    pub data: String,
+}
+
+/// 采样计划：预览的 shape、步长以及原网格中需要读取的位置
+pub struct PreviewPlan {
+    pub shape: [usize; 3],
+    pub stride: [usize; 3],
+    grid_shape: [usize; 3],
+}
+
+impl PreviewPlan {
+    /// 为 `grid_shape` 计算每个轴不超过 `resolution` 个采样点的计划
+    pub fn new(grid_shape: [usize; 3], resolution: usize) -> Self {
+        let resolution = resolution.max(1);
+        let stride = grid_shape.map(|n| n.div_ceil(resolution).max(1));
+        let shape = [0, 1, 2].map(|axis| grid_shape[axis].div_ceil(stride[axis]));
+        Self {
+            shape,
+            stride,
+            grid_shape,
+        }
+    }
+
+    /// 按预览的存储顺序（x 最快）依次给出每一行采样点：
+    /// 原网格中该行的起始线性索引，以及行内需要的 x 坐标
+    pub fn rows(&self) -> impl Iterator<Item = (usize, impl Iterator<Item = usize>)> + '_ {
+        let [nx, ny, _] = self.grid_shape;
+        let [sx, sy, sz] = self.stride;
+        let [px, py, pz] = self.shape;
+        (0..pz).flat_map(move |k| {
+            (0..py).map(move |j| {
+                let row_start = (k * sz) * nx * ny + (j * sy) * nx;
+                (row_start, (0..px).map(move |i| i * sx))
+            })
+        })
+    }
+
+    /// 原网格中每行需要读取的元素个数（到最后一个采样点为止）
+    pub fn row_span(&self) -> usize {
+        (self.shape[0] - 1) * self.stride[0] + 1
+    }
+
+    /// 采样点数量
+    pub fn len(&self) -> usize {
+        self.shape.iter().product()
+    }
+
+    /// 采样点是否为空（原网格某个轴长度为 0）
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 把按计划顺序采样到的值编码为预览
+    pub fn encode(&self, values: &[f64]) -> Preview {
+        let mut bytes = Vec::with_capacity(values.len() * std::mem::size_of::<f32>());
+        for value in values {
+            bytes.extend_from_slice(&(*value as f32).to_le_bytes());
+        }
+        Preview {
+            shape: self.shape,
+            stride: self.stride,
+            encoding: "base64-f32le",
+            data: STANDARD.encode(bytes),
+        }
+    }
+}
@@ -0,0 +1,108 @@
+/// 守恒重分箱：把网格从 `shape` 重新划分到 `target`，返回按 x 最快、z 最慢排列的新数据
+///
+/// 源网格与目标网格覆盖同一区域，每个源体素的值按与各目标体素的重叠体积占自身体积的比例分配到这些目标体素，
+/// 因此所有值之和保持不变（对电荷密度等守恒量，不同于取平均或跨步采样）。重叠体积可分离为三个轴上重叠长度的乘积，
+/// 依次沿 x、y、z 做一维重分箱即可。NaN / ±inf 视为 0
+pub fn rebin_conservative(data: &[f64], shape: [usize; 3], target: [usize; 3]) -> Vec<f64> {
+    let mut buffer: Vec<f64> = data
+        .iter()
+        .map(|&value| if value.is_finite() { value } else { 0.0 })
+        .collect();
+    let mut current = shape;
+    for axis in 0..3 {
+        if current[axis] == target[axis] {
+            continue;
+        }
+        let mut next = current;
+        next[axis] = target[axis];
+        buffer = rebin_axis(&buffer, current, next, axis);
+        current = next;
+    }
+    buffer
+}
+
+/// 沿 `axis` 把每一条线从 `shape[axis]` 个体素重分箱到 `target[axis]` 个，其余两个轴不变
+fn rebin_axis(data: &[f64], shape: [usize; 3], target: [usize; 3], axis: usize) -> Vec<f64> {
+    let weights = axis_weights(shape[axis], target[axis]);
+    let mut output = vec![0.0; target.iter().product()];
+    let mut index = 0;
+    for z in 0..shape[2] {
+        for y in 0..shape[1] {
+            for x in 0..shape[0] {
+                let value = data[index];
+                index += 1;
+                if value == 0.0 {
+                    continue;
+                }
+                let mut coords = [x, y, z];
+                for &(bin, fraction) in &weights[coords[axis]] {
+                    coords[axis] = bin;
+                    let [tx, ty, tz] = coords;
+                    output[(tz * target[1] + ty) * target[0] + tx] += value * fraction;
+                }
+            }
+        }
+    }
+    output
+}
+
+/// 一维重分箱的分配比例：第 i 个源体素分配到的 `(目标体素, 比例)`，每个源体素的比例之和为 1
+///
+/// 把区间长度放大到 `n * m`，源体素 i 覆盖 `[i*m, (i+1)*m)`，目标体素 j 覆盖 `[j*n, (j+1)*n)`，
+/// 重叠长度为整数，比例没有累积误差
+fn axis_weights(n: usize, m: usize) -> Vec<Vec<(usize, f64)>> {
+    (0..n)
+        .map(|i| {
+            let (start, end) = (i * m, (i + 1) * m);
+            (start / n..end.div_ceil(n))
+                .filter_map(|j| {
+                    let overlap = end.min((j + 1) * n).saturating_sub(start.max(j * n));
+                    (overlap > 0).then(|| (j, overlap as f64 / m as f64))
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn downsample_preserves_the_sum() {
+        // 非整数倍的降采样：源体素跨越目标体素边界，被拆分到相邻的目标体素中
+        let shape = [5, 7, 3];
+        let data: Vec<f64> = (0..105)
+            .map(|i| (f64::from(i) * 0.37).sin() + 1.5)
+            .collect();
+        let target = [2, 3, 2];
+        let rebinned = rebin_conservative(&data, shape, target);
+        assert_eq!(rebinned.len(), 12);
+        let (before, after): (f64, f64) = (data.iter().sum(), rebinned.iter().sum());
+        assert!(
+            (before - after).abs() < 1e-9 * before.abs(),
+            "{before} != {after}"
+        );
+    }
+
+    #[test]
+    fn integer_factor_sums_each_block() {
+        let rebinned = rebin_conservative(&[1.0; 64], [4, 4, 4], [2, 2, 2]);
+        assert_eq!(rebinned, vec![8.0; 8]);
+    }
+
+    #[test]
+    fn upsample_splits_values_by_overlap() {
+        let rebinned = rebin_conservative(&[2.0, 6.0], [2, 1, 1], [4, 1, 1]);
+        assert_eq!(rebinned, vec![1.0, 1.0, 3.0, 3.0]);
+        // 3 → 2：中间的源体素平分到两个目标体素
+        let rebinned = rebin_conservative(&[3.0, 6.0, 9.0], [3, 1, 1], [2, 1, 1]);
+        assert_eq!(rebinned, vec![6.0, 12.0]);
+    }
+
+    #[test]
+    fn non_finite_values_count_as_zero() {
+        let data = [1.0, f64::NAN, f64::INFINITY, 2.0];
+        assert_eq!(rebin_conservative(&data, [4, 1, 1], [1, 1, 1]), vec![3.0]);
+    }
+}
@@ -0,0 +1,104 @@
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use serde::Serialize;
+
+/// 列出资源文件时最多进入的子目录层数
+pub const MAX_LIST_DEPTH: usize = 16;
+
+/// 一次最多列出的文件数量，超出时截断
+pub const MAX_LISTED_FILES: usize = 10_000;
+
+/// 校验客户端传入的资源文件路径，返回相对于资源目录的路径
+///
+/// 允许子目录（如 `project_a/CHGCAR.vasp`），但每个部分都必须是普通的文件名：
+/// 拒绝绝对路径、`..`、`.`、空的部分（`a//b`）与反斜杠，保证结果不会离开资源目录
+pub fn sanitize_relative_path(file: &str) -> Result<PathBuf, String> {
+    if file.is_empty() {
+        return Err("文件路径不能为空".to_string());
+    }
+    if file.contains('\\') || file.contains('\0') {
+        return Err("文件路径不能包含反斜杠或空字符".to_string());
+    }
+    if file
+        .split('/')
+        .any(|part| part.is_empty() || part == "." || part == "..")
+    {
+        return Err(
+            "文件路径必须是资源目录下的相对路径，不能包含 `.`、`..` 或空的部分".to_string(),
+        );
+    }
+    let path = PathBuf::from(file);
+    if !path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return Err("文件路径必须是资源目录下的相对路径".to_string());
+    }
+    Ok(path)
+}
+
+/// 资源目录中的一个文件
+#[derive(Debug, Clone, Serialize)]
+pub struct ResourceFile {
+    /// 相对于资源目录的路径（以 `/` 分隔），可直接作为预处理接口的 `file` 参数
+    pub path: String,
+    /// 文件大小（字节）
+    pub size: u64,
+}
+
+/// 递归列出 `root` 下的所有文件，按路径排序
+///
+/// 跳过以 `.` 开头的隐藏文件与目录；不跟随指向目录的符号链接，避免循环。
+/// 超过 `MAX_LIST_DEPTH` 层的子目录不再进入；返回的布尔值表示结果是否因超过 `MAX_LISTED_FILES` 而被截断
+pub fn list_files(root: &Path) -> std::io::Result<(Vec<ResourceFile>, bool)> {
+    let mut files = Vec::new();
+    let truncated = collect_files(root, "", 0, &mut files)?;
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok((files, truncated))
+}
+
+/// 把 `dir`（相对路径为 `prefix`）下的文件追加到 `files`，返回是否已达到数量上限
+fn collect_files(
+    dir: &Path,
+    prefix: &str,
+    depth: usize,
+    files: &mut Vec<ResourceFile>,
+) -> std::io::Result<bool> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+            // 非 UTF-8 的文件名无法通过 `file` 参数引用
+            continue;
+        };
+        if name.starts_with('.') {
+            continue;
+        }
+        let path = format!("{prefix}{name}");
+
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            if depth < MAX_LIST_DEPTH
+                && collect_files(&entry.path(), &format!("{path}/"), depth + 1, files)?
+            {
+                return Ok(true);
+            }
+            continue;
+        }
+        // 符号链接只在指向普通文件时列出
+        let Ok(metadata) = fs::metadata(entry.path()) else {
+            continue;
+        };
+        if !metadata.is_file() {
+            continue;
+        }
+        if files.len() >= MAX_LISTED_FILES {
+            return Ok(true);
+        }
+        files.push(ResourceFile {
+            path,
+            size: metadata.len(),
+        });
+    }
+    Ok(false)
+}
@@ -0,0 +1,57 @@
+use crate::utils::voxel_grid::VoxelGrid;
+
+/// 稀疏体素网格：只保存绝对值大于阈值的体素的坐标与取值，其余体素视为 0
+///
+/// 适用于差分密度等大部分体素接近 0 的网格。体素按网格的线性顺序（x 最快、z 最慢）排列
+pub struct SparseVoxelGrid {
+    /// 原网格的维度 [nx, ny, nz]
+    pub shape: [usize; 3],
+    /// 生成时使用的阈值：绝对值大于该值的体素被保留
+    pub threshold: f64,
+    /// 保留体素的坐标 `[x, y, z]`，与 `values` 一一对应
+    pub coords: Vec<[u32; 3]>,
+    pub values: Vec<f64>,
+}
+
+impl SparseVoxelGrid {
+    /// 从稠密网格中取出绝对值大于 `threshold` 的体素，NaN 不保留
+    ///
+    /// 坐标以 u32 存储，调用方需保证每个维度都不超过 `u32::MAX`
+    pub fn from_dense(grid: &VoxelGrid, threshold: f64) -> Self {
+        let [nx, ny, _] = grid.shape;
+        let mut coords = Vec::new();
+        let mut values = Vec::new();
+        for (index, &value) in grid.data.iter().enumerate() {
+            if value.is_nan() || value.abs() <= threshold {
+                continue;
+            }
+            coords.push([index % nx, (index / nx) % ny, index / (nx * ny)].map(|c| c as u32));
+            values.push(value);
+        }
+        Self {
+            shape: grid.shape,
+            threshold,
+            coords,
+            values,
+        }
+    }
+
+    /// 保留的体素数量
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// 占用率：保留的体素数占全部体素的比例（0–1），空网格为 0
+    pub fn occupancy(&self) -> f64 {
+        let total: usize = self.shape.iter().product();
+        if total == 0 {
+            0.0
+        } else {
+            self.len() as f64 / total as f64
+        }
+    }
+}
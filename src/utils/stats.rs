@@ -0,0 +1,100 @@
+use serde::Serialize;
+
+/// 一组数值的基础统计量
+/// 非有限值（NaN / ±inf）不参与统计；没有任何有效值时 min/max/mean 为 None
+#[derive(Debug, Clone, Copy, Serialize, PartialEq)]
+pub struct GridStats {
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub mean: Option<f64>,
+    /// 参与统计的有效值数量
+    pub count: usize,
+}
+
+impl GridStats {
+    /// 对任意数值序列计算统计量（单次遍历）
+    pub fn from_values<I: IntoIterator<Item = f64>>(values: I) -> Self {
+        let mut min = f64::INFINITY;
+        let mut max = f64::NEG_INFINITY;
+        let mut sum = 0.0;
+        let mut count = 0usize;
+        for value in values {
+            if !value.is_finite() {
+                continue;
+            }
+            min = min.min(value);
+            max = max.max(value);
+            sum += value;
+            count += 1;
+        }
+
+        if count == 0 {
+            return Self {
+                min: None,
+                max: None,
+                mean: None,
+                count: 0,
+            };
+        }
+        Self {
+            min: Some(min),
+            max: Some(max),
+            mean: Some(sum / count as f64),
+            count,
+        }
+    }
+}
+
+/// 未指定 bin 数时直方图的 bin 数（预计算的直方图也使用该值）
+pub const DEFAULT_HISTOGRAM_BINS: usize = 64;
+
+/// 直方图 bin 数的上限
+pub const MAX_HISTOGRAM_BINS: usize = 4096;
+
+/// 有限值的等宽直方图，覆盖 `[min, max]`，最后一个 bin 包含 max
+/// 非有限值不计入；没有任何有效值时 min/max/bin_width 为 None，counts 全为 0
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Histogram {
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    /// 每个 bin 的宽度；所有有效值都相同时为 0，此时全部计入第一个 bin
+    pub bin_width: Option<f64>,
+    pub counts: Vec<usize>,
+    /// 参与统计的有效值数量
+    pub count: usize,
+}
+
+impl Histogram {
+    /// 按数据的取值范围划分 `bins` 个等宽 bin（两次遍历：先求范围，再计数），bins 为 0 时按 1 处理
+    pub fn from_values(values: &[f64], bins: usize) -> Self {
+        let bins = bins.max(1);
+        let mut counts = vec![0; bins];
+        let stats = GridStats::from_values(values.iter().copied());
+        let (Some(min), Some(max)) = (stats.min, stats.max) else {
+            return Self {
+                min: None,
+                max: None,
+                bin_width: None,
+                counts,
+                count: 0,
+            };
+        };
+
+        let bin_width = (max - min) / bins as f64;
+        for &value in values.iter().filter(|value| value.is_finite()) {
+            let bin = if bin_width > 0.0 {
+                (((value - min) / bin_width) as usize).min(bins - 1)
+            } else {
+                0
+            };
+            counts[bin] += 1;
+        }
+        Self {
+            min: Some(min),
+            max: Some(max),
+            bin_width: Some(bin_width),
+            counts,
+            count: stats.count,
+        }
+    }
+}
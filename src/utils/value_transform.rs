@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+
+/// 解析后逐元素应用的数值变换
+///
+/// 定义域之外的输入（例如 `log` 的非正数、`sqrt` 的负数）得到 NaN，
+/// 而不是 ±inf 或被静默截断；统计接口会跳过这些 NaN
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ValueTransform {
+    /// 不做变换（默认）
+    #[default]
+    None,
+    /// 绝对值
+    Abs,
+    /// 自然对数，要求 x > 0
+    Log,
+    /// ln(1 + x)，要求 x > -1；对接近 0 的值比 `log` 更精确
+    Log1p,
+    /// 平方根，要求 x >= 0
+    Sqrt,
+}
+
+impl ValueTransform {
+    /// 对单个值应用变换
+    pub fn apply(self, value: f64) -> f64 {
+        let result = match self {
+            ValueTransform::None => return value,
+            ValueTransform::Abs => value.abs(),
+            ValueTransform::Log => value.ln(),
+            ValueTransform::Log1p => value.ln_1p(),
+            ValueTransform::Sqrt => value.sqrt(),
+        };
+        // ln(0) 与 ln_1p(-1) 得到 -inf，同样视为超出定义域
+        if result == f64::NEG_INFINITY && value.is_finite() {
+            f64::NAN
+        } else {
+            result
+        }
+    }
+
+    pub fn is_none(&self) -> bool {
+        *self == ValueTransform::None
+    }
+}
@@ -0,0 +1,279 @@
+use std::collections::BTreeMap;
+
+use uuid::Uuid;
+
+/// 文件格式版本：224 起 per-grid 压缩标志、节点掩码压缩元数据与 grid 实例化均已引入
+const FILE_VERSION: u32 = 224;
+/// 写入头部的库版本号（仅作记录，读取端不据此判断格式）
+const LIBRARY_VERSION: (u32, u32) = (9, 1);
+/// 文件头魔数 `" BDV"`，按 int64 写入
+const MAGIC: i64 = 0x5644_4220;
+/// `writeCompressedValues` 的元数据标志：没有选择掩码，写出全部值
+const NO_MASK_AND_ALL_VALS: u8 = 6;
+/// 网格树结构：根节点 → 32³ 内部节点 → 16³ 内部节点 → 8³ 叶节点
+const GRID_TYPE: &str = "Tree_float_5_4_3";
+
+/// 各层节点的边长 log2（叶节点 3，两层内部节点分别为 4 与 5）
+const LEAF_LOG2: u32 = 3;
+const LOWER_LOG2: u32 = 4;
+const UPPER_LOG2: u32 = 5;
+/// 各层节点覆盖的体素边长 log2
+const LEAF_TOTAL: u32 = LEAF_LOG2;
+const LOWER_TOTAL: u32 = LOWER_LOG2 + LEAF_TOTAL;
+const UPPER_TOTAL: u32 = UPPER_LOG2 + LOWER_TOTAL;
+
+const LEAF_VALUES: usize = 1 << (3 * LEAF_LOG2);
+
+/// 写入的 VDB 文件的概况
+#[derive(Debug, Clone, Copy)]
+pub struct VdbSummary {
+    /// 叶节点（8³ 体素块）数量
+    pub leaf_count: usize,
+    /// 活跃体素数量
+    pub active_voxels: usize,
+}
+
+/// 8³ 叶节点：值按 OpenVDB 的顺序排列（z 最快、x 最慢）
+struct Leaf {
+    values: Vec<f32>,
+    mask: [u64; LEAF_VALUES / 64],
+}
+
+/// 内部节点：子节点按节点内偏移排序，写出顺序与 OpenVDB 遍历子节点掩码的顺序一致
+type LowerNode = BTreeMap<usize, Leaf>;
+type UpperNode = BTreeMap<usize, LowerNode>;
+
+/// 把网格写成只含一个 FloatGrid 的 OpenVDB 文件（不压缩）
+///
+/// `data` 按 x 最快、z 最慢的顺序排列，体素 `(i, j, k)` 对应 VDB 索引坐标 `(i, j, k)`，变换为单位矩阵。
+/// `threshold` 为 None 时稠密写出：网格内所有体素都是活跃的。
+/// 为 Some 时稀疏写出：只保留 `|v| > threshold` 的体素为活跃，不含活跃体素的 8³ 块不写入，
+/// `bounds`（各轴 `[min, max)`，通常为 `VoxelGrid::nonzero_bounds` 的结果）之外的区域直接跳过。
+/// 未写入的区域取背景值 0
+pub fn write_vdb(
+    data: &[f64],
+    shape: [usize; 3],
+    name: &str,
+    threshold: Option<f64>,
+    bounds: Option<([usize; 3], [usize; 3])>,
+) -> (Vec<u8>, VdbSummary) {
+    let (min, max) = bounds.unwrap_or(([0; 3], shape));
+    let is_active = |value: f64| match threshold {
+        None => true,
+        Some(threshold) => !value.is_nan() && value.abs() > threshold,
+    };
+
+    // 遍历与范围相交的叶节点，按根节点 → 上层 → 下层的路径插入树中
+    let mut roots: BTreeMap<[i32; 3], UpperNode> = BTreeMap::new();
+    let mut summary = VdbSummary {
+        leaf_count: 0,
+        active_voxels: 0,
+    };
+    let leaf_dim = 1usize << LEAF_LOG2;
+    let leaf_starts = |axis: usize| (min[axis] / leaf_dim * leaf_dim..max[axis]).step_by(leaf_dim);
+    let [nx, ny, _] = shape;
+    for ox in leaf_starts(0) {
+        for oy in leaf_starts(1) {
+            for oz in leaf_starts(2) {
+                let mut leaf = Leaf {
+                    values: vec![0.0; LEAF_VALUES],
+                    mask: [0; LEAF_VALUES / 64],
+                };
+                let mut active = 0;
+                for lx in 0..leaf_dim {
+                    for ly in 0..leaf_dim {
+                        for lz in 0..leaf_dim {
+                            let (x, y, z) = (ox + lx, oy + ly, oz + lz);
+                            if x >= shape[0] || y >= shape[1] || z >= shape[2] {
+                                continue;
+                            }
+                            let value = data[z * nx * ny + y * nx + x];
+                            let offset = (lx << (2 * LEAF_LOG2)) | (ly << LEAF_LOG2) | lz;
+                            leaf.values[offset] = value as f32;
+                            if is_active(value) {
+                                leaf.mask[offset >> 6] |= 1 << (offset & 63);
+                                active += 1;
+                            }
+                        }
+                    }
+                }
+                // 稀疏模式下没有活跃体素的块不写入
+                if threshold.is_some() && active == 0 {
+                    continue;
+                }
+                summary.leaf_count += 1;
+                summary.active_voxels += active;
+
+                let origin = [ox, oy, oz];
+                let root_key = origin.map(|c| ((c >> UPPER_TOTAL) << UPPER_TOTAL) as i32);
+                roots
+                    .entry(root_key)
+                    .or_default()
+                    .entry(child_offset(origin, UPPER_LOG2, LOWER_TOTAL))
+                    .or_default()
+                    .insert(child_offset(origin, LOWER_LOG2, LEAF_TOTAL), leaf);
+            }
+        }
+    }
+
+    let mut out = Vec::new();
+    write_file_header(&mut out);
+    write_meta_map(
+        &mut out,
+        &[("creator", MetaValue::String("demos-3d-backend"))],
+    );
+    // grid 数量
+    out.extend_from_slice(&1i32.to_le_bytes());
+
+    // grid 描述：名称、类型、实例父 grid（空），随后是三个流位置（grid、数据块、结束），写完后回填
+    write_string(&mut out, name);
+    write_string(&mut out, GRID_TYPE);
+    write_string(&mut out, "");
+    let positions_at = out.len();
+    out.extend_from_slice(&[0; 3 * 8]);
+    let grid_pos = out.len();
+
+    // 压缩标志：不压缩
+    out.extend_from_slice(&0u32.to_le_bytes());
+    let bbox_max = shape.map(|n| n.saturating_sub(1) as i32);
+    write_meta_map(
+        &mut out,
+        &[
+            ("class", MetaValue::String("unknown")),
+            ("name", MetaValue::String(name)),
+            ("file_bbox_min", MetaValue::Vec3i([0; 3])),
+            ("file_bbox_max", MetaValue::Vec3i(bbox_max)),
+            (
+                "file_voxel_count",
+                MetaValue::Int64(summary.active_voxels as i64),
+            ),
+        ],
+    );
+    write_identity_transform(&mut out);
+    write_topology(&mut out, &roots);
+    let block_pos = out.len();
+    write_buffers(&mut out, &roots);
+    let end_pos = out.len();
+
+    for (slot, position) in [grid_pos, block_pos, end_pos].into_iter().enumerate() {
+        let at = positions_at + slot * 8;
+        out[at..at + 8].copy_from_slice(&(position as i64).to_le_bytes());
+    }
+    (out, summary)
+}
+
+/// 子节点在父节点中的线性偏移（x 最慢、z 最快）
+fn child_offset(origin: [usize; 3], log2: u32, child_total: u32) -> usize {
+    let mask = (1usize << (log2 + child_total)) - 1;
+    let [x, y, z] = origin.map(|c| (c & mask) >> child_total);
+    (x << (2 * log2)) | (y << log2) | z
+}
+
+fn write_file_header(out: &mut Vec<u8>) {
+    out.extend_from_slice(&MAGIC.to_le_bytes());
+    out.extend_from_slice(&FILE_VERSION.to_le_bytes());
+    out.extend_from_slice(&LIBRARY_VERSION.0.to_le_bytes());
+    out.extend_from_slice(&LIBRARY_VERSION.1.to_le_bytes());
+    // 包含 grid 偏移，读取端可以只读取部分 grid
+    out.push(1);
+    // UUID 以 36 个字符的文本写入
+    out.extend_from_slice(Uuid::new_v4().hyphenated().to_string().as_bytes());
+}
+
+enum MetaValue<'a> {
+    String(&'a str),
+    Int64(i64),
+    Vec3i([i32; 3]),
+}
+
+/// 元数据表：数量，然后每项依次为名称、类型名、值的字节数与值
+fn write_meta_map(out: &mut Vec<u8>, entries: &[(&str, MetaValue)]) {
+    out.extend_from_slice(&(entries.len() as u32).to_le_bytes());
+    for (name, value) in entries {
+        write_string(out, name);
+        let (type_name, bytes) = match value {
+            MetaValue::String(s) => ("string", s.as_bytes().to_vec()),
+            MetaValue::Int64(v) => ("int64", v.to_le_bytes().to_vec()),
+            MetaValue::Vec3i(v) => ("vec3i", v.iter().flat_map(|c| c.to_le_bytes()).collect()),
+        };
+        write_string(out, type_name);
+        out.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+        out.extend_from_slice(&bytes);
+    }
+}
+
+/// 单位仿射变换：索引坐标即世界坐标
+fn write_identity_transform(out: &mut Vec<u8>) {
+    write_string(out, "AffineMap");
+    for row in 0..4 {
+        for column in 0..4 {
+            let value: f64 = if row == column { 1.0 } else { 0.0 };
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+}
+
+/// 树结构：缓冲区数量、根节点（背景值、tile 数、子节点数），然后按顺序写出各子节点的掩码与 tile 值
+fn write_topology(out: &mut Vec<u8>, roots: &BTreeMap<[i32; 3], UpperNode>) {
+    out.extend_from_slice(&1i32.to_le_bytes());
+    // 背景值
+    out.extend_from_slice(&0f32.to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out.extend_from_slice(&(roots.len() as u32).to_le_bytes());
+    for (origin, upper) in roots {
+        for coord in origin {
+            out.extend_from_slice(&coord.to_le_bytes());
+        }
+        write_internal_topology(out, UPPER_LOG2, upper.keys());
+        for lower in upper.values() {
+            write_internal_topology(out, LOWER_LOG2, lower.keys());
+            for leaf in lower.values() {
+                write_mask(out, &leaf.mask);
+            }
+        }
+    }
+}
+
+/// 内部节点：子节点掩码、值掩码（全部为 0，没有活跃 tile），然后是所有位置的 tile 值（均为背景值 0）
+fn write_internal_topology<'a>(
+    out: &mut Vec<u8>,
+    log2: u32,
+    children: impl Iterator<Item = &'a usize>,
+) {
+    let slots = 1usize << (3 * log2);
+    let mut child_mask = vec![0u64; slots / 64];
+    for &offset in children {
+        child_mask[offset >> 6] |= 1 << (offset & 63);
+    }
+    write_mask(out, &child_mask);
+    write_mask(out, &vec![0u64; slots / 64]);
+    out.push(NO_MASK_AND_ALL_VALS);
+    out.resize(out.len() + slots * std::mem::size_of::<f32>(), 0);
+}
+
+/// 数据块：按与树结构相同的顺序写出每个叶节点的值掩码与 512 个值
+fn write_buffers(out: &mut Vec<u8>, roots: &BTreeMap<[i32; 3], UpperNode>) {
+    for leaf in roots
+        .values()
+        .flat_map(|upper| upper.values())
+        .flat_map(|lower| lower.values())
+    {
+        write_mask(out, &leaf.mask);
+        out.push(NO_MASK_AND_ALL_VALS);
+        for value in &leaf.values {
+            out.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+}
+
+fn write_mask(out: &mut Vec<u8>, words: &[u64]) {
+    for word in words {
+        out.extend_from_slice(&word.to_le_bytes());
+    }
+}
+
+/// 字符串：u32 长度 + 字节
+fn write_string(out: &mut Vec<u8>, s: &str) {
+    out.extend_from_slice(&(s.len() as u32).to_le_bytes());
+    out.extend_from_slice(s.as_bytes());
+}
@@ -1,19 +1,69 @@
+use serde::{Deserialize, Serialize};
+use xxhash_rust::xxh3::Xxh3;
+
+use crate::utils::components::label_components;
+#[cfg(feature = "fft")]
+use crate::utils::fft_resample::fft_resample;
+use crate::utils::marching_cubes::marching_cubes;
+use crate::utils::png_slice::{Colormap, SliceAxis, render_png};
+use crate::utils::rebin::rebin_conservative;
+use crate::utils::sparse::SparseVoxelGrid;
+use crate::utils::stats::{GridStats, Histogram};
+use crate::utils::vdb::{VdbSummary, write_vdb};
+
 /// 体素网格数据结构
 /// 表示三维规则网格上的标量场数据
 #[derive(Debug, Clone)]
 pub struct VoxelGrid {
     /// 网格维度 [nx, ny, nz]
-    #[allow(dead_code)]
     pub shape: [usize; 3],
     /// 数据数组，按 C 语言顺序存储 (x变化最快，y其次，z最慢)
     /// 索引计算: index = k * nx * ny + j * nx + i
     pub data: Vec<f64>,
 }
 
+/// 线段 `start` → `end` 上等距 `samples` 个点中的第 `index` 个（含两个端点），`line_profile` 按此取点
+pub fn line_point(start: [f64; 3], end: [f64; 3], samples: usize, index: usize) -> [f64; 3] {
+    let t = index as f64 / samples.saturating_sub(1).max(1) as f64;
+    std::array::from_fn(|axis| start[axis] + (end[axis] - start[axis]) * t)
+}
+
+/// 网格的体素总数（三个维度之积）
+///
+/// 损坏的文件头可能给出极大的维度，乘积溢出 usize 时返回错误，而不是回绕成一个很小的数
+pub fn checked_grid_len(shape: [usize; 3]) -> Result<usize, String> {
+    shape
+        .iter()
+        .try_fold(1usize, |total, &n| total.checked_mul(n))
+        .ok_or_else(|| format!("shape {shape:?} 过大，体素总数超出 usize 范围"))
+}
+
+/// 文本格式中每个值至少占用的字节数（一位数字加一个分隔符）
+pub const MIN_TEXT_VALUE_BYTES: u64 = 2;
+
+/// 按文件头的 shape 预分配数据缓冲区时的元素数
+///
+/// 损坏的文件头可能声明远超文件内容的 shape（如 20 字节的文件声明 100000³），按 shape 直接预分配会因分配失败使整个进程中止。
+/// 预分配不超过数据段最多能容纳的值数量（`data_bytes` 除以每个值至少占用的 `value_bytes` 字节），
+/// 数据不足时由 `VoxelGrid::new` 报告截断
+pub fn prealloc_len(total_elements: usize, data_bytes: u64, value_bytes: u64) -> usize {
+    let available = data_bytes / value_bytes.max(1);
+    usize::try_from(available).map_or(total_elements, |available| total_elements.min(available))
+}
+
 impl VoxelGrid {
     /// 创建新的体素网格
     pub fn new(shape: [usize; 3], data: Vec<f64>) -> Result<Self, String> {
-        let total_elements = shape[0] * shape[1] * shape[2];
+        let total_elements = checked_grid_len(shape)?;
+
+        if data.len() < total_elements {
+            return Err(format!(
+                "数据被截断: shape {:?} 需要 {} 个值，但只读取到 {} 个",
+                shape,
+                total_elements,
+                data.len()
+            ));
+        }
 
         if data.len() != total_elements {
             return Err(format!(
@@ -33,8 +83,786 @@ impl VoxelGrid {
     }
 
     /// 获取 shape
-    #[allow(dead_code)]
     pub fn get_shape(&self) -> [usize; 3] {
         self.shape
     }
+
+    /// 将所有数值四舍五入到指定的小数位数（原地修改）
+    /// 去掉对可视化无意义的噪声位后数值重复度提高，gzip/brotli 等压缩效果更好
+    /// 放大后超出 f64 表示范围的值保持不变
+    pub fn round_to_decimals(&mut self, decimals: u32) {
+        round_values_to_decimals(&mut self.data, decimals);
+    }
+
+    /// 对每个值应用 `f`（原地修改），用于取对数、取绝对值等逐元素变换
+    pub fn map_values(&mut self, f: impl Fn(f64) -> f64) {
+        for value in self.data.iter_mut() {
+            *value = f(*value);
+        }
+    }
+
+    /// 窗宽窗位：把 `[level - window/2, level + window/2]` 线性映射到 `[0, 1]`，范围外的值截断，返回新网格
+    ///
+    /// 渲染时用于调整对比度；NaN 保持为 NaN，±inf 分别映射为 1 / 0。调用方需保证 `window > 0`
+    pub fn apply_window(&self, level: f64, window: f64) -> VoxelGrid {
+        let mut data = self.data.clone();
+        window_values(&mut data, level, window);
+        VoxelGrid {
+            shape: self.shape,
+            data,
+        }
+    }
+
+    /// 计算每个轴方向上的"连续性比值"，用于粗略检测数据顺序是否正确
+    ///
+    /// 对每个轴，统计相邻体素差值绝对值的平均值，再除以"远距离"样本对的差值平均值（基线）。
+    /// 连续的标量场在正确的轴上比值应明显小于 1；若某个轴的比值接近 1，说明沿该轴的
+    /// "相邻"体素实际上并不相邻，常见原因是 shape 维度顺序或存储顺序与声明不一致。
+    ///
+    /// 为避免大网格的全量遍历，每个轴最多采样约 `max_samples` 对。
+    /// 返回 None 表示数据不足或全部相同，无法判断
+    pub fn axis_continuity(&self, max_samples: usize) -> Option<[f64; 3]> {
+        let [nx, ny, nz] = self.shape;
+        let len = self.data.len();
+        if len < 2 {
+            return None;
+        }
+        let step = (len / max_samples.max(1)).max(1);
+
+        // 基线：相隔半个数组的样本对，近似于不相关的两个值
+        let half = len / 2;
+        let mut baseline_sum = 0.0;
+        let mut baseline_count = 0usize;
+        let mut index = 0;
+        while index + half < len {
+            let diff = (self.data[index] - self.data[index + half]).abs();
+            if diff.is_finite() {
+                baseline_sum += diff;
+                baseline_count += 1;
+            }
+            index += step;
+        }
+        if baseline_count == 0 || baseline_sum == 0.0 {
+            return None;
+        }
+        let baseline = baseline_sum / baseline_count as f64;
+
+        let strides = [1, nx, nx * ny];
+        let extents = [nx, ny, nz];
+        let mut ratios = [f64::NAN; 3];
+        for axis in 0..3 {
+            if extents[axis] < 2 {
+                continue;
+            }
+            let stride = strides[axis];
+            let mut sum = 0.0;
+            let mut count = 0usize;
+            let mut index = 0;
+            while index < len {
+                // 跳过沿该轴位于末端的体素（没有下一个邻居）
+                let coord = (index / stride) % extents[axis];
+                if coord + 1 < extents[axis] {
+                    let diff = (self.data[index] - self.data[index + stride]).abs();
+                    if diff.is_finite() {
+                        sum += diff;
+                        count += 1;
+                    }
+                }
+                index += step;
+            }
+            if count > 0 {
+                ratios[axis] = sum / count as f64 / baseline;
+            }
+        }
+        Some(ratios)
+    }
+
+    /// 三维高斯模糊（可分离卷积：沿 x、y、z 依次做一维卷积），返回新网格
+    ///
+    /// 卷积核半径为 `ceil(3 * sigma)`，边界处按最近的边界体素取值（clamp）。
+    /// 每一遍的开销与体素数成正比，大网格也可行；`sigma <= 0` 时原样返回
+    pub fn gaussian_blur(&self, sigma: f64) -> VoxelGrid {
+        let mut data = self.data.clone();
+        if sigma > 0.0 {
+            let kernel = gaussian_kernel(sigma);
+            for axis in 0..3 {
+                blur_axis(&mut data, self.shape, axis, &kernel);
+            }
+        }
+        VoxelGrid {
+            shape: self.shape,
+            data,
+        }
+    }
+
+    /// 直方图均衡化：按 `bins` 个等宽 bin 的累积分布把有效值重新映射到 `[0, 1]`，返回新网格
+    ///
+    /// 每个值映射为不大于它的有效值所占的比例（bin 内按位置线性插值），低对比度区域的取值差异被拉开，
+    /// 输出在 `[0, 1]` 上近似均匀分布。映射单调不减，最小值映射为 0、最大值映射为 1；
+    /// 非有限值保持不变，所有有效值都相同时全部映射为 0。`bins` 为 0 时按 1 处理
+    pub fn histogram_equalize(&self, bins: usize) -> VoxelGrid {
+        let histogram = Histogram::from_values(&self.data, bins);
+        let (Some(min), Some(bin_width)) = (histogram.min, histogram.bin_width) else {
+            return self.clone();
+        };
+        // cdf[i] 为前 i 个 bin 中的有效值所占的比例，cdf[bins] 为 1
+        let total = histogram.count as f64;
+        let mut cdf = Vec::with_capacity(histogram.counts.len() + 1);
+        let mut running = 0;
+        cdf.push(0.0);
+        for &count in &histogram.counts {
+            running += count;
+            cdf.push(running as f64 / total);
+        }
+        let last_bin = histogram.counts.len() - 1;
+        let data = self
+            .data
+            .iter()
+            .map(|&value| {
+                if !value.is_finite() {
+                    return value;
+                }
+                if bin_width <= 0.0 {
+                    return 0.0;
+                }
+                let position = (value - min) / bin_width;
+                let bin = (position as usize).min(last_bin);
+                let fraction = (position - bin as f64).clamp(0.0, 1.0);
+                cdf[bin] + (cdf[bin + 1] - cdf[bin]) * fraction
+            })
+            .collect();
+        VoxelGrid {
+            shape: self.shape,
+            data,
+        }
+    }
+
+    /// 用 FFT 重采样到 `target` shape（频域补零 / 截断），返回新网格
+    ///
+    /// 假定数据沿每个轴周期延拓：对周期性晶胞数据是精确的带限插值，非周期数据在边界附近会出现振铃。
+    /// NaN / ±inf 视为 0；`target` 的任一维度为 0 或体素总数溢出时返回错误
+    #[cfg(feature = "fft")]
+    pub fn fft_resample(&self, target: [usize; 3]) -> Result<VoxelGrid, String> {
+        if target.contains(&0) {
+            return Err(format!("无效的重采样 shape {target:?}：每个维度都必须大于 0"));
+        }
+        checked_grid_len(target)?;
+        if self.data.is_empty() {
+            return Err("空网格无法重采样".to_string());
+        }
+        VoxelGrid::new(target, fft_resample(&self.data, self.shape, target))
+    }
+
+    /// 守恒重分箱到 `target` shape：每个源体素的值按重叠体积的比例分配到目标体素，所有值之和保持不变
+    ///
+    /// 与 FFT 重采样和取平均不同，适合电荷密度等守恒量的降采样（也可以升采样）。NaN / ±inf 视为 0；
+    /// `target` 的任一维度为 0 时返回空网格，调用方需保证体素总数不溢出
+    pub fn rebin_conservative(&self, target: [usize; 3]) -> VoxelGrid {
+        VoxelGrid {
+            shape: target,
+            data: rebin_conservative(&self.data, self.shape, target),
+        }
+    }
+
+    /// 用 Marching Cubes 提取 `isovalue` 处的等值面，返回顶点坐标（体素索引单位）与三角形索引
+    pub fn marching_cubes(&self, isovalue: f64) -> (Vec<[f32; 3]>, Vec<u32>) {
+        marching_cubes(&self.data, self.shape, isovalue)
+    }
+
+    /// 转换为稀疏表示：只保留绝对值大于 `threshold` 的体素的坐标与取值
+    /// 坐标以 u32 存储，调用方需保证每个维度都不超过 `u32::MAX`
+    pub fn to_sparse(&self, threshold: f64) -> SparseVoxelGrid {
+        SparseVoxelGrid::from_dense(self, threshold)
+    }
+
+    /// 对 `value > threshold` 的体素做连通域标记，`connectivity` 为 6 或 26（其他值会 panic，调用方需先校验）
+    /// 返回每个体素的标签（背景为 0，连通域为 `1..=count`）与连通域数量
+    pub fn connected_components(&self, threshold: f64, connectivity: u8) -> (Vec<u32>, usize) {
+        label_components(&self.data, self.shape, threshold, connectivity)
+    }
+
+    /// 在连续坐标 `point`（体素索引单位，体素 `(i, j, k)` 位于 `(i, j, k)`）处三线性插值
+    ///
+    /// 坐标超出 `[0, n - 1]` 时返回 None；需要跨越晶胞边界时使用 `sample_trilinear_periodic`
+    pub fn sample_trilinear(&self, point: [f64; 3]) -> Option<f64> {
+        let mut base = [0; 3];
+        let mut next = [0; 3];
+        let mut fraction = [0.0; 3];
+        for axis in 0..3 {
+            let last = self.shape[axis].checked_sub(1)? as f64;
+            let coordinate = point[axis];
+            if !(0.0..=last).contains(&coordinate) {
+                return None;
+            }
+            let floor = coordinate.floor();
+            base[axis] = floor as usize;
+            next[axis] = (base[axis] + 1).min(self.shape[axis] - 1);
+            fraction[axis] = coordinate - floor;
+        }
+        Some(self.interpolate(base, next, fraction))
+    }
+
+    /// 按周期边界三线性插值：坐标按 shape 取模，最后一层与第 0 层之间同样插值
+    /// （坐标 `nx` 与 `0` 等价），适用于 VASP 等周期性晶胞。坐标不是有限数时返回 NaN
+    pub fn sample_trilinear_periodic(&self, point: [f64; 3]) -> f64 {
+        if self.data.is_empty() || point.iter().any(|coordinate| !coordinate.is_finite()) {
+            return f64::NAN;
+        }
+        let mut base = [0; 3];
+        let mut next = [0; 3];
+        let mut fraction = [0.0; 3];
+        for axis in 0..3 {
+            let length = self.shape[axis];
+            let wrapped = point[axis].rem_euclid(length as f64);
+            let floor = wrapped.floor();
+            // rem_euclid 在极小的负数上可能舍入为 length 本身
+            base[axis] = (floor as usize) % length;
+            next[axis] = (base[axis] + 1) % length;
+            fraction[axis] = wrapped - floor;
+        }
+        self.interpolate(base, next, fraction)
+    }
+
+    /// `point` 处的梯度（每体素的变化量），对插值场做步长为 1 个体素的中心差分
+    ///
+    /// 边界处改用单侧差分；坐标超出 `[0, n - 1]` 时返回 None。长度为 1 的轴梯度为 0
+    pub fn gradient(&self, point: [f64; 3]) -> Option<[f64; 3]> {
+        self.sample_trilinear(point)?;
+        let mut gradient = [0.0; 3];
+        for axis in 0..3 {
+            let last = (self.shape[axis] - 1) as f64;
+            let (mut lower, mut upper) = (point, point);
+            lower[axis] = (point[axis] - 1.0).max(0.0);
+            upper[axis] = (point[axis] + 1.0).min(last);
+            let step = upper[axis] - lower[axis];
+            if step > 0.0 {
+                gradient[axis] =
+                    (self.sample_trilinear(upper)? - self.sample_trilinear(lower)?) / step;
+            }
+        }
+        Some(gradient)
+    }
+
+    /// 周期边界下 `point` 处的梯度：中心差分的两个采样点按 `sample_trilinear_periodic` 跨越边界取值
+    pub fn gradient_periodic(&self, point: [f64; 3]) -> [f64; 3] {
+        let mut gradient = [0.0; 3];
+        for (axis, component) in gradient.iter_mut().enumerate() {
+            let (mut lower, mut upper) = (point, point);
+            lower[axis] -= 1.0;
+            upper[axis] += 1.0;
+            *component = (self.sample_trilinear_periodic(upper)
+                - self.sample_trilinear_periodic(lower))
+                / 2.0;
+        }
+        gradient
+    }
+
+    /// 每个体素处的拉普拉斯量（即梯度的散度），标准 7 点模板、体素间距为 1：沿每个轴累加 `f(i-1) - 2f(i) + f(i+1)`
+    ///
+    /// 边界体素沿该轴改用单侧二阶差分：轴长不少于 4 时为二阶精度的 `2f0 - 5f1 + 4f2 - f3`，轴长为 3 时为 `f0 - 2f1 + f2`，
+    /// 轴长小于 3 的轴贡献为 0。结果按 x 最快、z 最慢排列，与 `data` 等长
+    pub fn laplacian(&self) -> Vec<f64> {
+        let mut output = vec![0.0; self.data.len()];
+        for axis in 0..3 {
+            accumulate_second_difference(&self.data, self.shape, axis, false, &mut output);
+        }
+        output
+    }
+
+    /// 周期边界下的拉普拉斯量：边界体素的差分点跨越边界取值（第 0 层与最后一层相邻），不再使用单侧差分
+    pub fn laplacian_periodic(&self) -> Vec<f64> {
+        let mut output = vec![0.0; self.data.len()];
+        for axis in 0..3 {
+            accumulate_second_difference(&self.data, self.shape, axis, true, &mut output);
+        }
+        output
+    }
+
+    /// 沿线段 `start` → `end` 等距取 `samples` 个点（含两个端点）三线性插值，得到一维剖面
+    ///
+    /// 第 i 个点位于 `start + (end - start) * i / (samples - 1)`，`samples` 为 1 时只取 `start`。
+    /// 超出 `[0, n - 1]` 的点不外推，对应位置为 NaN，调用方据此找出越界的采样点
+    pub fn line_profile(&self, start: [f64; 3], end: [f64; 3], samples: usize) -> Vec<f64> {
+        (0..samples)
+            .map(|i| {
+                self.sample_trilinear(line_point(start, end, samples, i))
+                    .unwrap_or(f64::NAN)
+            })
+            .collect()
+    }
+
+    /// 在 `base` 与 `next` 围成的单元内按各轴的小数部分 `fraction` 做三线性插值
+    fn interpolate(&self, base: [usize; 3], next: [usize; 3], fraction: [f64; 3]) -> f64 {
+        let [nx, ny, _] = self.shape;
+        let value = |x: usize, y: usize, z: usize| self.data[z * nx * ny + y * nx + x];
+        let lerp = |a: f64, b: f64, t: f64| if t == 0.0 { a } else { a + (b - a) * t };
+        let [fx, fy, fz] = fraction;
+        let plane = |z: usize| {
+            let bottom = lerp(value(base[0], base[1], z), value(next[0], base[1], z), fx);
+            let top = lerp(value(base[0], next[1], z), value(next[0], next[1], z), fx);
+            lerp(bottom, top, fy)
+        };
+        lerp(plane(base[2]), plane(next[2]), fz)
+    }
+
+    /// 导出为 OpenVDB 文件（单个名为 `name` 的 FloatGrid），返回文件字节与概况
+    ///
+    /// `threshold` 为 None 时稠密写出；为 Some 时只写入 `nonzero_bounds(threshold)` 范围内含有
+    /// `|v| > threshold` 体素的 8³ 块，空白区域不占空间
+    pub fn to_vdb(&self, name: &str, threshold: Option<f64>) -> (Vec<u8>, VdbSummary) {
+        // 没有体素超过阈值时写出空网格
+        let bounds = threshold
+            .map(|threshold| self.nonzero_bounds(threshold).unwrap_or(([0; 3], [0; 3])));
+        write_vdb(&self.data, self.shape, name, threshold, bounds)
+    }
+
+    /// 计算绝对值超过 `threshold` 的体素的紧致包围盒
+    /// 返回 `(min, max)`，各轴范围为 `[min, max)`；没有体素超过阈值时返回 None
+    pub fn nonzero_bounds(&self, threshold: f64) -> Option<([usize; 3], [usize; 3])> {
+        let [nx, ny, _] = self.shape;
+        let mut min = [usize::MAX; 3];
+        let mut max = [0usize; 3];
+        for (index, value) in self.data.iter().enumerate() {
+            // NaN 不计入
+            if value.is_nan() || value.abs() <= threshold {
+                continue;
+            }
+            let coords = [index % nx, (index / nx) % ny, index / (nx * ny)];
+            for axis in 0..3 {
+                min[axis] = min[axis].min(coords[axis]);
+                max[axis] = max[axis].max(coords[axis] + 1);
+            }
+        }
+        (min[0] != usize::MAX).then_some((min, max))
+    }
+
+    /// 裁剪出各轴范围为 `[min, max)` 的子网格（复制数据，原网格不变）
+    pub fn crop(&self, min: [usize; 3], max: [usize; 3]) -> Result<VoxelGrid, String> {
+        if (0..3).any(|axis| min[axis] >= max[axis] || max[axis] > self.shape[axis]) {
+            return Err(format!(
+                "无效的裁剪范围: min {:?} max {:?}，网格 shape {:?}",
+                min, max, self.shape
+            ));
+        }
+        let [nx, ny, _] = self.shape;
+        let shape = [max[0] - min[0], max[1] - min[1], max[2] - min[2]];
+        let mut data = Vec::with_capacity(shape[0] * shape[1] * shape[2]);
+        // x 方向连续，按行整段复制
+        for k in min[2]..max[2] {
+            for j in min[1]..max[1] {
+                let row = k * nx * ny + j * nx;
+                data.extend_from_slice(&self.data[row + min[0]..row + max[0]]);
+            }
+        }
+        VoxelGrid::new(shape, data)
+    }
+
+    /// 把每个轴裁剪或填充到 2 的幂（部分 GPU / WebGL 只接受 2 的幂尺寸的 3D 纹理）
+    ///
+    /// 目标 shape 由 `pow2_shape` 给出，原数据在新网格中居中。返回新网格与偏移 `offset`：
+    /// 新网格中的体素 `c` 对应原网格中的体素 `c + offset`（裁剪时为正，填充时为负）。
+    /// 填充的体素按 `mode` 取 0 或最近的边界值；shape 已经是 2 的幂时返回原网格的副本、偏移为 0
+    pub fn pad_or_crop_to_pow2(&self, mode: PadMode) -> (VoxelGrid, [isize; 3]) {
+        let target = pow2_shape(self.shape, mode);
+        let offset: [isize; 3] =
+            std::array::from_fn(|axis| (self.shape[axis] as isize - target[axis] as isize) / 2);
+        // 每个轴上新坐标对应的原坐标，填充为 0 的位置为 None
+        let sources: [Vec<Option<usize>>; 3] = std::array::from_fn(|axis| {
+            let length = self.shape[axis] as isize;
+            (0..target[axis] as isize)
+                .map(|index| {
+                    let source = index + offset[axis];
+                    match mode {
+                        _ if (0..length).contains(&source) => Some(source as usize),
+                        PadMode::PadEdge => Some(source.clamp(0, length - 1) as usize),
+                        _ => None,
+                    }
+                })
+                .collect()
+        });
+
+        let [nx, ny, _] = self.shape;
+        let mut data = Vec::with_capacity(target.iter().product());
+        for &k in &sources[2] {
+            for &j in &sources[1] {
+                for &i in &sources[0] {
+                    let value = match (i, j, k) {
+                        (Some(i), Some(j), Some(k)) => self.data[k * nx * ny + j * nx + i],
+                        _ => 0.0,
+                    };
+                    data.push(value);
+                }
+            }
+        }
+        (
+            VoxelGrid {
+                shape: target,
+                data,
+            },
+            offset,
+        )
+    }
+
+    /// 把 `axis` 方向第 `index` 层切片渲染为 PNG，按整个网格的数值范围归一化
+    /// 切片图像的方向与 `SliceAxis::voxel_indices` 相同；HTTP 接口从任务的 chunk 中读取切片，这里供离线调试使用
+    pub fn to_png_slice(
+        &self,
+        axis: SliceAxis,
+        index: usize,
+        colormap: Colormap,
+    ) -> Result<Vec<u8>, String> {
+        if index >= self.shape[axis.number()] {
+            return Err(format!(
+                "无效的切片位置: {:?} 轴第 {} 层，网格 shape {:?}",
+                axis, index, self.shape
+            ));
+        }
+        let stats = GridStats::from_values(self.data.iter().copied());
+        let range = (stats.min.unwrap_or(0.0), stats.max.unwrap_or(0.0));
+        let values: Vec<f64> = axis
+            .voxel_indices(self.shape, index)
+            .map(|voxel| self.data[voxel])
+            .collect();
+        let (width, height) = axis.plane_dims(self.shape);
+        render_png(&values, width, height, range, colormap)
+    }
+
+    /// 所有有限值之和（NaN / ±inf 不计入），使用 Neumaier 补偿求和，大网格下误差更小
+    pub fn sum(&self) -> f64 {
+        let mut sum = 0.0f64;
+        let mut compensation = 0.0f64;
+        for &value in self.data.iter().filter(|value| value.is_finite()) {
+            let total = sum + value;
+            if sum.abs() >= value.abs() {
+                compensation += (sum - total) + value;
+            } else {
+                compensation += (value - total) + sum;
+            }
+            sum = total;
+        }
+        sum + compensation
+    }
+
+    /// 只统计满足 `predicate` 的体素（如值域范围内的 ROI）
+    /// 非有限值始终不计入；没有体素被选中时返回 count 为 0、min/max/mean 为 None 的统计量
+    pub fn masked_statistics(&self, predicate: impl Fn(f64) -> bool) -> GridStats {
+        GridStats::from_values(self.data.iter().copied().filter(|&value| predicate(value)))
+    }
+
+    /// 计算网格的 64 位校验和（xxh3，覆盖 shape 与全部数据）
+    /// 用于判断两个网格的数据是否完全相同，以及缓存校验
+    pub fn checksum(&self) -> u64 {
+        let mut hasher = Xxh3::new();
+        for dim in self.shape {
+            hasher.update(&(dim as u64).to_le_bytes());
+        }
+        // 分批转换为字节再送入 hasher，避免逐个值调用 update 的开销
+        let mut buffer = Vec::with_capacity(CHECKSUM_BATCH * 8);
+        for batch in self.data.chunks(CHECKSUM_BATCH) {
+            buffer.clear();
+            for value in batch {
+                buffer.extend_from_slice(&value.to_le_bytes());
+            }
+            hasher.update(&buffer);
+        }
+        hasher.digest()
+    }
+}
+
+/// 归一化的一维高斯核，长度为 `2 * ceil(3 * sigma) + 1`
+fn gaussian_kernel(sigma: f64) -> Vec<f64> {
+    let radius = (3.0 * sigma).ceil() as isize;
+    let mut kernel: Vec<f64> = (-radius..=radius)
+        .map(|offset| (-((offset * offset) as f64) / (2.0 * sigma * sigma)).exp())
+        .collect();
+    let total: f64 = kernel.iter().sum();
+    kernel.iter_mut().for_each(|weight| *weight /= total);
+    kernel
+}
+
+/// 沿 `axis` 对每一条线做一维卷积（原地），越界位置取最近的边界值
+fn blur_axis(data: &mut [f64], shape: [usize; 3], axis: usize, kernel: &[f64]) {
+    let length = shape[axis];
+    if length <= 1 {
+        return;
+    }
+    let [nx, ny, nz] = shape;
+    let stride = [1, nx, nx * ny][axis];
+    let radius = (kernel.len() / 2) as isize;
+    let last = length as isize - 1;
+
+    // 其余两个轴上的所有起点各对应一条线
+    let line_starts: Vec<usize> = match axis {
+        0 => (0..nz).flat_map(|k| (0..ny).map(move |j| k * nx * ny + j * nx)).collect(),
+        1 => (0..nz).flat_map(|k| (0..nx).map(move |i| k * nx * ny + i)).collect(),
+        _ => (0..ny).flat_map(|j| (0..nx).map(move |i| j * nx + i)).collect(),
+    };
+    let mut line = vec![0.0; length];
+    for start in line_starts {
+        for (position, value) in line.iter_mut().enumerate() {
+            *value = data[start + position * stride];
+        }
+        for position in 0..length {
+            let mut sum = 0.0;
+            for (tap, weight) in kernel.iter().enumerate() {
+                let source = (position as isize + tap as isize - radius).clamp(0, last);
+                sum += weight * line[source as usize];
+            }
+            data[start + position * stride] = sum;
+        }
+    }
+}
+
+/// 把沿 `axis` 的二阶差分累加到 `output`，边界规则见 `VoxelGrid::laplacian` / `laplacian_periodic`
+fn accumulate_second_difference(
+    data: &[f64],
+    shape: [usize; 3],
+    axis: usize,
+    periodic: bool,
+    output: &mut [f64],
+) {
+    let length = shape[axis];
+    let stride = [1, shape[0], shape[0] * shape[1]][axis];
+    for (index, out) in output.iter_mut().enumerate() {
+        let position = (index / stride) % length;
+        let at = |offset: isize| data[(index as isize + offset * stride as isize) as usize];
+        *out += if periodic {
+            if length == 1 {
+                continue;
+            }
+            let last = (length - 1) as isize;
+            let lower = if position == 0 { last } else { -1 };
+            let upper = if position == length - 1 { -last } else { 1 };
+            at(lower) - 2.0 * at(0) + at(upper)
+        } else if length < 3 {
+            continue;
+        } else if position > 0 && position < length - 1 {
+            at(-1) - 2.0 * at(0) + at(1)
+        } else {
+            // 单侧差分：左边界向 +1 方向取点，右边界向 -1 方向取点
+            let step = if position == 0 { 1 } else { -1 };
+            if length >= 4 {
+                2.0 * at(0) - 5.0 * at(step) + 4.0 * at(2 * step) - at(3 * step)
+            } else {
+                at(0) - 2.0 * at(step) + at(2 * step)
+            }
+        };
+    }
+}
+
+/// `VoxelGrid::pad_or_crop_to_pow2` 的方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PadMode {
+    /// 裁剪到不超过原尺寸的最大 2 的幂
+    Crop,
+    /// 填充到不小于原尺寸的最小 2 的幂，填充的体素为 0
+    PadZero,
+    /// 同 `PadZero`，填充的体素取最近的边界值
+    PadEdge,
+}
+
+/// 按 `mode` 得到各轴的 2 的幂尺寸；已经是 2 的幂的轴保持不变
+pub fn pow2_shape(shape: [usize; 3], mode: PadMode) -> [usize; 3] {
+    shape.map(|n| match mode {
+        PadMode::Crop => 1 << n.max(1).ilog2(),
+        PadMode::PadZero | PadMode::PadEdge => n.next_power_of_two(),
+    })
+}
+
+/// 计算校验和时每批处理的元素数量
+const CHECKSUM_BATCH: usize = 8192;
+
+/// 将一组数值四舍五入到指定的小数位数（原地修改），规则同 `VoxelGrid::round_to_decimals`
+/// 用于不经过完整网格、按 chunk 读取的数据
+pub fn round_values_to_decimals(values: &mut [f64], decimals: u32) {
+    let factor = 10f64.powi(decimals as i32);
+    for value in values.iter_mut() {
+        let scaled = *value * factor;
+        if scaled.is_finite() {
+            *value = scaled.round() / factor;
+        }
+    }
+}
+
+/// 对单个值应用窗宽窗位，规则同 `VoxelGrid::apply_window`
+pub fn window_value(value: f64, level: f64, window: f64) -> f64 {
+    ((value - (level - window / 2.0)) / window).clamp(0.0, 1.0)
+}
+
+/// 对一组数值应用窗宽窗位（原地修改），规则同 `VoxelGrid::apply_window`
+/// 用于按 chunk 应用，不需要复制完整网格
+pub fn window_values(values: &mut [f64], level: f64, window: f64) {
+    for value in values.iter_mut() {
+        *value = window_value(*value, level, window);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checked_grid_len_rejects_overflow() {
+        assert_eq!(checked_grid_len([4, 5, 6]), Ok(120));
+        assert!(checked_grid_len([usize::MAX, 2, 1]).is_err());
+        assert!(checked_grid_len([usize::MAX, 1, 1]).is_ok());
+    }
+
+    #[test]
+    fn prealloc_len_is_bounded_by_file_size() {
+        assert_eq!(prealloc_len(1_000_000_000_000_000, 20, 8), 2);
+        assert_eq!(prealloc_len(100, 1 << 20, MIN_TEXT_VALUE_BYTES), 100);
+        assert_eq!(prealloc_len(100, 1 << 20, 0), 100);
+    }
+
+    /// 中心为 1、其余为 0 的 `n`×`n`×`n` 网格
+    fn delta_grid(n: usize) -> VoxelGrid {
+        let mut data = vec![0.0; n * n * n];
+        let center = n / 2;
+        data[center * n * n + center * n + center] = 1.0;
+        VoxelGrid::new([n, n, n], data).unwrap()
+    }
+
+    #[test]
+    fn gaussian_blur_turns_delta_into_symmetric_gaussian() {
+        // sigma = 1 时卷积核半径为 3，9×9×9 网格的中心到边界有 4 个体素，不会触及边界
+        let n = 9;
+        let blurred = delta_grid(n).gaussian_blur(1.0);
+        let value = |x: usize, y: usize, z: usize| blurred.get_data()[z * n * n + y * n + x];
+
+        assert!((blurred.sum() - 1.0).abs() < 1e-12, "{}", blurred.sum());
+        for z in 0..n {
+            for y in 0..n {
+                for x in 0..n {
+                    let v = value(x, y, z);
+                    assert!((v - value(n - 1 - x, y, z)).abs() < 1e-15);
+                    assert!((v - value(y, x, z)).abs() < 1e-15);
+                    assert!((v - value(z, y, x)).abs() < 1e-15);
+                }
+            }
+        }
+        // 相邻体素之比为 exp(-d²/(2σ²))，超出半径的位置为 0
+        let center = value(4, 4, 4);
+        assert!((value(5, 4, 4) / center - (-0.5f64).exp()).abs() < 1e-12);
+        assert!((value(6, 4, 4) / center - (-2.0f64).exp()).abs() < 1e-12);
+        assert!((value(5, 5, 4) / center - (-1.0f64).exp()).abs() < 1e-12);
+        assert_eq!(value(0, 4, 4), 0.0);
+    }
+
+    #[test]
+    fn gaussian_blur_without_positive_sigma_is_noop() {
+        let grid = VoxelGrid::new([3, 2, 2], (0..12).map(|i| f64::from(i * i)).collect()).unwrap();
+        for sigma in [0.0, -1.0] {
+            assert_eq!(grid.gaussian_blur(sigma).get_data(), grid.get_data());
+        }
+    }
+
+    /// 值为 `f(x, y, z)` 的网格（x 最快）
+    fn grid_from(shape: [usize; 3], f: impl Fn(f64, f64, f64) -> f64) -> VoxelGrid {
+        let [nx, ny, nz] = shape;
+        let data = (0..nz)
+            .flat_map(|z| (0..ny).flat_map(move |y| (0..nx).map(move |x| [x, y, z])))
+            .map(|[x, y, z]| f(x as f64, y as f64, z as f64))
+            .collect();
+        VoxelGrid::new(shape, data).unwrap()
+    }
+
+    #[test]
+    fn periodic_sample_wraps_nx_to_zero() {
+        let grid = grid_from([4, 3, 2], |x, y, z| x * x + 10.0 * y + 100.0 * z);
+        for point in [[0.0, 1.0, 1.0], [0.0, 1.5, 0.5], [1.25, 0.0, 0.0]] {
+            let expected = grid.sample_trilinear(point).unwrap();
+            let mut shifted = point;
+            shifted[0] += 4.0;
+            assert_eq!(grid.sample_trilinear_periodic(shifted), expected);
+            shifted[0] -= 8.0;
+            assert_eq!(grid.sample_trilinear_periodic(shifted), expected);
+        }
+        // 非周期采样在 x = nx 处越界
+        assert_eq!(grid.sample_trilinear([4.0, 1.0, 1.0]), None);
+        // 最后一层与第 0 层之间插值：x = 3.5 取 f(3) 与 f(0) 的平均
+        assert_eq!(grid.sample_trilinear_periodic([3.5, 0.0, 0.0]), 4.5);
+        // z 轴同样回绕：z = 1.5 在第 1 层与第 0 层之间
+        assert_eq!(grid.sample_trilinear_periodic([0.0, 0.0, 1.5]), 50.0);
+    }
+
+    #[test]
+    fn gradient_of_linear_ramp_is_constant() {
+        let grid = grid_from([5, 4, 3], |x, y, z| 2.0 * x + 3.0 * y - z);
+        // 内部点用中心差分，边界点用单侧差分，对线性场都是精确的
+        for point in [
+            [2.0, 1.5, 1.0],
+            [0.0, 0.0, 0.0],
+            [4.0, 3.0, 2.0],
+            [3.7, 0.2, 1.9],
+        ] {
+            let gradient = grid.gradient(point).unwrap();
+            for (component, expected) in gradient.iter().zip([2.0, 3.0, -1.0]) {
+                assert!(
+                    (component - expected).abs() < 1e-12,
+                    "{point:?}: {gradient:?}"
+                );
+            }
+        }
+        assert_eq!(grid.gradient([5.0, 0.0, 0.0]), None);
+    }
+
+    #[test]
+    fn periodic_gradient_differences_across_the_boundary() {
+        let grid = grid_from([4, 3, 2], |x, _, _| x);
+        // 内部与非周期梯度相同
+        assert_eq!(grid.gradient_periodic([1.0, 1.0, 0.0])[0], 1.0);
+        // x = 0 处下侧采样点回绕到 x = 3：(f(1) - f(3)) / 2
+        assert_eq!(grid.gradient_periodic([0.0, 1.0, 0.0])[0], -1.0);
+        assert_eq!(grid.gradient_periodic([4.0, 1.0, 0.0])[0], -1.0);
+    }
+
+    /// 排序后第 k 个值与均匀分布的第 k 个分位点 `k / (n - 1)` 的最大偏差
+    fn max_deviation_from_uniform(values: &[f64]) -> f64 {
+        let mut sorted = values.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        let last = (sorted.len() - 1) as f64;
+        sorted
+            .iter()
+            .enumerate()
+            .map(|(k, value)| (value - k as f64 / last).abs())
+            .fold(0.0, f64::max)
+    }
+
+    #[test]
+    fn histogram_equalize_flattens_a_skewed_distribution() {
+        // 均匀分布的平方：取值集中在 0 附近
+        let n = 10_000;
+        let values = (0..n)
+            .map(|i| (f64::from(i) / f64::from(n - 1)).powi(2))
+            .collect();
+        let grid = VoxelGrid::new([100, 100, 1], values).unwrap();
+        assert!(max_deviation_from_uniform(grid.get_data()) > 0.2);
+
+        let equalized = grid.histogram_equalize(1000);
+        let data = equalized.get_data();
+        assert!(max_deviation_from_uniform(data) < 0.02);
+        assert_eq!(data[0], 0.0);
+        assert_eq!(data[data.len() - 1], 1.0);
+        assert!(data.windows(2).all(|pair| pair[0] <= pair[1]));
+    }
+
+    #[test]
+    fn histogram_equalize_maps_constant_input_to_zero() {
+        let grid = VoxelGrid::new([2, 2, 1], vec![3.0, 3.0, f64::NAN, 3.0]).unwrap();
+        let data = grid.histogram_equalize(16).get_data().clone();
+        assert_eq!(&data[..2], &[0.0, 0.0]);
+        assert!(data[2].is_nan());
+        assert_eq!(data[3], 0.0);
+    }
+
+    #[test]
+    fn histogram_equalize_with_one_bin_is_a_linear_rescale() {
+        let grid = VoxelGrid::new([4, 1, 1], vec![2.0, 3.0, 5.0, 10.0]).unwrap();
+        let expected = vec![0.0, 0.125, 0.375, 1.0];
+        assert_eq!(grid.histogram_equalize(1).get_data(), &expected);
+        // bins 为 0 时按 1 处理
+        assert_eq!(grid.histogram_equalize(0).get_data(), &expected);
+    }
 }
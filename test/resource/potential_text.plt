@@ -0,0 +1,27 @@
+3 200
+2 3 4
+0.0 1.0 0.0 1.0 0.0 1.0
+0.000000E+00
+5.000000E-01
+1.000000E+00
+1.500000E+00
+5.000000E+00
+5.500000E+00
+6.000000E+00
+6.500000E+00
+1.000000E+01
+1.050000E+01
+1.100000E+01
+1.150000E+01
+5.000000E+01
+5.050000E+01
+5.100000E+01
+5.150000E+01
+5.500000E+01
+5.550000E+01
+5.600000E+01
+5.650000E+01
+6.000000E+01
+6.050000E+01
+6.100000E+01
+6.150000E+01